@@ -2,10 +2,16 @@
 //!
 //! Run with: cargo bench
 
+use cc_statusline::bench::{Repo, parse_pr, parse_pr_cache, pr_cache_bytes};
 use cc_statusline::{abbreviate_path, hash_path, parse_github_url, percent_encode, shell_escape};
 use criterion::{Criterion, Throughput, black_box, criterion_group, criterion_main};
+use std::fmt::Write as _;
+use std::fs;
 use std::io::Write;
+use std::path::Path;
 use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use tempfile::TempDir;
 
 /// Benchmark the full binary startup with minimal JSON input
 fn bench_startup_minimal(c: &mut Criterion) {
@@ -193,6 +199,183 @@ fn bench_abbreviate_path(c: &mut Criterion) {
     group.finish();
 }
 
+// =============================================================================
+// Git and cache benchmarks against a generated repo
+// =============================================================================
+
+/// Files in the generated work tree, spread over nested directories
+const REPO_FILES: usize = 5_000;
+/// Commits on `main`, each after the first changing one file
+const REPO_COMMITS: usize = 2_000;
+/// Commits on each side since `main` diverged from `origin/main`
+const DIVERGED: usize = 100;
+/// Tracked files modified in the work tree, and untracked files added
+const DIRTY_FILES: usize = 50;
+const UNTRACKED_FILES: usize = 10;
+
+const PR_JSON: &str = r#"{"number": 42, "state": "OPEN", "url": "https://github.com/o/r/pull/42",
+    "comments": [{}, {}, {}], "changedFiles": 10, "createdAt": "2026-01-01T00:00:00Z",
+    "statusCheckRollup": [{"conclusion": "SUCCESS"}, {"conclusion": "SUCCESS"}, {"status": "IN_PROGRESS"}],
+    "reviewDecision": "APPROVED", "labels": [{"name": "bug"}, {"name": "ui"}], "mergeable": "MERGEABLE"}"#;
+
+fn git(dir: &Path, args: &[&str], stdin: Option<&[u8]>) {
+    let mut child = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to run git");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.unwrap_or_default())
+        .expect("failed to write to git");
+    assert!(child.wait().unwrap().success(), "git {args:?} failed");
+}
+
+fn file_path(i: usize) -> String {
+    format!("src/mod{}/sub{}/file{i}.rs", i % 50, i % 7)
+}
+
+/// Append a commit changing `files` to the fast-import stream
+fn commit(
+    stream: &mut String,
+    branch: &str,
+    mark: usize,
+    from: Option<usize>,
+    files: &[(String, String)],
+) {
+    let message = format!("commit {mark}");
+    let _ = writeln!(stream, "commit {branch}\nmark :{mark}");
+    let _ = writeln!(
+        stream,
+        "committer Bench <bench@example.com> {} +0000",
+        1_700_000_000 + mark
+    );
+    let _ = writeln!(stream, "data {}\n{message}", message.len());
+    if let Some(from) = from {
+        let _ = writeln!(stream, "from :{from}");
+    }
+    for (path, content) in files {
+        let _ = writeln!(
+            stream,
+            "M 100644 inline {path}\ndata {}\n{content}",
+            content.len()
+        );
+    }
+}
+
+/// Repo in a tempdir: REPO_FILES files, REPO_COMMITS commits, `main` DIVERGED
+/// commits ahead of and behind `origin/main`, and a dirty work tree
+fn generated_repo() -> &'static Path {
+    static REPO: OnceLock<TempDir> = OnceLock::new();
+    REPO.get_or_init(|| {
+        let dir = TempDir::new().expect("failed to create temp dir");
+        let path = dir.path();
+        git(path, &["init", "-q"], None);
+        // Keep the stats cache out of the real cache dir; it is read once, on first use
+        // SAFETY: set before anything reads the environment from another thread
+        unsafe { std::env::set_var("XDG_CACHE_HOME", path.join(".git")) };
+        git(path, &["symbolic-ref", "HEAD", "refs/heads/main"], None);
+
+        let mut stream = String::new();
+        let contents = |i: usize, version: usize| {
+            format!("// file {i} v{version}\n{}", "fn f() {}\n".repeat(20))
+        };
+        let all: Vec<_> = (0..REPO_FILES)
+            .map(|i| (file_path(i), contents(i, 0)))
+            .collect();
+        commit(&mut stream, "refs/heads/main", 1, None, &all);
+        for mark in 2..=REPO_COMMITS {
+            let i = mark % REPO_FILES;
+            commit(
+                &mut stream,
+                "refs/heads/main",
+                mark,
+                Some(mark - 1),
+                &[(file_path(i), contents(i, mark))],
+            );
+        }
+        let base = REPO_COMMITS - DIVERGED;
+        for n in 1..=DIVERGED {
+            let mark = REPO_COMMITS + n;
+            let from = if n == 1 { base } else { mark - 1 };
+            let i = (mark * 7) % REPO_FILES;
+            commit(
+                &mut stream,
+                "refs/remotes/origin/main",
+                mark,
+                Some(from),
+                &[(file_path(i), contents(i, mark))],
+            );
+        }
+        git(path, &["fast-import", "--quiet"], Some(stream.as_bytes()));
+        git(path, &["config", "branch.main.remote", "origin"], None);
+        git(
+            path,
+            &["config", "branch.main.merge", "refs/heads/main"],
+            None,
+        );
+        git(path, &["reset", "--hard", "-q"], None);
+
+        for i in 0..DIRTY_FILES {
+            let file = path.join(file_path(i * 97 % REPO_FILES));
+            fs::write(&file, "// changed\nfn g() {}\n").expect("failed to modify file");
+        }
+        for i in 0..UNTRACKED_FILES {
+            fs::write(path.join(format!("untracked{i}.txt")), "new\n").expect("failed to add file");
+        }
+        dir
+    })
+    .path()
+}
+
+fn bench_git(c: &mut Criterion) {
+    let dir = generated_repo().to_str().unwrap();
+    let repo = Repo::open(dir).expect("generated repo should open");
+    assert_eq!(repo.ahead_behind(), (DIVERGED as u32, DIVERGED as u32));
+
+    let mut group = c.benchmark_group("git");
+    group.sample_size(20);
+
+    group.bench_function("open", |b| b.iter(|| Repo::open(black_box(dir))));
+
+    group.bench_function("diff_stats_files", |b| {
+        b.iter(|| repo.diff_stats(black_box(false)))
+    });
+
+    group.bench_function("diff_stats_lines", |b| {
+        b.iter(|| repo.diff_stats(black_box(true)))
+    });
+
+    group.bench_function("ahead_behind", |b| b.iter(|| repo.ahead_behind()));
+
+    group.finish();
+}
+
+fn bench_cache(c: &mut Criterion) {
+    let repo = Repo::open(generated_repo().to_str().unwrap()).expect("generated repo should open");
+
+    let mut group = c.benchmark_group("cache");
+
+    group.bench_function("mmap_save", |b| b.iter(|| repo.save_stats_cache()));
+
+    repo.save_stats_cache();
+    group.bench_function("mmap_load", |b| b.iter(|| repo.load_stats_cache()));
+
+    group.bench_function("pr_json_parse", |b| b.iter(|| parse_pr(black_box(PR_JSON))));
+
+    let bytes = pr_cache_bytes("feature/login", parse_pr(PR_JSON).unwrap());
+    group.bench_function("pr_entry_decode", |b| {
+        b.iter(|| parse_pr_cache(black_box(&bytes)))
+    });
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_startup_minimal,
@@ -202,6 +385,8 @@ criterion_group!(
     bench_percent_encode,
     bench_parse_github_url,
     bench_abbreviate_path,
+    bench_git,
+    bench_cache,
 );
 
 criterion_main!(benches);
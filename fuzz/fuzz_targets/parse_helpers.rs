@@ -0,0 +1,25 @@
+//! Feed arbitrary input to the helpers that see untrusted strings:
+//! git remotes, paths, and Claude Code's stdin JSON
+
+#![no_main]
+
+use cc_statusline::{
+    ClaudeInput, abbreviate_path, parse_github_url, percent_encode, shell_escape,
+    validate_input,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(s) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Some((owner, repo)) = parse_github_url(s) {
+        assert!(!owner.contains('/') && !repo.contains('/'));
+    }
+    assert!(percent_encode(s).is_ascii());
+    assert!(shell_escape(s).starts_with('\''));
+    let width = data.first().map_or(0, |&b| usize::from(b % 64));
+    assert!(abbreviate_path(s, width).len() <= s.len());
+    let _ = ClaudeInput::from_json(s);
+    let _ = validate_input(s);
+});
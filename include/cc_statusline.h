@@ -0,0 +1,33 @@
+/*
+ * cc-statusline C API
+ *
+ * Build: cargo rustc --release --lib --features ffi --crate-type cdylib
+ */
+#ifndef CC_STATUSLINE_H
+#define CC_STATUSLINE_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * Render the status line for Claude Code's status line JSON.
+ *
+ * options: NULL or a JSON object with optional keys
+ *   "cwd"     directory to inspect for git state
+ *   "profile" profile to merge over the config file
+ *   "config"  inline config used instead of the config file
+ *
+ * Returns ANSI-colored rows separated by newlines, or NULL on invalid input.
+ * The result must be released with cc_statusline_free.
+ */
+char *cc_statusline_render(const char *json_input, const char *options);
+
+/* Free a string returned by cc_statusline_render. NULL is ignored. */
+void cc_statusline_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CC_STATUSLINE_H */
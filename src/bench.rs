@@ -0,0 +1,68 @@
+//! Crate-internal git and cache entry points for `benches/` (bench feature)
+//!
+//! Not a stable API: these wrap private functions so criterion can time them
+//! against generated repos.
+
+use crate::cache::{MmapCache, load_mmap_cache, oid_field, save_mmap_cache};
+use crate::git::{GitRepo, get_ahead_behind, get_git_repo};
+use crate::pr::{PrCacheEntry, PrCacheState, PrInfo, Provider, parse_pr_json};
+
+/// An opened repository
+pub struct Repo(GitRepo);
+
+impl Repo {
+    pub fn open(dir: &str) -> Option<Self> {
+        get_git_repo(dir).map(Self)
+    }
+
+    /// Changed files, and lines added and deleted when `with_lines` is set
+    pub fn diff_stats(&self, with_lines: bool) -> Option<(u32, u32, u32)> {
+        let stats = self.0.diff_stats(with_lines, None)?;
+        Some((stats.files, stats.added, stats.deleted))
+    }
+
+    /// Commits ahead of and behind the branch's upstream
+    pub fn ahead_behind(&self) -> (u32, u32) {
+        get_ahead_behind(&self.0.repo, &self.0.branch)
+    }
+
+    /// Write the stats cache entry for HEAD
+    pub fn save_stats_cache(&self) {
+        let cache = MmapCache {
+            index_mtime: self.0.index_mtime(),
+            head_oid: oid_field(&self.0.head_oid()),
+            files_changed: 12,
+            ..MmapCache::default()
+        };
+        save_mmap_cache(&self.0.git_dir, &self.0.branch, &cache);
+    }
+
+    /// Read the stats cache entry; true if it is valid
+    pub fn load_stats_cache(&self) -> bool {
+        load_mmap_cache(&self.0.git_dir, &self.0.branch).is_some()
+    }
+}
+
+/// Parse `gh pr view --json` output
+pub fn parse_pr(json: &str) -> Option<PrInfo> {
+    parse_pr_json(json)
+}
+
+/// A PR cache file holding `pr`
+pub fn pr_cache_bytes(branch: &str, pr: PrInfo) -> Vec<u8> {
+    PrCacheEntry {
+        branch: branch.to_string(),
+        provider: Some(Provider::Api),
+        fetched_at: 0,
+        attempted_at: 0,
+        etag: Some("\"etag\"".to_string()),
+        state: PrCacheState::Found(pr),
+    }
+    .to_bytes()
+    .unwrap_or_default()
+}
+
+/// Decode a PR cache file; true if it is valid
+pub fn parse_pr_cache(bytes: &[u8]) -> bool {
+    PrCacheEntry::from_bytes(bytes).is_some()
+}
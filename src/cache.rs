@@ -0,0 +1,944 @@
+//! Cache directory and on-disk caches for git state
+
+use crate::{get_home, hash_path};
+use memmap2::{MmapMut, MmapOptions};
+use std::borrow::Cow;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::thread::{self, JoinHandle};
+use std::time::SystemTime;
+
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+static STATE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Seconds between cache directory size checks
+const PRUNE_INTERVAL: u64 = 60 * 60;
+
+/// Get secure per-user cache directory
+/// Uses $XDG_CACHE_HOME/cc-statusline, %LOCALAPPDATA%\cc-statusline on Windows,
+/// or ~/.cache/cc-statusline
+pub(crate) fn get_cache_dir() -> Cow<'static, Path> {
+    #[cfg(feature = "testing")]
+    if let Some(dir) = crate::sys::cache_dir_override() {
+        return Cow::Owned(dir);
+    }
+    Cow::Borrowed(CACHE_DIR.get_or_init(|| {
+        private_dir(
+            app_dir("XDG_CACHE_HOME", &[".cache"], None),
+            "cc-statusline",
+        )
+    }))
+}
+
+/// Get secure per-user directory for state that should outlive a cache wipe,
+/// like notification and idle tracking; hardened like the cache directory
+/// Uses $XDG_STATE_HOME/cc-statusline, %LOCALAPPDATA%\cc-statusline\state on
+/// Windows, or ~/.local/state/cc-statusline
+pub(crate) fn get_state_dir() -> Cow<'static, Path> {
+    #[cfg(feature = "testing")]
+    if let Some(dir) = crate::sys::state_dir_override() {
+        return Cow::Owned(dir);
+    }
+    Cow::Borrowed(STATE_DIR.get_or_init(|| {
+        private_dir(
+            app_dir("XDG_STATE_HOME", &[".local", "state"], Some("state")),
+            "cc-statusline-state",
+        )
+    }))
+}
+
+/// `cc-statusline` under `$<xdg_var>`, under `%LOCALAPPDATA%` on Windows (in
+/// `windows_sub`, if given), or under `home_base` in home; None without a home
+fn app_dir(xdg_var: &str, home_base: &[&str], windows_sub: Option<&str>) -> Option<PathBuf> {
+    if let Some(base) = env::var_os(xdg_var).filter(|b| !b.is_empty()) {
+        return Some(PathBuf::from(base).join("cc-statusline"));
+    }
+    #[cfg(windows)]
+    if let Some(local) = env::var_os("LOCALAPPDATA").filter(|l| !l.is_empty()) {
+        let dir = PathBuf::from(local).join("cc-statusline");
+        return Some(match windows_sub {
+            Some(sub) => dir.join(sub),
+            None => dir,
+        });
+    }
+    #[cfg(not(windows))]
+    let _ = windows_sub;
+    let home = get_home();
+    (!home.is_empty()).then(|| {
+        home_base
+            .iter()
+            .fold(PathBuf::from(&*home), |path, part| path.join(part))
+            .join("cc-statusline")
+    })
+}
+
+/// `dir`, created with mode 0700 and owned by us; otherwise (or without `dir`)
+/// a per-user `temp_name` temp directory
+fn private_dir(dir: Option<PathBuf>, temp_name: &str) -> PathBuf {
+    let dir = dir.unwrap_or_else(|| {
+        // Fallback to system temp dir with user-specific subdirectory
+        // Use std::env::temp_dir() for portability
+        let mut base = env::temp_dir();
+        #[cfg(unix)]
+        let uid = unsafe { libc::getuid() };
+        #[cfg(not(unix))]
+        let uid = std::process::id();
+        base.push(format!("{temp_name}-{uid}"));
+        base.join("cc-statusline")
+    });
+    // Create directory with restricted permissions (0700)
+    let _ = fs::create_dir_all(&dir);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(&dir, fs::Permissions::from_mode(0o700));
+        // Security: verify the directory is owned by us (defense against pre-creation attacks)
+        // If ownership check fails, the directory may have been pre-created by an attacker
+        if let Ok(metadata) = fs::metadata(&dir) {
+            use std::os::unix::fs::MetadataExt;
+            let dir_uid = metadata.uid();
+            let our_uid = unsafe { libc::getuid() };
+            if dir_uid != our_uid {
+                // Directory not owned by us - try a per-user temp directory
+                let mut fallback_dir = env::temp_dir();
+                fallback_dir.push(format!("{temp_name}-{our_uid}"));
+                let _ = fs::create_dir_all(&fallback_dir);
+                let _ = fs::set_permissions(&fallback_dir, fs::Permissions::from_mode(0o700));
+
+                // Verify the fallback is owned by us
+                if let Ok(fb_meta) = fs::metadata(&fallback_dir)
+                    && fb_meta.is_dir()
+                    && fb_meta.uid() == our_uid
+                {
+                    return fallback_dir;
+                }
+                // If no safe directory can be created, disable caching
+                // Use a path that will fail gracefully on file operations
+                return PathBuf::from("/dev/null");
+            }
+        }
+    }
+    dir
+}
+
+/// Rename that atomically replaces the destination, so readers see the old
+/// file or the new one and never a missing path.
+///
+/// On Unix this is `rename(2)`. On Windows it is `MoveFileExW` with
+/// `MOVEFILE_REPLACE_EXISTING`, which replaces an existing file in one step.
+#[cfg(not(windows))]
+pub(crate) fn atomic_rename(from: &Path, to: &Path) -> io::Result<()> {
+    fs::rename(from, to)
+}
+
+#[cfg(windows)]
+pub(crate) fn atomic_rename(from: &Path, to: &Path) -> io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+
+    const MOVEFILE_REPLACE_EXISTING: u32 = 0x1;
+    const MOVEFILE_WRITE_THROUGH: u32 = 0x8;
+    unsafe extern "system" {
+        fn MoveFileExW(existing: *const u16, new: *const u16, flags: u32) -> i32;
+    }
+
+    let wide = |path: &Path| -> Vec<u16> {
+        path.as_os_str()
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect()
+    };
+    let (from, to) = (wide(from), wide(to));
+    let flags = MOVEFILE_REPLACE_EXISTING | MOVEFILE_WRITE_THROUGH;
+    if unsafe { MoveFileExW(from.as_ptr(), to.as_ptr(), flags) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Generate a unique hex string for temp file names
+/// Uses timestamp + pid + atomic counter to avoid collisions within same process
+pub(crate) fn unique_hex() -> String {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    #[allow(clippy::cast_possible_truncation)] // Truncation is fine for uniqueness
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    let pid = std::process::id();
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{nanos:016x}{pid:08x}{count:04x}")
+}
+
+/// File holding the UNIX time of the last size check
+fn get_prune_stamp_path() -> PathBuf {
+    get_cache_dir().join("prune.stamp")
+}
+
+/// Check the cache directory against `max_bytes` on the returned thread, at most
+/// once every `PRUNE_INTERVAL`; nothing is checked when `max_bytes` is 0
+pub(crate) fn prune_if_due(max_bytes: u64) -> Option<JoinHandle<()>> {
+    if max_bytes == 0 {
+        return None;
+    }
+    let stamp = get_prune_stamp_path();
+    let now = crate::sys::now_secs();
+    let last: u64 = fs::read_to_string(&stamp)
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0);
+    if now.saturating_sub(last) < PRUNE_INTERVAL {
+        return None;
+    }
+    // Stamp first so concurrent renders don't all walk the directory
+    let temp_path = get_cache_dir().join(format!("prune-tmp-{}", unique_hex()));
+    if fs::write(&temp_path, now.to_string()).is_err() || atomic_rename(&temp_path, &stamp).is_err()
+    {
+        return None;
+    }
+    let dir = get_cache_dir().into_owned();
+    Some(thread::spawn(move || prune_dir(&dir, max_bytes, &stamp)))
+}
+
+/// Remove the least recently used files in `dir`, by access or modification time,
+/// until the rest total at most `max_bytes`; `keep` is never removed
+fn prune_dir(dir: &Path, max_bytes: u64, keep: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut files: Vec<(SystemTime, u64, PathBuf)> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let meta = entry.metadata().ok()?;
+            if !meta.is_file() || entry.path() == keep {
+                return None;
+            }
+            let used = meta.accessed().ok().max(meta.modified().ok())?;
+            Some((used, meta.len(), entry.path()))
+        })
+        .collect();
+    let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+    files.sort();
+    for (_, len, path) in files {
+        if total <= max_bytes {
+            break;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total -= len;
+        }
+    }
+}
+
+/// Binary cache format for mmap (fixed 256 bytes)
+///
+/// Version 3 appends repo-wide fields after the version 2 layout; version 1
+/// and 2 files (128 bytes) still load, with `extended` false, and are
+/// rewritten as version 4 on the next save. Fields added later take the zeroed
+/// spare bytes, so older version 3 files read them as 0.
+/// Version 4 appends the last 24 bytes of SHA-256 oids; version 3 files
+/// (192 bytes) still load, with room for SHA-1 oids only.
+const CACHE_SIZE: usize = 256;
+const CACHE_MAGIC: &[u8; 4] = b"CCST";
+const CACHE_VERSION: u32 = 4;
+const CACHE_VERSION_V3: u32 = 3;
+const CACHE_SIZE_V3: usize = 192;
+/// Version 2 added `full`, `computed_at`, and a checksum
+const CACHE_VERSION_V2: u32 = 2;
+const CACHE_SIZE_V2: usize = 128;
+/// Version 1 stored the stats through `behind`, one file per repo
+const CACHE_VERSION_V1: u32 = 1;
+const CACHE_SIZE_V1: usize = 128;
+
+/// Bytes of a hex oid field: long enough for SHA-256
+pub(crate) const OID_FIELD_LEN: usize = 64;
+/// Part of an oid field stored in the version 3 layout, enough for SHA-1
+const OID_FIELD_V3: usize = 40;
+
+/// Hex `oid` as a zero-padded cache field, truncated if longer
+pub(crate) fn oid_field(oid: &str) -> [u8; OID_FIELD_LEN] {
+    let bytes = oid.as_bytes();
+    let len = bytes.len().min(OID_FIELD_LEN);
+    let mut field = [0u8; OID_FIELD_LEN];
+    field[..len].copy_from_slice(&bytes[..len]);
+    field
+}
+
+/// Offset of the CRC32 of the bytes before it: the last 4 bytes of each layout
+const fn checksum_offset(size: usize) -> usize {
+    size - 4
+}
+
+/// CRC-32 (IEEE), bitwise; the cache is small enough that a table isn't worth it
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &b| {
+        (0..8).fold(crc ^ u32::from(b), |c, _| {
+            (c >> 1) ^ (0xEDB8_8320 & (c & 1).wrapping_neg())
+        })
+    })
+}
+
+/// Operation in progress in the repository, as stored in the cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum RepoState {
+    #[default]
+    Clean,
+    Merge,
+    Rebase,
+    CherryPick,
+    Revert,
+    Bisect,
+    /// `git am`
+    Apply,
+}
+
+impl RepoState {
+    fn from_byte(b: u8) -> Self {
+        match b {
+            1 => Self::Merge,
+            2 => Self::Rebase,
+            3 => Self::CherryPick,
+            4 => Self::Revert,
+            5 => Self::Bisect,
+            6 => Self::Apply,
+            _ => Self::Clean,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            Self::Clean => 0,
+            Self::Merge => 1,
+            Self::Rebase => 2,
+            Self::CherryPick => 3,
+            Self::Revert => 4,
+            Self::Bisect => 5,
+            Self::Apply => 6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MmapCache {
+    pub(crate) index_mtime: u64,
+    pub(crate) head_oid: [u8; OID_FIELD_LEN],
+    pub(crate) files_changed: u32,
+    pub(crate) lines_added: u32,
+    pub(crate) lines_deleted: u32,
+    pub(crate) ahead: u32,
+    pub(crate) behind: u32,
+    /// Whether line counts were computed (full git mode)
+    pub(crate) full: bool,
+    /// Unix seconds when the stats were computed
+    pub(crate) computed_at: u64,
+    /// Whether the fields below were recorded; false for migrated version 1
+    /// and 2 files, whose stats lack them and have to be recomputed
+    pub(crate) extended: bool,
+    pub(crate) stash_count: u32,
+    pub(crate) staged: u32,
+    pub(crate) untracked: u32,
+    /// Upstream tip when the stats were computed, empty without an upstream
+    pub(crate) upstream_oid: [u8; OID_FIELD_LEN],
+    pub(crate) repo_state: RepoState,
+    /// Unix seconds of the HEAD commit
+    pub(crate) last_commit_at: u64,
+    /// Paths with unmerged index entries
+    pub(crate) conflicts: u32,
+    /// Index entries and milliseconds the last working-tree scan took
+    pub(crate) index_entries: u32,
+    pub(crate) scan_ms: u32,
+    /// The scan stopped at the `max_files` limit
+    pub(crate) capped: bool,
+    /// Unix seconds the untracked count was taken, which can be after `computed_at`
+    pub(crate) untracked_at: u64,
+    /// Fsmonitor answer or directory mtimes the untracked count was taken
+    /// against; 0 if unknown
+    pub(crate) untracked_key: u64,
+}
+
+impl Default for MmapCache {
+    fn default() -> Self {
+        Self {
+            index_mtime: 0,
+            head_oid: [0u8; OID_FIELD_LEN],
+            files_changed: 0,
+            lines_added: 0,
+            lines_deleted: 0,
+            ahead: 0,
+            behind: 0,
+            full: false,
+            computed_at: 0,
+            extended: false,
+            stash_count: 0,
+            staged: 0,
+            untracked: 0,
+            upstream_oid: [0u8; OID_FIELD_LEN],
+            repo_state: RepoState::Clean,
+            last_commit_at: 0,
+            conflicts: 0,
+            index_entries: 0,
+            scan_ms: 0,
+            capped: false,
+            untracked_at: 0,
+            untracked_key: 0,
+        }
+    }
+}
+
+impl MmapCache {
+    fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 || &data[0..4] != CACHE_MAGIC {
+            return None;
+        }
+        let version = u32::from_le_bytes(data[4..8].try_into().ok()?);
+        let size = match version {
+            CACHE_VERSION => CACHE_SIZE,
+            CACHE_VERSION_V3 => CACHE_SIZE_V3,
+            CACHE_VERSION_V2 => CACHE_SIZE_V2,
+            CACHE_VERSION_V1 => CACHE_SIZE_V1,
+            _ => return None,
+        };
+        if data.len() < size {
+            return None;
+        }
+        // A torn write or bit rot is a cache miss, never garbage stats
+        if version != CACHE_VERSION_V1 {
+            let sum_at = checksum_offset(size);
+            let checksum = u32::from_le_bytes(data[sum_at..size].try_into().ok()?);
+            if checksum != crc32(&data[..sum_at]) {
+                return None;
+            }
+        }
+
+        let mut head_oid = [0u8; OID_FIELD_LEN];
+        head_oid[..OID_FIELD_V3].copy_from_slice(&data[16..56]);
+        let mut cache = MmapCache {
+            index_mtime: u64::from_le_bytes(data[8..16].try_into().ok()?),
+            head_oid,
+            files_changed: u32::from_le_bytes(data[56..60].try_into().ok()?),
+            lines_added: u32::from_le_bytes(data[60..64].try_into().ok()?),
+            lines_deleted: u32::from_le_bytes(data[64..68].try_into().ok()?),
+            ahead: u32::from_le_bytes(data[68..72].try_into().ok()?),
+            behind: u32::from_le_bytes(data[72..76].try_into().ok()?),
+            ..Default::default()
+        };
+        if version != CACHE_VERSION_V1 {
+            cache.full = data[76] != 0;
+            cache.computed_at = u64::from_le_bytes(data[80..88].try_into().ok()?);
+        }
+        if matches!(version, CACHE_VERSION | CACHE_VERSION_V3) {
+            cache.extended = true;
+            cache.repo_state = RepoState::from_byte(data[77]);
+            cache.capped = data[78] != 0;
+            cache.stash_count = u32::from_le_bytes(data[88..92].try_into().ok()?);
+            cache.staged = u32::from_le_bytes(data[92..96].try_into().ok()?);
+            cache.untracked = u32::from_le_bytes(data[96..100].try_into().ok()?);
+            cache.upstream_oid[..OID_FIELD_V3].copy_from_slice(&data[100..140]);
+            cache.last_commit_at = u64::from_le_bytes(data[144..152].try_into().ok()?);
+            cache.conflicts = u32::from_le_bytes(data[152..156].try_into().ok()?);
+            cache.index_entries = u32::from_le_bytes(data[156..160].try_into().ok()?);
+            cache.scan_ms = u32::from_le_bytes(data[160..164].try_into().ok()?);
+            cache.untracked_at = u64::from_le_bytes(data[164..172].try_into().ok()?);
+            cache.untracked_key = u64::from_le_bytes(data[172..180].try_into().ok()?);
+        }
+        if version == CACHE_VERSION {
+            cache.head_oid[OID_FIELD_V3..].copy_from_slice(&data[180..204]);
+            cache.upstream_oid[OID_FIELD_V3..].copy_from_slice(&data[204..228]);
+        }
+        Some(cache)
+    }
+
+    fn to_bytes(&self, buf: &mut [u8]) {
+        buf[0..4].copy_from_slice(CACHE_MAGIC);
+        buf[4..8].copy_from_slice(&CACHE_VERSION.to_le_bytes());
+        buf[8..16].copy_from_slice(&self.index_mtime.to_le_bytes());
+        buf[16..56].copy_from_slice(&self.head_oid[..OID_FIELD_V3]);
+        buf[56..60].copy_from_slice(&self.files_changed.to_le_bytes());
+        buf[60..64].copy_from_slice(&self.lines_added.to_le_bytes());
+        buf[64..68].copy_from_slice(&self.lines_deleted.to_le_bytes());
+        buf[68..72].copy_from_slice(&self.ahead.to_le_bytes());
+        buf[72..76].copy_from_slice(&self.behind.to_le_bytes());
+        buf[76] = u8::from(self.full);
+        buf[77] = self.repo_state.to_byte();
+        buf[78] = u8::from(self.capped);
+        buf[80..88].copy_from_slice(&self.computed_at.to_le_bytes());
+        buf[88..92].copy_from_slice(&self.stash_count.to_le_bytes());
+        buf[92..96].copy_from_slice(&self.staged.to_le_bytes());
+        buf[96..100].copy_from_slice(&self.untracked.to_le_bytes());
+        buf[100..140].copy_from_slice(&self.upstream_oid[..OID_FIELD_V3]);
+        buf[144..152].copy_from_slice(&self.last_commit_at.to_le_bytes());
+        buf[152..156].copy_from_slice(&self.conflicts.to_le_bytes());
+        buf[156..160].copy_from_slice(&self.index_entries.to_le_bytes());
+        buf[160..164].copy_from_slice(&self.scan_ms.to_le_bytes());
+        buf[164..172].copy_from_slice(&self.untracked_at.to_le_bytes());
+        buf[172..180].copy_from_slice(&self.untracked_key.to_le_bytes());
+        buf[180..204].copy_from_slice(&self.head_oid[OID_FIELD_V3..]);
+        buf[204..228].copy_from_slice(&self.upstream_oid[OID_FIELD_V3..]);
+        let sum_at = checksum_offset(CACHE_SIZE);
+        let checksum = crc32(&buf[..sum_at]);
+        buf[sum_at..CACHE_SIZE].copy_from_slice(&checksum.to_le_bytes());
+    }
+
+    /// The entry was computed at `oid`, a SHA-1 or SHA-256 hex id or a prefix of one
+    pub(crate) fn head_oid_matches(&self, oid: &str) -> bool {
+        let oid_bytes = oid.as_bytes();
+        oid_bytes.len() <= OID_FIELD_LEN && self.head_oid[..oid_bytes.len()] == *oid_bytes
+    }
+}
+
+/// Stats are kept per branch, so switching branches without touching the
+/// index never serves the other branch's entry
+fn get_cache_path(git_dir: &str, branch: &str) -> PathBuf {
+    get_cache_dir().join(format!(
+        "status-{:016x}-{:016x}.cache",
+        hash_path(git_dir),
+        hash_path(branch)
+    ))
+}
+
+/// Where version 1 kept the stats, one file per repo
+fn get_v1_cache_path(git_dir: &str) -> PathBuf {
+    get_cache_dir().join(format!("status-{:016x}.cache", hash_path(git_dir)))
+}
+
+/// The branch's entry, or the repo's version 1 entry until the branch has one
+pub(crate) fn load_mmap_cache(git_dir: &str, branch: &str) -> Option<MmapCache> {
+    read_mmap_cache(&get_cache_path(git_dir, branch))
+        .or_else(|| read_mmap_cache(&get_v1_cache_path(git_dir)))
+}
+
+fn read_mmap_cache(path: &Path) -> Option<MmapCache> {
+    let file = OpenOptions::new().read(true).open(path).ok()?;
+    let mmap = unsafe { MmapOptions::new().map(&file).ok()? };
+    MmapCache::from_bytes(&mmap)
+}
+
+pub(crate) fn save_mmap_cache(git_dir: &str, branch: &str, cache: &MmapCache) {
+    let cache_path = get_cache_path(git_dir, branch);
+    // Atomic write: write to temp file, then rename
+    let temp_path = get_cache_dir().join(format!("status-tmp-{}.cache", unique_hex()));
+
+    let Ok(file) = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&temp_path)
+    else {
+        return;
+    };
+    if file.set_len(CACHE_SIZE as u64).is_err() {
+        let _ = fs::remove_file(&temp_path);
+        return;
+    }
+    let Ok(mut mmap) = (unsafe { MmapMut::map_mut(&file) }) else {
+        let _ = fs::remove_file(&temp_path);
+        return;
+    };
+    cache.to_bytes(&mut mmap);
+    if mmap.flush().is_err() {
+        let _ = fs::remove_file(&temp_path);
+        return;
+    }
+    drop(mmap);
+    drop(file);
+    let _ = atomic_rename(&temp_path, &cache_path);
+}
+
+pub(crate) struct GitPathCache {
+    pub(crate) git_path: String,
+    pub(crate) branch: String,
+}
+
+fn get_head_mtime(git_path: &str) -> u64 {
+    let head_path = format!("{}/HEAD", git_path.trim_end_matches('/'));
+    fs::metadata(&head_path)
+        .and_then(|m| m.modified())
+        .map(|t| {
+            t.duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+        })
+        .unwrap_or(0)
+}
+
+pub(crate) fn get_cached_git_info(working_dir: &str) -> Option<GitPathCache> {
+    let cache_path = get_cache_dir().join(format!("gitpath-{:016x}.cache", hash_path(working_dir)));
+    let content = fs::read_to_string(&cache_path).ok()?;
+    let mut lines = content.lines();
+
+    let git_path = lines.next()?.to_string();
+    let branch = lines.next()?.to_string();
+    let cached_mtime: u64 = lines.next()?.parse().ok()?;
+
+    if !Path::new(&git_path).exists() {
+        let _ = fs::remove_file(&cache_path);
+        return None;
+    }
+
+    let current_mtime = get_head_mtime(&git_path);
+    if current_mtime != cached_mtime {
+        return None;
+    }
+
+    Some(GitPathCache { git_path, branch })
+}
+
+pub(crate) fn cache_git_info(working_dir: &str, git_path: &str, branch: &str) {
+    let cache_path = get_cache_dir().join(format!("gitpath-{:016x}.cache", hash_path(working_dir)));
+    let head_mtime = get_head_mtime(git_path);
+    let content = format!("{git_path}\n{branch}\n{head_mtime}");
+    // Atomic write (Windows-compatible): write to temp, then rename
+    let temp_path = get_cache_dir().join(format!("gitpath-tmp-{}.cache", unique_hex()));
+    if fs::write(&temp_path, &content).is_ok() {
+        let _ = atomic_rename(&temp_path, &cache_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // =========================================================================
+    // MmapCache tests
+    // =========================================================================
+
+    #[test]
+    fn atomic_rename_replaces_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("new"), dir.path().join("cache"));
+        fs::write(&to, "old").unwrap();
+        fs::write(&from, "new").unwrap();
+        atomic_rename(&from, &to).unwrap();
+        assert_eq!(fs::read_to_string(&to).unwrap(), "new");
+        assert!(!from.exists());
+        assert!(atomic_rename(&from, &to).is_err());
+    }
+
+    #[test]
+    fn prune_evicts_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let keep = dir.path().join("prune.stamp");
+        fs::write(&keep, "0").unwrap();
+        for (name, age) in [("old", 300), ("mid", 200), ("new", 100)] {
+            let path = dir.path().join(name);
+            fs::write(&path, [0u8; 100]).unwrap();
+            let used = SystemTime::now() - std::time::Duration::from_secs(age);
+            let times = fs::FileTimes::new().set_accessed(used).set_modified(used);
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_times(times)
+                .unwrap();
+        }
+
+        prune_dir(dir.path(), 250, &keep);
+        assert!(!dir.path().join("old").exists());
+        assert!(dir.path().join("mid").exists() && dir.path().join("new").exists());
+
+        prune_dir(dir.path(), 0, &keep);
+        assert!(!dir.path().join("new").exists());
+        assert!(keep.exists());
+    }
+
+    #[test]
+    fn cache_round_trip() {
+        let original = MmapCache {
+            index_mtime: 1234567890,
+            head_oid: oid_field(&"abc123def4567890".repeat(4)),
+            files_changed: 42,
+            lines_added: 100,
+            lines_deleted: 50,
+            ahead: 3,
+            behind: 5,
+            full: true,
+            computed_at: 1700000000,
+            extended: true,
+            stash_count: 2,
+            staged: 4,
+            untracked: 6,
+            upstream_oid: oid_field("fedcba987654fedcba987654fedcba9876543210"),
+            repo_state: RepoState::Rebase,
+            last_commit_at: 1699999000,
+            conflicts: 1,
+            index_entries: 182_000,
+            scan_ms: 14,
+            capped: true,
+            untracked_at: 1700000003,
+            untracked_key: 0xfeed,
+        };
+
+        let mut buf = [0u8; CACHE_SIZE];
+        original.to_bytes(&mut buf);
+
+        let loaded = MmapCache::from_bytes(&buf).expect("should parse");
+        assert_eq!(loaded.index_mtime, original.index_mtime);
+        assert_eq!(loaded.head_oid, original.head_oid);
+        assert_eq!(loaded.files_changed, original.files_changed);
+        assert_eq!(loaded.lines_added, original.lines_added);
+        assert_eq!(loaded.lines_deleted, original.lines_deleted);
+        assert_eq!(loaded.ahead, original.ahead);
+        assert_eq!(loaded.behind, original.behind);
+        assert_eq!(loaded.full, original.full);
+        assert_eq!(loaded.computed_at, original.computed_at);
+        assert_eq!(loaded, original);
+        assert!(loaded.head_oid_matches(&"abc123def4567890".repeat(4)));
+    }
+
+    #[test]
+    fn sha256_head_hits_the_stats_cache() {
+        let oid = "ebf303899c2ca0c3c5a902827da91704f1d60b9cba560d8555ae9a4601c4a843";
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("status.cache");
+        let cache = MmapCache {
+            head_oid: oid_field(oid),
+            upstream_oid: oid_field(oid),
+            files_changed: 5,
+            ..MmapCache::default()
+        };
+        let mut buf = [0u8; CACHE_SIZE];
+        cache.to_bytes(&mut buf);
+        fs::write(&path, buf).unwrap();
+
+        let loaded = read_mmap_cache(&path).expect("cache should load");
+        assert!(loaded.head_oid_matches(oid));
+        assert!(!loaded.head_oid_matches(&oid.replace('e', "f")));
+        assert_eq!(loaded.upstream_oid, oid_field(oid));
+        assert_eq!(loaded.files_changed, 5);
+    }
+
+    #[test]
+    fn cache_reads_v3_layout() {
+        // Version 3: the version 4 fields without the SHA-256 oid tails, 192 bytes
+        let mut buf = [0u8; CACHE_SIZE_V3];
+        buf[0..4].copy_from_slice(CACHE_MAGIC);
+        buf[4..8].copy_from_slice(&CACHE_VERSION_V3.to_le_bytes());
+        buf[16..56].copy_from_slice(b"abc123def456abc123def456abc123def4567890");
+        buf[56..60].copy_from_slice(&9u32.to_le_bytes());
+        buf[96..100].copy_from_slice(&3u32.to_le_bytes());
+        buf[172..180].copy_from_slice(&0xfeedu64.to_le_bytes());
+        let sum_at = checksum_offset(CACHE_SIZE_V3);
+        let checksum = crc32(&buf[..sum_at]);
+        buf[sum_at..].copy_from_slice(&checksum.to_le_bytes());
+
+        let loaded = MmapCache::from_bytes(&buf).expect("v3 should load");
+        assert!(loaded.extended);
+        assert!(loaded.head_oid_matches("abc123def456abc123def456abc123def4567890"));
+        assert_eq!(loaded.files_changed, 9);
+        assert_eq!(loaded.untracked, 3);
+        assert_eq!(loaded.untracked_key, 0xfeed);
+    }
+
+    #[test]
+    fn cache_reads_v2_layout() {
+        // Version 2: same fields up to computed_at, 128 bytes, checksum last
+        let mut buf = [0u8; CACHE_SIZE_V2];
+        buf[0..4].copy_from_slice(CACHE_MAGIC);
+        buf[4..8].copy_from_slice(&CACHE_VERSION_V2.to_le_bytes());
+        buf[8..16].copy_from_slice(&77u64.to_le_bytes());
+        buf[56..60].copy_from_slice(&9u32.to_le_bytes());
+        buf[76] = 1;
+        let sum_at = checksum_offset(CACHE_SIZE_V2);
+        let checksum = crc32(&buf[..sum_at]);
+        buf[sum_at..].copy_from_slice(&checksum.to_le_bytes());
+
+        let loaded = MmapCache::from_bytes(&buf).expect("v2 should load");
+        assert_eq!(loaded.index_mtime, 77);
+        assert_eq!(loaded.files_changed, 9);
+        assert!(loaded.full);
+        assert!(!loaded.extended);
+        assert_eq!(loaded.repo_state, RepoState::Clean);
+
+        // A v3 header on a v2-sized file is truncated, not misread
+        buf[4..8].copy_from_slice(&CACHE_VERSION.to_le_bytes());
+        assert!(MmapCache::from_bytes(&buf).is_none());
+    }
+
+    #[test]
+    fn cache_reads_v1_layout() {
+        // Version 1: stats through `behind`, 128 bytes, no checksum
+        let mut buf = [0u8; CACHE_SIZE_V1];
+        buf[0..4].copy_from_slice(CACHE_MAGIC);
+        buf[4..8].copy_from_slice(&CACHE_VERSION_V1.to_le_bytes());
+        buf[8..16].copy_from_slice(&77u64.to_le_bytes());
+        buf[16..56].copy_from_slice(b"abc123def456abc123def456abc123def4567890");
+        buf[56..60].copy_from_slice(&9u32.to_le_bytes());
+        buf[68..72].copy_from_slice(&2u32.to_le_bytes());
+        buf[76] = 0xff;
+        buf[124..128].copy_from_slice(&[0xff; 4]);
+
+        let loaded = MmapCache::from_bytes(&buf).expect("v1 should load");
+        assert_eq!(loaded.index_mtime, 77);
+        assert!(loaded.head_oid_matches("abc123def456abc123def456abc123def4567890"));
+        assert_eq!(loaded.files_changed, 9);
+        assert_eq!(loaded.ahead, 2);
+        assert!(!loaded.full);
+        assert_eq!(loaded.computed_at, 0);
+        assert!(!loaded.extended);
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn load_falls_back_to_v1_file() {
+        let _sandbox = crate::testing::Sandbox::new();
+        let mut buf = [0u8; CACHE_SIZE_V1];
+        buf[0..4].copy_from_slice(CACHE_MAGIC);
+        buf[4..8].copy_from_slice(&CACHE_VERSION_V1.to_le_bytes());
+        buf[56..60].copy_from_slice(&5u32.to_le_bytes());
+        fs::write(get_v1_cache_path("/repo/.git"), buf).unwrap();
+
+        let loaded = load_mmap_cache("/repo/.git", "main").expect("v1 entry");
+        assert_eq!(loaded.files_changed, 5);
+        assert!(!loaded.extended);
+
+        let cache = MmapCache {
+            files_changed: 6,
+            extended: true,
+            ..Default::default()
+        };
+        save_mmap_cache("/repo/.git", "main", &cache);
+        assert_eq!(load_mmap_cache("/repo/.git", "main"), Some(cache));
+    }
+
+    #[test]
+    fn cache_invalid_magic() {
+        let mut buf = [0u8; CACHE_SIZE];
+        buf[0..4].copy_from_slice(b"XXXX"); // Wrong magic
+        assert!(MmapCache::from_bytes(&buf).is_none());
+    }
+
+    #[test]
+    fn cache_wrong_version() {
+        let mut buf = [0u8; CACHE_SIZE];
+        buf[0..4].copy_from_slice(CACHE_MAGIC);
+        buf[4..8].copy_from_slice(&99u32.to_le_bytes()); // Wrong version
+        assert!(MmapCache::from_bytes(&buf).is_none());
+    }
+
+    #[test]
+    fn cache_checksum_mismatch() {
+        let mut buf = [0u8; CACHE_SIZE];
+        MmapCache {
+            files_changed: 7,
+            ..Default::default()
+        }
+        .to_bytes(&mut buf);
+        assert!(MmapCache::from_bytes(&buf).is_some());
+
+        // Any flipped bit, in the payload or the checksum itself, is rejected
+        for i in [8, 56, 100, checksum_offset(CACHE_SIZE)] {
+            let mut corrupt = buf;
+            corrupt[i] ^= 0x01;
+            assert!(MmapCache::from_bytes(&corrupt).is_none(), "byte {i}");
+        }
+    }
+
+    #[test]
+    fn crc32_known_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn cache_truncated() {
+        let buf = [0u8; 10]; // Too small
+        assert!(MmapCache::from_bytes(&buf).is_none());
+    }
+
+    #[test]
+    fn cache_path_per_branch() {
+        let main = get_cache_path("/repo/.git", "main");
+        assert_eq!(main, get_cache_path("/repo/.git", "main"));
+        assert_ne!(main, get_cache_path("/repo/.git", "feature"));
+        assert_ne!(main, get_cache_path("/other/.git", "main"));
+    }
+
+    #[test]
+    fn cache_head_oid_matches_prefix() {
+        let cache = MmapCache {
+            head_oid: oid_field("abc123def456abc123def456abc123def4567890"),
+            ..Default::default()
+        };
+
+        // Full match
+        assert!(cache.head_oid_matches("abc123def456abc123def456abc123def4567890"));
+        // Prefix match (short oid)
+        assert!(cache.head_oid_matches("abc123"));
+        assert!(cache.head_oid_matches("abc123def456"));
+        // No match
+        assert!(!cache.head_oid_matches("xyz"));
+        assert!(!cache.head_oid_matches("abc124")); // Different character
+    }
+
+    #[test]
+    fn cache_head_oid_empty_matches() {
+        let cache = MmapCache::default();
+        // Empty oid should match empty string
+        assert!(cache.head_oid_matches(""));
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn cache_arbitrary_bytes_never_panic(data in proptest::collection::vec(proptest::num::u8::ANY, 0..256)) {
+            let _ = MmapCache::from_bytes(&data);
+        }
+
+        #[test]
+        fn cache_corrupted_header_bytes_never_panic(
+            body in proptest::collection::vec(proptest::num::u8::ANY, CACHE_SIZE..CACHE_SIZE + 8),
+        ) {
+            let mut data = body;
+            data[0..4].copy_from_slice(CACHE_MAGIC);
+            data[4..8].copy_from_slice(&CACHE_VERSION.to_le_bytes());
+            let _ = MmapCache::from_bytes(&data);
+        }
+
+        #[test]
+        fn cache_round_trips(
+            index_mtime: u64,
+            oid in "[0-9a-f]{40}|[0-9a-f]{64}",
+            counts: (u32, u32, u32, u32, u32),
+            full: bool,
+            computed_at: u64,
+            extra: (u32, u32, u32, u64, u32),
+            index: (u32, u32),
+            capped: bool,
+            state in 0u8..7,
+            untracked_check: (u64, u64),
+        ) {
+            let head_oid = oid_field(&oid);
+            let original = MmapCache {
+                index_mtime,
+                head_oid,
+                files_changed: counts.0,
+                lines_added: counts.1,
+                lines_deleted: counts.2,
+                ahead: counts.3,
+                behind: counts.4,
+                full,
+                computed_at,
+                extended: true,
+                stash_count: extra.0,
+                staged: extra.1,
+                untracked: extra.2,
+                upstream_oid: head_oid,
+                repo_state: RepoState::from_byte(state),
+                last_commit_at: extra.3,
+                conflicts: extra.4,
+                index_entries: index.0,
+                scan_ms: index.1,
+                capped,
+                untracked_at: untracked_check.0,
+                untracked_key: untracked_check.1,
+            };
+            let mut buf = [0u8; CACHE_SIZE];
+            original.to_bytes(&mut buf);
+            proptest::prop_assert_eq!(MmapCache::from_bytes(&buf), Some(original));
+        }
+    }
+}
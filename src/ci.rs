@@ -0,0 +1,248 @@
+//! Build status from CI services other than GitHub Actions
+//!
+//! The latest build of the branch is cached like the repo-level data and
+//! shown by `pr_checks` in place of the PR's GitHub check status.
+
+use crate::cache::get_cache_dir;
+use crate::config::{CiConfig, CiProvider};
+use crate::repo_status::cached;
+use crate::sys::{self, TlsOptions};
+use crate::{base64, display_bytes, hash_path, percent_encode, percent_encode_bytes};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+const CI_TTL: u64 = 60; // seconds
+
+/// Latest build of a branch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CiStatus {
+    /// "passed", "failed", or "pending", as for GitHub checks
+    pub(crate) status: String,
+    /// Web page of the build, if the service reports one
+    pub(crate) url: Option<String>,
+}
+
+impl CiStatus {
+    /// Cache value: status, then the URL after a space
+    fn parse(value: &str) -> Option<CiStatus> {
+        let (status, url) = value.split_once(' ').unwrap_or((value, ""));
+        Some(CiStatus {
+            status: status.to_string(),
+            url: Some(url.to_string()).filter(|u| !u.is_empty()),
+        })
+        .filter(|s| !s.status.is_empty())
+    }
+}
+
+impl CiProvider {
+    fn default_api(self) -> Option<&'static str> {
+        match self {
+            CiProvider::CircleCi => Some("https://circleci.com/api/v2"),
+            CiProvider::Buildkite => Some("https://api.buildkite.com/v2"),
+            CiProvider::Jenkins => None,
+        }
+    }
+
+    fn default_token_env(self) -> &'static str {
+        match self {
+            CiProvider::CircleCi => "CIRCLE_TOKEN",
+            CiProvider::Buildkite => "BUILDKITE_API_TOKEN",
+            CiProvider::Jenkins => "JENKINS_TOKEN",
+        }
+    }
+}
+
+/// Cached status of the latest build of `branch`, refreshed on the returned thread
+pub(crate) fn build_status(
+    ci: &CiConfig,
+    branch: &[u8],
+    offline: bool,
+    tls: &TlsOptions,
+) -> (Option<CiStatus>, Option<JoinHandle<()>>) {
+    let Some(api) = ci
+        .api
+        .as_deref()
+        .or(ci.provider.default_api())
+        .map(|a| a.trim_end_matches('/').to_string())
+    else {
+        return (None, None);
+    };
+    let token = sys::var(
+        ci.token_env
+            .as_deref()
+            .unwrap_or(ci.provider.default_token_env()),
+    );
+    let key = format!(
+        "{:?}/{api}/{}/{}",
+        ci.provider,
+        ci.pipeline,
+        display_bytes(branch)
+    );
+    let path = get_cache_dir().join(format!("ci-{:016x}.cache", hash_path(&key)));
+    let (provider, pipeline, branch, tls) = (
+        ci.provider,
+        ci.pipeline.clone(),
+        branch.to_vec(),
+        tls.clone(),
+    );
+    let (value, refresh) = cached(path, CI_TTL, offline, move || {
+        let client = Client { api, token, tls };
+        let status = match provider {
+            CiProvider::CircleCi => client.circleci(&pipeline, &branch),
+            CiProvider::Buildkite => client.buildkite(&pipeline, &branch),
+            CiProvider::Jenkins => client.jenkins(&pipeline, &branch),
+        };
+        status.map(|s| format!("{} {}", s.status, s.url.unwrap_or_default()))
+    });
+    (value.as_deref().and_then(CiStatus::parse), refresh)
+}
+
+struct Client {
+    api: String,
+    token: Option<String>,
+    tls: TlsOptions,
+}
+
+impl Client {
+    fn get(&self, path: &str, auth: Option<(&str, String)>) -> Option<serde_json::Value> {
+        let mut headers = vec![
+            ("Accept", "application/json"),
+            ("User-Agent", "cc-statusline"),
+        ];
+        if let Some((name, value)) = &auth {
+            headers.push((name, value));
+        }
+        let url = format!("{}/{path}", self.api);
+        let body = sys::http_get(&url, &headers, Some(Duration::from_secs(5)), &self.tls).ok()?;
+        serde_json::from_str(&body).ok()
+    }
+
+    /// Workflows of the branch's latest pipeline; `pipeline` is a project slug like "gh/org/repo"
+    fn circleci(&self, pipeline: &str, branch: &[u8]) -> Option<CiStatus> {
+        let auth = || self.token.clone().map(|t| ("Circle-Token", t));
+        let pipelines = self.get(
+            &format!(
+                "project/{pipeline}/pipeline?branch={}",
+                percent_encode_bytes(branch)
+            ),
+            auth(),
+        )?;
+        let latest = pipelines["items"].get(0)?;
+        let id = latest["id"].as_str()?;
+        let workflows = self.get(&format!("pipeline/{id}/workflow"), auth())?;
+        let states: Vec<&str> = workflows["items"]
+            .as_array()?
+            .iter()
+            .filter_map(|w| w["status"].as_str())
+            .collect();
+        let status = if states
+            .iter()
+            .any(|s| matches!(*s, "failed" | "failing" | "error" | "canceled"))
+        {
+            "failed"
+        } else if !states.is_empty() && states.iter().all(|s| *s == "success") {
+            "passed"
+        } else {
+            "pending"
+        };
+        let url = latest["number"].as_u64().and_then(|n| {
+            let (vcs, project) = pipeline.split_once('/')?;
+            let vcs = match vcs {
+                "gh" => "github",
+                "bb" => "bitbucket",
+                other => other,
+            };
+            Some(format!(
+                "https://app.circleci.com/pipelines/{vcs}/{project}/{n}"
+            ))
+        });
+        Some(CiStatus {
+            status: status.to_string(),
+            url,
+        })
+    }
+
+    /// Latest build of the branch; `pipeline` is "org/pipeline"
+    fn buildkite(&self, pipeline: &str, branch: &[u8]) -> Option<CiStatus> {
+        let (org, slug) = pipeline.split_once('/')?;
+        let auth = self
+            .token
+            .clone()
+            .map(|t| ("Authorization", format!("Bearer {t}")));
+        let builds = self.get(
+            &format!(
+                "organizations/{org}/pipelines/{slug}/builds?branch={}&per_page=1",
+                percent_encode_bytes(branch)
+            ),
+            auth,
+        )?;
+        let build = builds.get(0)?;
+        let status = match build["state"].as_str()? {
+            "passed" => "passed",
+            "failed" | "failing" | "canceled" | "canceling" | "timed_out" => "failed",
+            _ => "pending",
+        };
+        Some(CiStatus {
+            status: status.to_string(),
+            url: build["web_url"].as_str().map(str::to_string),
+        })
+    }
+
+    /// Last build of the branch job; `pipeline` is the multibranch job path like
+    /// "team/app", and the token is "user:api-token"
+    fn jenkins(&self, pipeline: &str, branch: &[u8]) -> Option<CiStatus> {
+        let jobs: String = pipeline
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(|s| format!("job/{}/", percent_encode(s)))
+            .collect();
+        let auth = self
+            .token
+            .as_ref()
+            .map(|t| ("Authorization", format!("Basic {}", base64(t.as_bytes()))));
+        let build = self.get(
+            &format!(
+                "{jobs}job/{}/lastBuild/api/json",
+                percent_encode(&percent_encode_bytes(branch))
+            ),
+            auth,
+        )?;
+        let status = if build["building"].as_bool() == Some(true) {
+            "pending"
+        } else {
+            match build["result"].as_str() {
+                Some("SUCCESS") => "passed",
+                Some("FAILURE" | "UNSTABLE" | "ABORTED") => "failed",
+                _ => "pending",
+            }
+        };
+        Some(CiStatus {
+            status: status.to_string(),
+            url: build["url"].as_str().map(str::to_string),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ci_cache_value() {
+        assert_eq!(
+            CiStatus::parse("passed https://ci.example.com/b/1"),
+            Some(CiStatus {
+                status: "passed".to_string(),
+                url: Some("https://ci.example.com/b/1".to_string()),
+            })
+        );
+        assert_eq!(
+            CiStatus::parse("pending "),
+            Some(CiStatus {
+                status: "pending".to_string(),
+                url: None,
+            })
+        );
+        assert_eq!(CiStatus::parse(""), None);
+    }
+}
@@ -0,0 +1,199 @@
+//! Command-line interface definition, shared by parsing, completions, and the man page
+
+use cc_statusline::get_config_path;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use std::io;
+
+/// A lightweight, fast status line for Claude Code CLI
+///
+/// Reads Claude Code's status line JSON from stdin and prints the rendered rows.
+#[derive(Parser)]
+#[command(name = "cc-statusline", version, disable_version_flag = true)]
+pub struct Cli {
+    /// Print version information
+    #[arg(short = 'V', long)]
+    pub version: bool,
+
+    /// With --version, also check GitHub for a newer release
+    #[arg(long, requires = "version")]
+    pub check_latest: bool,
+
+    /// Use a named profile (minimal, full, demo, or from config)
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Create default config file
+    #[arg(long)]
+    pub config_init: bool,
+
+    /// With --config-init, overwrite an existing config file
+    #[arg(long, requires = "config_init")]
+    pub force: bool,
+
+    /// Keep the git stats cache fresh for DIR (default: current directory; watch feature)
+    #[arg(long, value_name = "DIR")]
+    pub watch: Option<Option<String>>,
+
+    /// Check stdin against the Claude Code input schema and report problems
+    #[arg(long)]
+    pub validate_input: bool,
+
+    /// Render each line of stdin as one input, ending each block with an empty line
+    #[arg(long, conflicts_with_all = ["validate_input", "watch"])]
+    pub serve: bool,
+
+    /// With --serve, print only the rows that changed, framed as `@<rows>` and `<row>\t<text>` lines
+    #[arg(long, requires = "serve")]
+    pub diff: bool,
+
+    /// Print the man page (roff) to stdout
+    #[arg(long)]
+    pub man: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Render stdin like the default mode, optionally exported for screenshots
+    Render {
+        /// Convert the ANSI output to a standalone HTML or SVG document
+        #[arg(long, value_enum, value_name = "FORMAT")]
+        export: Option<Export>,
+    },
+    /// Summarize usage recorded with `session_stats`
+    Stats {
+        /// Only today's usage
+        #[arg(long, conflicts_with = "week")]
+        today: bool,
+        /// Only the last 7 days, including today
+        #[arg(long)]
+        week: bool,
+        /// Only sessions in the project directory named NAME
+        #[arg(long, value_name = "NAME")]
+        repo: Option<String>,
+    },
+    /// List projects recorded with `project_history`, most frecent first
+    Recent {
+        /// Print each project's score before its path
+        #[arg(long)]
+        scores: bool,
+    },
+    /// Answer a few questions to write a commented config file
+    Configure {
+        /// Overwrite an existing config file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Inspect the config file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Print shell completions for bash, zsh, fish, elvish, or powershell
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Report invalid values and unknown keys, then print the effective config
+    Check,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Export {
+    Html,
+    Svg,
+}
+
+/// The clap command, with the config path and environment listed in the help footer
+pub fn command() -> clap::Command {
+    Cli::command().after_help(format!(
+        "CONFIG:\n    {}\n\n\
+         ENVIRONMENT:\n    \
+         CC_STATUSLINE_PROFILE   Profile to use when --profile is not given\n    \
+         CC_STATUSLINE_GIT_MODE  Git detail level: full, fast, dirty, or minimal\n    \
+         CC_STATUSLINE_<KEY>     Override any config key; MODULES_<NAME>=false drops a component\n    \
+         CC_STATUSLINE_DEBUG     Log unknown input fields and PR lookup failures to stderr\n    \
+         CC_STATUSLINE_GITHUB_API_URL\n                            GitHub REST API base URL for PR lookups",
+        get_config_path().display()
+    ))
+}
+
+pub fn parse() -> Cli {
+    let matches = command().get_matches();
+    Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())
+}
+
+pub fn write_completions(shell: Shell, out: &mut impl io::Write) {
+    clap_complete::generate(shell, &mut command(), "cc-statusline", out);
+}
+
+pub fn write_man(out: &mut impl io::Write) -> io::Result<()> {
+    clap_mangen::Man::new(command()).render(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cli_definition_is_valid() {
+        command().debug_assert();
+    }
+
+    #[test]
+    fn force_requires_config_init() {
+        assert!(Cli::try_parse_from(["cc-statusline", "--force"]).is_err());
+        let cli = Cli::try_parse_from(["cc-statusline", "--config-init", "--force"]).unwrap();
+        assert!(cli.config_init && cli.force);
+    }
+
+    #[test]
+    fn render_export_format() {
+        let cli = Cli::try_parse_from(["cc-statusline", "render", "--export", "svg"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Render {
+                export: Some(Export::Svg)
+            })
+        ));
+        assert!(Cli::try_parse_from(["cc-statusline", "render", "--export", "png"]).is_err());
+    }
+
+    #[test]
+    fn stats_periods_conflict() {
+        let cli =
+            Cli::try_parse_from(["cc-statusline", "stats", "--week", "--repo", "app"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Stats { today: false, week: true, repo: Some(ref r) }) if r == "app"
+        ));
+        assert!(Cli::try_parse_from(["cc-statusline", "stats", "--today", "--week"]).is_err());
+    }
+
+    #[test]
+    fn config_check_subcommand() {
+        let cli = Cli::try_parse_from(["cc-statusline", "config", "check"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Config {
+                action: ConfigAction::Check
+            })
+        ));
+        assert!(Cli::try_parse_from(["cc-statusline", "config"]).is_err());
+    }
+
+    #[test]
+    fn watch_dir_is_optional() {
+        let cli = Cli::try_parse_from(["cc-statusline", "--watch"]).unwrap();
+        assert_eq!(cli.watch, Some(None));
+        let cli = Cli::try_parse_from(["cc-statusline", "--watch", "/repo"]).unwrap();
+        assert_eq!(cli.watch, Some(Some("/repo".to_string())));
+    }
+}
@@ -0,0 +1,303 @@
+//! Concurrent git and PR data collection with an overall deadline
+//!
+//! Once the repo is discovered, the index diff, ahead/behind walk, and PR
+//! lookup are independent, so each runs on its own thread. Anything that
+//! hasn't finished by the deadline is reported as pending and rendered as a
+//! placeholder; its thread keeps running so the cache is warm next time.
+//! Lookups with nothing cached yet get a longer, cold deadline, since their
+//! placeholder would otherwise stay up until the next render.
+
+use crate::git::{
+    DiffStats, GitMode, GitRepo, RepoLocation, diff_summary, get_ahead_behind, has_cached_stats,
+};
+use crate::pr::{PrInfo, PrSource, get_pr_data, pr_is_cached, prefetch_previous};
+use std::collections::HashSet;
+use std::sync::{Mutex, mpsc};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+/// Default overall budget for git stats and PR lookups
+pub(crate) const DEFAULT_DEADLINE_MS: u64 = 50;
+
+/// Default budget for lookups with nothing cached to show: the first render in a
+/// repo or on a branch, after HEAD moves, or once the PR entry expires
+pub(crate) const DEFAULT_COLD_DEADLINE_MS: u64 = 500;
+
+enum Done {
+    Diff(DiffStats),
+    AheadBehind(u32, u32),
+    Pr(Option<Box<PrInfo>>),
+}
+
+/// Git counts; each is None when not collected (not needed, git mode, or deadline)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct GitStats {
+    pub(crate) files: Option<u32>,
+    pub(crate) lines: Option<(u32, u32)>, // (added, deleted); full git mode only
+    pub(crate) ahead_behind: Option<(u32, u32)>,
+    /// Paths with merge conflicts, also counted in `files`
+    pub(crate) conflicts: Option<u32>,
+    /// Index entries and milliseconds the last working-tree scan took
+    pub(crate) index: Option<(u32, u32)>,
+    /// `files` stopped at the `max_files` limit
+    pub(crate) files_capped: bool,
+    /// `files` only tells whether anything changed (dirty git mode)
+    pub(crate) dirty_only: bool,
+}
+
+/// Data gathered before the deadline, plus handles to collectors still running
+#[derive(Default)]
+pub(crate) struct Collected {
+    pub(crate) git_stats: GitStats,
+    pub(crate) stats_pending: bool,
+    pub(crate) pr_data: Option<PrInfo>,
+    pub(crate) pr_pending: bool,
+    pub(crate) background: Vec<JoinHandle<()>>,
+}
+
+/// A collector's kind, git dir, and branch
+type CollectorKey = (&'static str, String, String);
+
+/// Collectors still running
+static IN_FLIGHT: Mutex<Option<HashSet<CollectorKey>>> = Mutex::new(None);
+
+/// A running collector's entry in `IN_FLIGHT`, removed when its thread ends
+struct InFlight(CollectorKey);
+
+impl InFlight {
+    /// None if the same collector is still running from an earlier render,
+    /// so a long-lived process doesn't pile up scans of one repo
+    fn claim(kind: &'static str, location: &RepoLocation) -> Option<Self> {
+        let key = (
+            kind,
+            location.git_dir().to_string(),
+            location.branch().to_string(),
+        );
+        let mut running = IN_FLIGHT.lock().unwrap_or_else(|e| e.into_inner());
+        let claimed = running.get_or_insert_with(HashSet::new).insert(key.clone());
+        drop(running);
+        claimed.then(|| Self(key))
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let mut running = IN_FLIGHT.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(running) = running.as_mut() {
+            running.remove(&self.0);
+        }
+    }
+}
+
+/// Run `task` against a thread-local handle to the repo on its own thread
+/// None if the same collector is already running
+fn spawn<F>(
+    tx: &mpsc::Sender<Done>,
+    kind: &'static str,
+    location: RepoLocation,
+    task: F,
+) -> Option<JoinHandle<()>>
+where
+    F: FnOnce(&GitRepo) -> Done + Send + 'static,
+{
+    let claim = InFlight::claim(kind, &location)?;
+    let tx = tx.clone();
+    Some(thread::spawn(move || {
+        let done = task(&location.open());
+        // Released before the result is sent, so the next render can run it again
+        drop(claim);
+        // Receiver is gone once the deadline passes; the result is only cached
+        let _ = tx.send(done);
+    }))
+}
+
+/// Collect the requested data for `git`, waiting until `deadline`, or `cold_deadline`
+/// while a lookup with nothing cached is still running
+/// Minimal git mode and bare repos skip the index walk, so only ahead/behind is collected
+pub(crate) fn collect(
+    git: &GitRepo,
+    want_stats: bool,
+    mode: GitMode,
+    max_files: Option<u32>,
+    pr_source: Option<&PrSource>,
+    deadline: Instant,
+    cold_deadline: Instant,
+) -> Collected {
+    let (tx, rx) = mpsc::channel();
+    let mut background = Vec::new();
+    let mut remaining = 0;
+    let (mut stats_running, mut pr_running) = (0, 0);
+
+    let location = git.location();
+    let want_diff = want_stats && mode != GitMode::Minimal && git.repo.work_dir().is_some();
+    // Checked before the collectors start, since they fill the caches. Ahead/behind
+    // has no cache of its own, so it's cold along with the index diff
+    let stats_cold = want_diff && !has_cached_stats(git);
+    let pr_cold = pr_source.is_some_and(|source| !pr_is_cached(git, source));
+    if want_diff
+        && let Some(handle) = spawn(&tx, "diff", location.clone(), move |g| {
+            Done::Diff(diff_summary(g, mode, max_files))
+        })
+    {
+        background.push(handle);
+        remaining += 1;
+        stats_running += 1;
+    }
+    if want_stats
+        && let Some(handle) = spawn(&tx, "ahead_behind", location.clone(), |g| {
+            let (ahead, behind) = get_ahead_behind(&g.repo, &g.branch);
+            Done::AheadBehind(ahead, behind)
+        })
+    {
+        background.push(handle);
+        remaining += 1;
+        stats_running += 1;
+    }
+    if let Some(source) = pr_source {
+        // The previous branch's refresh runs apart, so it can't hold up this one
+        let (previous, previous_source) = (location.clone(), source.clone());
+        background.push(thread::spawn(move || {
+            prefetch_previous(&previous.open(), &previous_source);
+        }));
+        let source = source.clone();
+        if let Some(handle) = spawn(&tx, "pr", location, move |g| {
+            Done::Pr(get_pr_data(g, &source).map(Box::new))
+        }) {
+            background.push(handle);
+            remaining += 1;
+            pr_running += 1;
+        }
+    }
+    drop(tx);
+
+    let mut cold = usize::from(stats_cold) * stats_running + usize::from(pr_cold) * pr_running;
+
+    let (mut diff, mut ahead_behind, mut pr) = (None, None, None);
+    while remaining > 0 {
+        let until = if cold > 0 {
+            cold_deadline.max(deadline)
+        } else {
+            deadline
+        };
+        let done = match rx.recv_timeout(until.saturating_duration_since(Instant::now())) {
+            Ok(done) => done,
+            Err(_) => break,
+        };
+        let was_cold = match done {
+            Done::Pr(_) => pr_cold,
+            Done::Diff(_) | Done::AheadBehind(..) => stats_cold,
+        };
+        match done {
+            Done::Diff(stats) => diff = Some(stats),
+            Done::AheadBehind(a, b) => ahead_behind = Some((a, b)),
+            Done::Pr(data) => pr = Some(data.map(|pr| *pr)),
+        }
+        cold -= usize::from(was_cold);
+        remaining -= 1;
+    }
+
+    let git_stats = GitStats {
+        files: diff.as_ref().map(|d| d.files),
+        lines: diff
+            .as_ref()
+            .filter(|_| mode == GitMode::Full)
+            .map(|d| (d.added, d.deleted)),
+        ahead_behind,
+        conflicts: diff.as_ref().map(|d| d.conflicts),
+        index: diff.as_ref().map(|d| (d.index_entries, d.scan_ms)),
+        files_capped: diff.as_ref().is_some_and(|d| d.capped),
+        dirty_only: mode == GitMode::Dirty,
+    };
+    Collected {
+        git_stats,
+        stats_pending: (want_diff && diff.is_none()) || (want_stats && ahead_behind.is_none()),
+        pr_pending: pr_source.is_some() && pr.is_none(),
+        pr_data: pr.flatten(),
+        background,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::get_git_repo;
+    use std::process::Command;
+    use std::time::Duration;
+
+    #[test]
+    fn collect_within_deadline() {
+        let dir = tempfile::tempdir().unwrap();
+        let status = Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(dir.path())
+            .status();
+        if !status.is_ok_and(|s| s.success()) {
+            return; // git not installed
+        }
+        let git = get_git_repo(dir.path().to_str().unwrap()).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut collected = collect(&git, true, GitMode::Fast, None, None, deadline, deadline);
+        assert!(!collected.stats_pending);
+        assert!(!collected.pr_pending);
+        assert_eq!(collected.git_stats.files, Some(0));
+        assert_eq!(collected.git_stats.lines, None);
+        assert_eq!(collected.git_stats.ahead_behind, Some((0, 0)));
+        for handle in collected.background.drain(..) {
+            handle.join().unwrap();
+        }
+
+        // Minimal mode never walks the index
+        let collected = collect(&git, true, GitMode::Minimal, None, None, deadline, deadline);
+        assert!(!collected.stats_pending);
+        assert_eq!(collected.git_stats.files, None);
+        assert_eq!(collected.git_stats.ahead_behind, Some((0, 0)));
+    }
+
+    #[test]
+    fn cold_lookups_wait_for_the_cold_deadline() {
+        let dir = tempfile::tempdir().unwrap();
+        let status = Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(dir.path())
+            .status();
+        if !status.is_ok_and(|s| s.success()) {
+            return; // git not installed
+        }
+        let git = get_git_repo(dir.path().to_str().unwrap()).unwrap();
+        assert!(!has_cached_stats(&git));
+
+        // The warm deadline has already passed, but nothing is cached for the repo yet
+        let now = Instant::now();
+        let cold_deadline = now + Duration::from_secs(10);
+        let mut collected = collect(&git, true, GitMode::Fast, None, None, now, cold_deadline);
+        assert!(!collected.stats_pending);
+        assert_eq!(collected.git_stats.files, Some(0));
+        for handle in collected.background.drain(..) {
+            handle.join().unwrap();
+        }
+        assert!(has_cached_stats(&git));
+    }
+
+    #[test]
+    fn running_collector_is_not_started_twice() {
+        let dir = tempfile::tempdir().unwrap();
+        let status = Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(dir.path())
+            .status();
+        if !status.is_ok_and(|s| s.success()) {
+            return; // git not installed
+        }
+        let location = get_git_repo(dir.path().to_str().unwrap())
+            .unwrap()
+            .location();
+
+        let claim = InFlight::claim("diff", &location);
+        assert!(claim.is_some());
+        assert!(InFlight::claim("diff", &location).is_none());
+        assert!(InFlight::claim("pr", &location).is_some());
+        drop(claim);
+        assert!(InFlight::claim("diff", &location).is_some());
+    }
+}
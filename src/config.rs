@@ -0,0 +1,1271 @@
+//! Config file loading, profiles, and `--config-init`
+
+use crate::git::GitMode;
+use crate::memo::Lru;
+use crate::rules::{self, Expr};
+use crate::sys::{self, TlsOptions};
+use crate::{get_home, glob_match};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// Configuration for display customization
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Each inner Vec is one row, containing component names in display order
+    pub rows: Vec<Vec<String>>,
+    /// Per-component rule expressions; a component renders only when its rule is true
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub conditions: HashMap<String, String>,
+    /// `conditions` parsed on first use, so each rule is parsed and reported once
+    #[serde(skip)]
+    pub(crate) parsed_conditions: OnceLock<HashMap<String, Option<Expr>>>,
+    /// User-defined components backed by shell commands, keyed by component name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub custom: HashMap<String, CustomSegment>,
+    /// Time budget per Lua plugin in milliseconds (plugins feature only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin_timeout_ms: Option<u64>,
+    /// Overall budget for git stats and PR lookups in milliseconds; late data shows as "…"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline_ms: Option<u64>,
+    /// Budget in milliseconds for lookups with nothing cached yet, such as the first
+    /// render in a repo; `deadline_ms` applies if it is longer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cold_deadline_ms: Option<u64>,
+    /// Git detail level: "full" (files and lines), "fast" (files), "dirty" (`●` on any
+    /// change), or "minimal" (no index walk)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_mode: Option<GitMode>,
+    /// GitHub REST API base URL for PR lookups (GitHub Enterprise or a test server)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_api_url: Option<String>,
+    /// Remote whose GitHub repo PRs are opened against (default: origin)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pr_remote: Option<String>,
+    /// Never touch the network: PR lookups and update checks only read their caches
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub offline: bool,
+    /// PEM bundle of extra CAs for the native GitHub client (a leading `~/` expands to home)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<String>,
+    /// Skip TLS certificate checks for the native GitHub client; internal hosts only
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub tls_skip_verify: bool,
+    /// Path globs where only the branch is shown (no diff stats or PR lookups)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+    /// Path prefixes reported by Claude (like a dev container's `/workspaces/app`)
+    /// mapped to where the repo is here (`~/code/app`), before repo discovery
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub path_map: HashMap<String, String>,
+    /// Display name, emoji, color, and protected branches per project directory
+    /// (a leading `~/` expands to home)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub projects: HashMap<String, ProjectStyle>,
+    /// Branch globs (like `main` or `release/*`) shown as a warning; a project's
+    /// own `protected_branches` replace these
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protected_branches: Vec<String>,
+    /// How the path component is shown: "home" (default) or "repo"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_mode: Option<PathMode>,
+    /// How the path component abbreviates long paths
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_style: Option<PathStyle>,
+    /// How much the PR components show: "compact", "normal" (default), or "detailed"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pr_verbosity: Option<PrVerbosity>,
+    /// How the duration component shows time: "minutes" (default) or "seconds"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_format: Option<DurationFormat>,
+    /// Show API time next to wall time in the duration component
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub api_duration: bool,
+    /// Count only notifications for the current repo in the notifications component
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub notifications_repo_only: bool,
+    /// Desktop notification when the PR's checks go from pending to passed or failed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub notify_checks: bool,
+    /// CI service whose builds `pr_checks` shows instead of GitHub checks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ci: Option<CiConfig>,
+    /// Self-hosted Gitea or Forgejo instances to look PRs up on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gitea: Option<GiteaConfig>,
+    /// Gerrit server the change component looks HEAD's Change-Id up on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gerrit: Option<GerritConfig>,
+    /// Seconds a GitHub token from the git credential helper is reused (default 900; 0 asks every time)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_cache_secs: Option<u64>,
+    /// Terminal title set with OSC 2 on each render, e.g. "{project}:{branch}"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_title: Option<String>,
+    /// Taskbar/tab progress (OSC 9;4) from "context" used or "checks" state
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<ProgressSource>,
+    /// Palette: "tokyo-night" (default), "deuteranopia", or "protanopia"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<Theme>,
+    /// Prefix check, review, and merge states with ✓ ✗ ◐ so they don't rely on color
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub status_glyphs: bool,
+    /// Columns a row may take; defaults to `COLUMNS`, and rows aren't fitted without either
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_width: Option<usize>,
+    /// Elision priority per component; lower ones are truncated or dropped first
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub priorities: HashMap<String, i32>,
+    /// Record session totals for `cc-statusline stats`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub session_stats: bool,
+    /// Record visited projects for `cc-statusline recent`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub project_history: bool,
+    /// Daily or weekly spend limit shown by the budget component
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<BudgetConfig>,
+    /// Minutes without activity before the idle component shows (default 10)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_after: Option<u64>,
+    /// Changed files to count before the walk stops and `files` shows `N+ files`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_files: Option<u32>,
+    /// Seconds to reprint the last output for identical input and repo state
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub render_cache: Option<u64>,
+    /// Minutes the HEAD commit may sit unpushed before ahead_behind shows its age
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unpushed_after: Option<u64>,
+    /// Remaining context percentage below which `compact_warning` shows (default 15)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compact_warning: Option<f64>,
+    /// Desktop notification when context first drops below the compact warning
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub notify_compact: bool,
+    /// Show tokens as context used out of the model's limit, e.g. `112K/200K`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub token_limit: bool,
+    /// Context limit per model id glob, over the built-in table
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub context_limits: HashMap<String, u64>,
+    /// Cache directory size limit in megabytes (default 5; 0 never evicts)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_max_mb: Option<u64>,
+}
+
+/// Cost and token limits for the budget component
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BudgetConfig {
+    #[serde(default)]
+    pub period: BudgetPeriod,
+    /// US dollars
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+    /// Input and output tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<u64>,
+}
+
+/// Span the budget covers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    /// Since local midnight
+    #[default]
+    Day,
+    /// Today and the 6 days before
+    Week,
+}
+
+/// Gerrit server for the change component
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GerritConfig {
+    /// Web and REST base URL, like "https://review.example.com"
+    pub url: String,
+    /// Environment variable holding "user:http-password"; anonymous access without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credentials_env: Option<String>,
+}
+
+/// Gitea or Forgejo instances whose remotes get the PR row
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GiteaConfig {
+    /// Instance hosts like "git.example.com" (https), or base URLs like "http://gitea.lan:3000"
+    pub hosts: Vec<String>,
+    /// Environment variable holding the API token (default: GITEA_TOKEN)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_env: Option<String>,
+}
+
+/// Where the branch's builds run, for repos not built by GitHub Actions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CiConfig {
+    pub provider: CiProvider,
+    /// API base URL; required for Jenkins, defaults to the public service otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api: Option<String>,
+    /// Environment variable holding the API token (Jenkins: "user:api-token")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_env: Option<String>,
+    /// CircleCI project slug ("gh/org/repo"), Buildkite "org/pipeline", or Jenkins job path
+    pub pipeline: String,
+}
+
+/// Supported CI services
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CiProvider {
+    #[serde(rename = "circleci")]
+    CircleCi,
+    Buildkite,
+    /// Multibranch pipeline jobs
+    Jenkins,
+}
+
+/// Path abbreviation settings for the path component
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PathStyle {
+    /// Trailing segments never abbreviated (default 2); dropped down to 1 if the path still doesn't fit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep: Option<usize>,
+    /// Segment globs never abbreviated, e.g. "src" or "crates"
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub full: Vec<String>,
+    /// Fish-style: keep enough leading characters to stay unique among sibling directories
+    pub unique: bool,
+}
+
+/// How the path component shows the current directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PathMode {
+    /// Absolute, with the home directory as `~`
+    #[default]
+    Home,
+    /// Relative to the git repo root, prefixed by the repo name (`myrepo//src/api`)
+    Repo,
+}
+
+/// How much of the PR the PR components show
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrVerbosity {
+    /// Only the number with a check mark, e.g. `#42 ✓`
+    Compact,
+    /// The configured components
+    #[default]
+    Normal,
+    /// Adds reviews, labels, age, and mergeability after the configured components
+    Detailed,
+}
+
+/// Precision of the duration component
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DurationFormat {
+    /// Whole minutes, e.g. `1h 4m`
+    #[default]
+    Minutes,
+    /// Down to the second, e.g. `4m32s`
+    Seconds,
+}
+
+/// Color palette for good, bad, and in-progress states
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    /// Tokyo Night's green, red, and orange
+    #[default]
+    TokyoNight,
+    /// Blue, vermillion, and gray, which stay apart without green-sensitive cones
+    Deuteranopia,
+    /// Blue, yellow, and gray, since reds look dark without red-sensitive cones
+    Protanopia,
+}
+
+/// What the terminal's progress indicator follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressSource {
+    /// Share of the context window used; a warning once little is left
+    Context,
+    /// Busy while checks are pending, an error when they failed, cleared otherwise
+    Checks,
+}
+
+const DEFAULT_TOKEN_CACHE_SECS: u64 = 15 * 60;
+const DEFAULT_CACHE_MAX_MB: u64 = 5;
+
+/// Built-in component names; any other name is a custom segment or plugin
+pub const COMPONENTS: &[&str] = &[
+    "hostname",
+    "project",
+    "package",
+    "path",
+    "dirs",
+    "no_git",
+    "branch",
+    "worktree",
+    "files",
+    "conflicts",
+    "index_stats",
+    "lines",
+    "ahead_behind",
+    "pr_number",
+    "pr_state",
+    "pr_comments",
+    "pr_files",
+    "pr_checks",
+    "pr_age",
+    "pr_auto_merge",
+    "pr_reviews",
+    "pr_labels",
+    "pr_mergeable",
+    "pr_access",
+    "deployment",
+    "release",
+    "vulns",
+    "notifications",
+    "change",
+    "signed",
+    "lfs",
+    "model",
+    "context",
+    "compact_warning",
+    "idle",
+    "last_visit",
+    "budget",
+    "style",
+    "cost",
+    "duration",
+    "session_lines",
+    "tokens",
+    "transcript",
+    "tools",
+    "last_tool",
+    "update",
+];
+
+/// A component whose text is the first line of a shell command's output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomSegment {
+    pub command: String,
+    /// Seconds before cached output is considered stale
+    #[serde(default = "default_custom_ttl")]
+    pub ttl: u64,
+    /// Maximum time to wait for the command before falling back to stale output
+    #[serde(default = "default_custom_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Color name (blue, cyan, purple, magenta, green, orange, teal, gray, red) or #rrggbb
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+/// Settings for one project directory
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectStyle {
+    /// Shown instead of the directory name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Shown before the name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+    /// Color name (blue, cyan, purple, magenta, green, orange, teal, gray, red) or #rrggbb
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Replaces the top-level `protected_branches` in this project
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protected_branches: Option<Vec<String>>,
+}
+
+fn default_custom_ttl() -> u64 {
+    60
+}
+
+fn default_custom_timeout_ms() -> u64 {
+    100
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        default_config()
+    }
+}
+
+impl Config {
+    /// Load config from the config file, merging the named profile (if any) over it
+    /// Falls back to defaults (with a warning on stderr) when the file is invalid
+    pub fn load(profile: Option<&str>) -> Config {
+        load_config(profile)
+    }
+
+    /// Like `load`, but reuses earlier results while the file's mtime is unchanged
+    /// Long-running modes call this per render, so edits apply without a restart
+    /// A few profiles are kept, so inputs alternating between them don't reparse
+    pub fn load_cached(profile: Option<&str>) -> Config {
+        /// File mtime the config was loaded at
+        type Loaded = (Option<SystemTime>, Config);
+        /// Config per profile
+        static LOADED: Mutex<Lru<Option<String>, Loaded>> = Mutex::new(Lru::new(4));
+        let mtime = fs::metadata(get_config_path())
+            .and_then(|m| m.modified())
+            .ok();
+        let key = profile.map(str::to_string);
+        let mut loaded = LOADED.lock().unwrap_or_else(|e| e.into_inner());
+        match loaded.get(&key) {
+            Some((m, config)) if *m == mtime => config.clone(),
+            _ => {
+                let config = load_config(profile);
+                loaded.insert(key, (mtime, config.clone()));
+                config
+            }
+        }
+    }
+
+    /// Check if any row contains one of the given components
+    /// Used to skip expensive data collection for components that won't be rendered
+    pub(crate) fn uses_any(&self, names: &[&str]) -> bool {
+        self.rows
+            .iter()
+            .flatten()
+            .any(|c| names.contains(&c.as_str()))
+    }
+
+    /// Offline via the `offline` key or a non-empty `NO_NETWORK` other than "0"
+    pub(crate) fn is_offline(&self) -> bool {
+        self.offline || sys::var("NO_NETWORK").is_some_and(|v| !v.is_empty() && v != "0")
+    }
+
+    /// TLS settings for native HTTP requests
+    pub(crate) fn tls_options(&self) -> TlsOptions {
+        let home = get_home();
+        TlsOptions {
+            ca_bundle: self
+                .ca_bundle
+                .as_ref()
+                .map(|path| match path.strip_prefix("~/") {
+                    Some(rest) if !home.is_empty() => format!("{home}/{rest}"),
+                    _ => path.clone(),
+                }),
+            skip_verify: self.tls_skip_verify,
+        }
+    }
+
+    /// Seconds a credential helper token is reused
+    pub(crate) fn token_cache_secs(&self) -> u64 {
+        self.token_cache_secs.unwrap_or(DEFAULT_TOKEN_CACHE_SECS)
+    }
+
+    /// Bytes the cache directory may hold before old files are evicted; 0 for no limit
+    pub(crate) fn cache_max_bytes(&self) -> u64 {
+        self.cache_max_mb
+            .unwrap_or(DEFAULT_CACHE_MAX_MB)
+            .saturating_mul(1024 * 1024)
+    }
+
+    /// `dir` with the longest matching `path_map` prefix replaced (a leading `~/`
+    /// in the target expands to home); prefixes only match whole path segments
+    pub(crate) fn map_path(&self, dir: &str) -> String {
+        let mapping = self
+            .path_map
+            .iter()
+            .map(|(from, to)| (from.trim_end_matches('/'), to))
+            .filter(|(from, _)| within(dir, from))
+            .max_by_key(|(from, _)| from.len());
+        let Some((from, to)) = mapping else {
+            return dir.to_string();
+        };
+        let to = expand_home(to);
+        format!("{}{}", to.trim_end_matches('/'), &dir[from.len()..])
+    }
+
+    /// The `projects` entry for the innermost configured directory containing `dir`
+    pub(crate) fn project_style(&self, dir: &str) -> Option<&ProjectStyle> {
+        self.projects
+            .iter()
+            .map(|(path, style)| (expand_home(path), style))
+            .filter(|(path, _)| within(dir, path.trim_end_matches('/')))
+            .max_by_key(|(path, _)| path.trim_end_matches('/').len())
+            .map(|(_, style)| style)
+    }
+
+    /// Whether `branch` matches the protected branch globs that apply in `dir`
+    pub(crate) fn is_protected(&self, dir: &str, branch: &str) -> bool {
+        self.project_style(dir)
+            .and_then(|s| s.protected_branches.as_ref())
+            .unwrap_or(&self.protected_branches)
+            .iter()
+            .any(|pattern| glob_match(pattern, branch))
+    }
+
+    /// Check if `dir` matches an `ignore` glob (a leading `~/` expands to home)
+    pub(crate) fn is_ignored(&self, dir: &str) -> bool {
+        let home = get_home();
+        self.ignore
+            .iter()
+            .any(|pattern| match pattern.strip_prefix("~/") {
+                Some(rest) if !home.is_empty() => glob_match(&format!("{home}/{rest}"), dir),
+                _ => glob_match(pattern, dir),
+            })
+    }
+
+    /// Parsed condition rules by component; rules that fail to parse are
+    /// reported on stderr the first time and map to None
+    pub(crate) fn parsed_conditions(&self) -> &HashMap<String, Option<Expr>> {
+        self.parsed_conditions.get_or_init(|| {
+            self.conditions
+                .iter()
+                .map(|(name, rule)| {
+                    let expr = rules::parse(rule)
+                        .map_err(|e| {
+                            eprintln!("cc-statusline: invalid condition for '{name}': {e}")
+                        })
+                        .ok();
+                    (name.clone(), expr)
+                })
+                .collect()
+        })
+    }
+
+    /// Check if any condition references one of the given rule variables
+    pub(crate) fn conditions_reference(&self, vars: &[&str]) -> bool {
+        self.conditions.values().any(|rule| {
+            rule.split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .any(|ident| vars.contains(&ident))
+        })
+    }
+}
+
+/// `path` with a leading `~/` expanded to home
+fn expand_home(path: &str) -> Cow<'_, str> {
+    let home = get_home();
+    match path.strip_prefix("~/") {
+        Some(rest) if !home.is_empty() => Cow::Owned(format!("{home}/{rest}")),
+        _ => Cow::Borrowed(path),
+    }
+}
+
+/// Whether `dir` is `prefix` or inside it; prefixes only match whole path segments
+fn within(dir: &str, prefix: &str) -> bool {
+    !prefix.is_empty()
+        && dir
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Get the default configuration (matches current hardcoded behavior)
+fn default_config() -> Config {
+    Config {
+        rows: vec![
+            vec![
+                "hostname".to_string(),
+                "project".to_string(),
+                "path".to_string(),
+                "dirs".to_string(),
+            ],
+            vec![
+                "no_git".to_string(),
+                "branch".to_string(),
+                "worktree".to_string(),
+                "files".to_string(),
+                "ahead_behind".to_string(),
+            ],
+            vec![
+                "pr_number".to_string(),
+                "pr_state".to_string(),
+                "pr_comments".to_string(),
+                "pr_files".to_string(),
+                "pr_checks".to_string(),
+            ],
+            vec![
+                "model".to_string(),
+                "context".to_string(),
+                "style".to_string(),
+            ],
+            vec!["duration".to_string(), "tokens".to_string()],
+        ],
+        conditions: HashMap::new(),
+        parsed_conditions: OnceLock::new(),
+        custom: HashMap::new(),
+        plugin_timeout_ms: None,
+        deadline_ms: None,
+        cold_deadline_ms: None,
+        git_mode: None,
+        path_mode: None,
+        path_style: None,
+        pr_verbosity: None,
+        duration_format: None,
+        api_duration: false,
+        notifications_repo_only: false,
+        notify_checks: false,
+        ci: None,
+        gitea: None,
+        gerrit: None,
+        token_cache_secs: None,
+        window_title: None,
+        progress: None,
+        theme: None,
+        status_glyphs: false,
+        max_width: None,
+        priorities: HashMap::new(),
+        session_stats: false,
+        project_history: false,
+        budget: None,
+        idle_after: None,
+        unpushed_after: None,
+        max_files: None,
+        render_cache: None,
+        compact_warning: None,
+        notify_compact: false,
+        token_limit: false,
+        context_limits: HashMap::new(),
+        cache_max_mb: None,
+        github_api_url: None,
+        pr_remote: None,
+        offline: false,
+        ca_bundle: None,
+        tls_skip_verify: false,
+        ignore: Vec::new(),
+        path_map: HashMap::new(),
+        projects: HashMap::new(),
+        protected_branches: Vec::new(),
+    }
+}
+
+/// Get path to config file
+/// Uses ~/.claude/cc-statusline.json; on Windows, %APPDATA%\cc-statusline\cc-statusline.json
+/// unless a config already exists at the first path
+pub fn get_config_path() -> PathBuf {
+    let home = get_home();
+    let path = if home.is_empty() {
+        PathBuf::from(".claude").join("cc-statusline.json")
+    } else {
+        PathBuf::from(&*home)
+            .join(".claude")
+            .join("cc-statusline.json")
+    };
+    #[cfg(windows)]
+    if !path.exists()
+        && let Some(app_data) = sys::var("APPDATA").filter(|d| !d.is_empty())
+    {
+        return PathBuf::from(app_data)
+            .join("cc-statusline")
+            .join("cc-statusline.json");
+    }
+    path
+}
+
+/// Built-in profile presets, selectable without any config file
+/// Each preset is a partial config merged over the base config
+fn builtin_profile(name: &str) -> Option<serde_json::Value> {
+    match name {
+        // Huge monorepos: no index walk, ahead/behind, or PR lookup
+        "minimal" => Some(serde_json::json!({
+            "rows": [["project", "path"], ["no_git", "branch"], ["model", "context"]],
+            "git_mode": "minimal"
+        })),
+        // Small projects: line counts, the whole PR, and session totals
+        "full" => Some(serde_json::json!({
+            "rows": [
+                ["hostname", "project", "package", "path", "dirs"],
+                ["no_git", "branch", "worktree", "files", "lines", "conflicts", "ahead_behind"],
+                ["pr_number", "pr_state", "pr_comments", "pr_files", "pr_checks"],
+                ["model", "context", "compact_warning", "style"],
+                ["duration", "cost", "tokens", "session_lines"]
+            ],
+            "git_mode": "full",
+            "pr_verbosity": "detailed"
+        })),
+        // Screenshots and recordings: no hostname or home path, and no network
+        "demo" => Some(serde_json::json!({
+            "rows": [
+                ["project", "path"],
+                ["no_git", "branch", "files", "ahead_behind"],
+                ["model", "context", "cost", "duration"]
+            ],
+            "path_mode": "repo",
+            "offline": true
+        })),
+        _ => None,
+    }
+}
+
+/// Merge a profile's keys over the base config (shallow: profile keys replace base keys)
+fn apply_profile(base: &mut serde_json::Value, profile: &serde_json::Value) {
+    if let (Some(base), Some(profile)) = (base.as_object_mut(), profile.as_object()) {
+        for (key, value) in profile {
+            base.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+/// Prefix of the environment variables that override config keys
+const ENV_PREFIX: &str = "CC_STATUSLINE_";
+
+/// Apply `CC_STATUSLINE_<KEY>` variables over the config, in name order
+/// `__` separates nested keys (`CC_STATUSLINE_PATH_STYLE__KEEP=3`), and values are JSON,
+/// else strings. `CC_STATUSLINE_MODULES_<NAME>=false` (or `COMPONENTS_<NAME>`) drops `<name>`
+/// and `<name>_*` components from the rows. Variables that would make the config invalid are skipped
+fn apply_env_overrides(value: &mut serde_json::Value, mut vars: Vec<(String, String)>) {
+    if serde_json::from_value::<Config>(value.clone()).is_err() {
+        return; // The defaults are used anyway
+    }
+    vars.sort();
+    for (name, raw) in vars {
+        let key = name[ENV_PREFIX.len()..].to_lowercase();
+        let toggle = ["modules_", "components_"]
+            .iter()
+            .find_map(|prefix| key.strip_prefix(prefix));
+        if let Some(component) = toggle {
+            if matches!(raw.as_str(), "false" | "0" | "off")
+                && let Some(rows) = value.get_mut("rows").and_then(|r| r.as_array_mut())
+            {
+                let group = format!("{component}_");
+                for row in rows.iter_mut().filter_map(|r| r.as_array_mut()) {
+                    row.retain(|c| {
+                        c.as_str()
+                            .is_none_or(|c| c != component && !c.starts_with(&group))
+                    });
+                }
+                rows.retain(|r| r.as_array().is_none_or(|r| !r.is_empty()));
+            }
+            continue;
+        }
+        let path: Vec<&str> = key.split("__").collect();
+        if !config_keys().contains(&path[0]) {
+            continue;
+        }
+        // `123` or `true` may be meant for a string key, so fall back to the raw text
+        let candidates = serde_json::from_str(&raw)
+            .into_iter()
+            .chain([serde_json::Value::String(raw.clone())]);
+        let mut error = None;
+        for candidate in candidates {
+            let mut updated = value.clone();
+            set_path(&mut updated, &path, candidate);
+            match serde_json::from_value::<Config>(updated.clone()) {
+                Ok(_) => {
+                    *value = updated;
+                    error = None;
+                    break;
+                }
+                Err(e) => error = error.or(Some(e)),
+            }
+        }
+        if let Some(e) = error {
+            eprintln!("cc-statusline: ignoring {name}: {e}");
+        }
+    }
+}
+
+/// Set the value at a path of object keys, creating objects along the way
+fn set_path(value: &mut serde_json::Value, path: &[&str], new: serde_json::Value) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let mut target = value;
+    for key in parents {
+        if !target.get(*key).is_some_and(serde_json::Value::is_object) {
+            target[*key] = serde_json::json!({});
+        }
+        target = &mut target[*key];
+    }
+    target[*last] = new;
+}
+
+/// Resolve a profile by name: user-defined profiles in the config file win over built-ins
+fn resolve_profile(file_value: &serde_json::Value, name: &str) -> Option<serde_json::Value> {
+    file_value
+        .get("profiles")
+        .and_then(|p| p.get(name))
+        .cloned()
+        .or_else(|| builtin_profile(name))
+}
+
+/// `content` with `//` and `/* */` comments blanked out, so the config file may be commented
+/// Newlines are kept, so parse errors still point at the right line
+fn strip_comments(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                    }
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Read the raw config file as JSON, returning None (with a warning) if missing or invalid
+fn read_config_value(config_path: &Path) -> Option<serde_json::Value> {
+    // If file doesn't exist, use defaults silently
+    if !config_path.exists() {
+        return None;
+    }
+
+    match fs::read_to_string(config_path) {
+        Ok(content) => match serde_json::from_str::<serde_json::Value>(&strip_comments(&content)) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                eprintln!(
+                    "cc-statusline: invalid config at {}: {e}",
+                    config_path.display()
+                );
+                None
+            }
+        },
+        Err(e) => {
+            eprintln!(
+                "cc-statusline: failed to read config at {}: {e}",
+                config_path.display()
+            );
+            None
+        }
+    }
+}
+
+/// Load configuration from file, returning default if missing or invalid
+/// The selected profile (if any) is merged over the file's top-level keys
+fn load_config(profile: Option<&str>) -> Config {
+    let config_path = get_config_path();
+    let file_value = read_config_value(&config_path);
+
+    let mut value = file_value
+        .clone()
+        .or_else(|| serde_json::to_value(default_config()).ok())
+        .unwrap_or_default();
+
+    if let Some(name) = profile {
+        let source = file_value.as_ref().unwrap_or(&serde_json::Value::Null);
+        match resolve_profile(source, name) {
+            Some(profile) => apply_profile(&mut value, &profile),
+            None => eprintln!("cc-statusline: unknown profile '{name}', ignoring"),
+        }
+    }
+    apply_env_overrides(&mut value, sys::vars_with_prefix(ENV_PREFIX));
+
+    let config = match serde_json::from_value::<Config>(value) {
+        Ok(config) => {
+            // Validate config has at least one non-empty row
+            if config.rows.iter().any(|row| !row.is_empty()) {
+                config
+            } else {
+                eprintln!(
+                    "cc-statusline: config at {} has no valid rows, using defaults",
+                    config_path.display()
+                );
+                default_config()
+            }
+        }
+        Err(e) => {
+            eprintln!(
+                "cc-statusline: invalid config at {}: {e}",
+                config_path.display()
+            );
+            default_config()
+        }
+    };
+    // Report invalid rules now, once per load, rather than on each render
+    config.parsed_conditions();
+    config
+}
+
+/// Write default config to file (for --config-init)
+/// Returns error if config file already exists (use --config-init --force to overwrite)
+pub fn write_config_init(force: bool) -> io::Result<()> {
+    let config_path = get_config_path();
+
+    // Check if config already exists
+    if config_path.exists() && !force {
+        return Err(io::Error::other(format!(
+            "config file already exists: {}\nUse --config-init --force to overwrite",
+            config_path.display()
+        )));
+    }
+
+    // Create parent directories if needed
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Write pretty-printed default config
+    let config = default_config();
+    let json = serde_json::to_string_pretty(&config)
+        .map_err(|e| io::Error::other(format!("failed to serialize config: {e}")))?;
+
+    fs::write(&config_path, json)?;
+    println!("Created config file: {}", config_path.display());
+    Ok(())
+}
+
+/// Problems with the config file: unreadable JSON, invalid values, and unknown keys
+/// Unknown keys come with the closest known key as a suggestion
+pub fn check_config() -> Vec<String> {
+    let config_path = get_config_path();
+    if !config_path.exists() {
+        return Vec::new();
+    }
+    let value = match fs::read_to_string(&config_path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| {
+            serde_json::from_str::<serde_json::Value>(&strip_comments(&s))
+                .map_err(|e| e.to_string())
+        }) {
+        Ok(value) => value,
+        Err(e) => return vec![e],
+    };
+    let mut problems = unknown_keys(&value, "");
+    if let Some(profiles) = value.get("profiles").and_then(|p| p.as_object()) {
+        for (name, profile) in profiles {
+            problems.extend(unknown_keys(profile, &format!("profiles.{name}.")));
+        }
+    }
+    if let Err(e) = serde_json::from_value::<Config>(value) {
+        problems.insert(0, e.to_string());
+    }
+    problems
+}
+
+/// Keys of `value` that aren't config fields, prefixed with `path`
+fn unknown_keys(value: &serde_json::Value, path: &str) -> Vec<String> {
+    let known = config_keys();
+    let Some(object) = value.as_object() else {
+        return Vec::new();
+    };
+    object
+        .keys()
+        .filter(|key| !known.contains(&key.as_str()))
+        // Profiles are read from the raw file, not deserialized
+        .filter(|key| !path.is_empty() || *key != "profiles")
+        .map(|key| {
+            let closest = known
+                .iter()
+                .map(|k| (edit_distance(key, k), k))
+                .filter(|(d, k)| *d <= k.len() / 3 + 1)
+                .min();
+            match closest {
+                Some((_, k)) => format!("unknown key `{path}{key}` (did you mean `{k}`?)"),
+                None => format!("unknown key `{path}{key}`"),
+            }
+        })
+        .collect()
+}
+
+/// Top-level keys of `Config`, as its derived `Deserialize` impl declares them
+fn config_keys() -> &'static [&'static str] {
+    use serde::de::{self, Visitor};
+
+    /// Deserializer that only records the field list it's asked for
+    struct Fields<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> de::Deserializer<'de> for Fields<'_> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("expected a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("fields recorded"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+            byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct map
+            enum identifier ignored_any
+        }
+    }
+
+    static KEYS: OnceLock<&'static [&'static str]> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        let mut fields: &'static [&'static str] = &[];
+        let _ = Config::deserialize(Fields(&mut fields));
+        fields
+    })
+}
+
+/// Levenshtein distance, for suggesting keys
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Get the Lua plugin directories, searched in order
+/// $XDG_CONFIG_HOME/cc-statusline/plugins (~/.config/cc-statusline/plugins by default),
+/// then ~/.claude/cc-statusline/plugins alongside the config file, where earlier
+/// versions looked
+#[cfg(feature = "plugins")]
+pub(crate) fn get_plugins_dirs() -> Vec<PathBuf> {
+    let config_home = sys::var("XDG_CONFIG_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            let home = get_home();
+            (!home.is_empty()).then(|| PathBuf::from(&*home).join(".config"))
+        });
+    config_home
+        .map(|dir| dir.join("cc-statusline").join("plugins"))
+        .into_iter()
+        .chain([get_config_path().with_extension("").join("plugins")])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // =========================================================================
+    // Config check tests
+    // =========================================================================
+
+    #[test]
+    fn comments_are_stripped_outside_strings() {
+        let content = "{\n  // rows\n  \"rows\": [[\"path\"]], /* a\nb */ \"x\": \"// not \\\" /* a comment\"\n}";
+        let value: serde_json::Value = serde_json::from_str(&strip_comments(content)).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"rows": [["path"]], "x": "// not \" /* a comment"})
+        );
+        assert_eq!(
+            strip_comments(content).lines().count(),
+            content.lines().count()
+        );
+    }
+
+    #[test]
+    fn env_overrides_config_keys() {
+        let vars = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (format!("{ENV_PREFIX}{k}"), v.to_string()))
+                .collect()
+        };
+        let mut value = serde_json::json!({
+            "rows": [["model", "cost"], ["pr_number", "pr_checks"]],
+            "path_style": {"full": ["src"]}
+        });
+        apply_env_overrides(
+            &mut value,
+            vars(&[
+                ("THEME", "deuteranopia"),
+                ("OFFLINE", "true"),
+                ("MAX_WIDTH", "120"),
+                ("PATH_STYLE__KEEP", "3"),
+                ("GITHUB_API_URL", "https://ghe.example.com/api/v3"),
+                ("MODULES_PR", "false"),
+                ("COMPONENTS_COST", "0"),
+                ("GIT_MODE", "full"),
+                ("DEBUG", "1"),
+                ("THEME_X", "1"),
+                ("PR_VERBOSITY", "loud"),
+            ]),
+        );
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "rows": [["model"]],
+                "theme": "deuteranopia",
+                "offline": true,
+                "max_width": 120,
+                "path_style": {"full": ["src"], "keep": 3},
+                "github_api_url": "https://ghe.example.com/api/v3",
+                "git_mode": "full"
+            })
+        );
+    }
+
+    #[test]
+    fn config_keys_come_from_the_struct() {
+        let keys = config_keys();
+        assert!(keys.contains(&"rows") && keys.contains(&"pr_verbosity"));
+        assert!(keys.contains(&"session_stats"));
+    }
+
+    #[test]
+    fn unknown_keys_suggest_the_closest() {
+        let value = serde_json::json!({
+            "rows": [["model"]],
+            "pr_verbostiy": "compact",
+            "profiles": {"work": {"offlin": true}},
+            "zzz": 1
+        });
+        assert_eq!(
+            unknown_keys(&value, ""),
+            [
+                "unknown key `pr_verbostiy` (did you mean `pr_verbosity`?)",
+                "unknown key `zzz`"
+            ]
+        );
+        assert_eq!(
+            unknown_keys(&value["profiles"]["work"], "profiles.work."),
+            ["unknown key `profiles.work.offlin` (did you mean `offline`?)"]
+        );
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    // =========================================================================
+    // Profile tests
+    // =========================================================================
+
+    #[test]
+    fn profile_overrides_base_keys() {
+        let mut base = serde_json::json!({"rows": [["model"]]});
+        let profile = serde_json::json!({"rows": [["branch"]]});
+        apply_profile(&mut base, &profile);
+        assert_eq!(base, serde_json::json!({"rows": [["branch"]]}));
+    }
+
+    #[test]
+    fn profile_user_defined_wins_over_builtin() {
+        let file = serde_json::json!({
+            "rows": [["model"]],
+            "profiles": {"minimal": {"rows": [["tokens"]]}}
+        });
+        let profile = resolve_profile(&file, "minimal").expect("should resolve");
+        assert_eq!(profile, serde_json::json!({"rows": [["tokens"]]}));
+    }
+
+    #[test]
+    fn profile_builtin_presets_resolve_and_merge() {
+        let file = serde_json::json!({"rows": [["model"]], "max_width": 100});
+        for name in ["minimal", "full", "demo"] {
+            let profile = resolve_profile(&file, name).expect("should resolve");
+            for base in [
+                serde_json::to_value(default_config()).unwrap(),
+                file.clone(),
+            ] {
+                let mut merged = base.clone();
+                apply_profile(&mut merged, &profile);
+                let config: Config = serde_json::from_value(merged.clone()).expect("should parse");
+                assert!(config.rows.iter().any(|r| !r.is_empty()), "{name}");
+                assert_eq!(merged["rows"], profile["rows"], "{name}");
+                // Keys the profile doesn't set come from the base
+                assert_eq!(merged["max_width"], base["max_width"], "{name}");
+            }
+        }
+        assert_eq!(
+            builtin_profile("minimal").unwrap()["git_mode"],
+            serde_json::json!("minimal")
+        );
+        assert!(builtin_profile("default").is_none() && builtin_profile("compact").is_none());
+    }
+
+    #[test]
+    fn profile_unknown_name() {
+        assert!(resolve_profile(&serde_json::Value::Null, "nope").is_none());
+    }
+
+    #[test]
+    fn path_map_replaces_longest_prefix() {
+        let mut config = default_config();
+        config.path_map = HashMap::from([
+            ("/workspaces".to_string(), "/srv".to_string()),
+            (
+                "/workspaces/app/".to_string(),
+                "/home/me/code/app".to_string(),
+            ),
+        ]);
+        assert_eq!(config.map_path("/workspaces/app"), "/home/me/code/app");
+        assert_eq!(
+            config.map_path("/workspaces/app/src"),
+            "/home/me/code/app/src"
+        );
+        assert_eq!(config.map_path("/workspaces/api"), "/srv/api");
+        assert_eq!(config.map_path("/workspaces-old"), "/workspaces-old");
+        assert_eq!(config.map_path("/tmp"), "/tmp");
+    }
+
+    #[test]
+    fn project_style_uses_innermost_directory() {
+        let mut config = default_config();
+        let style = |label: &str| ProjectStyle {
+            label: Some(label.to_string()),
+            ..ProjectStyle::default()
+        };
+        config.projects = HashMap::from([
+            ("/src".to_string(), style("all")),
+            ("/src/api/".to_string(), style("api")),
+        ]);
+        let label = |dir| config.project_style(dir).and_then(|s| s.label.as_deref());
+        assert_eq!(label("/src/api/handlers"), Some("api"));
+        assert_eq!(label("/src/apiv2"), Some("all"));
+        assert_eq!(label("/home"), None);
+    }
+
+    #[test]
+    fn protected_branches_per_project() {
+        let mut config = default_config();
+        config.protected_branches = vec!["main".to_string(), "release/*".to_string()];
+        config.projects = HashMap::from([(
+            "/src/scratch".to_string(),
+            ProjectStyle {
+                protected_branches: Some(Vec::new()),
+                ..ProjectStyle::default()
+            },
+        )]);
+        assert!(config.is_protected("/src/app", "main"));
+        assert!(config.is_protected("/src/app", "release/1.2"));
+        assert!(!config.is_protected("/src/app", "release/1.2/hotfix"));
+        assert!(!config.is_protected("/src/app", "feature"));
+        assert!(!config.is_protected("/src/scratch", "main"));
+    }
+}
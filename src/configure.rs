@@ -0,0 +1,290 @@
+//! `cc-statusline configure`: a prompt-based wizard that writes a commented config
+//!
+//! Every question has a default, taken on an empty answer or end of input.
+
+use serde_json::{Value, json};
+use std::io::{self, BufRead, Write};
+
+/// One config key with the comment written above it
+struct Entry {
+    comment: &'static str,
+    key: &'static str,
+    value: Value,
+}
+
+/// Ask the questions on `input` and `out`, returning the config file contents
+pub fn run(input: &mut impl BufRead, out: &mut impl Write) -> io::Result<String> {
+    let mut prompt = Prompt { input, out };
+    let mut entries = Vec::new();
+
+    writeln!(prompt.out, "Theme")?;
+    let theme = prompt.choose(
+        "Colors for good, bad, and in-progress states",
+        &[
+            "Tokyo Night (green, red, orange)",
+            "Deuteranopia (blue, vermillion, yellow)",
+            "Protanopia (blue, vermillion, yellow)",
+        ],
+        0,
+    )?;
+    if theme > 0 {
+        entries.push(Entry {
+            comment: "Palette: tokyo-night, deuteranopia, or protanopia",
+            key: "theme",
+            value: json!(["tokyo-night", "deuteranopia", "protanopia"][theme]),
+        });
+    }
+    if prompt.confirm("Mark states with ✓ ✗ ◐ as well as color?", theme > 0)? {
+        entries.push(Entry {
+            comment: "Prefix check, review, and merge states with ✓ ✗ ◐",
+            key: "status_glyphs",
+            value: json!(true),
+        });
+    }
+
+    writeln!(prompt.out, "\nSegments")?;
+    let mut location = vec!["project", "path", "dirs"];
+    if prompt.confirm("Show the hostname over SSH?", true)? {
+        location.insert(0, "hostname");
+    }
+    let mut rows = vec![
+        location,
+        vec!["no_git", "branch", "worktree", "files", "ahead_behind"],
+    ];
+
+    writeln!(prompt.out, "\nPull requests")?;
+    let provider = prompt.choose(
+        "Where are your PRs?",
+        &[
+            "GitHub or Azure DevOps (detected from the remote)",
+            "Gitea or Forgejo",
+            "Gerrit",
+            "None (hide the PR row)",
+        ],
+        0,
+    )?;
+    match provider {
+        0 => writeln!(
+            prompt.out,
+            "  Set GITHUB_TOKEN (or run `gh auth login`), or AZURE_DEVOPS_EXT_PAT for Azure DevOps"
+        )?,
+        1 => {
+            let host = prompt.ask("Instance host", "git.example.com")?;
+            let token_env = prompt.ask("Environment variable with the API token", "GITEA_TOKEN")?;
+            let mut gitea = json!({ "hosts": [host] });
+            if token_env != "GITEA_TOKEN" {
+                gitea["token_env"] = json!(token_env);
+            }
+            entries.push(Entry {
+                comment: "Gitea or Forgejo instances to look PRs up on",
+                key: "gitea",
+                value: gitea,
+            });
+        }
+        2 => {
+            let url = prompt.ask("Gerrit URL", "https://review.example.com")?;
+            let credentials_env = prompt.ask(
+                "Environment variable with user:http-password (empty for anonymous)",
+                "",
+            )?;
+            let mut gerrit = json!({ "url": url });
+            if !credentials_env.is_empty() {
+                gerrit["credentials_env"] = json!(credentials_env);
+            }
+            entries.push(Entry {
+                comment: "Gerrit server to look HEAD's Change-Id up on",
+                key: "gerrit",
+                value: gerrit,
+            });
+        }
+        _ => {}
+    }
+    match provider {
+        0 | 1 => rows.push(vec![
+            "pr_number",
+            "pr_state",
+            "pr_comments",
+            "pr_files",
+            "pr_checks",
+        ]),
+        2 => rows.push(vec!["change"]),
+        _ => {}
+    }
+
+    writeln!(prompt.out, "\nSession")?;
+    let mut claude = vec!["model", "context", "style"];
+    if prompt.confirm("Warn when context is low enough to auto-compact?", true)? {
+        claude.push("compact_warning");
+    }
+    rows.push(claude);
+    let mut session = Vec::new();
+    if prompt.confirm("Show session duration?", true)? {
+        session.push("duration");
+    }
+    if prompt.confirm("Show cost?", false)? {
+        session.push("cost");
+    }
+    let tokens = prompt.choose(
+        "Token display",
+        &[
+            "Input/output totals (125K/42K)",
+            "Context used out of the model's limit (112K/200K)",
+            "Hidden",
+        ],
+        0,
+    )?;
+    if tokens < 2 {
+        session.push("tokens");
+    }
+    if tokens == 1 {
+        entries.push(Entry {
+            comment: "Show tokens as context used out of the model's limit",
+            key: "token_limit",
+            value: json!(true),
+        });
+    }
+    if !session.is_empty() {
+        rows.push(session);
+    }
+
+    entries.insert(
+        0,
+        Entry {
+            comment: "Components on each row, in order",
+            key: "rows",
+            value: json!(rows),
+        },
+    );
+    Ok(format(&entries))
+}
+
+/// `entries` as a JSON object with a `//` comment above each key
+fn format(entries: &[Entry]) -> String {
+    let body: Vec<String> = entries
+        .iter()
+        .map(|e| {
+            let value = match &e.value {
+                // One row per line
+                Value::Array(rows) if e.key == "rows" => {
+                    let rows: Vec<String> = rows.iter().map(|r| format!("    {r}")).collect();
+                    format!("[\n{}\n  ]", rows.join(",\n"))
+                }
+                value => value.to_string(),
+            };
+            format!("  // {}\n  \"{}\": {value}", e.comment, e.key)
+        })
+        .collect();
+    format!("{{\n{}\n}}\n", body.join(",\n"))
+}
+
+struct Prompt<'a, R, W> {
+    input: &'a mut R,
+    out: &'a mut W,
+}
+
+impl<R: BufRead, W: Write> Prompt<'_, R, W> {
+    /// Trimmed answer to `prompt`; empty at end of input
+    fn line(&mut self, prompt: &str) -> io::Result<String> {
+        write!(self.out, "{prompt}")?;
+        self.out.flush()?;
+        let mut line = String::new();
+        self.input.read_line(&mut line)?;
+        Ok(line.trim().to_string())
+    }
+
+    /// Free-form answer, or `default` when empty
+    fn ask(&mut self, question: &str, default: &str) -> io::Result<String> {
+        let answer = if default.is_empty() {
+            self.line(&format!("{question}: "))?
+        } else {
+            self.line(&format!("{question} [{default}]: "))?
+        };
+        Ok(if answer.is_empty() {
+            default.to_string()
+        } else {
+            answer
+        })
+    }
+
+    fn confirm(&mut self, question: &str, default: bool) -> io::Result<bool> {
+        let hint = if default { "Y/n" } else { "y/N" };
+        loop {
+            match self
+                .line(&format!("{question} [{hint}]: "))?
+                .to_lowercase()
+                .as_str()
+            {
+                "" => return Ok(default),
+                "y" | "yes" => return Ok(true),
+                "n" | "no" => return Ok(false),
+                _ => writeln!(self.out, "  Answer y or n")?,
+            }
+        }
+    }
+
+    /// Index into `options`, numbered from 1 on screen
+    fn choose(&mut self, question: &str, options: &[&str], default: usize) -> io::Result<usize> {
+        writeln!(self.out, "{question}")?;
+        for (i, option) in options.iter().enumerate() {
+            writeln!(self.out, "  {}) {option}", i + 1)?;
+        }
+        loop {
+            let answer = self.ask("Choice", &(default + 1).to_string())?;
+            match answer.parse::<usize>() {
+                Ok(n) if (1..=options.len()).contains(&n) => return Ok(n - 1),
+                _ => writeln!(self.out, "  Pick 1 to {}", options.len())?,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cc_statusline::Config;
+
+    fn answers(input: &str) -> (String, Config) {
+        let mut out = Vec::new();
+        let text = run(&mut input.as_bytes(), &mut out).unwrap();
+        let stripped: String = text
+            .lines()
+            .filter(|l| !l.trim_start().starts_with("//"))
+            .collect();
+        let config = serde_json::from_str(&stripped).expect("wizard wrote an invalid config");
+        (text, config)
+    }
+
+    #[test]
+    fn defaults_on_empty_input() {
+        let (text, config) = answers("");
+        assert!(text.starts_with("{\n  // Components on each row, in order\n  \"rows\": [\n"));
+        assert_eq!(config.rows.len(), 5);
+        assert_eq!(config.rows[0][0], "hostname");
+        assert_eq!(config.rows[2][0], "pr_number");
+        assert_eq!(config.rows[4], ["duration", "tokens"]);
+        assert!(config.theme.is_none() && !config.token_limit);
+    }
+
+    #[test]
+    fn answers_shape_the_config() {
+        // Protanopia, default glyphs, no hostname, Gitea, context warning off,
+        // no duration, cost, tokens against the limit
+        let (_, config) = answers("3\n\nn\n2\ngit.lan\nFORGE_TOKEN\nn\nn\ny\n2\n");
+        assert_eq!(config.theme, Some(cc_statusline::Theme::Protanopia));
+        assert!(config.status_glyphs && config.token_limit);
+        assert_eq!(config.rows[0], ["project", "path", "dirs"]);
+        let gitea = config.gitea.unwrap();
+        assert_eq!(gitea.hosts, ["git.lan"]);
+        assert_eq!(gitea.token_env.as_deref(), Some("FORGE_TOKEN"));
+        assert_eq!(config.rows[3], ["model", "context", "style"]);
+        assert_eq!(config.rows[4], ["cost", "tokens"]);
+    }
+
+    #[test]
+    fn invalid_choices_ask_again() {
+        let mut out = Vec::new();
+        run(&mut "9\nx\n2\n".as_bytes(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches("Pick 1 to 3").count(), 2);
+    }
+}
@@ -0,0 +1,223 @@
+//! Convert rendered ANSI output into standalone HTML or SVG
+//!
+//! Only the escapes the renderer emits are understood: truecolor SGR
+//! foregrounds (`38;2;r;g;b`), resets, and OSC 8 hyperlinks. Anything else is
+//! dropped, so the export always matches what a terminal would show.
+
+use std::fmt::Write as _;
+
+/// Tokyo Night background and default foreground, matching the screenshots
+const BACKGROUND: &str = "#1a1b26";
+const FOREGROUND: &str = "#c0caf5";
+/// Monospace cell size used to lay out SVG text
+const CHAR_WIDTH: f32 = 8.4;
+const LINE_HEIGHT: f32 = 20.0;
+const PADDING: f32 = 12.0;
+
+/// A run of text sharing one color and link
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Run {
+    text: String,
+    color: Option<(u8, u8, u8)>,
+    link: Option<String>,
+}
+
+/// Apply an SGR parameter list to the current color
+fn apply_sgr(params: &str, color: &mut Option<(u8, u8, u8)>) {
+    let codes: Vec<u16> = params.split(';').map(|p| p.parse().unwrap_or(0)).collect();
+    let mut i = 0;
+    while i < codes.len() {
+        match codes[i] {
+            0 | 39 => *color = None,
+            38 if codes.get(i + 1) == Some(&2) && i + 4 < codes.len() => {
+                let c = |n: u16| u8::try_from(n).unwrap_or(u8::MAX);
+                *color = Some((c(codes[i + 2]), c(codes[i + 3]), c(codes[i + 4])));
+                i += 4;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+/// Split ANSI output into lines of styled runs
+fn parse(ansi: &str) -> Vec<Vec<Run>> {
+    let mut lines = Vec::new();
+    for line in ansi.lines() {
+        let mut runs: Vec<Run> = Vec::new();
+        let mut color = None;
+        let mut link: Option<String> = None;
+        let mut rest = line;
+        while !rest.is_empty() {
+            if let Some(seq) = rest.strip_prefix("\x1b[") {
+                // CSI: parameters up to the final byte
+                let end = seq
+                    .find(|c: char| c.is_ascii_alphabetic())
+                    .unwrap_or(seq.len());
+                if seq[end..].starts_with('m') {
+                    apply_sgr(&seq[..end], &mut color);
+                }
+                rest = seq.get(end + 1..).unwrap_or_default();
+            } else if let Some(seq) = rest.strip_prefix("\x1b]") {
+                // OSC: terminated by BEL or ST
+                let (body, after) = match (seq.find('\x07'), seq.find("\x1b\\")) {
+                    (Some(b), Some(s)) if s < b => (&seq[..s], &seq[s + 2..]),
+                    (Some(b), _) => (&seq[..b], &seq[b + 1..]),
+                    (None, Some(s)) => (&seq[..s], &seq[s + 2..]),
+                    (None, None) => (seq, ""),
+                };
+                if let Some(url) = body.strip_prefix("8;").and_then(|b| b.split_once(';')) {
+                    link = Some(url.1.to_string()).filter(|u| !u.is_empty());
+                }
+                rest = after;
+            } else if let Some(seq) = rest.strip_prefix('\x1b') {
+                // Unknown escape: drop the next character
+                let mut chars = seq.chars();
+                chars.next();
+                rest = chars.as_str();
+            } else {
+                let end = rest.find('\x1b').unwrap_or(rest.len());
+                let text = &rest[..end];
+                match runs.last_mut() {
+                    Some(last) if last.color == color && last.link == link => {
+                        last.text.push_str(text);
+                    }
+                    _ => runs.push(Run {
+                        text: text.to_string(),
+                        color,
+                        link: link.clone(),
+                    }),
+                }
+                rest = &rest[end..];
+            }
+        }
+        lines.push(runs);
+    }
+    lines
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn hex((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Convert ANSI output into a standalone `<pre>` block with inline styles
+pub fn to_html(ansi: &str) -> String {
+    let mut out = format!(
+        "<pre class=\"cc-statusline\" style=\"background:{BACKGROUND};color:{FOREGROUND};\
+         padding:{PADDING}px;font-family:Menlo,monospace\">"
+    );
+    for (i, line) in parse(ansi).iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        for run in line {
+            let mut html = escape(&run.text);
+            if let Some(color) = run.color {
+                html = format!("<span style=\"color:{}\">{html}</span>", hex(color));
+            }
+            if let Some(link) = &run.link {
+                html = format!(
+                    "<a href=\"{}\" style=\"color:inherit\">{html}</a>",
+                    escape(link)
+                );
+            }
+            out.push_str(&html);
+        }
+    }
+    out.push_str("</pre>\n");
+    out
+}
+
+/// Convert ANSI output into a standalone SVG image
+#[allow(clippy::cast_precision_loss)] // Line and column counts are small
+pub fn to_svg(ansi: &str) -> String {
+    let lines = parse(ansi);
+    let columns = lines
+        .iter()
+        .map(|l| l.iter().map(|r| r.text.chars().count()).sum::<usize>())
+        .max()
+        .unwrap_or(0);
+    let width = PADDING * 2.0 + columns as f32 * CHAR_WIDTH;
+    let height = PADDING * 2.0 + lines.len() as f32 * LINE_HEIGHT;
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         font-family=\"Menlo,monospace\" font-size=\"14\" xml:space=\"preserve\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"{BACKGROUND}\"/>\n"
+    );
+    for (i, line) in lines.iter().enumerate() {
+        let y = PADDING + (i as f32 + 0.75) * LINE_HEIGHT;
+        let _ = write!(
+            out,
+            "<text x=\"{PADDING}\" y=\"{y}\" fill=\"{FOREGROUND}\">"
+        );
+        for run in line {
+            let fill = run
+                .color
+                .map(|c| format!(" fill=\"{}\"", hex(c)))
+                .unwrap_or_default();
+            let tspan = format!("<tspan{fill}>{}</tspan>", escape(&run.text));
+            match &run.link {
+                Some(link) => {
+                    let _ = write!(out, "<a href=\"{}\">{tspan}</a>", escape(link));
+                }
+                None => out.push_str(&tspan),
+            }
+        }
+        out.push_str("</text>\n");
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\x1b[38;2;187;154;247mmain\x1b[0m\x1b[38;2;86;95;137m • \x1b[0m\
+        \x1b]8;;https://x/pull/42\x07\x1b[38;2;125;207;255m#42\x1b[0m\x1b]8;;\x07\n\
+        plain <text>\n";
+
+    #[test]
+    fn parse_colors_and_links() {
+        let lines = parse(SAMPLE);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0][0].text, "main");
+        assert_eq!(lines[0][0].color, Some((187, 154, 247)));
+        assert_eq!(lines[0][2].text, "#42");
+        assert_eq!(lines[0][2].link.as_deref(), Some("https://x/pull/42"));
+        assert_eq!(lines[1][0].color, None);
+    }
+
+    #[test]
+    fn html_export() {
+        let html = to_html(SAMPLE);
+        assert!(html.contains("<span style=\"color:#bb9af7\">main</span>"));
+        assert!(html.contains("<a href=\"https://x/pull/42\""));
+        assert!(html.contains("plain &lt;text&gt;"));
+        assert!(!html.contains('\x1b'));
+    }
+
+    #[test]
+    fn svg_export() {
+        let svg = to_svg(SAMPLE);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("<tspan fill=\"#bb9af7\">main</tspan>"));
+        assert!(svg.contains("<a href=\"https://x/pull/42\">"));
+        assert_eq!(svg.matches("<text ").count(), 2);
+    }
+}
@@ -0,0 +1,151 @@
+//! C ABI for embedding the renderer in editors (ffi feature)
+//!
+//! Build the shared library with:
+//!
+//! ```sh
+//! cargo rustc --release --lib --features ffi --crate-type cdylib
+//! ```
+//!
+//! See `include/cc_statusline.h` for the C declarations. Strings returned by
+//! [`cc_statusline_render`] are owned by the library and must be released with
+//! [`cc_statusline_free`].
+
+use crate::{ClaudeInput, Config, StatusLine};
+use serde::Deserialize;
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+/// Options JSON accepted by `cc_statusline_render`
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RenderOptions {
+    /// Directory to inspect for git state (defaults to the input's cwd)
+    cwd: Option<String>,
+    /// Profile to merge over the config file
+    profile: Option<String>,
+    /// Inline config used instead of the config file
+    config: Option<Config>,
+}
+
+/// Read a nullable C string; None for NULL or invalid UTF-8
+unsafe fn read_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    // SAFETY: caller guarantees a valid NUL-terminated string
+    unsafe { CStr::from_ptr(s) }.to_str().ok()
+}
+
+fn render_to_string(json_input: &str, options: &str) -> Option<String> {
+    let options: RenderOptions = if options.is_empty() {
+        RenderOptions::default()
+    } else {
+        serde_json::from_str(options).ok()?
+    };
+    let config = options
+        .config
+        .unwrap_or_else(|| Config::load(options.profile.as_deref()));
+
+    let mut builder = StatusLine::builder(ClaudeInput::from_json(json_input)).config(config);
+    if let Some(cwd) = options.cwd {
+        builder = builder.cwd(cwd);
+    }
+
+    let mut out = Vec::new();
+    builder.build().render(&mut out).ok()?;
+    String::from_utf8(out).ok()
+}
+
+/// Render the status line for `json_input` (Claude Code's status line JSON)
+///
+/// `options` is a nullable JSON object with optional `cwd`, `profile`, and
+/// `config` keys. Returns the ANSI-colored rows separated by newlines, or
+/// NULL if an argument is invalid. Free the result with `cc_statusline_free`.
+///
+/// # Safety
+///
+/// `json_input` and `options` must each be NULL or a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cc_statusline_render(
+    json_input: *const c_char,
+    options: *const c_char,
+) -> *mut c_char {
+    // SAFETY: forwarded from the caller's contract
+    let Some(json_input) = (unsafe { read_str(json_input) }) else {
+        return ptr::null_mut();
+    };
+    let options = if options.is_null() {
+        ""
+    } else {
+        // SAFETY: forwarded from the caller's contract
+        match unsafe { read_str(options) } {
+            Some(options) => options,
+            None => return ptr::null_mut(),
+        }
+    };
+
+    // Never unwind across the C boundary
+    let rendered = panic::catch_unwind(AssertUnwindSafe(|| render_to_string(json_input, options)));
+    match rendered.ok().flatten().and_then(|s| CString::new(s).ok()) {
+        Some(s) => s.into_raw(),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Free a string returned by `cc_statusline_render`
+///
+/// # Safety
+///
+/// `s` must be NULL or a pointer returned by `cc_statusline_render` that has
+/// not already been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn cc_statusline_free(s: *mut c_char) {
+    if !s.is_null() {
+        // SAFETY: caller guarantees `s` came from CString::into_raw
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(json: &str, options: Option<&str>) -> Option<String> {
+        let json = CString::new(json).unwrap();
+        let options = options.map(|o| CString::new(o).unwrap());
+        let options_ptr = options.as_ref().map_or(ptr::null(), |o| o.as_ptr());
+        unsafe {
+            let out = cc_statusline_render(json.as_ptr(), options_ptr);
+            if out.is_null() {
+                return None;
+            }
+            let s = CStr::from_ptr(out).to_str().unwrap().to_string();
+            cc_statusline_free(out);
+            Some(s)
+        }
+    }
+
+    #[test]
+    fn render_with_inline_config() {
+        let out = render(
+            r#"{"cwd": "/tmp", "git": {"branch": "main"}, "model": {"display_name": "Opus"}}"#,
+            Some(r#"{"config": {"rows": [["branch", "model"]]}}"#),
+        )
+        .unwrap();
+        assert!(out.contains("main"), "{out}");
+        assert!(out.contains("Opus"), "{out}");
+        assert!(out.ends_with('\n'));
+    }
+
+    #[test]
+    fn render_invalid_arguments_return_null() {
+        assert!(render("{}", Some("not json")).is_none());
+        assert!(unsafe { cc_statusline_render(ptr::null(), ptr::null()) }.is_null());
+    }
+
+    #[test]
+    fn free_null_is_noop() {
+        unsafe { cc_statusline_free(ptr::null_mut()) };
+    }
+}
@@ -0,0 +1,56 @@
+//! Row-diff framing for `--serve --diff`
+//!
+//! Hosts that redraw on every input (tmux, watch loops, SSH) only need the
+//! rows that changed. Each render is sent as a frame:
+//!
+//! ```text
+//! @3              the status line now has 3 rows; drop any beyond them
+//! 1<TAB>row text  row 1 (counting from 0) changed to this
+//!                 an empty line ends the frame
+//! ```
+//!
+//! The first frame lists every row. A frame with only the header means
+//! nothing changed.
+
+use std::fmt::Write;
+
+/// Rows of the previous render, to diff the next one against
+#[derive(Debug, Default)]
+pub struct RowDiff {
+    previous: Vec<String>,
+}
+
+impl RowDiff {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Frame for `rendered` (newline-terminated rows), relative to the last call
+    pub fn frame(&mut self, rendered: &str) -> String {
+        let rows: Vec<String> = rendered.lines().map(str::to_string).collect();
+        let mut frame = format!("@{}\n", rows.len());
+        for (i, row) in rows.iter().enumerate() {
+            if self.previous.get(i) != Some(row) {
+                let _ = writeln!(frame, "{i}\t{row}");
+            }
+        }
+        frame.push('\n');
+        self.previous = rows;
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_carry_only_changed_rows() {
+        let mut diff = RowDiff::new();
+        assert_eq!(diff.frame("a\nb\n"), "@2\n0\ta\n1\tb\n\n");
+        assert_eq!(diff.frame("a\nb\n"), "@2\n\n");
+        assert_eq!(diff.frame("a\nc\nd\n"), "@3\n1\tc\n2\td\n\n");
+        assert_eq!(diff.frame("a\n"), "@1\n\n");
+        assert_eq!(diff.frame("a\nd\n"), "@2\n1\td\n\n");
+    }
+}
@@ -0,0 +1,279 @@
+//! Builtin filesystem monitor (`core.fsmonitor = true`)
+//!
+//! When git writes the index with the monitor enabled, it stores the
+//! daemon's token and a bitmap of entries that weren't known clean (the
+//! FSMN extension). The daemon answers which paths changed since that
+//! token, so only those and the dirty entries need a stat.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::time::Duration;
+
+/// Time allowed for the daemon to answer
+const QUERY_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Index entries that may differ from the working tree
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct FsMonitor {
+    /// Entry positions git had already marked dirty
+    dirty: Vec<bool>,
+    /// Paths the daemon reported; a trailing '/' covers a whole directory
+    changed: Vec<Vec<u8>>,
+}
+
+impl FsMonitor {
+    /// Whether the entry at `idx` with `path` has to be checked on disk
+    pub(crate) fn needs_check(&self, idx: usize, path: &[u8]) -> bool {
+        self.dirty.get(idx).copied().unwrap_or(true)
+            || self.changed.iter().any(|c| {
+                if c.ends_with(b"/") {
+                    path.starts_with(c)
+                } else {
+                    c.as_slice() == path
+                }
+            })
+    }
+
+    /// Identifies the set of changed paths; equal fingerprints for the same
+    /// index mean nothing in the working tree changed in between. Never 0
+    pub(crate) fn fingerprint(&self) -> u64 {
+        let mut changed: Vec<&[u8]> = self.changed.iter().map(Vec::as_slice).collect();
+        changed.sort_unstable();
+        changed.dedup();
+        let mut hasher = DefaultHasher::new();
+        changed.hash(&mut hasher);
+        hasher.finish().max(1)
+    }
+}
+
+/// Ask the builtin daemon what changed since the index was written
+/// None means a full walk: the monitor is off, unreachable, or asked for one
+pub(crate) fn query(repo: &gix::Repository) -> Option<FsMonitor> {
+    if repo.config_snapshot().boolean("core.fsmonitor") != Some(true) {
+        return None;
+    }
+    let file = File::open(repo.index_path()).ok()?;
+    let index = unsafe { memmap2::Mmap::map(&file).ok()? };
+    let (token, dirty) = read_extension(&index, repo.object_hash().len_in_bytes())?;
+    let response = send_query(&repo.git_dir().join("fsmonitor--daemon.ipc"), &token)?;
+    let changed = parse_response(&response)?;
+    Some(FsMonitor { dirty, changed })
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Token and dirty bitmap from the index's FSMN extension
+fn read_extension(data: &[u8], hash_len: usize) -> Option<(Vec<u8>, Vec<bool>)> {
+    if data.get(0..4)? != b"DIRC" {
+        return None;
+    }
+    let version = be_u32(data, 4)?;
+    let count = be_u32(data, 8)?;
+    let mut pos = 12;
+    for _ in 0..count {
+        let flags_at = pos + 40 + hash_len;
+        let flags = u16::from_be_bytes(data.get(flags_at..flags_at + 2)?.try_into().ok()?);
+        let extended = version >= 3 && flags & 0x4000 != 0;
+        let path_at = flags_at + 2 + if extended { 2 } else { 0 };
+        pos = if version == 4 {
+            // Prefix-compressed path: a varint, then a NUL-terminated suffix
+            let varint_len = data.get(path_at..)?.iter().position(|b| b & 0x80 == 0)? + 1;
+            let suffix_at = path_at + varint_len;
+            suffix_at + data.get(suffix_at..)?.iter().position(|&b| b == 0)? + 1
+        } else {
+            let name_len = match usize::from(flags & 0xfff) {
+                0xfff => data.get(path_at..)?.iter().position(|&b| b == 0)?,
+                n => n,
+            };
+            // Entries are NUL-padded to a multiple of 8 bytes
+            pos + (path_at - pos + name_len + 8) / 8 * 8
+        };
+    }
+
+    let end = data.len().checked_sub(hash_len)?;
+    while pos + 8 <= end {
+        let signature = &data[pos..pos + 4];
+        let size = usize::try_from(be_u32(data, pos + 4)?).ok()?;
+        let body = data.get(pos + 8..pos + 8 + size)?;
+        match signature {
+            // Split index: entry positions refer to the shared index
+            b"link" => return None,
+            b"FSMN" => return parse_fsmn(body, count as usize),
+            _ => pos += 8 + size,
+        }
+    }
+    None
+}
+
+/// FSMN body: version 2, NUL-terminated token, then an EWAH bitmap
+fn parse_fsmn(body: &[u8], entries: usize) -> Option<(Vec<u8>, Vec<bool>)> {
+    if be_u32(body, 0)? != 2 {
+        return None; // Version 1 tokens are hook timestamps
+    }
+    let token_len = body.get(4..)?.iter().position(|&b| b == 0)?;
+    let token = body[4..4 + token_len].to_vec();
+    let bitmap_at = 4 + token_len + 1;
+    let size = usize::try_from(be_u32(body, bitmap_at)?).ok()?;
+    let mut dirty = decode_ewah(body.get(bitmap_at + 4..bitmap_at + 4 + size)?)?;
+    dirty.resize(entries, false);
+    Some((token, dirty))
+}
+
+/// Decode git's EWAH-compressed bitmap into one bool per bit
+fn decode_ewah(data: &[u8]) -> Option<Vec<bool>> {
+    let bits = usize::try_from(be_u32(data, 0)?).ok()?;
+    let word_count = usize::try_from(be_u32(data, 4)?).ok()?;
+    let word = |i: usize| {
+        let at = 8 + i * 8;
+        Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+    };
+    let mut out = Vec::with_capacity(bits);
+    let mut i = 0;
+    while i < word_count {
+        // Marker word: run bit, run length in words, then literal word count
+        let marker = word(i)?;
+        let run_len = usize::try_from((marker >> 1) & 0xffff_ffff).ok()?;
+        let literals = usize::try_from(marker >> 33).ok()?;
+        out.resize(out.len() + run_len * 64, marker & 1 == 1);
+        for j in 0..literals {
+            let literal = word(i + 1 + j)?;
+            out.extend((0..64).map(|b| literal >> b & 1 == 1));
+        }
+        i += 1 + literals;
+    }
+    out.truncate(bits);
+    Some(out)
+}
+
+/// Paths from a daemon answer: token, then NUL-separated paths
+/// A lone "/" means the daemon lost track and a full walk is needed
+fn parse_response(response: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut parts = response.split(|&b| b == 0);
+    parts.next()?;
+    let paths: Vec<Vec<u8>> = parts
+        .filter(|p| !p.is_empty())
+        .map(<[u8]>::to_vec)
+        .collect();
+    if paths.first().is_some_and(|p| p == b"/") {
+        return None;
+    }
+    Some(paths)
+}
+
+/// One request over the daemon's socket, framed as pkt-lines
+#[cfg(unix)]
+fn send_query(socket: &Path, token: &[u8]) -> Option<Vec<u8>> {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+
+    let mut stream = UnixStream::connect(socket).ok()?;
+    stream.set_read_timeout(Some(QUERY_TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(QUERY_TIMEOUT)).ok()?;
+    let mut request = format!("{:04x}", token.len() + 4).into_bytes();
+    request.extend_from_slice(token);
+    request.extend_from_slice(b"0000");
+    stream.write_all(&request).ok()?;
+
+    let mut response = Vec::new();
+    loop {
+        let mut len = [0u8; 4];
+        stream.read_exact(&mut len).ok()?;
+        let len = usize::from_str_radix(std::str::from_utf8(&len).ok()?, 16).ok()?;
+        if len == 0 {
+            return Some(response);
+        }
+        let mut payload = vec![0u8; len.checked_sub(4)?];
+        stream.read_exact(&mut payload).ok()?;
+        response.extend_from_slice(&payload);
+    }
+}
+
+#[cfg(not(unix))]
+fn send_query(_socket: &Path, _token: &[u8]) -> Option<Vec<u8>> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// EWAH bitmap: one marker word with a single literal word
+    fn ewah(bits: u32, literal: u64) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&bits.to_be_bytes());
+        out.extend_from_slice(&2u32.to_be_bytes());
+        out.extend_from_slice(&(1u64 << 33).to_be_bytes());
+        out.extend_from_slice(&literal.to_be_bytes());
+        out.extend_from_slice(&0u32.to_be_bytes());
+        out
+    }
+
+    #[test]
+    fn ewah_literal_bits() {
+        let bits = decode_ewah(&ewah(4, 0b0101)).unwrap();
+        assert_eq!(bits, vec![true, false, true, false]);
+        assert_eq!(decode_ewah(&[0, 0]), None);
+    }
+
+    #[test]
+    fn reads_fsmn_after_entries() {
+        // Header plus one v2 entry for "a.txt" (62 fixed bytes + 5, padded to 72)
+        let mut index = b"DIRC".to_vec();
+        index.extend_from_slice(&2u32.to_be_bytes());
+        index.extend_from_slice(&1u32.to_be_bytes());
+        let mut entry = vec![0u8; 60];
+        entry.extend_from_slice(&5u16.to_be_bytes());
+        entry.extend_from_slice(b"a.txt");
+        entry.resize(72, 0);
+        index.extend_from_slice(&entry);
+
+        let mut body = 2u32.to_be_bytes().to_vec();
+        body.extend_from_slice(b"builtin:42\0");
+        let bitmap = ewah(1, 1);
+        body.extend_from_slice(&u32::try_from(bitmap.len()).unwrap().to_be_bytes());
+        body.extend_from_slice(&bitmap);
+        index.extend_from_slice(b"FSMN");
+        index.extend_from_slice(&u32::try_from(body.len()).unwrap().to_be_bytes());
+        index.extend_from_slice(&body);
+        index.extend_from_slice(&[0u8; 20]);
+
+        let (token, dirty) = read_extension(&index, 20).unwrap();
+        assert_eq!(token, b"builtin:42");
+        assert_eq!(dirty, vec![true]);
+    }
+
+    #[test]
+    fn daemon_response_paths() {
+        let paths = parse_response(b"builtin:43\0src/main.rs\0docs/\0").unwrap();
+        assert_eq!(paths, vec![b"src/main.rs".to_vec(), b"docs/".to_vec()]);
+        // Trivial response: everything must be checked
+        assert_eq!(parse_response(b"builtin:43\0/\0"), None);
+
+        let monitor = FsMonitor {
+            dirty: vec![false, true, false, false],
+            changed: paths,
+        };
+        assert!(!monitor.needs_check(0, b"README.md"));
+        assert!(monitor.needs_check(1, b"README.md"));
+        assert!(monitor.needs_check(2, b"src/main.rs"));
+        assert!(monitor.needs_check(3, b"docs/guide.md"));
+        // Entries added after the index was written have no bit
+        assert!(monitor.needs_check(9, b"new.rs"));
+    }
+
+    #[test]
+    fn fingerprint_ignores_order() {
+        let monitor = |changed: &[&[u8]]| FsMonitor {
+            dirty: Vec::new(),
+            changed: changed.iter().map(|p| p.to_vec()).collect(),
+        };
+        let a = monitor(&[b"a.txt", b"docs/"]).fingerprint();
+        assert_eq!(a, monitor(&[b"docs/", b"a.txt", b"a.txt"]).fingerprint());
+        assert_ne!(a, monitor(&[b"a.txt", b"docs/", b"u1.txt"]).fingerprint());
+        assert_ne!(monitor(&[]).fingerprint(), 0);
+    }
+}
@@ -0,0 +1,144 @@
+//! Gerrit review state of the HEAD commit's change
+//!
+//! The change is found by the `Change-Id` trailer Gerrit's commit-msg hook
+//! adds, and cached like the other slowly changing server data.
+
+use crate::cache::get_cache_dir;
+use crate::config::GerritConfig;
+use crate::repo_status::cached;
+use crate::sys::{self, TlsOptions};
+use crate::{base64, hash_path, percent_encode};
+use serde::{Deserialize, Serialize};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+const GERRIT_TTL: u64 = 60; // seconds
+
+/// Prefix Gerrit puts before JSON responses against XSSI
+const XSSI_PREFIX: &str = ")]}'";
+
+/// A Gerrit change and its score per label
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct GerritChange {
+    pub(crate) number: u64,
+    /// Web page of the change
+    pub(crate) url: String,
+    /// Code-Review score; None before anyone has voted
+    pub(crate) code_review: Option<i64>,
+    /// Verified score; None if the project has no such label or no votes
+    pub(crate) verified: Option<i64>,
+}
+
+impl GerritChange {
+    /// Scores like "CR+2 V+1", skipping labels without votes
+    pub(crate) fn scores(&self) -> String {
+        [("CR", self.code_review), ("V", self.verified)]
+            .iter()
+            .filter_map(|(name, score)| score.map(|s| format!("{name}{s:+}")))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Cached review state of change `change_id`, refreshed on the returned thread
+pub(crate) fn change_status(
+    gerrit: &GerritConfig,
+    change_id: &str,
+    offline: bool,
+    tls: &TlsOptions,
+) -> (Option<GerritChange>, Option<JoinHandle<()>>) {
+    let server = gerrit.url.trim_end_matches('/').to_string();
+    let path = get_cache_dir().join(format!(
+        "gerrit-{:016x}.cache",
+        hash_path(&format!("{server}/{change_id}"))
+    ));
+    let credentials = gerrit.credentials_env.as_deref().and_then(sys::var);
+    let (change_id, tls) = (change_id.to_string(), tls.clone());
+    let (value, refresh) = cached(path, GERRIT_TTL, offline, move || {
+        let change = fetch_change(&server, &change_id, credentials.as_deref(), &tls)?;
+        serde_json::to_string(&change).ok()
+    });
+    (value.and_then(|v| serde_json::from_str(&v).ok()), refresh)
+}
+
+/// Query the change with detailed labels; authenticated requests go under `/a/`
+fn fetch_change(
+    server: &str,
+    change_id: &str,
+    credentials: Option<&str>,
+    tls: &TlsOptions,
+) -> Option<GerritChange> {
+    let auth = credentials.map(|c| format!("Basic {}", base64(c.as_bytes())));
+    let mut headers = vec![
+        ("Accept", "application/json"),
+        ("User-Agent", "cc-statusline"),
+    ];
+    if let Some(auth) = &auth {
+        headers.push(("Authorization", auth));
+    }
+    let prefix = if auth.is_some() { "/a" } else { "" };
+    let url = format!(
+        "{server}{prefix}/changes/?q={}&o=DETAILED_LABELS&n=1",
+        percent_encode(&format!("change:{change_id}"))
+    );
+    let body = sys::http_get(&url, &headers, Some(Duration::from_secs(5)), tls).ok()?;
+    parse_changes(server, &body)
+}
+
+/// First change of a query response
+fn parse_changes(server: &str, body: &str) -> Option<GerritChange> {
+    let json = body.strip_prefix(XSSI_PREFIX).unwrap_or(body);
+    let changes: serde_json::Value = serde_json::from_str(json).ok()?;
+    let change = changes.get(0)?;
+    let number = change["_number"].as_u64()?;
+    Some(GerritChange {
+        number,
+        url: format!("{server}/c/{number}"),
+        code_review: label_score(&change["labels"]["Code-Review"]),
+        verified: label_score(&change["labels"]["Verified"]),
+    })
+}
+
+/// The most negative vote if any blocks, else the highest
+fn label_score(label: &serde_json::Value) -> Option<i64> {
+    let votes: Vec<i64> = label["all"]
+        .as_array()?
+        .iter()
+        .filter_map(|v| v["value"].as_i64())
+        .filter(|v| *v != 0)
+        .collect();
+    let min = votes.iter().copied().min()?;
+    if min < 0 {
+        Some(min)
+    } else {
+        votes.iter().copied().max()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_labels_from_query() {
+        let body = r#")]}'
+[{"_number": 4711, "labels": {
+    "Code-Review": {"all": [{"value": 1}, {"value": 2}, {"value": 0}]},
+    "Verified": {"all": [{"value": 1}, {"value": -1}]}
+}}]"#;
+        let change = parse_changes("https://review.example.com", body).unwrap();
+        assert_eq!(change.number, 4711);
+        assert_eq!(change.url, "https://review.example.com/c/4711");
+        assert_eq!(change.scores(), "CR+2 V-1");
+    }
+
+    #[test]
+    fn unvoted_labels_are_skipped() {
+        let body = r#")]}'
+[{"_number": 7, "labels": {"Code-Review": {"all": [{"value": 0}]}}}]"#;
+        let change = parse_changes("https://r", body).unwrap();
+        assert_eq!(change.code_review, None);
+        assert_eq!(change.scores(), "");
+        assert_eq!(parse_changes("https://r", ")]}'\n[]"), None);
+    }
+}
@@ -0,0 +1,1317 @@
+//! Git repository discovery and status
+
+use crate::cache::{
+    MmapCache, RepoState, cache_git_info, get_cached_git_info, load_mmap_cache, oid_field,
+    save_mmap_cache,
+};
+use crate::fsmonitor::{self, FsMonitor};
+use crate::{display_bytes, memo, netfs, sys};
+use gix::Repository;
+use imara_diff::intern::InternedInput;
+use imara_diff::sink::Counter;
+use imara_diff::{Algorithm, sources};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+use std::time::{Instant, SystemTime};
+
+/// Full-mode line counts are reused for this many seconds when the index and HEAD match
+const FULL_CACHE_TTL: u64 = 5;
+
+/// On a network filesystem, stats this fresh are reused even after the index changes
+const SLOW_FS_TTL: u64 = 30;
+
+/// Seconds a long-running process reuses stats before checking whether
+/// untracked files appeared, which stats directories rather than walking them
+const UNTRACKED_RECHECK: u64 = 2;
+
+/// How much work to spend on working-tree stats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", try_from = "String")]
+pub enum GitMode {
+    /// Changed files plus added/deleted line counts
+    Full,
+    /// Changed files only
+    #[default]
+    Fast,
+    /// Whether anything changed at all; the walk stops at the first change
+    Dirty,
+    /// Branch and ahead/behind only; the index is never walked
+    Minimal,
+}
+
+impl GitMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "full" => Some(Self::Full),
+            "fast" => Some(Self::Fast),
+            "dirty" => Some(Self::Dirty),
+            "minimal" => Some(Self::Minimal),
+            _ => None,
+        }
+    }
+
+    /// Cheaper mode for a repo on a network filesystem: no line counts
+    pub(crate) fn for_slow_fs(self, slow: bool) -> Self {
+        match self {
+            Self::Full if slow => Self::Fast,
+            mode => mode,
+        }
+    }
+}
+
+/// Parsed leniently, so `CC_STATUSLINE_GIT_MODE=FAST` works like the config key
+impl TryFrom<String> for GitMode {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        Self::parse(&name).ok_or_else(|| {
+            format!("unknown git_mode `{name}`, expected full, fast, dirty, or minimal")
+        })
+    }
+}
+
+/// Git state for the current directory
+/// Counts are None when no configured component or condition needed them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitInfo {
+    pub branch: String,
+    pub worktree: Option<String>,
+    pub changed_files: Option<u32>,
+    pub lines_added: Option<u32>,
+    pub lines_deleted: Option<u32>,
+    pub ahead: Option<u32>,
+    pub behind: Option<u32>,
+}
+
+/// Holds repository state for lazy evaluation of expensive git operations
+pub(crate) struct GitRepo {
+    pub(crate) repo: Repository,
+    /// Branch name for display, see [`display_bytes`]
+    pub(crate) branch: String,
+    /// Branch name as stored in the ref, which need not be UTF-8
+    pub(crate) branch_ref: Vec<u8>,
+    pub(crate) worktree: Option<String>,
+    pub(crate) git_dir: String,
+    pub(crate) work_dir: String,
+    /// Work tree is on NFS, SMB, or FUSE
+    pub(crate) slow_fs: bool,
+}
+
+/// Shareable handle to a discovered repo; unlike `GitRepo` it can move across threads
+#[derive(Clone)]
+pub(crate) struct RepoLocation {
+    repo: gix::ThreadSafeRepository,
+    git_dir: String,
+    work_dir: String,
+    branch: String,
+    branch_ref: Vec<u8>,
+    worktree: Option<String>,
+    slow_fs: bool,
+}
+
+impl RepoLocation {
+    pub(crate) fn git_dir(&self) -> &str {
+        &self.git_dir
+    }
+
+    pub(crate) fn branch(&self) -> &str {
+        &self.branch
+    }
+
+    /// Thread-local handle to the already-open repository, without touching disk
+    pub(crate) fn open(self) -> GitRepo {
+        GitRepo {
+            repo: self.repo.to_thread_local(),
+            branch: self.branch,
+            branch_ref: self.branch_ref,
+            worktree: self.worktree,
+            git_dir: self.git_dir,
+            work_dir: self.work_dir,
+            slow_fs: self.slow_fs,
+        }
+    }
+}
+
+impl GitRepo {
+    pub(crate) fn location(&self) -> RepoLocation {
+        RepoLocation {
+            repo: self.repo.clone().into_sync(),
+            git_dir: self.git_dir.clone(),
+            work_dir: self.work_dir.clone(),
+            branch: self.branch.clone(),
+            branch_ref: self.branch_ref.clone(),
+            worktree: self.worktree.clone(),
+            slow_fs: self.slow_fs,
+        }
+    }
+
+    /// Compute diff stats like `git status`: a file counts once if it is staged
+    /// (index differs from HEAD) or modified in the working tree (differs from
+    /// the index). Files whose size and mtime match the index are skipped;
+    /// the rest are hashed, so touched-but-identical files don't count.
+    /// With `with_lines`, lines are counted from HEAD to the working tree.
+    /// With the builtin fsmonitor running, only paths it reports are checked.
+    /// Large indexes are split across threads; with `max_files`, the walk
+    /// stops once that many changed files are found and `capped` is set.
+    pub(crate) fn diff_stats(&self, with_lines: bool, max_files: Option<u32>) -> Option<DiffStats> {
+        self.diff_stats_split(with_lines, max_files, None)
+    }
+
+    /// [`diff_stats`](Self::diff_stats) over `threads` parts of the index,
+    /// or as many as its size and the CPUs warrant
+    fn diff_stats_split(
+        &self,
+        with_lines: bool,
+        max_files: Option<u32>,
+        threads: Option<usize>,
+    ) -> Option<DiffStats> {
+        let started = Instant::now();
+        let index = self.repo.index().ok()?;
+        let workdir = self.repo.work_dir()?;
+        let head_tree = self.repo.head_tree_id().ok();
+        // A valid cache-tree matching HEAD means nothing is staged, which
+        // spares expanding HEAD's tree; an unborn branch has everything staged
+        let nothing_staged = index
+            .tree()
+            .zip(head_tree)
+            .is_some_and(|(t, head)| t.num_entries.is_some() && t.id == head.detach());
+        let head = head_tree
+            .filter(|_| !nothing_staged)
+            .and_then(|id| self.repo.index_from_tree(&id).ok());
+        let monitor = fsmonitor::query(&self.repo);
+        // Taken before the walk, so files created during it show up next time
+        let untracked_key = self.untracked_key(&index, monitor.as_ref());
+        let scan = Scan {
+            index: &index,
+            head: head.as_deref(),
+            nothing_staged,
+            monitor: monitor.as_ref(),
+            workdir,
+            stat: self.repo.stat_options().unwrap_or_default(),
+            fs: self.repo.filesystem_options().unwrap_or_default(),
+            with_lines,
+            max_files: max_files.filter(|&m| m > 0).unwrap_or(u32::MAX),
+            found: AtomicU32::new(0),
+        };
+
+        let len = index.entries().len();
+        let threads = threads.unwrap_or_else(|| {
+            thread::available_parallelism()
+                .map_or(1, NonZeroUsize::get)
+                .min(MAX_SCAN_THREADS)
+                .min(len / PARALLEL_SCAN_ENTRIES)
+                .max(1)
+        });
+        let mut stats = if threads == 1 {
+            scan.entries(&self.repo, 0..len)
+        } else {
+            let shared = self.repo.clone().into_sync();
+            let chunk = len.div_ceil(threads);
+            thread::scope(|s| {
+                let workers: Vec<_> = (0..len)
+                    .step_by(chunk)
+                    .map(|start| {
+                        let (scan, shared) = (&scan, &shared);
+                        s.spawn(move || {
+                            scan.entries(&shared.to_thread_local(), start..len.min(start + chunk))
+                        })
+                    })
+                    .collect();
+                workers
+                    .into_iter()
+                    .try_fold(DiffStats::default(), |mut total, w| {
+                        total.merge(w.join().ok()?);
+                        Some(total)
+                    })
+            })?
+        };
+        stats.capped = scan.found.load(Ordering::Relaxed) >= scan.max_files;
+
+        if stats.capped {
+            stats.files = scan.max_files;
+        } else {
+            // Staged deletions: in HEAD but no longer in the index
+            if let Some(head) = &head {
+                for entry in head.entries() {
+                    if index.entry_by_path(entry.path(head)).is_none() {
+                        stats.files += 1;
+                        stats.staged += 1;
+                        if with_lines {
+                            stats.add_lines(&blob(&self.repo, entry.id), &[]);
+                        }
+                    }
+                }
+            }
+            self.add_untracked(&mut stats, &index, scan.max_files);
+        }
+        stats.untracked_key = untracked_key;
+        stats.index_entries = u32::try_from(len).unwrap_or(u32::MAX);
+        stats.scan_ms = u32::try_from(started.elapsed().as_millis()).unwrap_or(u32::MAX);
+        Some(stats)
+    }
+
+    /// Count untracked files into `stats`, stopping once `files` reaches `max_files`
+    fn add_untracked(&self, stats: &mut DiffStats, index: &gix::index::State, max_files: u32) {
+        let room = max_files.saturating_sub(stats.files);
+        stats.untracked = if room > 0 {
+            self.untracked_count(index, room)
+        } else {
+            0
+        };
+        stats.files += stats.untracked;
+        if stats.files >= max_files {
+            stats.capped = true;
+            stats.files = max_files;
+        }
+    }
+
+    /// Recount the untracked part of stats whose tracked part is still current
+    fn refresh_untracked(&self, stats: &mut DiffStats, max_files: Option<u32>) {
+        // A walk capped before any untracked file was counted stays capped
+        if (stats.capped && stats.untracked == 0) || self.untracked_mode().is_none() {
+            return;
+        }
+        stats.files -= stats.untracked;
+        stats.untracked = 0;
+        stats.capped = false;
+        if let Ok(index) = self.repo.index() {
+            let max_files = max_files.filter(|&m| m > 0).unwrap_or(u32::MAX);
+            self.add_untracked(stats, &index, max_files);
+        }
+    }
+
+    /// What the untracked count depends on; a different key means it has to be
+    /// retaken. With the fsmonitor running, that's its answer. Otherwise it's
+    /// the mtimes of the work tree root and every directory holding tracked
+    /// files, since creating or removing a file changes its directory's mtime;
+    /// files under ignored or untracked directories don't change it, and the
+    /// default `showUntrackedFiles` mode counts such a directory once anyway.
+    /// Never 0
+    fn untracked_key(&self, index: &gix::index::State, monitor: Option<&FsMonitor>) -> u64 {
+        if let Some(monitor) = monitor {
+            return monitor.fingerprint();
+        }
+        let mut hasher = DefaultHasher::new();
+        if let Some(workdir) = self.repo.work_dir() {
+            // Each entry's directory and its ancestors, the root as ""
+            let mut dirs: HashSet<&[u8]> = HashSet::from([&b""[..]]);
+            for entry in index.entries() {
+                let mut path: &[u8] = entry.path(index);
+                while let Some(slash) = path.iter().rposition(|&b| b == b'/') {
+                    path = &path[..slash];
+                    if !dirs.insert(path) {
+                        break;
+                    }
+                }
+            }
+            let mut dirs: Vec<_> = dirs.into_iter().collect();
+            dirs.sort_unstable();
+            for dir in dirs {
+                let mtime = gix::path::try_from_byte_slice(dir)
+                    .ok()
+                    .and_then(|dir| fs::symlink_metadata(workdir.join(dir)).ok())
+                    .and_then(|m| m.modified().ok());
+                (dir, mtime).hash(&mut hasher);
+            }
+        }
+        hasher.finish().max(1)
+    }
+
+    /// How `status.showUntrackedFiles` lists untracked files; None for `no`
+    fn untracked_mode(&self) -> Option<gix::dir::walk::EmissionMode> {
+        use gix::dir::walk::EmissionMode;
+        let config = self.repo.config_snapshot();
+        match config
+            .string("status.showUntrackedFiles")
+            .map(|v| v.to_string().to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("no" | "false" | "off" | "0") => None,
+            Some("all") => Some(EmissionMode::Matching),
+            _ => Some(EmissionMode::CollapseDirectory),
+        }
+    }
+
+    /// Untracked files as `git status` lists them: none with
+    /// `status.showUntrackedFiles=no`, one per file with `all`, and otherwise
+    /// one per untracked directory. Ignore rules, including `core.excludesFile`
+    /// and `.git/info/exclude`, apply as usual. The walk stops at `limit`.
+    fn untracked_count(&self, index: &gix::index::State, limit: u32) -> u32 {
+        let Some(mode) = self.untracked_mode() else {
+            return 0;
+        };
+        let Ok(options) = self.repo.dirwalk_options() else {
+            return 0;
+        };
+        let mut untracked = Untracked { count: 0, limit };
+        let interrupt = std::sync::atomic::AtomicBool::new(false);
+        let walked = self.repo.dirwalk(
+            index,
+            std::iter::empty::<&str>(),
+            &interrupt,
+            options.emit_untracked(mode),
+            &mut untracked,
+        );
+        if walked.is_err() {
+            return 0;
+        }
+        untracked.count
+    }
+}
+
+/// Counts untracked entries of a directory walk, up to `limit`
+struct Untracked {
+    count: u32,
+    limit: u32,
+}
+
+impl gix::dir::walk::Delegate for Untracked {
+    fn emit(
+        &mut self,
+        entry: gix::dir::EntryRef<'_>,
+        _collapsed_directory_status: Option<gix::dir::entry::Status>,
+    ) -> gix::dir::walk::Action {
+        if entry.status == gix::dir::entry::Status::Untracked {
+            self.count += 1;
+        }
+        if self.count >= self.limit {
+            gix::dir::walk::Action::Cancel
+        } else {
+            gix::dir::walk::Action::Continue
+        }
+    }
+}
+
+/// Index entries to give each thread before a scan is split
+const PARALLEL_SCAN_ENTRIES: usize = 8192;
+/// Most threads one working-tree scan uses
+const MAX_SCAN_THREADS: usize = 8;
+
+/// Shared state of one working-tree scan
+struct Scan<'a> {
+    index: &'a gix::index::State,
+    head: Option<&'a gix::index::State>,
+    nothing_staged: bool,
+    monitor: Option<&'a FsMonitor>,
+    workdir: &'a Path,
+    /// Stat fields to compare, from `core.trustctime`, `core.checkStat`, and friends
+    stat: gix::index::entry::stat::Options,
+    /// `core.symlinks` and `core.fileMode`
+    fs: gix::fs::Capabilities,
+    with_lines: bool,
+    max_files: u32,
+    /// Changed files found by all threads so far
+    found: AtomicU32,
+}
+
+impl Scan<'_> {
+    /// Stats of the index entries in `range`; stops early once `max_files` is reached
+    fn entries(&self, repo: &Repository, range: Range<usize>) -> DiffStats {
+        let entries = self.index.entries();
+        let mut stats = DiffStats::default();
+        for idx in range {
+            if self.found.load(Ordering::Relaxed) >= self.max_files {
+                break;
+            }
+            let entry = &entries[idx];
+            let path = entry.path(self.index);
+            // Conflicts are listed once per path, whichever stages it has
+            if entry.stage_raw() != 0 {
+                let first = idx == 0 || entries[idx - 1].path(self.index) != path;
+                if first {
+                    stats.files += 1;
+                    stats.conflicts += 1;
+                    self.found.fetch_add(1, Ordering::Relaxed);
+                }
+                continue;
+            }
+            let head_entry = self.head.and_then(|h| h.entry_by_path(path));
+            let staged = !self.nothing_staged
+                && head_entry.is_none_or(|h| h.id != entry.id || h.mode != entry.mode);
+            // Paths are bytes; only platforms that can't name such a file
+            // (Windows, for ill-formed UTF-8) fail here, and then only the
+            // worktree check is skipped
+            let file_path = gix::path::try_from_bstr(path).map(|p| self.workdir.join(p));
+            let worktree = match file_path {
+                Ok(file_path) if self.monitor.is_none_or(|m| m.needs_check(idx, path)) => {
+                    self.worktree_change(repo, entry, &file_path)
+                }
+                _ => None,
+            };
+            if !staged && worktree.is_none() {
+                continue;
+            }
+            stats.files += 1;
+            stats.staged += u32::from(staged);
+            self.found.fetch_add(1, Ordering::Relaxed);
+            if self.with_lines {
+                let new = match worktree {
+                    Some(WorktreeChange::Modified(data)) => data,
+                    Some(WorktreeChange::Deleted) => Vec::new(),
+                    None => blob(repo, entry.id),
+                };
+                let base = if self.nothing_staged {
+                    Some(entry.id)
+                } else {
+                    head_entry.map(|h| h.id)
+                };
+                stats.add_lines(&base.map(|id| blob(repo, id)).unwrap_or_default(), &new);
+            }
+        }
+        stats
+    }
+
+    /// How the working-tree file differs from its index entry, if it does
+    /// Checked the way `git status` does: mode first, then stat data, then
+    /// content for entries whose stat doesn't settle it. Content is only read
+    /// to hash a same-size file or, with `with_lines`, to count lines
+    fn worktree_change(
+        &self,
+        repo: &Repository,
+        entry: &gix::index::Entry,
+        file_path: &Path,
+    ) -> Option<WorktreeChange> {
+        use gix::index::entry::{Mode, Stat};
+        if entry.mode == Mode::COMMIT {
+            return None; // Submodule state is its own repo's business
+        }
+        let Ok(metadata) = gix::index::fs::Metadata::from_path_no_follow(file_path) else {
+            return Some(WorktreeChange::Deleted);
+        };
+        let mode_changed = entry
+            .mode
+            .change_to_match_fs(&metadata, self.fs.symlink, self.fs.executable_bit)
+            .is_some();
+        let stat = Stat::from_fs(&metadata).ok()?;
+        // Entries written in the same instant as the index may hide a
+        // same-size edit behind matching stat data
+        if !mode_changed
+            && entry.stat.matches(&stat, self.stat)
+            && !entry.stat.is_racy(self.index.timestamp(), self.stat)
+        {
+            return None;
+        }
+        let same_size = !mode_changed && stat.size == entry.stat.size;
+        if !same_size && !self.with_lines {
+            return Some(WorktreeChange::Modified(Vec::new()));
+        }
+        let data = if metadata.is_symlink() {
+            fs::read_link(file_path)
+                .ok()?
+                .into_os_string()
+                .into_encoded_bytes()
+        } else if metadata.is_file() {
+            fs::read(file_path).ok()?
+        } else {
+            return Some(WorktreeChange::Modified(Vec::new()));
+        };
+        if same_size
+            && gix::objs::compute_hash(repo.object_hash(), gix::object::Kind::Blob, &data)
+                == entry.id
+        {
+            return None; // Touched but unchanged
+        }
+        Some(WorktreeChange::Modified(data))
+    }
+}
+
+/// Blob content, empty if it can't be read
+fn blob(repo: &Repository, id: gix::ObjectId) -> Vec<u8> {
+    repo.find_object(id)
+        .map(|o| o.detach().data)
+        .unwrap_or_default()
+}
+
+impl GitRepo {
+    /// Get index mtime for cache invalidation
+    pub(crate) fn index_mtime(&self) -> u64 {
+        let index_path = format!("{}/index", self.git_dir.trim_end_matches('/'));
+        fs::metadata(&index_path)
+            .and_then(|m| m.modified())
+            .map(|t| {
+                t.duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs()
+            })
+            .unwrap_or(0)
+    }
+
+    /// Get HEAD oid for cache invalidation
+    /// Reads the branch ref as a loose file, then from packed-refs, then the
+    /// tip of its reflog, before falling back to a full HEAD resolution
+    pub(crate) fn head_oid(&self) -> String {
+        let common_dir = self.repo.common_dir();
+        let ref_name = [b"refs/heads/".as_slice(), &self.branch_ref].concat();
+        let ref_path = gix::path::from_byte_slice(&ref_name);
+        fs::read_to_string(common_dir.join(ref_path))
+            .ok()
+            .map(|oid| oid.trim().to_string())
+            .filter(|oid| is_hex_oid(oid))
+            .or_else(|| self.packed_ref(&ref_name))
+            .or_else(|| reflog_tip(&fs::read(common_dir.join("logs").join(ref_path)).ok()?))
+            .or_else(|| self.repo.head_id().ok().map(|id| id.to_string()))
+            .unwrap_or_default()
+    }
+
+    /// Oid of `ref_name` in packed-refs, through the store's cached buffer
+    /// so a sorted file is binary searched and only re-read when it changes
+    fn packed_ref(&self, ref_name: &[u8]) -> Option<String> {
+        let packed = self.repo.refs.cached_packed_buffer().ok()??;
+        let name = gix::bstr::BStr::new(ref_name);
+        let found = packed.try_find(name).ok()??;
+        Some(found.target().to_string())
+    }
+
+    /// Merge, rebase, or other operation in progress
+    pub(crate) fn state(&self) -> RepoState {
+        use gix::state::InProgress;
+        match self.repo.state() {
+            None => RepoState::Clean,
+            Some(InProgress::Merge) => RepoState::Merge,
+            Some(InProgress::Rebase | InProgress::RebaseInteractive) => RepoState::Rebase,
+            Some(InProgress::CherryPick | InProgress::CherryPickSequence) => RepoState::CherryPick,
+            Some(InProgress::Revert | InProgress::RevertSequence) => RepoState::Revert,
+            Some(InProgress::Bisect) => RepoState::Bisect,
+            Some(InProgress::ApplyMailbox | InProgress::ApplyMailboxRebase) => RepoState::Apply,
+        }
+    }
+
+    /// Stashes, one per line of the `refs/stash` reflog
+    pub(crate) fn stash_count(&self) -> u32 {
+        let log = fs::read(self.repo.common_dir().join("logs/refs/stash")).unwrap_or_default();
+        let lines = log.split(|&b| b == b'\n').filter(|l| !l.is_empty()).count();
+        u32::try_from(lines).unwrap_or(u32::MAX)
+    }
+
+    /// Unix seconds of the HEAD commit, 0 for an unborn branch
+    pub(crate) fn last_commit_at(&self) -> u64 {
+        self.repo
+            .head_commit()
+            .ok()
+            .and_then(|c| c.time().ok())
+            .and_then(|t| u64::try_from(t.seconds).ok())
+            .unwrap_or(0)
+    }
+}
+
+/// A full SHA-1 or SHA-256 hex object id
+fn is_hex_oid(s: &str) -> bool {
+    matches!(s.len(), 40 | 64) && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// New oid of the last reflog entry: `<old> <new> <committer>\t<message>`
+fn reflog_tip(content: &[u8]) -> Option<String> {
+    let last = String::from_utf8_lossy(content).lines().last()?.to_string();
+    let new = last.split(' ').nth(1)?;
+    is_hex_oid(new).then(|| new.to_string())
+}
+
+/// Working-tree stats gathered in one pass over the index
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct DiffStats {
+    pub(crate) files: u32,
+    pub(crate) added: u32,
+    pub(crate) deleted: u32,
+    /// Files whose index entry differs from HEAD
+    staged: u32,
+    /// Untracked files or directories, also included in `files`
+    untracked: u32,
+    /// Paths with unmerged entries, also included in `files`
+    pub(crate) conflicts: u32,
+    /// Index entries, and milliseconds the scan took (index_stats component)
+    pub(crate) index_entries: u32,
+    pub(crate) scan_ms: u32,
+    /// The walk stopped at `max_files`; `files` is that limit
+    pub(crate) capped: bool,
+    /// What the untracked count was taken against (see `untracked_key`)
+    untracked_key: u64,
+}
+
+impl DiffStats {
+    /// Add the counts of another part of the index
+    fn merge(&mut self, other: DiffStats) {
+        self.files += other.files;
+        self.added += other.added;
+        self.deleted += other.deleted;
+        self.staged += other.staged;
+        self.conflicts += other.conflicts;
+    }
+
+    fn add_lines(&mut self, old: &[u8], new: &[u8]) {
+        if let Some((a, d)) = count_line_changes(old, new) {
+            self.added += a;
+            self.deleted += d;
+        }
+    }
+}
+
+enum WorktreeChange {
+    Modified(Vec<u8>),
+    Deleted,
+}
+
+/// Detect linked worktree name from `git_dir` path
+fn get_worktree_name(git_dir: &str) -> Option<String> {
+    // Linked worktrees have git_dir like: /path/.git/worktrees/<name>
+    if let Some(idx) = git_dir.find("/.git/worktrees/") {
+        let name = &git_dir[idx + 16..]; // skip "/.git/worktrees/"
+        let name = name.trim_end_matches('/');
+        if !name.is_empty() {
+            return Some(name.to_string());
+        }
+    }
+    None
+}
+
+/// Open the repo named by `GIT_DIR` (with `GIT_WORK_TREE`, if set), as git does
+/// Returns None when `GIT_DIR` is unset
+fn open_from_env(dir: &str) -> Option<Repository> {
+    sys::var("GIT_DIR")?;
+    gix::ThreadSafeRepository::open_with_environment_overrides(dir, Default::default())
+        .ok()
+        .map(Into::into)
+}
+
+/// Resolve symlinks in a git dir, so a repo reached through a symlink and
+/// through its real path shares one set of cache keys
+fn canonical_dir(path: &Path) -> String {
+    fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Repos opened by this process, so long-lived modes like `--serve` open each once
+static OPENED: Mutex<Vec<(String, gix::ThreadSafeRepository)>> = Mutex::new(Vec::new());
+
+/// Open the repo at `git_path`, reusing a handle from earlier in the process
+fn open_cached(git_path: &str) -> Option<Repository> {
+    let mut opened = OPENED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, repo)) = opened.iter().find(|(path, _)| path == git_path) {
+        return Some(repo.to_thread_local());
+    }
+    let repo = gix::open(git_path).ok()?;
+    opened.push((git_path.to_string(), repo.clone().into_sync()));
+    Some(repo)
+}
+
+/// Discover the repo for `dir`; bare repos have no work tree, so only the branch is shown
+pub(crate) fn get_git_repo(dir: &str) -> Option<GitRepo> {
+    trace_span!("discover");
+    // The environment decides the repo, so the per-directory cache doesn't apply
+    let env_repo = open_from_env(dir);
+    if env_repo.is_some() {
+        return discover_git_repo(dir, env_repo);
+    }
+    if let Some(git) = memo::repo(dir) {
+        return Some(git);
+    }
+    let git = discover_git_repo(dir, None)?;
+    memo::remember_repo(dir, &git);
+    Some(git)
+}
+
+/// Find the repo for `dir` through the on-disk cache, or `env_repo` if the
+/// environment named one
+fn discover_git_repo(dir: &str, env_repo: Option<Repository>) -> Option<GitRepo> {
+    // Try cache first
+    if env_repo.is_none()
+        && let Some(cache) = get_cached_git_info(dir)
+    {
+        let repo = open_cached(&cache.git_path)?;
+        let git_dir = canonical_dir(Path::new(&cache.git_path));
+        let work_dir = repo
+            .work_dir()
+            .map_or_else(|| dir.to_string(), |p| p.to_string_lossy().into_owned());
+        let worktree = get_worktree_name(&git_dir);
+        let slow_fs = netfs::is_slow(Path::new(&work_dir));
+        return Some(GitRepo {
+            repo,
+            branch_ref: cache.branch.clone().into_bytes(),
+            branch: cache.branch,
+            worktree,
+            git_dir,
+            work_dir,
+            slow_fs,
+        });
+    }
+
+    // Discover repo
+    let from_env = env_repo.is_some();
+    let repo = match env_repo {
+        Some(repo) => repo,
+        None => gix::discover(dir).ok()?,
+    };
+    let git_dir = canonical_dir(repo.git_dir());
+    let work_dir = repo
+        .work_dir()
+        .map_or_else(|| dir.to_string(), |p| p.to_string_lossy().into_owned());
+
+    // Get branch name from HEAD
+    let head = repo.head().ok()?;
+    let branch_ref = head
+        .referent_name()
+        .map_or_else(|| b"HEAD".to_vec(), |n| n.shorten().to_vec());
+    let branch = display_bytes(&branch_ref);
+
+    let worktree = get_worktree_name(&git_dir);
+
+    // The cache stores text, so only branches that display as themselves go in it
+    if !from_env && branch.as_bytes() == branch_ref {
+        cache_git_info(dir, &git_dir, &branch);
+    }
+    let slow_fs = netfs::is_slow(Path::new(&work_dir));
+    Some(GitRepo {
+        repo,
+        branch,
+        branch_ref,
+        worktree,
+        git_dir,
+        work_dir,
+        slow_fs,
+    })
+}
+
+/// Find the configured upstream ref for a branch
+/// Reads branch.<name>.remote and branch.<name>.merge from git config
+fn find_upstream_ref(repo: &gix::Repository, branch: &str) -> Option<String> {
+    let config = repo.config_snapshot();
+
+    // Get branch.<name>.remote (e.g., "origin")
+    let remote_key = format!("branch.{branch}.remote");
+    let remote = config.string(remote_key.as_str())?;
+    let remote = remote.to_string();
+
+    // Get branch.<name>.merge (e.g., "refs/heads/main")
+    let merge_key = format!("branch.{branch}.merge");
+    let merge_ref = config.string(merge_key.as_str())?;
+    let merge_ref = merge_ref.to_string();
+
+    // Convert refs/heads/X to refs/remotes/<remote>/X
+    let upstream_branch = merge_ref.strip_prefix("refs/heads/")?;
+    Some(format!("refs/remotes/{remote}/{upstream_branch}"))
+}
+
+/// Tip of the branch's upstream: the configured one, or origin/<branch>
+fn upstream_id<'r>(repo: &'r gix::Repository, branch: &str) -> Option<gix::Id<'r>> {
+    let upstream_ref =
+        find_upstream_ref(repo, branch).unwrap_or_else(|| format!("refs/remotes/origin/{branch}"));
+    repo.find_reference(&upstream_ref)
+        .ok()?
+        .into_fully_peeled_id()
+        .ok()
+}
+
+/// Get ahead/behind counts relative to upstream using gix
+pub(crate) fn get_ahead_behind(repo: &gix::Repository, branch: &str) -> (u32, u32) {
+    trace_span!("ahead_behind");
+    // Get HEAD commit
+    let Ok(head_id) = repo.head_id() else {
+        return (0, 0);
+    };
+
+    let Some(upstream_id) = upstream_id(repo, branch) else {
+        return (0, 0); // No upstream
+    };
+
+    // If same commit, no ahead/behind
+    if head_id == upstream_id {
+        return (0, 0);
+    }
+
+    // Count commits reachable from HEAD but not upstream (ahead)
+    let ahead = count_commits_not_in(repo, head_id.detach(), upstream_id.detach());
+    // Count commits reachable from upstream but not HEAD (behind)
+    let behind = count_commits_not_in(repo, upstream_id.detach(), head_id.detach());
+
+    (ahead, behind)
+}
+
+/// Commits on HEAD since `tag`; None if the tag isn't in the local repo
+pub(crate) fn commits_since_tag(repo: &gix::Repository, tag: &str) -> Option<u32> {
+    let head_id = repo.head_id().ok()?;
+    let tag_id = repo
+        .find_reference(&format!("refs/tags/{tag}"))
+        .ok()?
+        .into_fully_peeled_id()
+        .ok()?;
+    Some(count_commits_not_in(
+        repo,
+        head_id.detach(),
+        tag_id.detach(),
+    ))
+}
+
+/// Whether HEAD is signed, and whether new commits will be (`commit.gpgsign`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Signing {
+    pub(crate) head_signed: bool,
+    pub(crate) configured: bool,
+}
+
+/// Signing state of the HEAD commit; None for an unborn branch
+/// GPG, SSH, and X.509 signatures all count, unverified
+pub(crate) fn head_signing(repo: &gix::Repository) -> Option<Signing> {
+    let commit = repo.head_commit().ok()?;
+    Some(Signing {
+        head_signed: commit.signature().ok()?.is_some(),
+        configured: repo.config_snapshot().boolean("commit.gpgsign") == Some(true),
+    })
+}
+
+/// Gerrit `Change-Id` trailer of the HEAD commit
+pub(crate) fn head_change_id(repo: &gix::Repository) -> Option<String> {
+    let commit = repo.head_commit().ok()?;
+    let message = commit.message_raw().ok()?;
+    parse_change_id(&String::from_utf8_lossy(message))
+}
+
+/// Last `Change-Id: I<40 hex>` line of a commit message
+fn parse_change_id(message: &str) -> Option<String> {
+    message.lines().rev().find_map(|line| {
+        let id = line.strip_prefix("Change-Id:")?.trim();
+        let hex = id.strip_prefix('I')?;
+        (hex.len() == 40 && hex.bytes().all(|b| b.is_ascii_hexdigit())).then(|| id.to_string())
+    })
+}
+
+/// Count commits reachable from `from` but not from `exclude`
+///
+/// Note: Uses a 10k commit safety limit to prevent runaway computation in very large repos.
+/// In repos with >10k commits between branches, counts may be approximate. This is an
+/// intentional trade-off for predictable performance in a status line tool.
+fn count_commits_not_in(
+    repo: &gix::Repository,
+    from: gix::ObjectId,
+    exclude: gix::ObjectId,
+) -> u32 {
+    // First, collect all commits reachable from exclude (the "stop" set)
+    let mut exclude_set = std::collections::HashSet::new();
+    let Ok(exclude_iter) = repo.rev_walk([exclude]).all() else {
+        return 0;
+    };
+    for info in exclude_iter {
+        let Ok(info) = info else { break };
+        exclude_set.insert(info.id);
+        if exclude_set.len() > 10000 {
+            break; // Safety limit
+        }
+    }
+
+    // Now count commits from `from` that aren't in exclude_set
+    // Don't break on first intersection - merges can have commits on both sides
+    let Ok(from_iter) = repo.rev_walk([from]).all() else {
+        return 0;
+    };
+    let mut count = 0u32;
+    let mut visited = 0u32;
+    for info in from_iter {
+        let Ok(info) = info else { break };
+        visited += 1;
+        if !exclude_set.contains(&info.id) {
+            count += 1;
+        }
+        if visited > 10000 {
+            break; // Safety limit
+        }
+    }
+    count
+}
+
+/// Content that looks binary (NUL in the first 8KB, like git) has no line counts
+fn is_binary(data: &[u8]) -> bool {
+    data[..data.len().min(8000)].contains(&0)
+}
+
+/// Count (inserted, removed) lines between two versions of a file; None for binary content
+fn count_line_changes(old: &[u8], new: &[u8]) -> Option<(u32, u32)> {
+    if is_binary(old) || is_binary(new) {
+        return None;
+    }
+    let input = InternedInput::new(sources::byte_lines(old), sources::byte_lines(new));
+    let counter = imara_diff::diff(Algorithm::Histogram, &input, Counter::default());
+    Some((counter.insertions, counter.removals))
+}
+
+/// Working-tree stats for the given mode
+/// Fast reuses any cache entry matching the index and HEAD; full additionally
+/// needs an entry with line counts younger than FULL_CACHE_TTL; minimal is never called
+/// On a network filesystem, an entry for HEAD younger than SLOW_FS_TTL is
+/// reused even if the index has changed since
+/// An entry capped at another `max_files` is recomputed, as is one migrated
+/// from an older layout, which lacks staged, untracked, and conflict counts
+/// A reused entry's untracked count is retaken when its untracked key no
+/// longer matches: the fsmonitor reports other changes or, without one, a
+/// directory holding tracked files has a new mtime
+/// In a long-running process, stats from an earlier call are reused while the
+/// repo is unchanged, without reading the cache
+pub(crate) fn diff_summary(git: &GitRepo, mode: GitMode, max_files: Option<u32>) -> DiffStats {
+    trace_span!("git");
+    let full = mode == GitMode::Full;
+    // Dirty mode is a walk capped at one change
+    let max_files = if mode == GitMode::Dirty {
+        Some(1)
+    } else {
+        max_files
+    };
+    // Untracked files can appear at any time; UNTRACKED_RECHECK is below FULL_CACHE_TTL
+    if let Some(stats) = memo::stats(git, mode, max_files, Some(UNTRACKED_RECHECK)) {
+        return stats;
+    }
+    let seen = memo::changes();
+    let (stats, computed_at) = cached_or_computed(git, full, max_files);
+    memo::remember_stats(git, mode, max_files, &stats, computed_at, seen);
+    stats
+}
+
+/// Whether the stats cache has an entry for HEAD to update, rather than a
+/// repo or branch seen for the first time, or a HEAD that just moved
+pub(crate) fn has_cached_stats(git: &GitRepo) -> bool {
+    load_mmap_cache(&git.git_dir, &git.branch)
+        .is_some_and(|c| c.extended && c.head_oid_matches(&git.head_oid()))
+}
+
+/// Stats from the mmap cache if it's usable, or a fresh scan, with when they were
+/// last known current
+fn cached_or_computed(git: &GitRepo, full: bool, max_files: Option<u32>) -> (DiffStats, u64) {
+    let current_mtime = git.index_mtime();
+    let current_oid = git.head_oid();
+
+    if let Some(mut c) = load_mmap_cache(&git.git_dir, &git.branch)
+        && c.extended
+        && c.head_oid_matches(&current_oid)
+    {
+        let now = sys::now_secs();
+        let age = now.saturating_sub(c.computed_at);
+        let fresh = c.index_mtime == current_mtime || (git.slow_fs && age < SLOW_FS_TTL);
+        let same_cap = !c.capped || Some(c.files_changed) == max_files;
+        if fresh && same_cap && (!full || (c.full && age < FULL_CACHE_TTL)) {
+            let mut stats = DiffStats {
+                files: c.files_changed,
+                added: c.lines_added,
+                deleted: c.lines_deleted,
+                staged: c.staged,
+                untracked: c.untracked,
+                conflicts: c.conflicts,
+                index_entries: c.index_entries,
+                scan_ms: c.scan_ms,
+                capped: c.capped,
+                untracked_key: c.untracked_key,
+            };
+            // Taken before recounting, so changes during the walk show up next time
+            let key = git
+                .repo
+                .index()
+                .ok()
+                .map(|index| git.untracked_key(&index, fsmonitor::query(&git.repo).as_ref()));
+            if key.is_none_or(|key| key != c.untracked_key) {
+                git.refresh_untracked(&mut stats, max_files);
+                c.files_changed = stats.files;
+                c.untracked = stats.untracked;
+                c.capped = stats.capped;
+                c.untracked_at = now;
+                c.untracked_key = key.unwrap_or(0);
+                save_mmap_cache(&git.git_dir, &git.branch, &c);
+            }
+            // An uncapped count over the cap is clamped like a capped walk
+            stats.capped |= max_files.is_some_and(|m| m > 0 && stats.files > m);
+            stats.files = stats
+                .files
+                .min(max_files.filter(|_| stats.capped).unwrap_or(u32::MAX));
+            let at = if full { c.computed_at } else { c.untracked_at };
+            return (stats, at);
+        }
+    }
+    let stats = compute_and_cache_git_stats(git, full, max_files, current_mtime, &current_oid);
+    (stats, sys::now_secs())
+}
+
+fn compute_and_cache_git_stats(
+    git: &GitRepo,
+    full: bool,
+    max_files: Option<u32>,
+    mtime: u64,
+    oid: &str,
+) -> DiffStats {
+    let started_at = sys::now_secs();
+    let stats = git.diff_stats(full, max_files).unwrap_or_default();
+
+    let upstream = upstream_id(&git.repo, &git.branch).map(|id| id.to_string());
+
+    let cache = MmapCache {
+        index_mtime: mtime,
+        head_oid: oid_field(oid),
+        files_changed: stats.files,
+        lines_added: stats.added,
+        lines_deleted: stats.deleted,
+        ahead: 0,
+        behind: 0,
+        full,
+        computed_at: sys::now_secs(),
+        extended: true,
+        stash_count: git.stash_count(),
+        staged: stats.staged,
+        untracked: stats.untracked,
+        upstream_oid: oid_field(upstream.as_deref().unwrap_or_default()),
+        repo_state: git.state(),
+        last_commit_at: git.last_commit_at(),
+        conflicts: stats.conflicts,
+        index_entries: stats.index_entries,
+        scan_ms: stats.scan_ms,
+        capped: stats.capped,
+        untracked_at: started_at,
+        untracked_key: stats.untracked_key,
+    };
+    save_mmap_cache(&git.git_dir, &git.branch, &cache);
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // =========================================================================
+    // get_worktree_name tests
+    // =========================================================================
+
+    #[test]
+    fn worktree_name_linked() {
+        let git_dir = "/home/user/project/.git/worktrees/feature-branch";
+        let result = get_worktree_name(git_dir);
+        assert_eq!(result, Some("feature-branch".to_string()));
+    }
+
+    #[test]
+    fn worktree_name_linked_trailing_slash() {
+        let git_dir = "/home/user/project/.git/worktrees/feature-branch/";
+        let result = get_worktree_name(git_dir);
+        assert_eq!(result, Some("feature-branch".to_string()));
+    }
+
+    #[test]
+    fn worktree_name_main_repo() {
+        // Main repo has git_dir like /path/.git, not a worktree
+        let git_dir = "/home/user/project/.git";
+        let result = get_worktree_name(git_dir);
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn worktree_name_empty_name() {
+        // Edge case: empty worktree name (shouldn't happen in practice)
+        let git_dir = "/home/user/project/.git/worktrees/";
+        let result = get_worktree_name(git_dir);
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn worktree_name_nested_path() {
+        // Worktree name with nested structure (rare but possible)
+        let git_dir = "/repo/.git/worktrees/release-v1";
+        let result = get_worktree_name(git_dir);
+        assert_eq!(result, Some("release-v1".to_string()));
+    }
+
+    // =========================================================================
+    // head_oid fallback tests
+    // =========================================================================
+
+    #[test]
+    fn packed_ref_lookup() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            std::process::Command::new("git")
+                .args(["-c", "user.name=T", "-c", "user.email=t@example.com"])
+                .args(args)
+                .current_dir(dir.path())
+                .output()
+                .ok()
+                .filter(|o| o.status.success())
+                .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        };
+        if git(&["init", "-q", "-b", "main"]).is_none() {
+            return; // git not installed
+        }
+        git(&["commit", "-q", "--allow-empty", "-m", "one"]);
+        git(&["branch", "feature"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "two"]);
+        git(&["pack-refs", "--all"]);
+        // Without loose refs or reflogs, only packed-refs knows the branches
+        fs::remove_dir_all(dir.path().join(".git/logs")).unwrap();
+        let head = git(&["rev-parse", "HEAD"]).unwrap();
+        let feature = git(&["rev-parse", "feature"]).unwrap();
+
+        let repo = get_git_repo(dir.path().to_str().unwrap()).unwrap();
+        assert!(!dir.path().join(".git/refs/heads/main").exists());
+        assert_eq!(repo.packed_ref(b"refs/heads/main"), Some(head.clone()));
+        assert_eq!(repo.packed_ref(b"refs/heads/feature"), Some(feature));
+        assert_eq!(repo.packed_ref(b"refs/heads/ma"), None);
+        assert_eq!(repo.head_oid(), head);
+    }
+
+    #[test]
+    fn reflog_tip_is_last_new_oid() {
+        let log = b"0000000000000000000000000000000000000000 \
+            aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa A U <a@u> 1 +0000\tcommit (initial): one\n\
+            aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa \
+            bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb A U <a@u> 2 +0000\tcommit: two\n";
+        assert_eq!(
+            reflog_tip(log).as_deref(),
+            Some("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb")
+        );
+        assert_eq!(reflog_tip(b""), None);
+    }
+
+    // =========================================================================
+    // GitMode tests
+    // =========================================================================
+
+    #[test]
+    fn git_mode_parse() {
+        assert_eq!(GitMode::parse("full"), Some(GitMode::Full));
+        assert_eq!(GitMode::parse(" FAST "), Some(GitMode::Fast));
+        assert_eq!(GitMode::parse("dirty"), Some(GitMode::Dirty));
+        assert_eq!(GitMode::parse("minimal"), Some(GitMode::Minimal));
+        assert_eq!(GitMode::parse("turbo"), None);
+        assert_eq!(GitMode::default(), GitMode::Fast);
+    }
+
+    #[test]
+    fn git_mode_for_slow_fs() {
+        assert_eq!(GitMode::Full.for_slow_fs(true), GitMode::Fast);
+        assert_eq!(GitMode::Full.for_slow_fs(false), GitMode::Full);
+        assert_eq!(GitMode::Minimal.for_slow_fs(true), GitMode::Minimal);
+    }
+
+    // =========================================================================
+    // count_line_changes tests
+    // =========================================================================
+
+    #[test]
+    fn line_changes_counts_insertions_and_removals() {
+        let old = b"a\nb\nc\n";
+        let new = b"a\nB\nc\nd\n";
+        assert_eq!(count_line_changes(old, new), Some((2, 1)));
+    }
+
+    #[test]
+    fn line_changes_deleted_file() {
+        assert_eq!(count_line_changes(b"one\ntwo\n", b""), Some((0, 2)));
+    }
+
+    #[test]
+    fn line_changes_skips_binary() {
+        assert_eq!(count_line_changes(b"\0\x01", b"text\n"), None);
+    }
+
+    // =========================================================================
+    // parse_change_id tests
+    // =========================================================================
+
+    #[test]
+    fn change_id_from_trailer() {
+        let id = "I8473b95934b5732ac55d26311a706c9c2bde9940";
+        let message = format!("Fix parser\n\nSome detail\n\nChange-Id: {id}\n");
+        assert_eq!(parse_change_id(&message), Some(id.to_string()));
+        assert_eq!(parse_change_id("Fix parser\n\nChange-Id: Ishort\n"), None);
+        assert_eq!(parse_change_id("Fix parser\n"), None);
+    }
+
+    // =========================================================================
+    // diff_stats tests
+    // =========================================================================
+
+    #[test]
+    fn split_scan_matches_serial() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            std::process::Command::new("git")
+                .args(["-c", "user.name=T", "-c", "user.email=t@example.com"])
+                .args(args)
+                .current_dir(dir.path())
+                .output()
+                .is_ok_and(|o| o.status.success())
+        };
+        if !git(&["init", "-q"]) {
+            return; // git not installed
+        }
+        let write = |name: &str, content: &str| fs::write(dir.path().join(name), content).unwrap();
+        for i in 0..12 {
+            write(&format!("f{i:02}"), "base");
+        }
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "base"]);
+        git(&["checkout", "-q", "-b", "other"]);
+        for name in ["f03", "f04"] {
+            write(name, "theirs");
+        }
+        git(&["commit", "-q", "-am", "theirs"]);
+        git(&["checkout", "-q", "-"]);
+        for name in ["f03", "f04"] {
+            write(name, "ours");
+        }
+        git(&["commit", "-q", "-am", "ours"]);
+        git(&["merge", "-q", "other"]);
+        write("f09", "changed");
+
+        let repo = get_git_repo(dir.path().to_str().unwrap()).unwrap();
+        let serial = repo.diff_stats_split(true, None, Some(1)).unwrap();
+        assert_eq!((serial.files, serial.conflicts), (3, 2));
+        // Chunk edges fall between the stages of conflicted paths
+        for threads in [2, 3, 5, 8] {
+            let split = repo.diff_stats_split(true, None, Some(threads)).unwrap();
+            assert_eq!(
+                (split.files, split.conflicts, split.added, split.deleted),
+                (serial.files, serial.conflicts, serial.added, serial.deleted),
+                "{threads} threads"
+            );
+        }
+        let capped = repo.diff_stats_split(false, Some(2), Some(3)).unwrap();
+        assert!(capped.capped);
+        assert_eq!(capped.files, 2);
+    }
+
+    #[test]
+    fn stash_count_and_upstream() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            std::process::Command::new("git")
+                .args(["-c", "user.name=T", "-c", "user.email=t@example.com"])
+                .args(args)
+                .current_dir(dir.path())
+                .output()
+                .is_ok_and(|o| o.status.success())
+        };
+        if !git(&["init", "-q", "-b", "main"]) {
+            return; // git not installed
+        }
+        fs::write(dir.path().join("a"), "base").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "base"]);
+        let repo = get_git_repo(dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(repo.stash_count(), 0);
+        assert!(upstream_id(&repo.repo, "main").is_none());
+
+        for content in ["one", "two"] {
+            fs::write(dir.path().join("a"), content).unwrap();
+            git(&["stash", "-q"]);
+        }
+        git(&["update-ref", "refs/remotes/origin/main", "HEAD"]);
+        let repo = get_git_repo(dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(repo.stash_count(), 2);
+        assert_eq!(
+            upstream_id(&repo.repo, "main").map(|id| id.to_string()),
+            Some(repo.head_oid())
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! Recently visited projects and the `recent` report
+//!
+//! With `project_history` on (or a last_visit component), renders record the
+//! project directory in `projects.json` in the state dir. A visit is one
+//! session in a project: later renders in the same session only move its time.
+
+use crate::cache::{atomic_rename, get_state_dir, unique_hex};
+use crate::sys;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+/// Minimum seconds between writes for the same session and project
+const RECORD_INTERVAL: u64 = 60;
+const HISTORY_FILE: &str = "projects.json";
+/// Projects kept; the least recently visited are dropped first
+const MAX_PROJECTS: usize = 500;
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+
+/// Visits to one project directory
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectVisit {
+    /// Sessions seen in the project
+    pub visits: u32,
+    /// Unix seconds of the latest render
+    pub last: u64,
+    /// Unix seconds of the latest render in an earlier session
+    pub previous: Option<u64>,
+    /// Session of the latest render
+    pub session: String,
+}
+
+/// Every recorded project, keyed by directory
+pub fn load() -> HashMap<String, ProjectVisit> {
+    fs::read_to_string(get_state_dir().join(HISTORY_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// When an earlier session was last in `dir`, as of a render now; nothing is written
+pub(crate) fn previous_visit(dir: &str, session: Option<&str>) -> Option<u64> {
+    if dir.is_empty() {
+        return None;
+    }
+    let mut projects = load();
+    visit(
+        &mut projects,
+        dir,
+        session.unwrap_or_default(),
+        sys::now_secs(),
+    )
+    .0
+}
+
+/// Record a render in `dir`
+pub(crate) fn record(dir: &str, session: Option<&str>) {
+    if dir.is_empty() {
+        return;
+    }
+    let now = sys::now_secs();
+    let mut projects = load();
+    let (_, changed) = visit(&mut projects, dir, session.unwrap_or_default(), now);
+    if changed {
+        if projects.len() > MAX_PROJECTS {
+            let mut times: Vec<u64> = projects.values().map(|p| p.last).collect();
+            times.sort_unstable_by(|a, b| b.cmp(a));
+            let cutoff = times[MAX_PROJECTS - 1];
+            projects.retain(|_, p| p.last >= cutoff);
+        }
+        save(&projects);
+    }
+}
+
+/// Update `dir`'s entry; returns the earlier session's time and whether to write
+fn visit(
+    projects: &mut HashMap<String, ProjectVisit>,
+    dir: &str,
+    session: &str,
+    now: u64,
+) -> (Option<u64>, bool) {
+    let entry = projects.entry(dir.to_string()).or_default();
+    if entry.visits > 0 && entry.session == session {
+        let due = now.saturating_sub(entry.last) >= RECORD_INTERVAL;
+        if due {
+            entry.last = now;
+        }
+        return (entry.previous, due);
+    }
+    if entry.visits > 0 {
+        entry.previous = Some(entry.last);
+    }
+    entry.visits = entry.visits.saturating_add(1);
+    entry.last = now;
+    entry.session = session.to_string();
+    (entry.previous, true)
+}
+
+fn save(projects: &HashMap<String, ProjectVisit>) {
+    let Ok(json) = serde_json::to_string(projects) else {
+        return;
+    };
+    let temp_path = get_state_dir().join(format!("projects-tmp-{}", unique_hex()));
+    if fs::write(&temp_path, json).is_ok() {
+        let _ = atomic_rename(&temp_path, &get_state_dir().join(HISTORY_FILE));
+    }
+}
+
+/// Frecency: visits weighted by how recent the latest one was, as zoxide ranks
+pub fn score(visit: &ProjectVisit, now: u64) -> f64 {
+    let weight = match now.saturating_sub(visit.last) {
+        ..HOUR => 4.0,
+        HOUR..DAY => 2.0,
+        DAY..604_800 => 0.5,
+        _ => 0.25,
+    };
+    f64::from(visit.visits) * weight
+}
+
+/// Projects with their scores, highest first
+pub fn ranked(projects: &HashMap<String, ProjectVisit>, now: u64) -> Vec<(String, f64)> {
+    let mut ranked: Vec<(String, f64)> = projects
+        .iter()
+        .map(|(dir, visit)| (dir.clone(), score(visit, now)))
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visits_count_sessions() {
+        let mut projects = HashMap::new();
+        assert_eq!(visit(&mut projects, "/src/app", "a", 1000), (None, true));
+        // Same session: throttled, then the time moves
+        assert_eq!(visit(&mut projects, "/src/app", "a", 1030), (None, false));
+        assert_eq!(visit(&mut projects, "/src/app", "a", 1100), (None, true));
+        assert_eq!(projects["/src/app"].visits, 1);
+
+        assert_eq!(
+            visit(&mut projects, "/src/app", "b", 5000),
+            (Some(1100), true)
+        );
+        assert_eq!(
+            visit(&mut projects, "/src/app", "b", 5100),
+            (Some(1100), true)
+        );
+        assert_eq!(projects["/src/app"].visits, 2);
+    }
+
+    #[test]
+    fn frecency_favors_recent_visits() {
+        let now = 100 * DAY;
+        let project = |visits, last| ProjectVisit {
+            visits,
+            last,
+            ..ProjectVisit::default()
+        };
+        let projects = HashMap::from([
+            ("/old".to_string(), project(10, now - 30 * DAY)),
+            ("/today".to_string(), project(3, now - 2 * HOUR)),
+            ("/now".to_string(), project(1, now - 60)),
+        ]);
+        let ranked: Vec<String> = ranked(&projects, now).into_iter().map(|r| r.0).collect();
+        assert_eq!(ranked, ["/today", "/now", "/old"]);
+    }
+}
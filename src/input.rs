@@ -0,0 +1,179 @@
+//! Status line JSON input from Claude Code
+
+use crate::pr::Deployment;
+use crate::{display_os, get_home, wsl};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::path::Path;
+
+/// Status line JSON sent by Claude Code on stdin
+/// Every field is optional; unknown fields are ignored
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ClaudeInput {
+    pub session_id: Option<String>,
+    pub cwd: Option<String>,
+    /// Session transcript (JSONL) written by Claude Code
+    pub transcript_path: Option<String>,
+    pub model: Model,
+    pub context_window: ContextWindow,
+    pub cost: Cost,
+    pub output_style: OutputStyle,
+    pub workspace: Workspace,
+    pub git: GitInput,
+    pub pr: PrInput,
+}
+
+impl ClaudeInput {
+    /// Parse input JSON, returning an error if it is malformed
+    /// Under WSL, Windows paths from a Windows-side Claude Code are mapped to their mounts
+    pub fn parse(json: &str) -> serde_json::Result<Self> {
+        let mut input: Self = serde_json::from_str(json)?;
+        for path in [
+            &mut input.cwd,
+            &mut input.workspace.current_dir,
+            &mut input.workspace.project_dir,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if let Cow::Owned(translated) = wsl::translate_path(path) {
+                *path = translated;
+            }
+        }
+        Ok(input)
+    }
+
+    /// Parse input JSON, falling back to an empty input if it is malformed
+    pub fn from_json(json: &str) -> Self {
+        trace_span!("parse");
+        Self::parse(json).unwrap_or_default()
+    }
+
+    /// Plain `project • path` from the input alone, for when rendering fails
+    /// Falls back to the process working directory; home is shown as `~`
+    pub fn fallback_line(&self) -> String {
+        let dir = self
+            .cwd
+            .clone()
+            .or_else(|| self.workspace.current_dir.clone())
+            .or_else(|| self.workspace.project_dir.clone())
+            .or_else(|| {
+                std::env::current_dir()
+                    .ok()
+                    .map(|d| display_os(d.as_os_str()))
+            })
+            .unwrap_or_default();
+        let home = get_home();
+        let path = match dir.strip_prefix(&*home) {
+            Some(rest) if !home.is_empty() => format!("~{rest}"),
+            _ => dir.clone(),
+        };
+        let project = self
+            .workspace
+            .project_dir
+            .as_deref()
+            .and_then(|p| Path::new(p).file_name())
+            .map(display_os);
+        match project {
+            Some(project) if !project.is_empty() => format!("{project} • {path}"),
+            _ => path,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Model {
+    pub id: Option<String>,
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ContextWindow {
+    pub remaining_percentage: Option<f64>,
+    pub total_input_tokens: Option<u64>,
+    pub total_output_tokens: Option<u64>,
+    pub context_window_size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Cost {
+    pub total_cost_usd: Option<f64>,
+    pub total_duration_ms: Option<u64>,
+    /// Time spent waiting on the API, part of `total_duration_ms`
+    pub total_api_duration_ms: Option<u64>,
+    /// Lines Claude has added this session, regardless of what's still uncommitted
+    pub total_lines_added: Option<u64>,
+    pub total_lines_removed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct OutputStyle {
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Workspace {
+    pub project_dir: Option<String>,
+    pub current_dir: Option<String>,
+    /// Extra directories added to the session (`/add-dir`, `--add-dir`)
+    pub added_dirs: Vec<String>,
+}
+
+/// Git info from JSON input (for screenshots/testing)
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct GitInput {
+    pub branch: Option<String>,
+    pub worktree: Option<String>,
+    pub changed_files: Option<u32>,
+    pub lines_added: Option<u32>,
+    pub lines_deleted: Option<u32>,
+    pub ahead: Option<u32>,
+    pub behind: Option<u32>,
+}
+
+/// PR info from JSON input (for screenshots/testing)
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct PrInput {
+    pub number: Option<u32>,
+    pub state: Option<String>,
+    pub url: Option<String>,
+    pub comments: Option<u32>,
+    pub changed_files: Option<u32>,
+    pub check_status: Option<String>,
+    /// UNIX time the PR was opened
+    pub created_at: Option<u64>,
+    pub auto_merge: Option<bool>,
+    pub review_decision: Option<String>,
+    pub labels: Option<Vec<String>>,
+    pub mergeable: Option<String>,
+    pub deployment: Option<Deployment>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn claude_input_parse_strict_and_lenient() {
+        assert!(ClaudeInput::parse("not json").is_err());
+        assert!(ClaudeInput::from_json("not json").cwd.is_none());
+    }
+
+    #[test]
+    fn fallback_line_from_workspace() {
+        let input = ClaudeInput::from_json(
+            r#"{"cwd": "/srv/app/src", "workspace": {"project_dir": "/srv/app"}}"#,
+        );
+        assert_eq!(input.fallback_line(), "app • /srv/app/src");
+        let input = ClaudeInput::from_json(r#"{"cwd": "/srv/app"}"#);
+        assert_eq!(input.fallback_line(), "/srv/app");
+    }
+}
@@ -0,0 +1,120 @@
+//! Git LFS checkouts
+//!
+//! A repo uses LFS when its root `.gitattributes` routes paths through
+//! `filter=lfs`. Files that were never smudged stay small pointer files, so
+//! tracked entries whose indexed size fits a pointer are read to see whether
+//! they still are one. Nested `.gitattributes` files aren't consulted.
+
+use crate::git::GitRepo;
+use crate::{glob_match, sys};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+const POINTER_PREFIX: &[u8] = b"version https://git-lfs.github.com/spec/v1";
+/// Pointer files are about 130 bytes; anything larger is real content
+const MAX_POINTER_SIZE: u32 = 200;
+
+/// An LFS problem worth showing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LfsStatus {
+    /// The repo uses LFS but `git-lfs` isn't on the PATH
+    NotInstalled,
+    /// Tracked files still checked out as pointers
+    Missing(u32),
+}
+
+/// LFS state of the repo's working tree; None if it doesn't use LFS or all
+/// objects are checked out
+pub(crate) fn status(git: &GitRepo) -> Option<LfsStatus> {
+    let workdir = git.repo.work_dir()?;
+    let attributes = std::fs::read_to_string(workdir.join(".gitattributes")).ok()?;
+    let patterns = lfs_patterns(&attributes);
+    if patterns.is_empty() {
+        return None;
+    }
+    if !is_installed() {
+        return Some(LfsStatus::NotInstalled);
+    }
+    let index = git.repo.index().ok()?;
+    let mut missing = 0;
+    for entry in index.entries() {
+        if entry.stage_raw() != 0 || entry.stat.size > MAX_POINTER_SIZE {
+            continue;
+        }
+        let Ok(path) = std::str::from_utf8(entry.path(&index)) else {
+            continue;
+        };
+        if patterns.iter().any(|p| is_match(p, path)) && is_pointer(&workdir.join(path)) {
+            missing += 1;
+        }
+    }
+    (missing > 0).then_some(LfsStatus::Missing(missing))
+}
+
+/// Patterns of `.gitattributes` lines that set `filter=lfs`
+fn lfs_patterns(attributes: &str) -> Vec<&str> {
+    attributes
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pattern = fields.next().filter(|p| !p.starts_with('#'))?;
+            fields.any(|a| a == "filter=lfs").then_some(pattern)
+        })
+        .collect()
+}
+
+/// Match a repo-relative path like git does: a pattern without a slash
+/// matches the file name at any depth, others match from the root
+fn is_match(pattern: &str, path: &str) -> bool {
+    if pattern.trim_end_matches('/').contains('/') {
+        glob_match(pattern.trim_start_matches('/'), path)
+    } else {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        glob_match(pattern, name)
+    }
+}
+
+fn is_pointer(path: &Path) -> bool {
+    let mut head = [0u8; POINTER_PREFIX.len()];
+    File::open(path)
+        .and_then(|mut f| f.read_exact(&mut head))
+        .is_ok_and(|()| head == POINTER_PREFIX)
+}
+
+/// Whether `git-lfs` is on the PATH
+fn is_installed() -> bool {
+    let name = if cfg!(windows) {
+        "git-lfs.exe"
+    } else {
+        "git-lfs"
+    };
+    sys::var("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|d| d.join(name).is_file()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_with_the_lfs_filter() {
+        let attributes = "\
+# Assets
+*.psd filter=lfs diff=lfs merge=lfs -text
+/models/** filter=lfs diff=lfs merge=lfs -text
+*.txt text
+#*.bin filter=lfs
+";
+        assert_eq!(lfs_patterns(attributes), ["*.psd", "/models/**"]);
+    }
+
+    #[test]
+    fn patterns_match_like_gitattributes() {
+        assert!(is_match("*.psd", "art/cover.psd"));
+        assert!(is_match("*.psd", "cover.psd"));
+        assert!(!is_match("*.psd", "cover.psd.txt"));
+        assert!(is_match("/models/**", "models/v1/weights.bin"));
+        assert!(!is_match("/models/**", "src/models/weights.bin"));
+    }
+}
@@ -1,10 +1,105 @@
 //! cc-statusline library
 //!
-//! This module exposes pure functions for use in benchmarks and tests.
-//! The main binary logic remains in main.rs.
+//! Parses Claude Code's status line JSON, collects git and PR data, and renders
+//! the configured rows. The binary in main.rs is a thin shim over [`StatusLine`];
+//! other tools (tmux plugins, editors) can embed the same pipeline:
+//!
+//! ```no_run
+//! use cc_statusline::{ClaudeInput, Config, StatusLine};
+//!
+//! let input = ClaudeInput::from_json(r#"{"model": {"display_name": "Opus"}}"#);
+//! let status = StatusLine::builder(input)
+//!     .config(Config::load(None))
+//!     .build();
+//! status.render(&mut std::io::stdout()).unwrap();
+//! ```
+
+/// Enter a tracing span named `$name` until the end of the enclosing block
+/// Expands to nothing without the trace feature
+macro_rules! trace_span {
+    ($name:literal) => {
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!($name).entered();
+    };
+}
+
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod bench;
+mod cache;
+mod ci;
+mod collect;
+mod config;
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod frame;
+mod fsmonitor;
+mod gerrit;
+mod git;
+pub mod history;
+mod input;
+mod lfs;
+pub mod memo;
+mod netfs;
+mod notify;
+mod package;
+#[cfg(feature = "plugins")]
+mod plugins;
+mod pr;
+mod render;
+pub mod render_cache;
+mod repo_status;
+pub mod rules;
+mod schema;
+pub mod stats;
+mod sys;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "trace")]
+pub mod trace;
+mod transcript;
+mod update;
+mod vcs;
+#[cfg(feature = "watch")]
+pub mod watch;
+mod wsl;
+
+pub use config::{
+    BudgetConfig, BudgetPeriod, COMPONENTS, CiConfig, CiProvider, Config, CustomSegment,
+    DurationFormat, GerritConfig, GiteaConfig, PathMode, PathStyle, PrVerbosity, ProgressSource,
+    ProjectStyle, Theme, check_config, get_config_path, write_config_init,
+};
+pub use git::{GitInfo, GitMode};
+pub use input::{
+    ClaudeInput, ContextWindow, Cost, GitInput, Model, OutputStyle, PrInput, Workspace,
+};
+pub use pr::{Deployment, PrInfo};
+pub use render::{Color, Segment, Span, StatusLine, StatusLineBuilder};
+pub use schema::{FieldKind, InputDiagnostic, validate_input};
+pub use update::check_latest;
 
 use std::borrow::Cow;
+use std::env;
+use std::ffi::OsStr;
 use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+static HOME_DIR: OnceLock<String> = OnceLock::new();
+
+fn get_home() -> Cow<'static, str> {
+    if sys::sandboxed() {
+        return Cow::Owned(sys::var("HOME").unwrap_or_default());
+    }
+    Cow::Borrowed(HOME_DIR.get_or_init(|| {
+        // Try HOME first (Unix standard), then USERPROFILE (Windows standard)
+        env::var("HOME")
+            .or_else(|_| env::var("USERPROFILE"))
+            .unwrap_or_default()
+    }))
+}
 
 /// Hash a path string to a u64 using a simple polynomial hash.
 /// Used for generating unique cache file names.
@@ -23,8 +118,13 @@ pub fn shell_escape(s: &str) -> String {
 /// Percent-encode a string for use in URLs.
 /// Encodes characters that are not unreserved per RFC 3986.
 pub fn percent_encode(s: &str) -> String {
-    let mut result = String::with_capacity(s.len() * 3);
-    for byte in s.bytes() {
+    percent_encode_bytes(s.as_bytes())
+}
+
+/// Percent-encode raw bytes, for refs and paths that aren't valid UTF-8
+pub(crate) fn percent_encode_bytes(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity(bytes.len() * 3);
+    for &byte in bytes {
         match byte {
             // Unreserved characters (RFC 3986): ALPHA / DIGIT / "-" / "." / "_" / "~"
             b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
@@ -40,42 +140,107 @@ pub fn percent_encode(s: &str) -> String {
     result
 }
 
+/// Text for bytes that should be UTF-8 but aren't guaranteed to be, like paths and refs
+/// Invalid bytes and control characters become `\xNN`, so nothing is silently lost
+/// and the result can't carry terminal escape sequences
+pub(crate) fn display_bytes(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        out.push_str(&escape_controls(chunk.valid()));
+        for byte in chunk.invalid() {
+            let _ = write!(out, "\\x{byte:02X}");
+        }
+    }
+    out
+}
+
+/// Escape C0 and C1 control characters as `\xNN`, so externally sourced text
+/// (branch names, PR titles, input fields) can't inject terminal escape sequences
+pub(crate) fn escape_controls(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(char::is_control) {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        if c.is_control() {
+            let _ = write!(out, "\\x{:02X}", u32::from(c));
+        } else {
+            out.push(c);
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// [`display_bytes`] for an OS string
+pub(crate) fn display_os(s: &OsStr) -> String {
+    display_bytes(s.as_encoded_bytes())
+}
+
+/// Standard base64 with padding, for HTTP Basic auth
+pub(crate) fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(char::from(ALPHABET[(n >> (18 - 6 * i) & 63) as usize]));
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
 /// Parse owner/repo from a GitHub URL.
 /// Validates the host is exactly `github.com` to avoid false positives.
 ///
 /// Handles:
 /// - SSH format: `git@github.com:owner/repo.git`
 /// - HTTPS format: `https://github.com/owner/repo.git`
+/// - SSH URLs, with optional user and port: `ssh://git@github.com:22/owner/repo.git`
 pub fn parse_github_url(url: &str) -> Option<(String, String)> {
     // SSH format: git@github.com:owner/repo.git (exact prefix match)
     if let Some(rest) = url.strip_prefix("git@github.com:") {
-        let path = rest.trim_end_matches(".git");
-        let mut parts = path.splitn(2, '/');
-        let owner = parts.next()?.to_string();
-        let repo = parts.next()?.to_string();
-        if !owner.is_empty() && !repo.is_empty() {
-            return Some((owner, repo));
-        }
+        return split_owner_repo(rest);
     }
 
-    // HTTPS format: https://github.com/owner/repo.git
+    // URL format: scheme://[user@]github.com[:port]/owner/repo.git
+    let (scheme, rest) = url.split_once("://")?;
+    if !["https", "http", "ssh", "git+ssh"]
+        .iter()
+        .any(|s| scheme.eq_ignore_ascii_case(s))
+    {
+        return None;
+    }
+    let (authority, path) = rest.split_once('/')?;
+    let host = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+    let host = match host.split_once(':') {
+        Some((h, port)) if !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => h,
+        Some(_) => return None,
+        None => host,
+    };
     // Validate host is exactly github.com (not notgithub.com, etc.)
-    let url_lower = url.to_lowercase();
-    if url_lower.starts_with("https://github.com/") || url_lower.starts_with("http://github.com/") {
-        let proto_end = url.find("://")? + 3;
-        let path_start = proto_end + "github.com/".len();
-        if url.len() > path_start {
-            let path = url[path_start..].trim_end_matches(".git");
-            let mut parts = path.splitn(2, '/');
-            let owner = parts.next()?.to_string();
-            let repo = parts.next()?.to_string();
-            if !owner.is_empty() && !repo.is_empty() {
-                return Some((owner, repo));
-            }
-        }
+    if !host.eq_ignore_ascii_case("github.com") {
+        return None;
     }
+    split_owner_repo(path)
+}
 
-    None
+/// Split `owner/repo[.git][/...]` into its two non-empty segments
+fn split_owner_repo(path: &str) -> Option<(String, String)> {
+    let mut parts = path.split('/');
+    let owner = parts.next()?;
+    let repo = parts.next()?;
+    let repo = repo.strip_suffix(".git").unwrap_or(repo);
+    if owner.is_empty() || repo.is_empty() {
+        return None;
+    }
+    Some((owner.to_string(), repo.to_string()))
 }
 
 /// Abbreviate a filesystem path to fit within a given width.
@@ -85,63 +250,523 @@ pub fn parse_github_url(url: &str) -> Option<(String, String)> {
 /// - Otherwise, abbreviate parent directories to first character
 /// - Always preserve the last two segments (parent/leaf) if possible
 pub fn abbreviate_path(path: &str, max_width: usize) -> Cow<'_, str> {
+    abbreviate_path_with(path, max_width, &PathStyle::default(), None)
+}
+
+/// Abbreviate a path like [`abbreviate_path`], following `style`
+///
+/// `real_dir` is the directory `path` displays (it may be shown with `~` or
+/// relative to a repo); it is needed for `style.unique`, which reads sibling
+/// directories, and otherwise ignored.
+pub fn abbreviate_path_with<'a>(
+    path: &'a str,
+    max_width: usize,
+    style: &PathStyle,
+    real_dir: Option<&Path>,
+) -> Cow<'a, str> {
     if path.len() <= max_width {
         return Cow::Borrowed(path);
     }
+    let segments: Vec<&str> = path.split('/').collect();
+    let n = segments.len();
+    if n < 2 {
+        return Cow::Borrowed(path);
+    }
 
-    let bytes = path.as_bytes();
-    let mut seg_starts: [usize; 32] = [0; 32];
-    let mut seg_count = 1;
-    seg_starts[0] = 0;
-
-    for (i, &b) in bytes.iter().enumerate() {
-        if b == b'/' && seg_count < 32 {
-            seg_starts[seg_count] = i + 1;
-            seg_count += 1;
+    let short: Vec<Cow<'_, str>> = segments
+        .iter()
+        .enumerate()
+        .map(|(i, &seg)| {
+            if seg.is_empty() || seg == "~" || style.full.iter().any(|p| glob_match(p, seg)) {
+                return Cow::Borrowed(seg);
+            }
+            let dir = real_dir
+                .filter(|_| style.unique)
+                .and_then(|d| d.ancestors().nth(n - 1 - i));
+            Cow::Owned(match dir {
+                Some(dir) => unique_prefix(seg, dir),
+                None => prefix(seg, min_prefix_chars(seg)).to_string(),
+            })
+        })
+        .collect();
+    let build = |keep: usize| {
+        let mut result = String::with_capacity(path.len());
+        for (i, seg) in segments.iter().enumerate() {
+            if i > 0 {
+                result.push('/');
+            }
+            result.push_str(if i + keep >= n { seg } else { &short[i] });
         }
-    }
+        result
+    };
 
-    if seg_count < 2 {
-        return Cow::Borrowed(path);
+    // Give up kept segments, down to the leaf, until the path fits
+    let mut keep = style.keep.unwrap_or(2).clamp(1, n);
+    let mut result = build(keep);
+    while result.len() > max_width && keep > 1 {
+        keep -= 1;
+        result = build(keep);
     }
+    Cow::Owned(result)
+}
 
-    let last_start = seg_starts[seg_count - 1];
-    let parent_start = seg_starts[seg_count - 2];
-    let last_seg = &path[last_start..];
-    let parent_seg = &path[parent_start..last_start.saturating_sub(1)];
+/// First `chars` characters of `seg`
+fn prefix(seg: &str, chars: usize) -> &str {
+    seg.char_indices()
+        .nth(chars)
+        .map_or(seg, |(end, _)| &seg[..end])
+}
 
-    let abbrev_prefix_len = (seg_count - 2) * 2;
-    let try1_len = abbrev_prefix_len + parent_seg.len() + 1 + last_seg.len();
+/// Shortest abbreviation: one character, or two for dotfiles like `.config`
+fn min_prefix_chars(seg: &str) -> usize {
+    if seg.starts_with('.') { 2 } else { 1 }
+}
 
-    let mut result = String::with_capacity(max_width + 10);
+/// Shortest prefix of `dir`'s name `seg` that no sibling directory shares
+fn unique_prefix(seg: &str, dir: &Path) -> String {
+    let siblings: Vec<String> = dir
+        .parent()
+        .and_then(|parent| fs::read_dir(parent).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|name| name != seg)
+        .collect();
+    let total = seg.chars().count();
+    (min_prefix_chars(seg)..total)
+        .map(|chars| prefix(seg, chars))
+        .find(|p| !siblings.iter().any(|s| s.starts_with(p)))
+        .unwrap_or(seg)
+        .to_string()
+}
 
-    if try1_len <= max_width || seg_count <= 2 {
-        for &start in seg_starts.iter().take(seg_count.saturating_sub(2)) {
-            if start < bytes.len() && bytes[start] != b'/' {
-                result.push(bytes[start] as char);
-                result.push('/');
-            }
-        }
-        result.push_str(parent_seg);
-        result.push('/');
-        result.push_str(last_seg);
-    } else {
-        for &start in seg_starts.iter().take(seg_count - 1) {
-            if start < bytes.len() && bytes[start] != b'/' {
-                result.push(bytes[start] as char);
-                result.push('/');
-            }
-        }
-        result.push_str(last_seg);
+/// Match a path against a glob pattern.
+///
+/// `*` and `?` match within a single path segment; `**` matches any number of
+/// segments, including none, so `/repo/**` matches `/repo` and everything below it.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.trim_end_matches('/').split('/').collect();
+    let path: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    match_segments(&pattern, &path)
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|i| match_segments(rest, &path[i..])),
+        Some((segment, rest)) => path.split_first().is_some_and(|(name, tail)| {
+            wildcard_match(segment, name) && match_segments(rest, tail)
+        }),
     }
+}
 
-    Cow::Owned(result)
+/// Match a single segment with `*` and `?` wildcards (greedy with backtracking)
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let (p, n): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    let (mut pi, mut ni) = (0, 0);
+    let mut backtrack = None;
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            backtrack = Some((pi, ni));
+            pi += 1;
+        } else if let Some((star, matched)) = backtrack {
+            pi = star + 1;
+            ni = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    // =========================================================================
+    // hash_path tests
+    // =========================================================================
+
+    #[test]
+    fn hash_path_deterministic() {
+        let path = "/home/user/project";
+        assert_eq!(hash_path(path), hash_path(path));
+    }
+
+    #[test]
+    fn hash_path_different_inputs() {
+        let path1 = "/home/user/project1";
+        let path2 = "/home/user/project2";
+        assert_ne!(hash_path(path1), hash_path(path2));
+    }
+
+    #[test]
+    fn hash_path_empty_string() {
+        // Empty string should produce a consistent hash (0 in this case)
+        assert_eq!(hash_path(""), 0);
+    }
+
+    #[test]
+    fn hash_path_similar_paths() {
+        // Paths that differ by one character should produce different hashes
+        assert_ne!(hash_path("/a/b/c"), hash_path("/a/b/d"));
+    }
+
+    // =========================================================================
+    // parse_github_url tests
+    // =========================================================================
+
+    #[test]
+    fn parse_ssh_url() {
+        let result = parse_github_url("git@github.com:owner/repo.git");
+        assert_eq!(result, Some(("owner".to_string(), "repo".to_string())));
+    }
+
+    #[test]
+    fn parse_ssh_url_without_git_suffix() {
+        // SSH URLs sometimes don't have .git suffix
+        let result = parse_github_url("git@github.com:owner/repo");
+        assert_eq!(result, Some(("owner".to_string(), "repo".to_string())));
+    }
+
+    #[test]
+    fn parse_https_url() {
+        let result = parse_github_url("https://github.com/owner/repo.git");
+        assert_eq!(result, Some(("owner".to_string(), "repo".to_string())));
+    }
+
+    #[test]
+    fn parse_https_url_without_git_suffix() {
+        let result = parse_github_url("https://github.com/owner/repo");
+        assert_eq!(result, Some(("owner".to_string(), "repo".to_string())));
+    }
+
+    #[test]
+    fn parse_http_url() {
+        let result = parse_github_url("http://github.com/owner/repo.git");
+        assert_eq!(result, Some(("owner".to_string(), "repo".to_string())));
+    }
+
+    #[test]
+    fn reject_non_github_ssh_urls() {
+        assert_eq!(parse_github_url("git@gitlab.com:owner/repo.git"), None);
+        assert_eq!(parse_github_url("git@bitbucket.org:owner/repo.git"), None);
+    }
+
+    #[test]
+    fn reject_non_github_https_urls() {
+        assert_eq!(parse_github_url("https://gitlab.com/owner/repo.git"), None);
+        assert_eq!(
+            parse_github_url("https://bitbucket.org/owner/repo.git"),
+            None
+        );
+    }
+
+    #[test]
+    fn reject_malformed_urls() {
+        assert_eq!(parse_github_url(""), None);
+        assert_eq!(parse_github_url("not-a-url"), None);
+        assert_eq!(parse_github_url("git@github.com:"), None);
+        assert_eq!(parse_github_url("git@github.com:owner"), None);
+        assert_eq!(parse_github_url("https://github.com/"), None);
+        assert_eq!(parse_github_url("https://github.com/owner"), None);
+    }
+
+    #[test]
+    fn reject_github_like_urls() {
+        // Ensure we don't match domains that contain "github.com" but aren't exactly it
+        assert_eq!(
+            parse_github_url("https://notgithub.com/owner/repo.git"),
+            None
+        );
+        assert_eq!(
+            parse_github_url("https://github.com.evil.com/owner/repo.git"),
+            None
+        );
+    }
+
+    #[test]
+    fn parse_ssh_scheme_urls() {
+        let expected = Some(("owner".to_string(), "repo".to_string()));
+        assert_eq!(
+            parse_github_url("ssh://git@github.com/owner/repo.git"),
+            expected
+        );
+        assert_eq!(
+            parse_github_url("ssh://git@github.com:22/owner/repo.git"),
+            expected
+        );
+        assert_eq!(parse_github_url("ssh://github.com/owner/repo"), expected);
+        assert_eq!(
+            parse_github_url("git+ssh://git@github.com/owner/repo.git"),
+            expected
+        );
+        assert_eq!(
+            parse_github_url("https://user@github.com/owner/repo.git"),
+            expected
+        );
+        assert_eq!(
+            parse_github_url("ssh://git@github.com:port/owner/repo.git"),
+            None
+        );
+        assert_eq!(
+            parse_github_url("ssh://git@gitlab.com:22/owner/repo.git"),
+            None
+        );
+        assert_eq!(parse_github_url("ftp://github.com/owner/repo"), None);
+    }
+
+    #[test]
+    fn parse_github_url_case_insensitive_https() {
+        // HTTPS URLs should be case-insensitive for the host
+        let result = parse_github_url("https://GitHub.com/owner/repo.git");
+        assert_eq!(result, Some(("owner".to_string(), "repo".to_string())));
+
+        let result = parse_github_url("HTTPS://GITHUB.COM/owner/repo.git");
+        assert_eq!(result, Some(("owner".to_string(), "repo".to_string())));
+    }
+
+    // =========================================================================
+    // glob_match tests
+    // =========================================================================
+
+    #[test]
+    fn glob_double_star_matches_dir_and_descendants() {
+        assert!(glob_match("/mnt/hugerepo/**", "/mnt/hugerepo"));
+        assert!(glob_match("/mnt/hugerepo/**", "/mnt/hugerepo/a/b/c"));
+        assert!(!glob_match("/mnt/hugerepo/**", "/mnt/hugerepo2"));
+        assert!(glob_match(
+            "**/node_modules/**",
+            "/home/u/app/node_modules/x"
+        ));
+    }
+
+    #[test]
+    fn glob_single_segment_wildcards() {
+        assert!(glob_match("/src/*/big-*", "/src/work/big-repo"));
+        assert!(!glob_match("/src/*", "/src/work/big-repo"));
+        assert!(glob_match("/src/repo?", "/src/repo2"));
+        assert!(!glob_match("/src/repo?", "/src/repo"));
+        assert!(glob_match("/src/a*b*c", "/src/aXbYbZc"));
+    }
+
+    #[test]
+    fn glob_exact_and_trailing_slash() {
+        assert!(glob_match("/src/repo/", "/src/repo"));
+        assert!(!glob_match("/src/repo", "/src/repo/sub"));
+    }
+
+    // =========================================================================
+    // abbreviate_path tests
+    // =========================================================================
+
+    #[test]
+    fn path_within_width_unchanged() {
+        let path = "~/short";
+        let result = abbreviate_path(path, 50);
+        assert_eq!(result.as_ref(), path);
+    }
+
+    #[test]
+    fn path_abbreviated_correctly() {
+        let path = "~/very/long/deeply/nested/path/to/project";
+        let result = abbreviate_path(path, 30);
+        // Should abbreviate parent directories to first char
+        assert!(result.len() <= 35); // Allow some slack
+        assert!(result.ends_with("project"));
+    }
+
+    #[test]
+    fn single_segment_path() {
+        let path = "project";
+        let result = abbreviate_path(path, 5);
+        // Single segment can't be abbreviated further
+        assert_eq!(result.as_ref(), path);
+    }
+
+    #[test]
+    fn root_path() {
+        let path = "/";
+        let result = abbreviate_path(path, 50);
+        assert_eq!(result.as_ref(), path);
+    }
+
+    #[test]
+    fn two_segment_path() {
+        let path = "~/project";
+        let result = abbreviate_path(path, 5);
+        // Should keep both segments as much as possible
+        assert!(result.contains("project"));
+    }
+
+    #[test]
+    fn path_keeps_configured_trailing_segments_and_full_segments() {
+        let path = "~/work/monorepo/crates/server/src/handlers";
+        let style = PathStyle {
+            keep: Some(3),
+            full: vec!["crates".into()],
+            unique: false,
+        };
+        assert_eq!(
+            abbreviate_path_with(path, 40, &style, None),
+            "~/w/m/crates/server/src/handlers"
+        );
+        // Kept segments are given up, down to the leaf, when the path won't fit
+        assert_eq!(
+            abbreviate_path_with(path, 20, &style, None),
+            "~/w/m/crates/s/s/handlers"
+        );
+        assert_eq!(
+            abbreviate_path("~/.config/nvim/lua/plugins", 20),
+            "~/.c/n/lua/plugins"
+        );
+    }
+
+    #[test]
+    fn path_unique_prefixes_among_siblings() {
+        let root = env::temp_dir().join(format!("cc-statusline-abbrev-{}", std::process::id()));
+        let leaf = root.join("projects/app/src");
+        fs::create_dir_all(&leaf).unwrap();
+        fs::create_dir_all(root.join("private")).unwrap();
+        fs::create_dir_all(root.join("projects/api")).unwrap();
+
+        let display = format!("{}/projects/app/src", root.display());
+        let style = PathStyle {
+            keep: Some(1),
+            unique: true,
+            ..PathStyle::default()
+        };
+        let result = abbreviate_path_with(&display, 10, &style, Some(&leaf));
+        let _ = fs::remove_dir_all(&root);
+        assert!(result.ends_with("/pro/app/src"), "{result}");
+    }
+
+    #[test]
+    fn tilde_home_preserved() {
+        let path = "~/a/b/c/d/project";
+        let result = abbreviate_path(path, 20);
+        // Tilde should be preserved as first char abbreviation
+        assert!(result.starts_with('~'));
+    }
+
+    // =========================================================================
+    // shell_escape tests
+    // =========================================================================
+
+    #[test]
+    fn shell_escape_single_quotes() {
+        let result = shell_escape("it's a test");
+        assert_eq!(result, "'it'\\''s a test'");
+    }
+
+    #[test]
+    fn shell_escape_empty_string() {
+        let result = shell_escape("");
+        assert_eq!(result, "''");
+    }
+
+    #[test]
+    fn shell_escape_no_escape_needed() {
+        let result = shell_escape("simple");
+        assert_eq!(result, "'simple'");
+    }
+
+    #[test]
+    fn shell_escape_special_chars() {
+        // Special shell characters should be safely escaped inside single quotes
+        let result = shell_escape("$HOME && rm -rf /");
+        assert_eq!(result, "'$HOME && rm -rf /'");
+    }
+
+    #[test]
+    fn shell_escape_multiple_quotes() {
+        let result = shell_escape("it's Bob's");
+        assert_eq!(result, "'it'\\''s Bob'\\''s'");
+    }
+
+    // =========================================================================
+    // percent_encode tests
+    // =========================================================================
+
+    #[test]
+    fn percent_encode_spaces() {
+        let result = percent_encode("hello world");
+        assert_eq!(result, "hello%20world");
+    }
+
+    #[test]
+    fn percent_encode_special_chars() {
+        let result = percent_encode("test#branch");
+        assert_eq!(result, "test%23branch");
+    }
+
+    #[test]
+    fn percent_encode_unreserved_chars_unchanged() {
+        // RFC 3986 unreserved: ALPHA / DIGIT / "-" / "." / "_" / "~"
+        let result = percent_encode("azAZ09-._~");
+        assert_eq!(result, "azAZ09-._~");
+    }
+
+    #[test]
+    fn percent_encode_slash() {
+        let result = percent_encode("path/to/file");
+        assert_eq!(result, "path%2Fto%2Ffile");
+    }
+
+    #[test]
+    fn percent_encode_unicode() {
+        let result = percent_encode("日本語");
+        // Each UTF-8 byte should be encoded
+        assert!(result.contains("%"));
+        assert!(!result.contains("日"));
+    }
+
+    #[test]
+    fn percent_encode_bytes_non_utf8() {
+        assert_eq!(percent_encode_bytes(b"caf\xe9/x"), "caf%E9%2Fx");
+    }
+
+    #[test]
+    fn escape_controls_c0_and_c1() {
+        assert!(matches!(escape_controls("main"), Cow::Borrowed("main")));
+        assert_eq!(
+            escape_controls("main\x1b]0;owned\x07"),
+            "main\\x1B]0;owned\\x07"
+        );
+        assert_eq!(escape_controls("a\u{9b}31mb"), "a\\x9B31mb");
+    }
+
+    #[test]
+    fn display_bytes_escapes_invalid_and_control() {
+        assert_eq!(display_bytes("feat/日本".as_bytes()), "feat/日本");
+        assert_eq!(display_bytes(b"caf\xe9"), "caf\\xE9");
+        assert_eq!(display_bytes(b"x\x1b[31my\n"), "x\\x1B[31my\\x0A");
+    }
+
+    #[test]
+    fn percent_encode_empty() {
+        let result = percent_encode("");
+        assert_eq!(result, "");
+    }
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64(b"user:token"), "dXNlcjp0b2tlbg==");
+        assert_eq!(base64(b"abc"), "YWJj");
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(base64(b""), "");
+    }
+
+    // =========================================================================
+    // Pure helper smoke tests
+    // =========================================================================
+
     #[test]
     fn test_hash_path_deterministic() {
         let path = "/home/user/project";
@@ -0,0 +1,348 @@
+//! In-process memoization for long-running modes
+//!
+//! `--serve` renders many inputs in one process, so later renders can reuse
+//! what earlier ones found instead of rereading cache files: the repo found
+//! for a directory, working-tree stats, and PR data. Each kind is kept in a
+//! small LRU. Entries for a repo are dropped once its `HEAD` or index changes;
+//! with the watch feature a file watcher reports that, otherwise both files'
+//! mtimes are compared on every lookup. PR data also expires after
+//! `PR_TTL`, since background refreshes write it without touching the repo.
+//!
+//! One-shot runs never enable it, so every lookup there is a miss.
+
+use crate::git::{DiffStats, GitMode, GitRepo, RepoLocation};
+use crate::pr::PrInfo;
+use crate::sys;
+use std::collections::VecDeque;
+use std::fs;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::SystemTime;
+
+/// Directories whose repo is remembered
+const MAX_REPOS: usize = 16;
+/// Stats entries; one per repo, branch, and mode in use
+const MAX_STATS: usize = 32;
+/// PR entries; one per repo and branch
+const MAX_PRS: usize = 32;
+/// Seconds a memoized PR is used before the cache file is read again
+const PR_TTL: u64 = 10;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Bumped on every invalidation, so a result computed across one isn't kept
+static CHANGES: AtomicU64 = AtomicU64::new(0);
+static MEMO: Mutex<Memo> = Mutex::new(Memo::new());
+
+/// Least recently used entries are evicted first once `capacity` is reached
+#[derive(Debug)]
+pub(crate) struct Lru<K, V> {
+    entries: VecDeque<(K, V)>,
+    capacity: usize,
+}
+
+impl<K: PartialEq, V> Lru<K, V> {
+    pub(crate) const fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// The entry for `key`, marked as most recently used
+    pub(crate) fn get(&mut self, key: &K) -> Option<&V> {
+        let idx = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(idx)?;
+        self.entries.push_back(entry);
+        self.entries.back().map(|(_, v)| v)
+    }
+
+    pub(crate) fn insert(&mut self, key: K, value: V) {
+        self.entries.retain(|(k, _)| *k != key);
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((key, value));
+    }
+
+    pub(crate) fn retain(&mut self, mut keep: impl FnMut(&K, &V) -> bool) {
+        self.entries.retain(|(k, v)| keep(k, v));
+    }
+}
+
+/// Modification times of a repo's `HEAD` and index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    head: Option<SystemTime>,
+    index: Option<SystemTime>,
+}
+
+impl Stamp {
+    fn of(git_dir: &str) -> Self {
+        let modified = |name: &str| {
+            fs::metadata(format!("{}/{name}", git_dir.trim_end_matches('/')))
+                .and_then(|m| m.modified())
+                .ok()
+        };
+        Self {
+            head: modified("HEAD"),
+            index: modified("index"),
+        }
+    }
+}
+
+/// Stats for one repo, branch, mode, and `max_files`
+#[derive(Debug, PartialEq)]
+struct StatsKey {
+    git_dir: String,
+    branch: String,
+    mode: GitMode,
+    max_files: Option<u32>,
+}
+
+struct Memo {
+    repos: Lru<String, RepoLocation>,
+    /// Stats with the unix seconds they were computed at
+    stats: Lru<StatsKey, (DiffStats, u64)>,
+    /// PRs by git dir and branch, with the unix seconds they were memoized at
+    prs: Lru<(String, String), (PrInfo, u64)>,
+    /// Last seen stamp of each repo that isn't watched
+    stamps: Lru<String, Stamp>,
+}
+
+impl Memo {
+    const fn new() -> Self {
+        Self {
+            repos: Lru::new(MAX_REPOS),
+            stats: Lru::new(MAX_STATS),
+            prs: Lru::new(MAX_PRS),
+            stamps: Lru::new(MAX_REPOS),
+        }
+    }
+
+    /// Drop everything known about the repo at `git_dir`
+    fn forget(&mut self, git_dir: &str) {
+        self.repos.retain(|_, r| r.git_dir() != git_dir);
+        self.stats.retain(|k, _| k.git_dir != git_dir);
+        self.prs.retain(|(dir, _), _| dir != git_dir);
+        self.stamps.retain(|dir, _| dir != git_dir);
+    }
+
+    /// Forget the repo if it changed since its entries were stored
+    fn validate(&mut self, git_dir: &str) {
+        if watched(git_dir) {
+            return;
+        }
+        let stamp = Stamp::of(git_dir);
+        match self.stamps.get(&git_dir.to_string()) {
+            Some(seen) if *seen == stamp => {}
+            Some(_) => {
+                self.forget(git_dir);
+                self.stamps.insert(git_dir.to_string(), stamp);
+            }
+            None => self.stamps.insert(git_dir.to_string(), stamp),
+        }
+    }
+}
+
+/// Turn memoization on for the rest of the process
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+fn memo() -> Option<std::sync::MutexGuard<'static, Memo>> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    Some(MEMO.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Drop every entry for the repo at `git_dir`; called when its metadata changes
+#[cfg(feature = "watch")]
+fn invalidate(git_dir: &str) {
+    CHANGES.fetch_add(1, Ordering::Relaxed);
+    if let Some(mut memo) = memo() {
+        memo.forget(git_dir);
+    }
+}
+
+/// The repo found earlier for `dir`
+pub(crate) fn repo(dir: &str) -> Option<GitRepo> {
+    let mut memo = memo()?;
+    let git_dir = memo.repos.get(&dir.to_string())?.git_dir().to_string();
+    memo.validate(&git_dir);
+    memo.repos
+        .get(&dir.to_string())
+        .cloned()
+        .map(RepoLocation::open)
+}
+
+pub(crate) fn remember_repo(dir: &str, git: &GitRepo) {
+    let Some(mut memo) = memo() else {
+        return;
+    };
+    memo.validate(&git.git_dir);
+    memo.repos.insert(dir.to_string(), git.location());
+    drop(memo);
+    watch(git);
+}
+
+/// Stats computed earlier for the same repo state, at most `max_age` seconds old
+/// Looking up first records the repo's state, so stats remembered after a miss
+/// are dropped if the repo changed while they were computed
+pub(crate) fn stats(
+    git: &GitRepo,
+    mode: GitMode,
+    max_files: Option<u32>,
+    max_age: Option<u64>,
+) -> Option<DiffStats> {
+    let mut memo = memo()?;
+    memo.validate(&git.git_dir);
+    let (stats, at) = memo.stats.get(&stats_key(git, mode, max_files))?;
+    let fresh = max_age.is_none_or(|max| sys::now_secs().saturating_sub(*at) < max);
+    fresh.then(|| stats.clone())
+}
+
+/// Invalidations so far; pass to [`remember_stats`] to drop stats that
+/// were computed while the repo changed
+pub(crate) fn changes() -> u64 {
+    CHANGES.load(Ordering::Relaxed)
+}
+
+/// Remember stats computed (or read from the cache) at unix time `at`,
+/// unless the repo changed since [`changes`] returned `seen`
+pub(crate) fn remember_stats(
+    git: &GitRepo,
+    mode: GitMode,
+    max_files: Option<u32>,
+    stats: &DiffStats,
+    at: u64,
+    seen: u64,
+) {
+    if let Some(mut memo) = memo()
+        && changes() == seen
+    {
+        let key = stats_key(git, mode, max_files);
+        memo.stats.insert(key, (stats.clone(), at));
+    }
+}
+
+fn stats_key(git: &GitRepo, mode: GitMode, max_files: Option<u32>) -> StatsKey {
+    StatsKey {
+        git_dir: git.git_dir.clone(),
+        branch: git.branch.clone(),
+        mode,
+        max_files,
+    }
+}
+
+/// The PR found for the branch within the last `PR_TTL` seconds
+pub(crate) fn pr(git: &GitRepo) -> Option<PrInfo> {
+    let mut memo = memo()?;
+    memo.validate(&git.git_dir);
+    let (pr, at) = memo.prs.get(&(git.git_dir.clone(), git.branch.clone()))?;
+    (sys::now_secs().saturating_sub(*at) < PR_TTL).then(|| pr.clone())
+}
+
+pub(crate) fn remember_pr(git: &GitRepo, pr: &PrInfo) {
+    if let Some(mut memo) = memo() {
+        let key = (git.git_dir.clone(), git.branch.clone());
+        memo.prs.insert(key, (pr.clone(), sys::now_secs()));
+    }
+}
+
+#[cfg(feature = "watch")]
+mod watcher {
+    use super::invalidate;
+    use crate::git::GitRepo;
+    use crate::watch::{is_relevant, watch_repo};
+    use notify::{Event, RecommendedWatcher};
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    /// Kept apart from the watched paths: the watcher's callback reads those,
+    /// so adding a path while holding them could deadlock
+    static WATCHER: Mutex<Option<RecommendedWatcher>> = Mutex::new(None);
+    /// Watched paths with the git dir each belongs to
+    static ROOTS: Mutex<Vec<(PathBuf, String)>> = Mutex::new(Vec::new());
+
+    /// Repos in `roots` whose metadata the event changed
+    fn changed(event: &Event, roots: &[(PathBuf, String)]) -> Vec<String> {
+        let mut dirs: Vec<String> = roots
+            .iter()
+            .filter(|(root, _)| {
+                event
+                    .paths
+                    .iter()
+                    .any(|p| is_relevant(p) && p.starts_with(root))
+            })
+            .map(|(_, git_dir)| git_dir.clone())
+            .collect();
+        dirs.dedup();
+        dirs
+    }
+
+    pub(super) fn watched(git_dir: &str) -> bool {
+        let roots = ROOTS.lock().unwrap_or_else(|e| e.into_inner());
+        roots.iter().any(|(_, dir)| dir == git_dir)
+    }
+
+    /// Start watching the repo; without a watcher its entries are checked by mtime
+    pub(super) fn watch(git: &GitRepo) {
+        if watched(&git.git_dir) {
+            return;
+        }
+        let mut watcher = WATCHER.lock().unwrap_or_else(|e| e.into_inner());
+        if watcher.is_none() {
+            *watcher = notify::recommended_watcher(|res: notify::Result<Event>| {
+                let Ok(event) = res else {
+                    return;
+                };
+                let dirs = changed(&event, &ROOTS.lock().unwrap_or_else(|e| e.into_inner()));
+                for dir in dirs {
+                    invalidate(&dir);
+                }
+            })
+            .ok();
+        }
+        let Some(watcher) = watcher.as_mut() else {
+            return;
+        };
+        if let Ok(paths) = watch_repo(watcher, git) {
+            let mut roots = ROOTS.lock().unwrap_or_else(|e| e.into_inner());
+            roots.extend(paths.into_iter().map(|p| (p, git.git_dir.clone())));
+        }
+    }
+}
+
+#[cfg(feature = "watch")]
+use watcher::{watch, watched};
+
+#[cfg(not(feature = "watch"))]
+fn watch(_git: &GitRepo) {}
+
+#[cfg(not(feature = "watch"))]
+fn watched(_git_dir: &str) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lru_evicts_the_least_recently_used() {
+        let mut lru = Lru::new(2);
+        lru.insert("a", 1);
+        lru.insert("b", 2);
+        assert_eq!(lru.get(&"a"), Some(&1));
+        lru.insert("c", 3);
+        assert_eq!(lru.get(&"b"), None);
+        assert_eq!(lru.get(&"a"), Some(&1));
+        assert_eq!(lru.get(&"c"), Some(&3));
+
+        lru.insert("c", 4);
+        assert_eq!(lru.get(&"c"), Some(&4));
+        lru.retain(|k, _| *k != "a");
+        assert_eq!(lru.get(&"a"), None);
+    }
+}
@@ -0,0 +1,114 @@
+//! Network filesystem detection
+//!
+//! Every stat on NFS, SMB, or a FUSE mount is a round trip, so a repo on one
+//! gets a cheaper git mode and longer-lived stats instead of a slow prompt.
+//! Windows drives mounted into WSL count too.
+
+use crate::{sys, wsl};
+use std::path::Path;
+
+/// Whether `path` is on a network or FUSE filesystem, or a Windows drive under WSL
+/// `CC_STATUSLINE_SLOW_FS` forces the answer (`1`/`true` or `0`/`false`)
+pub(crate) fn is_slow(path: &Path) -> bool {
+    match sys::var("CC_STATUSLINE_SLOW_FS")
+        .as_deref()
+        .map(str::trim)
+        .map(str::to_ascii_lowercase)
+        .as_deref()
+    {
+        Some("1" | "true") => true,
+        Some("0" | "false") => false,
+        _ => detect(path) || wsl::is_drvfs(path),
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn detect(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    const NFS: i64 = 0x6969;
+    const SMB: i64 = 0x517b;
+    const CIFS: i64 = 0xff53_4d42;
+    const SMB2: i64 = 0xfe53_4d42;
+    const FUSE: i64 = 0x6573_5546;
+    const AFS: i64 = 0x5346_414f;
+    const CEPH: i64 = 0x00c3_6400;
+    const V9FS: i64 = 0x0102_1997;
+
+    let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &raw mut stat) } != 0 {
+        return false;
+    }
+    // f_type's width differs between targets
+    #[allow(clippy::useless_conversion, clippy::unnecessary_cast)]
+    let magic = i64::from(stat.f_type as u32);
+    matches!(magic, NFS | SMB | CIFS | SMB2 | FUSE | AFS | CEPH | V9FS)
+}
+
+#[cfg(target_os = "macos")]
+fn detect(path: &Path) -> bool {
+    use std::ffi::CStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &raw mut stat) } != 0 {
+        return false;
+    }
+    let name = unsafe { CStr::from_ptr(stat.f_fstypename.as_ptr()) };
+    matches!(
+        name.to_bytes(),
+        b"nfs" | b"smbfs" | b"afpfs" | b"webdav" | b"macfuse" | b"osxfuse" | b"fusefs"
+    )
+}
+
+#[cfg(windows)]
+fn detect(path: &Path) -> bool {
+    use std::path::{Component, Prefix};
+
+    const DRIVE_REMOTE: u32 = 4;
+    unsafe extern "system" {
+        fn GetDriveTypeW(root: *const u16) -> u32;
+    }
+
+    // UNC paths (\\server\share) are always remote
+    let drive = match path.components().next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::UNC(..) | Prefix::VerbatimUNC(..) => return true,
+            Prefix::Disk(d) | Prefix::VerbatimDisk(d) => d,
+            _ => return false,
+        },
+        _ => return false,
+    };
+    let root: Vec<u16> = format!("{}:\\\0", char::from(drive))
+        .encode_utf16()
+        .collect();
+    unsafe { GetDriveTypeW(root.as_ptr()) == DRIVE_REMOTE }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    windows
+)))]
+fn detect(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_dir_is_not_slow() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!detect(dir.path()));
+        assert!(!detect(Path::new("/nonexistent/netfs-test")));
+    }
+}
@@ -0,0 +1,136 @@
+//! Desktop notifications when a PR's checks finish or context runs low
+//!
+//! The state last seen for each branch or session is kept in a small state file,
+//! so whichever render first sees the change notices the transition.
+
+use crate::cache::{atomic_rename, get_state_dir, unique_hex};
+use crate::hash_path;
+use crate::sys;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Notify if `status` finishes a run that was pending for the same `pr` last time
+/// `pr` identifies the PR (like "#42"), so switching PRs doesn't notify
+pub(crate) fn check_transition(git_dir: &str, branch: &str, pr: &str, status: &str) {
+    let path = get_state_dir().join(format!(
+        "checks-{:016x}.state",
+        hash_path(&format!("{git_dir}:{branch}"))
+    ));
+    let Some(previous) = swap_state(&path, &format!("{pr} {status}")) else {
+        return;
+    };
+    if let Some(message) = finished(previous.as_deref(), pr, status) {
+        desktop_notify(&format!("{branch} {pr}"), message);
+    }
+}
+
+/// Notify once when the session's remaining context first drops below the warning
+pub(crate) fn compact_transition(session_id: &str, low: bool, remaining: f64) {
+    let path = get_state_dir().join(format!("compact-{:016x}.state", hash_path(session_id)));
+    let current = if low { "low" } else { "ok" };
+    if let Some(previous) = swap_state(&path, current)
+        && low
+        && previous.as_deref() != Some("low")
+    {
+        desktop_notify(
+            "Context running low",
+            &format!("{remaining:.0}% left before auto-compact"),
+        );
+    }
+}
+
+/// Replace the state at `path` with `current`, returning the previous state
+/// None when nothing changed or the state couldn't be saved
+fn swap_state(path: &Path, current: &str) -> Option<Option<String>> {
+    let previous = fs::read_to_string(path).ok();
+    if previous.as_deref() == Some(current) {
+        return None;
+    }
+    let temp_path = get_state_dir().join(format!("state-tmp-{}", unique_hex()));
+    if fs::write(&temp_path, current).is_err() || atomic_rename(&temp_path, path).is_err() {
+        return None; // Without the state file every render would notify again
+    }
+    Some(previous)
+}
+
+/// Message for a pending → passed/failed change of the same PR
+fn finished(previous: Option<&str>, pr: &str, status: &str) -> Option<&'static str> {
+    let (previous_pr, previous_status) = previous?.split_once(' ')?;
+    if previous_pr != pr || previous_status != "pending" {
+        return None;
+    }
+    match status {
+        "passed" => Some("Checks passed"),
+        "failed" => Some("Checks failed"),
+        _ => None,
+    }
+}
+
+/// Show a notification with the platform's tool, without waiting for it
+fn desktop_notify(title: &str, body: &str) {
+    if sys::sandboxed() {
+        return;
+    }
+    let mut command = if cfg!(target_os = "macos") {
+        // Arguments rather than interpolation, so titles need no AppleScript quoting
+        let mut command = Command::new("osascript");
+        command.args([
+            "-e",
+            "on run argv",
+            "-e",
+            "display notification (item 2 of argv) with title (item 1 of argv)",
+            "-e",
+            "end run",
+            title,
+            body,
+        ]);
+        command
+    } else if cfg!(windows) {
+        let mut command = Command::new("powershell");
+        command
+            .args(["-NoProfile", "-NonInteractive", "-Command", WINDOWS_TOAST])
+            .env("CC_STATUSLINE_TITLE", title)
+            .env("CC_STATUSLINE_BODY", body);
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.args(["--app-name=cc-statusline", title, body]);
+        command
+    };
+    let _ = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+}
+
+/// Toast through the WinRT API; the text comes from the environment
+const WINDOWS_TOAST: &str = "\
+$m = [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime]
+$xml = $m::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02)
+$text = $xml.GetElementsByTagName('text')
+$null = $text.Item(0).AppendChild($xml.CreateTextNode($env:CC_STATUSLINE_TITLE))
+$null = $text.Item(1).AppendChild($xml.CreateTextNode($env:CC_STATUSLINE_BODY))
+$m::CreateToastNotifier('cc-statusline').Show([Windows.UI.Notifications.ToastNotification]::new($xml))";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_finished_runs_notify() {
+        assert_eq!(
+            finished(Some("#42 pending"), "#42", "passed"),
+            Some("Checks passed")
+        );
+        assert_eq!(
+            finished(Some("#42 pending"), "#42", "failed"),
+            Some("Checks failed")
+        );
+        assert_eq!(finished(Some("#42 pending"), "#42", "pending"), None);
+        assert_eq!(finished(Some("#42 failed"), "#42", "passed"), None);
+        assert_eq!(finished(Some("#41 pending"), "#42", "passed"), None);
+        assert_eq!(finished(None, "#42", "passed"), None);
+    }
+}
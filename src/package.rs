@@ -0,0 +1,104 @@
+//! Monorepo sub-package detection
+//!
+//! Finds the nearest directory with a package manifest between the current
+//! directory and the repo root, so the status line can show which package
+//! of a large repo you are in.
+
+use crate::cache::{atomic_rename, get_cache_dir, unique_hex};
+use crate::{display_os, hash_path, sys};
+use std::fs;
+use std::path::Path;
+
+/// Files that mark a package root
+const MANIFESTS: &[&str] = &[
+    "Cargo.toml",
+    "package.json",
+    "go.mod",
+    "pyproject.toml",
+    "BUILD",
+    "BUILD.bazel",
+];
+
+/// Seconds a lookup is reused before walking the directories again
+const PACKAGE_CACHE_TTL: u64 = 300;
+
+fn has_manifest(dir: &Path) -> bool {
+    MANIFESTS.iter().any(|m| dir.join(m).is_file())
+}
+
+/// Nearest directory at or above `dir` with a manifest, stopping below `root`
+fn find_package_dir<'a>(dir: &'a Path, root: &Path) -> Option<&'a Path> {
+    if !dir.starts_with(root) {
+        return None;
+    }
+    dir.ancestors()
+        .take_while(|d| *d != root)
+        .find(|d| has_manifest(d))
+}
+
+/// Parse cache content: "<checked_at>\n<package_dir>" (dir is empty if none was found)
+fn parse_package_cache(content: &str) -> Option<(u64, String)> {
+    let mut lines = content.lines();
+    let checked_at = lines.next()?.parse().ok()?;
+    let dir = lines.next().unwrap_or_default().to_string();
+    Some((checked_at, dir))
+}
+
+/// Name of the package containing `dir` inside the repo at `root`, cached per directory
+pub(crate) fn package_name(dir: &str, root: &str) -> Option<String> {
+    let cache_path = get_cache_dir().join(format!("package-{:016x}.cache", hash_path(dir)));
+    let now = sys::now_secs();
+    let cached = fs::read_to_string(&cache_path)
+        .ok()
+        .and_then(|c| parse_package_cache(&c))
+        .filter(|(checked_at, pkg)| {
+            now.saturating_sub(*checked_at) < PACKAGE_CACHE_TTL
+                && (pkg.is_empty() || has_manifest(Path::new(pkg)))
+        });
+    let pkg_dir = match cached {
+        Some((_, pkg)) => pkg,
+        None => {
+            let pkg = find_package_dir(Path::new(dir), Path::new(root))
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let temp_path = get_cache_dir().join(format!("package-tmp-{}.cache", unique_hex()));
+            if fs::write(&temp_path, format!("{now}\n{pkg}")).is_ok() {
+                let _ = atomic_rename(&temp_path, &cache_path);
+            }
+            pkg
+        }
+    };
+    Path::new(&pkg_dir).file_name().map(display_os)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn finds_nearest_manifest_below_root() {
+        let root = env::temp_dir().join(format!("cc-statusline-package-{}", unique_hex()));
+        let web = root.join("apps/web-frontend");
+        let src = web.join("src/components");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(root.join("package.json"), "{}").unwrap();
+        fs::write(web.join("package.json"), "{}").unwrap();
+
+        assert_eq!(find_package_dir(&src, &root), Some(web.as_path()));
+        // The repo root's own manifest doesn't count as a sub-package
+        assert_eq!(find_package_dir(&root.join("apps"), &root), None);
+        assert_eq!(find_package_dir(Path::new("/elsewhere"), &root), None);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn package_cache_parse() {
+        assert_eq!(
+            parse_package_cache("100\n/repo/apps/web"),
+            Some((100, "/repo/apps/web".to_string()))
+        );
+        assert_eq!(parse_package_cache("100"), Some((100, String::new())));
+        assert_eq!(parse_package_cache("x"), None);
+    }
+}
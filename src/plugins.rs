@@ -0,0 +1,228 @@
+//! Lua plugin segments
+//!
+//! A plugin is a Lua file in the plugins directory whose name (minus `.lua`)
+//! is used as a component name in `rows`. The chunk must return either a
+//! function or a table with a `render` function. It is called with a context
+//! table (`input`, `git`, `pr`, `cwd`, `project`) and returns nil (hide), a
+//! string, or a table `{ text = "...", color = "cyan" }`.
+//!
+//! Each plugin runs in a fresh Lua state with a time budget enforced by an
+//! instruction-count hook, and a memory cap, so a broken plugin can't stall
+//! or bloat the status line. Only the string, table, math, utf8, and
+//! coroutine libraries are loaded; there is no `io`, `os`, or `require`, and
+//! the base library's file loaders are removed.
+
+use mlua::{Function, HookTriggers, Lua, LuaOptions, LuaSerdeExt, StdLib, Value};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Memory cap per plugin state
+const PLUGIN_MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Text and optional color returned by a plugin
+pub struct PluginSegment {
+    pub text: String,
+    pub color: Option<String>,
+}
+
+/// Run the plugin named `name` from the first of `dirs` that has it, returning
+/// Ok(None) if none does or it produced nothing
+/// Returns Err if the plugin fails to load, errors, or exceeds its time budget
+pub fn render(
+    dirs: &[PathBuf],
+    name: &str,
+    ctx: &serde_json::Value,
+    budget: Duration,
+) -> Result<Option<PluginSegment>, String> {
+    // Names come from config; reject anything that could escape the plugins directory
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err("invalid plugin name".to_string());
+    }
+    let mut found = None;
+    for dir in dirs {
+        let path = dir.join(format!("{name}.lua"));
+        match fs::read_to_string(&path) {
+            Ok(source) => {
+                found = Some((path, source));
+                break;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("{}: {e}", path.display())),
+        }
+    }
+    // Not a plugin: unknown components are ignored for forward compatibility
+    let Some((path, source)) = found else {
+        return Ok(None);
+    };
+
+    let libs = StdLib::STRING | StdLib::TABLE | StdLib::MATH | StdLib::UTF8 | StdLib::COROUTINE;
+    let lua = Lua::new_with(libs, LuaOptions::default()).map_err(|e| e.to_string())?;
+    for loader in ["dofile", "loadfile"] {
+        lua.globals()
+            .set(loader, Value::Nil)
+            .map_err(|e| e.to_string())?;
+    }
+    lua.set_memory_limit(PLUGIN_MEMORY_LIMIT)
+        .map_err(|e| e.to_string())?;
+
+    let deadline = Instant::now() + budget;
+    lua.set_hook(
+        HookTriggers::new().every_nth_instruction(1000),
+        move |_lua, _debug| {
+            if Instant::now() > deadline {
+                Err(mlua::Error::runtime("time budget exceeded"))
+            } else {
+                Ok(())
+            }
+        },
+    );
+
+    let chunk: Value = lua
+        .load(&source)
+        .set_name(format!("@{}", path.display()))
+        .eval()
+        .map_err(|e| e.to_string())?;
+
+    let render_fn: Function = match chunk {
+        Value::Function(f) => f,
+        Value::Table(t) => t
+            .get("render")
+            .map_err(|_| "plugin table has no render function".to_string())?,
+        _ => return Err("plugin must return a function or a table".to_string()),
+    };
+
+    let ctx = lua.to_value(ctx).map_err(|e| e.to_string())?;
+    let result: Value = render_fn.call(ctx).map_err(|e| e.to_string())?;
+
+    match result {
+        Value::Nil => Ok(None),
+        Value::String(s) => Ok(segment(s.to_string_lossy().into_owned(), None)),
+        Value::Table(t) => {
+            let text: Option<String> = t.get("text").map_err(|e| e.to_string())?;
+            let color: Option<String> = t.get("color").map_err(|e| e.to_string())?;
+            Ok(text.and_then(|text| segment(text, color)))
+        }
+        _ => Err("render must return nil, a string, or a table".to_string()),
+    }
+}
+
+fn segment(text: String, color: Option<String>) -> Option<PluginSegment> {
+    let text = text.lines().next().unwrap_or("").trim().to_string();
+    if text.is_empty() {
+        None
+    } else {
+        Some(PluginSegment { text, color })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(source: &str, budget_ms: u64) -> Result<Option<PluginSegment>, String> {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("test.lua"), source).unwrap();
+        let ctx = serde_json::json!({"git": {"branch": "main"}});
+        render(
+            &[dir.path().to_path_buf()],
+            "test",
+            &ctx,
+            Duration::from_millis(budget_ms),
+        )
+    }
+
+    #[test]
+    fn plugin_function_receives_context() {
+        let result = run(
+            "return function(ctx) return 'on ' .. ctx.git.branch end",
+            1000,
+        );
+        assert_eq!(result.unwrap().unwrap().text, "on main");
+    }
+
+    #[test]
+    fn plugin_table_with_color() {
+        let source = "return { render = function() return { text = 'hi', color = 'red' } end }";
+        let segment = run(source, 1000).unwrap().unwrap();
+        assert_eq!(segment.text, "hi");
+        assert_eq!(segment.color.as_deref(), Some("red"));
+    }
+
+    #[test]
+    fn plugin_nil_hides_segment() {
+        assert!(
+            run("return function() return nil end", 1000)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn plugin_time_budget_enforced() {
+        let err = run("return function() while true do end end", 20)
+            .err()
+            .unwrap();
+        assert!(err.contains("time budget exceeded"), "{err}");
+    }
+
+    #[test]
+    fn plugin_missing_is_silent() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = serde_json::Value::Null;
+        let dirs = [dir.path().to_path_buf()];
+        let result = render(&dirs, "absent", &ctx, Duration::from_millis(10));
+        assert!(result.unwrap().is_none());
+    }
+
+    #[test]
+    fn plugin_found_in_first_dir_that_has_it() {
+        let (first, second) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        fs::write(
+            second.path().join("p.lua"),
+            "return function() return 'old' end",
+        )
+        .unwrap();
+        let dirs = [first.path().to_path_buf(), second.path().to_path_buf()];
+        let ctx = serde_json::Value::Null;
+        let budget = Duration::from_millis(1000);
+        assert_eq!(
+            render(&dirs, "p", &ctx, budget).unwrap().unwrap().text,
+            "old"
+        );
+
+        fs::write(
+            first.path().join("p.lua"),
+            "return function() return 'new' end",
+        )
+        .unwrap();
+        assert_eq!(
+            render(&dirs, "p", &ctx, budget).unwrap().unwrap().text,
+            "new"
+        );
+    }
+
+    #[test]
+    fn plugin_has_no_io_or_os() {
+        for source in [
+            "return function() return os.getenv('HOME') end",
+            "return function() return io.open('/etc/passwd'):read('l') end",
+            "return function() return dofile('/etc/passwd') end",
+            "return function() return require('os').time() end",
+        ] {
+            assert!(run(source, 1000).is_err(), "{source}");
+        }
+        let segment = run(
+            "return function() return string.upper(('x'):rep(2)) end",
+            1000,
+        );
+        assert_eq!(segment.unwrap().unwrap().text, "XX");
+    }
+
+    #[test]
+    fn plugin_invalid_name_rejected() {
+        let ctx = serde_json::Value::Null;
+        let budget = Duration::from_millis(10);
+        assert!(render(&[PathBuf::from("/tmp")], "../evil", &ctx, budget).is_err());
+    }
+}
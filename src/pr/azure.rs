@@ -0,0 +1,320 @@
+//! Azure DevOps remote detection and REST API fetch
+//!
+//! PRs and pipeline builds are mapped onto the same shape as GitHub's, so
+//! the PR components render them unchanged.
+
+use super::cache::{
+    PrCacheEntry, PrError, PrState, Provider, parse_pr_json, read_pr_entry, save_pr_entry,
+};
+use super::github::{RemoteConfig, api_get};
+use super::{PrInfo, PrSource};
+use crate::sys::{self, TlsOptions};
+use crate::{base64, percent_encode};
+
+/// Default REST API base URL
+const DEFAULT_API_URL: &str = "https://dev.azure.com";
+
+const API_VERSION: &str = "7.1";
+
+/// Repo in an Azure DevOps project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct AzureTarget {
+    pub(crate) organization: String,
+    pub(crate) project: String,
+    pub(crate) repo: String,
+}
+
+/// Parse an Azure DevOps remote URL
+/// Handles: `https://[user@]dev.azure.com/org/project/_git/repo`,
+/// `git@ssh.dev.azure.com:v3/org/project/repo`, and the older
+/// `https://org.visualstudio.com/[DefaultCollection/]project/_git/repo` and
+/// `org@vs-ssh.visualstudio.com:v3/org/project/repo`
+pub(crate) fn parse_azure_url(url: &str) -> Option<AzureTarget> {
+    let target = |organization: &str, project: &str, repo: &str| {
+        let repo = repo.strip_suffix(".git").unwrap_or(repo);
+        (![organization, project, repo].contains(&"")).then(|| AzureTarget {
+            organization: organization.to_string(),
+            project: project.to_string(),
+            repo: repo.to_string(),
+        })
+    };
+
+    // SSH: the path is always v3/org/project/repo
+    if let Some((user_host, path)) = url.split_once(":v3/")
+        && !user_host.contains('/')
+    {
+        let host = user_host.rsplit_once('@').map_or(user_host, |(_, h)| h);
+        if !host.eq_ignore_ascii_case("ssh.dev.azure.com")
+            && !host.eq_ignore_ascii_case("vs-ssh.visualstudio.com")
+        {
+            return None;
+        }
+        let mut parts = path.splitn(3, '/');
+        return target(parts.next()?, parts.next()?, parts.next()?);
+    }
+
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("HTTPS://"))?;
+    let (authority, path) = rest.split_once('/')?;
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, h)| h)
+        .to_ascii_lowercase();
+    let (project_path, repo) = path.split_once("/_git/")?;
+    let repo = repo.split('/').next()?;
+    if host == "dev.azure.com" {
+        let (organization, project) = project_path.split_once('/')?;
+        target(organization, project, repo)
+    } else {
+        let organization = host.strip_suffix(".visualstudio.com")?;
+        let project = project_path
+            .strip_prefix("DefaultCollection/")
+            .unwrap_or(project_path);
+        target(organization, project, repo)
+    }
+}
+
+/// Find the Azure DevOps repo to query for `branch`'s PR
+pub(crate) fn resolve_target(
+    repo: &gix::Repository,
+    branch: &str,
+    preferred: Option<&str>,
+) -> Option<AzureTarget> {
+    RemoteConfig::load(repo, branch).find(preferred, parse_azure_url)
+}
+
+/// Personal access token from `AZURE_DEVOPS_EXT_PAT`, as used by `az devops`
+fn get_azure_token() -> Option<String> {
+    sys::var("AZURE_DEVOPS_EXT_PAT").filter(|token| !token.is_empty())
+}
+
+/// API base URL: `CC_STATUSLINE_AZURE_API_URL`, then dev.azure.com
+fn api_base_url() -> String {
+    sys::var("CC_STATUSLINE_AZURE_API_URL")
+        .filter(|url| !url.is_empty())
+        .map_or_else(
+            || DEFAULT_API_URL.to_string(),
+            |url| url.trim_end_matches('/').to_string(),
+        )
+}
+
+/// Refresh the PR cache from the Azure DevOps API (synchronous)
+pub(crate) fn refresh_pr_azure(
+    git_dir: &str,
+    branch: &str,
+    source: &PrSource,
+    target: &AzureTarget,
+) {
+    let Some(token) = get_azure_token() else {
+        return; // No PAT, skip PR feature
+    };
+    let auth = format!("Basic {}", base64(format!(":{token}").as_bytes()));
+    let headers = [
+        ("Authorization", auth.as_str()),
+        ("Accept", "application/json"),
+        ("User-Agent", "cc-statusline"),
+    ];
+    let now = sys::now_secs();
+    let base = format!(
+        "{}/{}/{}/_apis",
+        api_base_url(),
+        target.organization,
+        target.project
+    );
+    let repo = &target.repo;
+
+    // Newest PR from this branch in any state, like GitHub's state=all
+    let url = format!(
+        "{base}/git/repositories/{repo}/pullrequests?searchCriteria.sourceRefName={}\
+         &searchCriteria.status=all&$top=1&api-version={API_VERSION}",
+        percent_encode(&format!("refs/heads/{branch}"))
+    );
+    let state = match api_get(&url, &headers, &source.tls) {
+        Ok(resp) => {
+            let Ok(list) = serde_json::from_str::<serde_json::Value>(&resp.body) else {
+                return;
+            };
+            match list["value"].get(0) {
+                None => PrState::NoPr,
+                Some(pr) => match pr_info(&base, target, pr, &headers, &source.tls) {
+                    Some(pr) => PrState::Found(pr),
+                    None => return,
+                },
+            }
+        }
+        Err(e) => {
+            let previous = read_pr_entry(git_dir, branch);
+            let entry = PrCacheEntry::failed(
+                now,
+                branch,
+                Provider::Azure,
+                PrError::http(&e),
+                previous.as_ref(),
+            );
+            save_pr_entry(git_dir, branch, &entry);
+            return;
+        }
+    };
+
+    let entry = PrCacheEntry {
+        branch: branch.to_string(),
+        provider: Some(Provider::Azure),
+        fetched_at: now,
+        attempted_at: now,
+        etag: None,
+        state,
+    };
+    save_pr_entry(git_dir, branch, &entry);
+}
+
+/// Comments and PR build status for `pr`, mapped to gh's JSON shape
+fn pr_info(
+    base: &str,
+    target: &AzureTarget,
+    pr: &serde_json::Value,
+    headers: &[(&str, &str)],
+    tls: &TlsOptions,
+) -> Option<PrInfo> {
+    let id = pr["pullRequestId"].as_u64()?;
+    let get = |url: String| {
+        api_get(&url, headers, tls)
+            .ok()
+            .and_then(|resp| serde_json::from_str::<serde_json::Value>(&resp.body).ok())
+            .unwrap_or_default()
+    };
+
+    // Only people's comments; threads also hold system notes like pushes and votes
+    let threads = get(format!(
+        "{base}/git/repositories/{}/pullRequests/{id}/threads?api-version={API_VERSION}",
+        target.repo
+    ));
+    let comments = threads["value"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|t| t["isDeleted"].as_bool() != Some(true))
+        .flat_map(|t| t["comments"].as_array().into_iter().flatten())
+        .filter(|c| c["commentType"].as_str() == Some("text"))
+        .count();
+
+    // PR validation builds run against the merge ref
+    let builds = get(format!(
+        "{base}/build/builds?branchName={}&queryOrder=queueTimeDescending&$top=1\
+         &api-version={API_VERSION}",
+        percent_encode(&format!("refs/pull/{id}/merge"))
+    ));
+    let check_rollup: Vec<serde_json::Value> = builds["value"]
+        .get(0)
+        .map(|build| {
+            let conclusion = match (build["status"].as_str(), build["result"].as_str()) {
+                (Some("completed"), Some("succeeded")) => Some("SUCCESS"),
+                (Some("completed"), Some(_)) => Some("FAILURE"),
+                _ => None,
+            };
+            vec![serde_json::json!({ "conclusion": conclusion })]
+        })
+        .unwrap_or_default();
+
+    let state = match pr["status"].as_str()? {
+        "active" => "OPEN",
+        "completed" => "MERGED",
+        _ => "CLOSED",
+    };
+    let url = pr["repository"]["webUrl"].as_str().map_or_else(
+        || {
+            format!(
+                "https://dev.azure.com/{}/{}/_git/{}/pullrequest/{id}",
+                target.organization, target.project, target.repo
+            )
+        },
+        |web| format!("{web}/pullrequest/{id}"),
+    );
+
+    // Votes: 10 approved, 5 approved with suggestions, -5 waiting for author, -10 rejected
+    let votes: Vec<i64> = pr["reviewers"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|r| r["vote"].as_i64())
+        .collect();
+    let review_decision = if votes.iter().any(|v| *v < 0) {
+        "CHANGES_REQUESTED"
+    } else if votes.iter().any(|v| *v > 0) {
+        "APPROVED"
+    } else {
+        ""
+    };
+    let mergeable = match pr["mergeStatus"].as_str() {
+        Some("succeeded") => "MERGEABLE",
+        Some("conflicts") => "CONFLICTING",
+        _ => "UNKNOWN",
+    };
+    let labels: Vec<serde_json::Value> = pr["labels"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|l| l["active"].as_bool() != Some(false))
+        .map(|l| serde_json::json!({ "name": l["name"] }))
+        .collect();
+
+    let gh_json = serde_json::json!({
+        "number": id,
+        "state": state,
+        "url": url,
+        "commentsCount": comments,
+        "statusCheckRollup": check_rollup,
+        "createdAt": pr["creationDate"],
+        "autoMergeRequest": pr["autoCompleteSetBy"],
+        "reviewDecision": review_decision,
+        "labels": labels,
+        "mergeable": mergeable
+    });
+    parse_pr_json(&gh_json.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(organization: &str, project: &str, repo: &str) -> Option<AzureTarget> {
+        Some(AzureTarget {
+            organization: organization.to_string(),
+            project: project.to_string(),
+            repo: repo.to_string(),
+        })
+    }
+
+    #[test]
+    fn parse_azure_url_formats() {
+        let expected = target("acme", "Web", "app");
+        assert_eq!(
+            parse_azure_url("https://acme@dev.azure.com/acme/Web/_git/app"),
+            expected
+        );
+        assert_eq!(
+            parse_azure_url("git@ssh.dev.azure.com:v3/acme/Web/app"),
+            expected
+        );
+        assert_eq!(
+            parse_azure_url("https://acme.visualstudio.com/DefaultCollection/Web/_git/app"),
+            expected
+        );
+        assert_eq!(
+            parse_azure_url("acme@vs-ssh.visualstudio.com:v3/acme/Web/app"),
+            expected
+        );
+    }
+
+    #[test]
+    fn parse_azure_url_rejects_other_hosts() {
+        assert_eq!(parse_azure_url("git@github.com:acme/app.git"), None);
+        assert_eq!(parse_azure_url("https://github.com/acme/app"), None);
+        assert_eq!(parse_azure_url("git@evil.com:v3/acme/Web/app"), None);
+        assert_eq!(
+            parse_azure_url("https://dev.azure.com.evil.com/acme/Web/_git/app"),
+            None
+        );
+        assert_eq!(parse_azure_url("https://dev.azure.com/acme/_git/app"), None);
+    }
+}
@@ -0,0 +1,902 @@
+//! Per-branch PR cache files
+
+use super::{Deployment, PrInfo};
+use crate::cache::{atomic_rename, get_cache_dir, unique_hex};
+use crate::{hash_path, sys};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const PR_CACHE_TTL: u64 = 60; // seconds
+const PR_NEGATIVE_CACHE_TTL: u64 = 300; // 5 minutes for "no PR" cache
+const PR_ERROR_RETRY_AFTER: u64 = 60; // seconds before retrying a failed refresh
+/// First wait after a transient failure; doubles per consecutive one, up to `PR_ERROR_RETRY_AFTER`
+const PR_TRANSIENT_RETRY_AFTER: u64 = 2;
+/// Branches per repo whose entries are kept; less recently active ones are evicted
+const PR_CACHE_BRANCHES: usize = 8;
+
+/// Result of loading PR cache - handles all states in one read
+pub(crate) enum PrCacheResult {
+    Hit(PrInfo),             // Valid PR data
+    NoPr,                    // Negative cache: no PR exists for this branch
+    Stale(Option<PrInfo>),   // Needs refresh; holds expired PR data, if any
+    Backoff(Option<PrInfo>), // Last refresh failed and its retry time hasn't come; last good data, if any
+}
+
+const PR_CACHE_MAGIC: &[u8; 4] = b"CCPR";
+const PR_CACHE_VERSION: u32 = 6; // Version 1 was the line-based text format
+/// Longest error message kept in an entry
+const MAX_ERROR_LEN: usize = 200;
+
+/// Where an entry's data came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum Provider {
+    /// `gh pr view`, run in the background
+    Gh,
+    /// The REST API, called directly
+    Api,
+    /// The Azure DevOps REST API
+    Azure,
+    /// A Gitea or Forgejo instance's REST API
+    Gitea,
+}
+
+/// Why a refresh failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum PrError {
+    /// The API answered with a non-success status
+    Status(u16),
+    /// Connection, TLS, or read failure
+    Transport(String),
+    /// gh exited with an error other than "no PR"
+    Gh(String),
+    /// 403 or 404 with a classic token that lacks the `repo` scope
+    MissingScope(u16),
+}
+
+impl PrError {
+    /// Error from the native client
+    pub(crate) fn http(e: &sys::HttpError) -> Self {
+        match e {
+            sys::HttpError::Status(code) => Self::Status(*code),
+            sys::HttpError::Transport(message) => Self::Transport(bounded(message)),
+        }
+    }
+
+    /// Connection failure, rate limit, or server error: worth retrying soon
+    pub(crate) fn is_transient(&self) -> bool {
+        matches!(self, Self::Transport(_) | Self::Status(429 | 500..=599))
+    }
+
+    /// The token can't see the repo or its PRs, as opposed to a network or server failure
+    pub(crate) fn is_access_denied(&self) -> bool {
+        match self {
+            Self::Status(code) => matches!(code, 401 | 403 | 404),
+            Self::MissingScope(_) => true,
+            Self::Transport(_) => false,
+            Self::Gh(message) => [
+                "Could not resolve to a Repository",
+                "required scopes",
+                "HTTP 401",
+                "HTTP 403",
+                "HTTP 404",
+            ]
+            .iter()
+            .any(|m| message.contains(m)),
+        }
+    }
+}
+
+impl fmt::Display for PrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingScope(code) => write!(
+                f,
+                "HTTP {code}: the token lacks the `repo` scope needed for private repos"
+            ),
+            Self::Status(401) => {
+                f.write_str("HTTP 401: the token was rejected (expired or revoked?)")
+            }
+            Self::Status(code @ (403 | 404)) => write!(
+                f,
+                "HTTP {code}: the token can't see this repo (private, or SSO not authorized?)"
+            ),
+            Self::Status(code) => write!(f, "HTTP {code}"),
+            Self::Transport(message) | Self::Gh(message) => f.write_str(message),
+        }
+    }
+}
+
+/// Outcome of the last refresh
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum PrState {
+    Found(PrInfo),
+    NoPr,
+    /// Retried after `retry_at`; keeps the last good data so a network blip doesn't hide the PR
+    Failed {
+        retry_at: u64,
+        error: PrError,
+        last: Option<PrInfo>,
+    },
+    /// No refresh has finished yet; the entry only records the attempt
+    Pending,
+}
+
+/// One branch's PR cache file: a magic, a version, then this struct in bincode
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct PrCacheEntry {
+    pub(crate) branch: String,
+    /// None for entries migrated from the text format
+    pub(crate) provider: Option<Provider>,
+    /// UNIX time of the refresh
+    pub(crate) fetched_at: u64,
+    /// UNIX time of the last refresh attempt, which throttles the next one
+    pub(crate) attempted_at: u64,
+    /// `ETag` of the PR list response, for a conditional request next time
+    pub(crate) etag: Option<String>,
+    pub(crate) state: PrState,
+}
+
+/// Version 5 entry, from before attempts were recorded in the entry
+#[derive(Deserialize)]
+struct PrCacheEntryV5 {
+    branch: String,
+    provider: Option<Provider>,
+    fetched_at: u64,
+    etag: Option<String>,
+    state: PrState,
+}
+
+impl From<PrCacheEntryV5> for PrCacheEntry {
+    fn from(v5: PrCacheEntryV5) -> Self {
+        Self {
+            branch: v5.branch,
+            provider: v5.provider,
+            fetched_at: v5.fetched_at,
+            attempted_at: v5.fetched_at,
+            etag: v5.etag,
+            state: v5.state,
+        }
+    }
+}
+
+impl PrCacheEntry {
+    /// What the entry answers at `now`
+    fn lookup(self, now: u64) -> PrCacheResult {
+        let age = now.saturating_sub(self.fetched_at);
+        match self.state {
+            // Negative cache with longer TTL
+            PrState::NoPr if age < PR_NEGATIVE_CACHE_TTL => PrCacheResult::NoPr,
+            PrState::NoPr => PrCacheResult::Stale(None),
+            // Failed refresh: wait until its retry time, showing the last good data
+            PrState::Failed { retry_at, last, .. } if now < retry_at => {
+                PrCacheResult::Backoff(last)
+            }
+            PrState::Failed { last, .. } => PrCacheResult::Stale(last),
+            // Expired data is kept for offline rendering
+            PrState::Found(pr) if age <= PR_CACHE_TTL => PrCacheResult::Hit(pr),
+            PrState::Found(pr) => PrCacheResult::Stale(Some(pr)),
+            PrState::Pending => PrCacheResult::Stale(None),
+        }
+    }
+
+    /// Entry for a failed refresh at `now`, keeping `previous`'s last good data
+    pub(crate) fn failed(
+        now: u64,
+        branch: &str,
+        provider: Provider,
+        error: PrError,
+        previous: Option<&Self>,
+    ) -> Self {
+        // Transient failures back off from a short wait, doubling while they repeat
+        let retry_after = match previous {
+            _ if !error.is_transient() => PR_ERROR_RETRY_AFTER,
+            Some(Self {
+                fetched_at,
+                state:
+                    PrState::Failed {
+                        retry_at,
+                        error: last,
+                        ..
+                    },
+                ..
+            }) if last.is_transient() => (retry_at.saturating_sub(*fetched_at) * 2)
+                .clamp(PR_TRANSIENT_RETRY_AFTER, PR_ERROR_RETRY_AFTER),
+            _ => PR_TRANSIENT_RETRY_AFTER,
+        };
+        Self {
+            branch: branch.to_string(),
+            provider: Some(provider),
+            fetched_at: now,
+            attempted_at: now,
+            etag: None,
+            state: PrState::Failed {
+                retry_at: now + retry_after,
+                error,
+                last: previous.and_then(Self::last_good).cloned(),
+            },
+        }
+    }
+
+    /// Placeholder recording a refresh attempt at `now` for a branch with no entry yet
+    pub(crate) fn pending(now: u64, branch: &str) -> Self {
+        Self {
+            branch: branch.to_string(),
+            provider: None,
+            fetched_at: 0,
+            attempted_at: now,
+            etag: None,
+            state: PrState::Pending,
+        }
+    }
+
+    /// Last PR data seen, from a hit or a failed refresh
+    pub(crate) fn last_good(&self) -> Option<&PrInfo> {
+        match &self.state {
+            PrState::Found(pr) => Some(pr),
+            PrState::NoPr | PrState::Pending => None,
+            PrState::Failed { last, .. } => last.as_ref(),
+        }
+    }
+
+    pub(crate) fn to_bytes(&self) -> Option<Vec<u8>> {
+        let mut buf = PR_CACHE_MAGIC.to_vec();
+        buf.extend_from_slice(&PR_CACHE_VERSION.to_le_bytes());
+        bincode_options().serialize_into(&mut buf, self).ok()?;
+        Some(buf)
+    }
+
+    /// Decode a cache file, upgrading version 5 entries
+    pub(crate) fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.get(0..4)? != PR_CACHE_MAGIC {
+            return None;
+        }
+        match u32::from_le_bytes(data.get(4..8)?.try_into().ok()?) {
+            PR_CACHE_VERSION => bincode_options().deserialize(&data[8..]).ok(),
+            5 => bincode_options()
+                .deserialize::<PrCacheEntryV5>(&data[8..])
+                .ok()
+                .map(Into::into),
+            _ => None,
+        }
+    }
+
+    /// Entry in the text format: older cache files and gh refresh output
+    ///   1st line: UNIX timestamp (seconds since epoch)
+    ///   2nd line: branch name
+    ///   remaining lines: JSON payload, "NO_PR" marker, or an error entry:
+    ///   "ERROR:<retry_at>:<message>" followed by the last good payload, if any
+    fn from_text(content: &str, provider: Option<Provider>) -> Option<Self> {
+        let mut lines = content.splitn(3, '\n');
+        let fetched_at = lines.next()?.parse().ok()?;
+        let branch = lines.next()?.to_string();
+        let body = lines.next()?;
+        let state = if body == "NO_PR" {
+            PrState::NoPr
+        } else if let Some(error) = body.strip_prefix("ERROR:") {
+            let (line, previous) = error.split_once('\n').unwrap_or((error, ""));
+            // Entries without a retry time (older versions) are retried now
+            let (retry_at, message) = line
+                .split_once(':')
+                .and_then(|(t, message)| Some((t.parse().ok()?, message)))
+                .unwrap_or((0, line));
+            PrState::Failed {
+                retry_at,
+                error: PrError::Gh(bounded(message)),
+                last: parse_pr_json(previous),
+            }
+        } else {
+            PrState::Found(parse_pr_json(body)?)
+        };
+        Some(Self {
+            branch,
+            provider,
+            fetched_at,
+            attempted_at: fetched_at,
+            etag: None,
+            state,
+        })
+    }
+}
+
+/// Fixed-width integers; the size limit guards against corrupt length prefixes
+fn bincode_options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_limit(64 * 1024)
+}
+
+/// `message` on one line, cut to `MAX_ERROR_LEN` bytes
+fn bounded(message: &str) -> String {
+    let mut message = message.replace(['\n', '\r'], " ");
+    if message.len() > MAX_ERROR_LEN {
+        let mut end = MAX_ERROR_LEN;
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        message.truncate(end);
+    }
+    message
+}
+
+/// JSON structure from gh pr view (or native API cache)
+/// Supports both gh CLI format (comments as array) and native format (commentsCount as number)
+#[derive(Deserialize, Default)]
+struct GhPrJson {
+    number: Option<u64>,
+    state: Option<String>,
+    url: Option<String>,
+    /// gh CLI returns array, native API stores count directly
+    comments: Option<Vec<serde_json::Value>>,
+    /// Native API stores count directly (preferred, avoids large array allocation)
+    #[serde(rename = "commentsCount")]
+    comments_count: Option<u64>,
+    #[serde(rename = "changedFiles")]
+    changed_files: Option<u64>,
+    #[serde(rename = "statusCheckRollup")]
+    status_check_rollup: Option<Vec<GhCheckRun>>,
+    /// ISO 8601, e.g. "2024-05-01T12:00:00Z"
+    #[serde(rename = "createdAt")]
+    created_at: Option<String>,
+    /// Null unless auto-merge is enabled
+    #[serde(rename = "autoMergeRequest")]
+    auto_merge_request: Option<serde_json::Value>,
+    /// APPROVED, CHANGES_REQUESTED, REVIEW_REQUIRED, or empty
+    #[serde(rename = "reviewDecision")]
+    review_decision: Option<String>,
+    labels: Option<Vec<GhLabel>>,
+    /// MERGEABLE, CONFLICTING, or UNKNOWN
+    mergeable: Option<String>,
+    /// Added by the refresh when the deployment component is used
+    deployment: Option<Deployment>,
+}
+
+#[derive(Deserialize)]
+struct GhLabel {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct GhCheckRun {
+    conclusion: Option<String>,
+}
+
+pub(crate) fn get_pr_cache_path(repo_path: &str, branch: &str) -> PathBuf {
+    let key = format!("{repo_path}:{branch}");
+    get_cache_dir().join(format!("pr-{:016x}.cache", hash_path(&key)))
+}
+
+/// Marker file that throttled refreshes before `attempted_at`; only removed now
+fn get_pr_attempt_path(repo_path: &str, branch: &str) -> PathBuf {
+    let key = format!("{repo_path}:{branch}");
+    get_cache_dir().join(format!("pr-attempt-{:016x}", hash_path(&key)))
+}
+
+/// Text output of a background gh refresh, folded into the cache on the next read
+pub(crate) fn get_gh_output_path(repo_path: &str, branch: &str) -> PathBuf {
+    let key = format!("{repo_path}:{branch}");
+    get_cache_dir().join(format!("pr-gh-{:016x}.out", hash_path(&key)))
+}
+
+/// Recently active branches of a repo, most recent first, one per line
+fn get_pr_branches_path(repo_path: &str) -> PathBuf {
+    get_cache_dir().join(format!("pr-branches-{:016x}", hash_path(repo_path)))
+}
+
+/// Move `branch` to the front of the repo's recently active branches, removing the
+/// cache files of branches past `PR_CACHE_BRANCHES` and legacy attempt markers
+/// Returns the branch that was active before `branch`, if any
+pub(crate) fn record_active_branch(repo_path: &str, branch: &str) -> Option<String> {
+    let path = get_pr_branches_path(repo_path);
+    let content = fs::read_to_string(&path).unwrap_or_default();
+    let mut branches: Vec<&str> = content.lines().filter(|b| !b.is_empty()).collect();
+    if branches.first() != Some(&branch) {
+        branches.retain(|b| *b != branch);
+        branches.insert(0, branch);
+        for evicted in branches.drain(PR_CACHE_BRANCHES.min(branches.len())..) {
+            let _ = fs::remove_file(get_pr_cache_path(repo_path, evicted));
+            let _ = fs::remove_file(get_gh_output_path(repo_path, evicted));
+            let _ = fs::remove_file(get_pr_attempt_path(repo_path, evicted));
+        }
+        for kept in &branches {
+            let _ = fs::remove_file(get_pr_attempt_path(repo_path, kept));
+        }
+        let temp_path = get_cache_dir().join(format!("pr-branches-tmp-{}", unique_hex()));
+        if fs::write(&temp_path, branches.join("\n")).is_ok() {
+            let _ = atomic_rename(&temp_path, &path);
+        }
+    }
+    branches.get(1).map(ToString::to_string)
+}
+
+/// Read a cache file, migrating older formats; the flag is set when it was one
+fn read_entry(path: &Path) -> Option<(PrCacheEntry, bool)> {
+    let data = fs::read(path).ok()?;
+    if let Some(entry) = PrCacheEntry::from_bytes(&data) {
+        let current = data.get(4..8) == Some(PR_CACHE_VERSION.to_le_bytes().as_slice());
+        return Some((entry, !current));
+    }
+    let entry = PrCacheEntry::from_text(std::str::from_utf8(&data).ok()?, None)?;
+    Some((entry, true))
+}
+
+/// Cached entry for `branch`, if any
+pub(crate) fn read_pr_entry(repo_path: &str, branch: &str) -> Option<PrCacheEntry> {
+    read_entry(&get_pr_cache_path(repo_path, branch))
+        .map(|(entry, _)| entry)
+        .filter(|entry| entry.branch == branch)
+}
+
+/// Atomically replace the cache entry for `branch`
+pub(crate) fn save_pr_entry(repo_path: &str, branch: &str, entry: &PrCacheEntry) {
+    let Some(bytes) = entry.to_bytes() else {
+        return;
+    };
+    let temp_path = get_cache_dir().join(format!("pr-tmp-{}.cache", unique_hex()));
+    if fs::write(&temp_path, bytes).is_ok() {
+        let _ = atomic_rename(&temp_path, &get_pr_cache_path(repo_path, branch));
+    }
+}
+
+/// Load PR cache - reads file once and handles all states
+pub(crate) fn load_pr_cache(repo_path: &str, branch: &str) -> PrCacheResult {
+    let cache_path = get_pr_cache_path(repo_path, branch);
+    let mut entry = read_entry(&cache_path);
+
+    // A finished gh refresh replaces the entry; failures keep its last good data
+    let gh_path = get_gh_output_path(repo_path, branch);
+    if let Ok(output) = fs::read_to_string(&gh_path) {
+        let _ = fs::remove_file(&gh_path);
+        if let Some(mut fresh) = PrCacheEntry::from_text(&output, Some(Provider::Gh)) {
+            if let PrState::Failed { last, .. } = &mut fresh.state
+                && last.is_none()
+            {
+                *last = entry
+                    .as_ref()
+                    .and_then(|(previous, _)| previous.last_good())
+                    .cloned();
+            }
+            entry = Some((fresh, true));
+        }
+    }
+
+    let Some((entry, migrate)) = entry else {
+        return PrCacheResult::Stale(None);
+    };
+
+    // Validate branch matches
+    if entry.branch != branch {
+        let _ = fs::remove_file(&cache_path);
+        return PrCacheResult::Stale(None);
+    }
+    if migrate {
+        save_pr_entry(repo_path, branch, &entry);
+    }
+    entry.lookup(sys::now_secs())
+}
+
+/// Whether `load_pr_cache` would answer without a refresh: a finished gh refresh,
+/// or an entry that hasn't expired. Reads only, so it can run beside a lookup
+pub(crate) fn pr_cache_is_warm(repo_path: &str, branch: &str) -> bool {
+    get_gh_output_path(repo_path, branch).exists()
+        || read_pr_entry(repo_path, branch)
+            .is_some_and(|entry| !matches!(entry.lookup(sys::now_secs()), PrCacheResult::Stale(_)))
+}
+
+/// `ERROR:<retry_at>:` marker for a gh failure at `now`
+pub(crate) fn error_prefix(now: u64) -> String {
+    format!("ERROR:{}:", now + PR_ERROR_RETRY_AFTER)
+}
+
+/// Parse a gh or native API JSON payload, returning None if required fields are missing
+pub(crate) fn parse_pr_json(json_str: &str) -> Option<PrInfo> {
+    let pr: GhPrJson = serde_json::from_str(json_str).ok()?;
+
+    // Compute check status from rollup
+    // Note: gh CLI returns uppercase (SUCCESS), REST API returns lowercase (success)
+    let check_status = match &pr.status_check_rollup {
+        None => String::new(),
+        Some(checks) if checks.is_empty() => String::new(),
+        Some(checks) => {
+            // Case-insensitive check for passing conclusions
+            let is_passing = |s: &str| {
+                matches!(
+                    s.to_ascii_uppercase().as_str(),
+                    "SUCCESS" | "SKIPPED" | "NEUTRAL"
+                )
+            };
+
+            // Treat any non-success conclusion as a failure
+            let has_failure = checks.iter().any(|c| {
+                match c.conclusion.as_deref() {
+                    Some(conc) if is_passing(conc) => false,
+                    Some(_) => true, // FAILURE, CANCELLED, TIMED_OUT, ACTION_REQUIRED, etc.
+                    None => false,
+                }
+            });
+            let has_pending = checks.iter().any(|c| c.conclusion.is_none());
+            let all_passed = checks.iter().all(|c| match c.conclusion.as_deref() {
+                Some(conc) => is_passing(conc),
+                None => false,
+            });
+
+            if has_failure {
+                "failed".to_string()
+            } else if all_passed {
+                "passed".to_string()
+            } else if has_pending {
+                "pending".to_string()
+            } else {
+                String::new()
+            }
+        }
+    };
+
+    // Validate required fields - treat missing/invalid data as stale
+    #[allow(clippy::cast_possible_truncation)] // PR numbers/counts won't exceed u32::MAX
+    let number = match pr.number {
+        Some(n) if n > 0 => n as u32,
+        _ => return None,
+    };
+
+    let state = match pr.state {
+        Some(s) if !s.is_empty() => s,
+        _ => return None,
+    };
+
+    let url = match pr.url {
+        Some(u) if !u.is_empty() => u,
+        _ => return None,
+    };
+
+    // Prefer commentsCount (numeric) over comments array to avoid large allocations
+    #[allow(clippy::cast_possible_truncation)] // PR numbers/counts won't exceed u32::MAX
+    let comments = pr
+        .comments_count
+        .map(|c| c as u32)
+        .or_else(|| pr.comments.map(|c| c.len() as u32))
+        .unwrap_or(0);
+
+    #[allow(clippy::cast_possible_truncation)] // PR numbers/counts won't exceed u32::MAX
+    Some(PrInfo {
+        number,
+        state,
+        url,
+        comments,
+        changed_files: pr.changed_files.unwrap_or(0) as u32,
+        check_status,
+        created_at: pr
+            .created_at
+            .as_deref()
+            .and_then(parse_timestamp)
+            .unwrap_or(0),
+        auto_merge: pr.auto_merge_request.is_some_and(|r| !r.is_null()),
+        review_decision: pr.review_decision.unwrap_or_default().to_ascii_lowercase(),
+        labels: pr
+            .labels
+            .unwrap_or_default()
+            .into_iter()
+            .map(|l| l.name)
+            .collect(),
+        mergeable: match pr.mergeable.as_deref() {
+            Some("MERGEABLE") => "mergeable".to_string(),
+            Some("CONFLICTING") => "conflicting".to_string(),
+            _ => String::new(),
+        },
+        deployment: pr.deployment,
+        stale: false,
+    })
+}
+
+/// UNIX time of a UTC ISO 8601 timestamp ("2024-05-01T12:00:00Z"); fractions are dropped
+fn parse_timestamp(s: &str) -> Option<u64> {
+    let (date, time) = s.strip_suffix('Z')?.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let mut time = time
+        .split('.')
+        .next()?
+        .splitn(3, ':')
+        .map(str::parse::<i64>);
+    let (hour, min, sec) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Days since 1970-01-01 in the proleptic Gregorian calendar
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    u64::try_from(days * 86_400 + hour * 3600 + min * 60 + sec).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_pr_json_gh_format() {
+        let json = r#"{"number": 42, "state": "OPEN", "url": "https://github.com/o/r/pull/42",
+            "comments": [{}, {}], "changedFiles": 3,
+            "statusCheckRollup": [{"conclusion": "SUCCESS"}, {"conclusion": "SKIPPED"}]}"#;
+        let pr = parse_pr_json(json).unwrap();
+        assert_eq!(pr.number, 42);
+        assert_eq!(pr.comments, 2);
+        assert_eq!(pr.changed_files, 3);
+        assert_eq!(pr.check_status, "passed");
+    }
+
+    #[test]
+    fn parse_pr_json_native_format() {
+        let json = r#"{"number": 7, "state": "closed", "url": "https://github.com/o/r/pull/7",
+            "commentsCount": 5, "statusCheckRollup": [{"conclusion": "success"}, {"conclusion": null}]}"#;
+        let pr = parse_pr_json(json).unwrap();
+        assert_eq!(pr.comments, 5);
+        assert_eq!(pr.check_status, "pending");
+    }
+
+    #[test]
+    fn parse_pr_json_failure_wins() {
+        let json = r#"{"number": 1, "state": "OPEN", "url": "u",
+            "statusCheckRollup": [{"conclusion": null}, {"conclusion": "CANCELLED"}]}"#;
+        assert_eq!(parse_pr_json(json).unwrap().check_status, "failed");
+    }
+
+    fn pr(number: u32) -> PrInfo {
+        PrInfo {
+            number,
+            state: "OPEN".to_string(),
+            url: "u".to_string(),
+            ..PrInfo::default()
+        }
+    }
+
+    #[test]
+    fn entry_round_trip() {
+        let entry = PrCacheEntry {
+            branch: "feat/new\nline".to_string(),
+            provider: Some(Provider::Api),
+            fetched_at: 1_700_000_000,
+            attempted_at: 1_700_000_000,
+            etag: Some("W/\"abc\"".to_string()),
+            state: PrState::Found(pr(12)),
+        };
+        let bytes = entry.to_bytes().unwrap();
+        assert_eq!(PrCacheEntry::from_bytes(&bytes), Some(entry));
+        assert_eq!(PrCacheEntry::from_bytes(b"CCPR\x01\0\0\0"), None);
+        assert_eq!(PrCacheEntry::from_bytes(&bytes[..bytes.len() - 1]), None);
+    }
+
+    #[test]
+    fn version_5_entry_is_upgraded() {
+        let entry = PrCacheEntry {
+            branch: "main".to_string(),
+            provider: Some(Provider::Gitea),
+            fetched_at: 1_700_000_000,
+            attempted_at: 1_700_000_000,
+            etag: None,
+            state: PrState::NoPr,
+        };
+        let mut bytes = PR_CACHE_MAGIC.to_vec();
+        bytes.extend_from_slice(&5u32.to_le_bytes());
+        let v5 = (
+            &entry.branch,
+            entry.provider,
+            entry.fetched_at,
+            &entry.etag,
+            &entry.state,
+        );
+        bincode_options().serialize_into(&mut bytes, &v5).unwrap();
+        assert_eq!(PrCacheEntry::from_bytes(&bytes), Some(entry));
+    }
+
+    #[test]
+    fn failed_entry_keeps_last_good_until_retry() {
+        let repo = format!("/nonexistent/error-entry-{}", crate::cache::unique_hex());
+        let now = sys::now_secs();
+        let found = PrCacheEntry {
+            branch: "main".to_string(),
+            provider: Some(Provider::Api),
+            fetched_at: now,
+            attempted_at: now,
+            etag: None,
+            state: PrState::Found(pr(3)),
+        };
+        let error = PrError::http(&sys::HttpError::Transport("x".repeat(1000)));
+        assert!(matches!(&error, PrError::Transport(m) if m.len() == MAX_ERROR_LEN));
+
+        let failed = PrCacheEntry::failed(now, "main", Provider::Api, error, Some(&found));
+        save_pr_entry(&repo, "main", &failed);
+        assert!(matches!(
+            load_pr_cache(&repo, "main"),
+            PrCacheResult::Backoff(Some(pr)) if pr.number == 3
+        ));
+
+        // Past the retry time, the refresh runs again
+        let failed =
+            PrCacheEntry::failed(now - 120, "main", Provider::Api, PrError::Status(503), None);
+        save_pr_entry(&repo, "main", &failed);
+        assert!(matches!(
+            load_pr_cache(&repo, "main"),
+            PrCacheResult::Stale(None)
+        ));
+        let _ = fs::remove_file(get_pr_cache_path(&repo, "main"));
+    }
+
+    #[test]
+    fn transient_failures_back_off_exponentially() {
+        let retry_after = |entry: &PrCacheEntry| match entry.state {
+            PrState::Failed { retry_at, .. } => retry_at - entry.fetched_at,
+            _ => unreachable!(),
+        };
+        let fail = |now, error, previous: Option<&PrCacheEntry>| {
+            PrCacheEntry::failed(now, "main", Provider::Api, error, previous)
+        };
+
+        let first = fail(100, PrError::Status(503), None);
+        assert_eq!(retry_after(&first), PR_TRANSIENT_RETRY_AFTER);
+        let second = fail(102, PrError::Transport("reset".into()), Some(&first));
+        assert_eq!(retry_after(&second), PR_TRANSIENT_RETRY_AFTER * 2);
+        let mut last = second;
+        for now in 0..10 {
+            last = fail(200 + now, PrError::Status(429), Some(&last));
+        }
+        assert_eq!(retry_after(&last), PR_ERROR_RETRY_AFTER);
+
+        // Errors a retry won't fix wait the full interval
+        assert_eq!(
+            retry_after(&fail(300, PrError::Status(401), Some(&first))),
+            PR_ERROR_RETRY_AFTER
+        );
+    }
+
+    #[test]
+    fn text_entries_are_migrated() {
+        let repo = format!("/nonexistent/migrate-{}", crate::cache::unique_hex());
+        let path = get_pr_cache_path(&repo, "main");
+        let now = sys::now_secs();
+
+        fs::write(
+            &path,
+            format!("{now}\nmain\n{{\"number\": 5, \"state\": \"OPEN\", \"url\": \"u\"}}"),
+        )
+        .unwrap();
+        assert!(matches!(load_pr_cache(&repo, "main"), PrCacheResult::Hit(pr) if pr.number == 5));
+        assert!(fs::read(&path).unwrap().starts_with(PR_CACHE_MAGIC));
+        assert_eq!(read_pr_entry(&repo, "main").unwrap().provider, None);
+
+        // Entries from older versions have no retry time
+        fs::write(&path, format!("{now}\nmain\nERROR:HTTP 500")).unwrap();
+        assert!(matches!(
+            load_pr_cache(&repo, "main"),
+            PrCacheResult::Stale(None)
+        ));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn active_branches_keep_entries_and_evict_oldest() {
+        let repo = format!("/nonexistent/branches-{}", crate::cache::unique_hex());
+        let entry = |branch: &str| PrCacheEntry {
+            branch: branch.to_string(),
+            provider: Some(Provider::Api),
+            fetched_at: 0,
+            attempted_at: 0,
+            etag: None,
+            state: PrState::NoPr,
+        };
+
+        assert_eq!(record_active_branch(&repo, "main"), None);
+        save_pr_entry(&repo, "main", &entry("main"));
+        assert_eq!(
+            record_active_branch(&repo, "feature").as_deref(),
+            Some("main")
+        );
+        assert_eq!(
+            record_active_branch(&repo, "feature").as_deref(),
+            Some("main")
+        );
+        assert_eq!(
+            record_active_branch(&repo, "main").as_deref(),
+            Some("feature")
+        );
+        assert!(read_pr_entry(&repo, "main").is_some());
+
+        for i in 0..PR_CACHE_BRANCHES - 1 {
+            record_active_branch(&repo, &format!("b{i}"));
+        }
+        assert!(read_pr_entry(&repo, "main").is_some());
+        record_active_branch(&repo, "last");
+        assert!(read_pr_entry(&repo, "main").is_none());
+        let _ = fs::remove_file(get_pr_branches_path(&repo));
+    }
+
+    #[test]
+    fn gh_output_is_folded_into_cache() {
+        let repo = format!("/nonexistent/gh-output-{}", crate::cache::unique_hex());
+        let gh_path = get_gh_output_path(&repo, "main");
+        let now = sys::now_secs();
+
+        fs::write(
+            &gh_path,
+            format!("{now}\nmain\n{{\"number\": 8, \"state\": \"OPEN\", \"url\": \"u\"}}"),
+        )
+        .unwrap();
+        assert!(matches!(load_pr_cache(&repo, "main"), PrCacheResult::Hit(pr) if pr.number == 8));
+        assert!(!gh_path.exists());
+        assert_eq!(
+            read_pr_entry(&repo, "main").unwrap().provider,
+            Some(Provider::Gh)
+        );
+
+        // A gh failure keeps the PR already in the cache
+        fs::write(
+            &gh_path,
+            format!("{now}\nmain\n{}auth required", error_prefix(now)),
+        )
+        .unwrap();
+        assert!(matches!(
+            load_pr_cache(&repo, "main"),
+            PrCacheResult::Backoff(Some(pr)) if pr.number == 8
+        ));
+        let entry = read_pr_entry(&repo, "main").unwrap();
+        assert!(
+            matches!(entry.state, PrState::Failed { error: PrError::Gh(m), .. } if m == "auth required")
+        );
+        let _ = fs::remove_file(get_pr_cache_path(&repo, "main"));
+    }
+
+    #[test]
+    fn parse_pr_json_age_and_auto_merge() {
+        let json = r#"{"number": 4, "state": "OPEN", "url": "u",
+            "createdAt": "2024-05-01T12:00:00Z", "autoMergeRequest": {"mergeMethod": "SQUASH"}}"#;
+        let pr = parse_pr_json(json).unwrap();
+        assert_eq!(pr.created_at, 1_714_564_800);
+        assert!(pr.auto_merge);
+
+        let json = r#"{"number": 4, "state": "OPEN", "url": "u", "autoMergeRequest": null}"#;
+        let pr = parse_pr_json(json).unwrap();
+        assert_eq!(pr.created_at, 0);
+        assert!(!pr.auto_merge);
+    }
+
+    #[test]
+    fn parse_pr_json_reviews_labels_mergeable() {
+        let json = r#"{"number": 4, "state": "OPEN", "url": "u", "reviewDecision": "CHANGES_REQUESTED",
+            "labels": [{"name": "bug"}, {"name": "p1"}], "mergeable": "CONFLICTING"}"#;
+        let pr = parse_pr_json(json).unwrap();
+        assert_eq!(pr.review_decision, "changes_requested");
+        assert_eq!(pr.labels, ["bug", "p1"]);
+        assert_eq!(pr.mergeable, "conflicting");
+
+        let json = r#"{"number": 4, "state": "OPEN", "url": "u", "reviewDecision": "", "mergeable": "UNKNOWN"}"#;
+        let pr = parse_pr_json(json).unwrap();
+        assert_eq!(pr.review_decision, "");
+        assert!(pr.labels.is_empty());
+        assert_eq!(pr.mergeable, "");
+    }
+
+    #[test]
+    fn parse_timestamp_formats() {
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(
+            parse_timestamp("2000-02-29T23:59:59.123Z"),
+            Some(951_868_799)
+        );
+        assert_eq!(parse_timestamp("2024-05-01T12:00:00+02:00"), None);
+        assert_eq!(parse_timestamp("2024-13-01T12:00:00Z"), None);
+    }
+
+    #[test]
+    fn parse_pr_json_missing_fields() {
+        assert!(parse_pr_json(r#"{"number": 0, "state": "OPEN", "url": "u"}"#).is_none());
+        assert!(parse_pr_json(r#"{"number": 1, "url": "u"}"#).is_none());
+        assert!(parse_pr_json(r#"{"number": 1, "state": "OPEN"}"#).is_none());
+        assert!(parse_pr_json("not json").is_none());
+    }
+}
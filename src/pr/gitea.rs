@@ -0,0 +1,272 @@
+//! Gitea and Forgejo remote detection and REST API fetch
+//!
+//! Instances are only queried when listed in the `gitea` config, since any
+//! self-hosted remote could be one.
+
+use super::cache::{
+    PrCacheEntry, PrError, PrState, Provider, parse_pr_json, read_pr_entry, save_pr_entry,
+};
+use super::github::{RemoteConfig, api_get};
+use super::{PrInfo, PrSource};
+use crate::config::GiteaConfig;
+use crate::percent_encode;
+use crate::sys::{self, TlsOptions};
+
+/// PRs scanned for the branch; Gitea can't filter the list by head branch
+const PR_SCAN_LIMIT: u32 = 50;
+
+/// Repo on a configured Gitea or Forgejo instance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GiteaTarget {
+    /// API base URL, like `https://git.example.com/api/v1`
+    pub(crate) api: String,
+    pub(crate) owner: String,
+    pub(crate) repo: String,
+}
+
+/// Host, owner, and repo of an SSH or HTTP(S) remote URL
+/// Handles: `git@host:owner/repo.git` and `scheme://[user@]host[:port]/owner/repo.git`
+fn split_remote(url: &str) -> Option<(String, String, String)> {
+    let (host, path) = match url.split_once("://") {
+        Some((_, rest)) => {
+            let (authority, path) = rest.split_once('/')?;
+            let host = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+            (host.split(':').next()?, path)
+        }
+        None => {
+            let (user_host, path) = url.split_once(':')?;
+            (
+                user_host.rsplit_once('@').map_or(user_host, |(_, h)| h),
+                path,
+            )
+        }
+    };
+    let mut parts = path.trim_start_matches('/').split('/');
+    let owner = parts.next()?;
+    let repo = parts.next()?;
+    let repo = repo.strip_suffix(".git").unwrap_or(repo);
+    if host.is_empty() || owner.is_empty() || repo.is_empty() {
+        return None;
+    }
+    Some((
+        host.to_ascii_lowercase(),
+        owner.to_string(),
+        repo.to_string(),
+    ))
+}
+
+/// Base URL of a configured instance: `git.example.com` means https
+fn instance_url(host: &str) -> String {
+    let host = host.trim_end_matches('/');
+    if host.contains("://") {
+        host.to_string()
+    } else {
+        format!("https://{host}")
+    }
+}
+
+/// Host name of a configured instance, without scheme, port, or path
+fn instance_host(host: &str) -> String {
+    let rest = host.split_once("://").map_or(host, |(_, rest)| rest);
+    let authority = rest.split('/').next().unwrap_or(rest);
+    authority
+        .split(':')
+        .next()
+        .unwrap_or(authority)
+        .to_ascii_lowercase()
+}
+
+/// The repo of a remote URL, if its host is a configured instance
+pub(crate) fn parse_gitea_url(url: &str, config: &GiteaConfig) -> Option<GiteaTarget> {
+    let (host, owner, repo) = split_remote(url)?;
+    let instance = config.hosts.iter().find(|h| instance_host(h) == host)?;
+    Some(GiteaTarget {
+        api: format!("{}/api/v1", instance_url(instance)),
+        owner,
+        repo,
+    })
+}
+
+/// Find the Gitea or Forgejo repo to query for `branch`'s PR
+pub(crate) fn resolve_target(
+    repo: &gix::Repository,
+    branch: &str,
+    preferred: Option<&str>,
+    config: &GiteaConfig,
+) -> Option<GiteaTarget> {
+    RemoteConfig::load(repo, branch).find(preferred, |url| parse_gitea_url(url, config))
+}
+
+/// Token from the configured variable, else `GITEA_TOKEN`
+fn get_gitea_token(config: &GiteaConfig) -> Option<String> {
+    sys::var(config.token_env.as_deref().unwrap_or("GITEA_TOKEN")).filter(|t| !t.is_empty())
+}
+
+/// Refresh the PR cache from the Gitea API (synchronous)
+pub(crate) fn refresh_pr_gitea(
+    git_dir: &str,
+    branch: &str,
+    source: &PrSource,
+    config: &GiteaConfig,
+    target: &GiteaTarget,
+) {
+    let Some(token) = get_gitea_token(config) else {
+        return; // No token, skip PR feature
+    };
+    let auth = format!("token {token}");
+    let headers = [
+        ("Authorization", auth.as_str()),
+        ("Accept", "application/json"),
+        ("User-Agent", "cc-statusline"),
+    ];
+    let now = sys::now_secs();
+    let repo_api = format!("{}/repos/{}/{}", target.api, target.owner, target.repo);
+
+    // Newest PR whose head is this branch, in any state
+    let url = format!("{repo_api}/pulls?state=all&sort=recentupdate&limit={PR_SCAN_LIMIT}");
+    let state = match api_get(&url, &headers, &source.tls) {
+        Ok(resp) => {
+            let Ok(prs) = serde_json::from_str::<Vec<serde_json::Value>>(&resp.body) else {
+                return;
+            };
+            let pr = prs
+                .iter()
+                .filter(|pr| pr["head"]["ref"].as_str() == Some(branch))
+                .max_by_key(|pr| pr["number"].as_u64());
+            match pr {
+                None => PrState::NoPr,
+                Some(pr) => match pr_info(&repo_api, pr, &headers, &source.tls) {
+                    Some(pr) => PrState::Found(pr),
+                    None => return,
+                },
+            }
+        }
+        Err(e) => {
+            let previous = read_pr_entry(git_dir, branch);
+            let entry = PrCacheEntry::failed(
+                now,
+                branch,
+                Provider::Gitea,
+                PrError::http(&e),
+                previous.as_ref(),
+            );
+            save_pr_entry(git_dir, branch, &entry);
+            return;
+        }
+    };
+
+    let entry = PrCacheEntry {
+        branch: branch.to_string(),
+        provider: Some(Provider::Gitea),
+        fetched_at: now,
+        attempted_at: now,
+        etag: None,
+        state,
+    };
+    save_pr_entry(git_dir, branch, &entry);
+}
+
+/// Combined commit status of the PR head, mapped to gh's JSON shape
+fn pr_info(
+    repo_api: &str,
+    pr: &serde_json::Value,
+    headers: &[(&str, &str)],
+    tls: &TlsOptions,
+) -> Option<PrInfo> {
+    let sha = pr["head"]["sha"].as_str().unwrap_or_default();
+    let url = format!("{repo_api}/commits/{}/status", percent_encode(sha));
+    let status: serde_json::Value = api_get(&url, headers, tls)
+        .ok()
+        .and_then(|resp| serde_json::from_str(&resp.body).ok())
+        .unwrap_or_default();
+    // No statuses at all reads as "pending" with an empty list
+    let has_statuses = status["statuses"].as_array().is_some_and(|s| !s.is_empty());
+    let check_rollup: Vec<serde_json::Value> = match status["state"].as_str() {
+        Some(_) if !has_statuses => Vec::new(),
+        Some("success") => vec![serde_json::json!({ "conclusion": "SUCCESS" })],
+        Some("failure" | "error" | "warning") => {
+            vec![serde_json::json!({ "conclusion": "FAILURE" })]
+        }
+        Some(_) => vec![serde_json::json!({ "conclusion": null })],
+        None => Vec::new(),
+    };
+
+    let state = match (pr["state"].as_str()?, pr["merged"].as_bool()) {
+        (_, Some(true)) => "MERGED",
+        ("open", _) => "OPEN",
+        _ => "CLOSED",
+    };
+    let mergeable = match pr["mergeable"].as_bool() {
+        Some(true) => "MERGEABLE",
+        Some(false) => "CONFLICTING",
+        None => "UNKNOWN",
+    };
+
+    let gh_json = serde_json::json!({
+        "number": pr["number"],
+        "state": state,
+        "url": pr["html_url"],
+        "commentsCount": pr["comments"].as_u64().unwrap_or(0),
+        "changedFiles": pr["changed_files"].as_u64().unwrap_or(0),
+        "statusCheckRollup": check_rollup,
+        "createdAt": pr["created_at"],
+        "labels": pr["labels"],
+        "mergeable": mergeable
+    });
+    parse_pr_json(&gh_json.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(hosts: &[&str]) -> GiteaConfig {
+        GiteaConfig {
+            hosts: hosts.iter().map(ToString::to_string).collect(),
+            token_env: None,
+        }
+    }
+
+    #[test]
+    fn parse_gitea_url_configured_hosts() {
+        let config = config(&["git.example.com", "http://localhost:3000"]);
+        let expected = Some(GiteaTarget {
+            api: "https://git.example.com/api/v1".to_string(),
+            owner: "team".to_string(),
+            repo: "app".to_string(),
+        });
+        assert_eq!(
+            parse_gitea_url("git@git.example.com:team/app.git", &config),
+            expected
+        );
+        assert_eq!(
+            parse_gitea_url("https://git.example.com/team/app", &config),
+            expected
+        );
+        assert_eq!(
+            parse_gitea_url("ssh://git@GIT.example.com:2222/team/app.git", &config),
+            expected
+        );
+        assert_eq!(
+            parse_gitea_url("http://localhost:3000/team/app.git", &config).map(|t| t.api),
+            Some("http://localhost:3000/api/v1".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_gitea_url_ignores_other_hosts() {
+        let config = config(&["git.example.com"]);
+        assert_eq!(
+            parse_gitea_url("git@github.com:team/app.git", &config),
+            None
+        );
+        assert_eq!(
+            parse_gitea_url("https://example.com/team/app", &config),
+            None
+        );
+        assert_eq!(
+            parse_gitea_url("https://git.example.com/team", &config),
+            None
+        );
+    }
+}
@@ -0,0 +1,712 @@
+//! GitHub remote detection and native REST API fetch
+
+use super::cache::{
+    PrCacheEntry, PrError, PrState, Provider, parse_pr_json, read_pr_entry, save_pr_entry,
+};
+use super::{Deployment, PrInfo, PrSource};
+use crate::cache::{atomic_rename, get_cache_dir, unique_hex};
+use crate::sys::{self, HttpError, HttpResponse};
+use crate::{parse_github_url, percent_encode};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Get GitHub token for API authentication
+/// Tries: 1) `GITHUB_TOKEN` env var, 2) `GH_TOKEN` env var, 3) git credential fill,
+/// whose answer is reused for `cache_ttl` seconds
+pub(crate) fn get_github_token(cache_ttl: u64) -> Option<String> {
+    // Try GITHUB_TOKEN env first
+    if let Some(token) = sys::var("GITHUB_TOKEN")
+        && !token.is_empty()
+    {
+        return Some(token);
+    }
+
+    // Try GH_TOKEN (used by gh CLI)
+    if let Some(token) = sys::var("GH_TOKEN")
+        && !token.is_empty()
+    {
+        return Some(token);
+    }
+
+    if sys::sandboxed() {
+        return None;
+    }
+
+    if cache_ttl == 0 {
+        return credential_helper_token();
+    }
+    let path = get_cache_dir().join("github-token.cache");
+    if let Some(cached) = read_token_cache(&path, sys::now_secs()) {
+        return cached;
+    }
+    let token = credential_helper_token();
+    // Missing credentials are remembered briefly, so fixing them shows up soon
+    let ttl = if token.is_some() {
+        cache_ttl
+    } else {
+        cache_ttl.min(NO_TOKEN_TTL)
+    };
+    write_token_cache(&path, sys::now_secs() + ttl, token.as_deref());
+    token
+}
+
+/// How long a credential helper without GitHub credentials isn't asked again
+const NO_TOKEN_TTL: u64 = 60; // seconds
+
+/// Cached helper answer, unless expired: "<expires_at>\n<token>", empty for none
+fn read_token_cache(path: &Path, now: u64) -> Option<Option<String>> {
+    let content = fs::read_to_string(path).ok()?;
+    let (expires_at, token) = content.split_once('\n').unwrap_or((&content, ""));
+    if expires_at.parse::<u64>().ok()? <= now {
+        return None;
+    }
+    Some(Some(token.to_string()).filter(|t| !t.is_empty()))
+}
+
+/// Write the cache readable only by the user (0600), then move it into place
+fn write_token_cache(path: &Path, expires_at: u64, token: Option<&str>) {
+    let temp_path = path.with_file_name(format!("token-tmp-{}", unique_hex()));
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let written = options
+        .open(&temp_path)
+        .and_then(|mut file| write!(file, "{expires_at}\n{}", token.unwrap_or_default()));
+    if written.is_ok() {
+        let _ = atomic_rename(&temp_path, path);
+    } else {
+        let _ = fs::remove_file(&temp_path);
+    }
+}
+
+/// Ask the git credential helper for github.com (may prompt or be slow)
+fn credential_helper_token() -> Option<String> {
+    let mut child = Command::new("git")
+        .args(["credential", "fill"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    // Write credential request to stdin
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = writeln!(stdin, "protocol=https");
+        let _ = writeln!(stdin, "host=github.com");
+        let _ = writeln!(stdin);
+    }
+
+    // Parse password from output
+    let output = child.wait_with_output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for line in stdout.lines() {
+        if let Some(token) = line.strip_prefix("password=") {
+            return Some(token.to_string());
+        }
+    }
+    None
+}
+
+/// Repo to query for a branch's PR, and whose fork the branch lives in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PrTarget {
+    pub(crate) owner: String,
+    pub(crate) repo: String,
+    /// Owner used in the `head=owner:branch` filter; differs from `owner` for forks
+    pub(crate) head_owner: String,
+}
+
+impl PrTarget {
+    /// Branch as GitHub names it from the base repo: `owner:branch` for forks
+    pub(crate) fn head(&self, branch: &str) -> String {
+        if self.head_owner == self.owner {
+            branch.to_string()
+        } else {
+            format!("{}:{branch}", self.head_owner)
+        }
+    }
+}
+
+/// Remotes and the branch's tracking, from the repo's resolved config
+#[derive(Debug, Default)]
+pub(super) struct RemoteConfig {
+    /// (name, url) in config order, with `insteadOf` aliases resolved
+    remotes: Vec<(String, String)>,
+    /// Remote the branch fetches from: `branch.<name>.remote`
+    fetch: Option<String>,
+    /// Remote the branch pushes to: `branch.<name>.pushRemote`,
+    /// then `remote.pushDefault`, then the fetch remote
+    push: Option<String>,
+}
+
+impl RemoteConfig {
+    /// Read through gix so includes, conditional includes, and worktree
+    /// config apply as they do for git itself
+    pub(super) fn load(repo: &gix::Repository, branch: &str) -> Self {
+        use gix::remote::Direction;
+
+        let mut remotes: Vec<(String, String)> = Vec::new();
+        let config = repo.config_snapshot();
+        for section in config
+            .plumbing()
+            .sections_by_name("remote")
+            .into_iter()
+            .flatten()
+        {
+            let Some(name) = section.header().subsection_name() else {
+                continue;
+            };
+            let name = name.to_string();
+            if remotes.iter().any(|(n, _)| *n == name) {
+                continue;
+            }
+            // find_remote applies url.<base>.insteadOf rewrites
+            if let Ok(remote) = repo.find_remote(name.as_str())
+                && let Some(url) = remote.url(Direction::Fetch)
+            {
+                remotes.push((name, url.to_bstring().to_string()));
+            }
+        }
+        let remote_name = |direction| {
+            repo.branch_remote_name(branch, direction)
+                .map(|name| name.as_bstr().to_string())
+        };
+        Self {
+            remotes,
+            fetch: remote_name(Direction::Fetch),
+            push: remote_name(Direction::Push),
+        }
+    }
+
+    /// `parse` applied to the URL of `preferred`, else origin, else the first
+    /// remote it accepts
+    pub(super) fn find<T>(
+        &self,
+        preferred: Option<&str>,
+        parse: impl Fn(&str) -> Option<T>,
+    ) -> Option<T> {
+        let named = |name: &str| {
+            self.remotes
+                .iter()
+                .find(|(n, _)| n == name)
+                .and_then(|(_, url)| parse(url))
+        };
+        preferred
+            .and_then(named)
+            .or_else(|| named("origin"))
+            .or_else(|| self.remotes.iter().find_map(|(_, url)| parse(url)))
+    }
+
+    /// Owner and repo of the named remote, if it is on GitHub
+    fn github(&self, name: &str) -> Option<(String, String)> {
+        self.remotes
+            .iter()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, url)| parse_github_url(url))
+    }
+
+    /// Pick the repo PRs live in and the owner of the branch's fork
+    /// The base is `preferred`, else the tracked remote in a triangular
+    /// workflow, else origin, else the first GitHub remote
+    fn target(&self, preferred: Option<&str>) -> Option<PrTarget> {
+        let push = self.push.as_deref();
+        // Pulling from one remote and pushing to another: PRs go upstream
+        let upstream = self.fetch.as_deref().filter(|r| Some(*r) != push);
+        let (owner, repo) = preferred
+            .and_then(|name| self.github(name))
+            .or_else(|| upstream.and_then(|name| self.github(name)))
+            .or_else(|| self.github("origin"))
+            .or_else(|| {
+                self.remotes
+                    .iter()
+                    .find_map(|(_, url)| parse_github_url(url))
+            })?;
+        let head_owner = push
+            .and_then(|name| self.github(name))
+            .map_or_else(|| owner.clone(), |(head_owner, _)| head_owner);
+        Some(PrTarget {
+            owner,
+            repo,
+            head_owner,
+        })
+    }
+}
+
+/// Find the GitHub repo to query for `branch`'s PR
+/// Handles: git@github.com:owner/repo.git, <https://github.com/owner/repo.git>,
+/// ssh:// URLs, and aliases from `url.<base>.insteadOf`
+pub(crate) fn resolve_target(
+    repo: &gix::Repository,
+    branch: &str,
+    preferred: Option<&str>,
+) -> Option<PrTarget> {
+    RemoteConfig::load(repo, branch).target(preferred)
+}
+
+/// Default REST API base URL
+pub(crate) const DEFAULT_API_URL: &str = "https://api.github.com";
+
+/// Resolve the REST API base URL: `CC_STATUSLINE_GITHUB_API_URL`, then config, then api.github.com
+pub(crate) fn api_base_url(configured: Option<&str>) -> String {
+    sys::var("CC_STATUSLINE_GITHUB_API_URL")
+        .filter(|url| !url.is_empty())
+        .or_else(|| configured.map(str::to_string))
+        .map_or_else(
+            || DEFAULT_API_URL.to_string(),
+            |url| url.trim_end_matches('/').to_string(),
+        )
+}
+
+/// Refresh PR cache using native HTTP (synchronous)
+/// Works on all platforms, no gh CLI required
+/// Note: Runs synchronously because threads don't survive process exit.
+/// First call may be slow (~500ms), but throttling ensures subsequent calls use cache.
+pub(crate) fn refresh_pr_native(git_dir: &str, branch: &str, source: &PrSource, target: &PrTarget) {
+    // Get auth token (may block on git credential helper)
+    let Some(token) = get_github_token(source.token_cache_secs) else {
+        return; // No auth, skip PR feature
+    };
+
+    fetch_pr_data_native(git_dir, branch, source, target, &token);
+}
+
+/// GET in a single attempt: this runs during the render, so a transient
+/// failure is left to a later render once the entry's `retry_at` passes
+pub(super) fn api_get(
+    url: &str,
+    headers: &[(&str, &str)],
+    tls: &sys::TlsOptions,
+) -> Result<HttpResponse, HttpError> {
+    sys::http_get_response(url, headers, None, tls)
+}
+
+/// Fetch PR data using native HTTP (ureq)
+/// The PR list is requested with the last `ETag`, so an unchanged answer is a free 304
+fn fetch_pr_data_native(
+    git_dir: &str,
+    branch: &str,
+    source: &PrSource,
+    target: &PrTarget,
+    token: &str,
+) {
+    let (owner, repo) = (target.owner.as_str(), target.repo.as_str());
+    let (api, tls) = (source.api.as_str(), &source.tls);
+    let now = sys::now_secs();
+    let auth = format!("Bearer {token}");
+    let headers = [
+        ("Authorization", auth.as_str()),
+        ("Accept", "application/vnd.github+json"),
+        ("User-Agent", "cc-statusline"),
+        ("X-GitHub-Api-Version", "2022-11-28"),
+    ];
+
+    let previous = read_pr_entry(git_dir, branch);
+    let etag = previous.as_ref().and_then(|p| p.etag.clone());
+    let mut list_headers = headers.to_vec();
+    if let Some(etag) = &etag {
+        list_headers.push(("If-None-Match", etag));
+    }
+
+    // GitHub API: GET /repos/{owner}/{repo}/pulls?head={head_owner}:{branch}&state=all
+    // Use state=all to show merged/closed PRs too (not just open)
+    // URL-encode the branch name to handle special characters like # or spaces
+    let encoded_branch = percent_encode(branch);
+    let head_owner = &target.head_owner;
+    let url =
+        format!("{api}/repos/{owner}/{repo}/pulls?head={head_owner}:{encoded_branch}&state=all");
+
+    let (mut state, etag) = match api_get(&url, &list_headers, tls) {
+        // Unchanged list: the same PR (or none), whose details may still have moved
+        Ok(resp) if resp.status == 304 => {
+            let state = match previous.map(|p| p.state) {
+                Some(PrState::Found(pr)) => {
+                    match pr_info(api, owner, repo, pr.number.into(), None, &headers, tls) {
+                        Some(pr) => PrState::Found(pr),
+                        None => return,
+                    }
+                }
+                Some(PrState::NoPr) => PrState::NoPr,
+                _ => return,
+            };
+            (state, etag)
+        }
+        Ok(resp) => {
+            // Parse as array of PRs
+            let prs: Vec<serde_json::Value> = match serde_json::from_str(&resp.body) {
+                Ok(p) => p,
+                Err(_) => return,
+            };
+            let state = match prs.first() {
+                // No PR for this branch - negative cache
+                None => PrState::NoPr,
+                Some(pr) => {
+                    let number = pr["number"].as_u64().unwrap_or(0);
+                    match pr_info(api, owner, repo, number, Some(pr), &headers, tls) {
+                        Some(pr) => PrState::Found(pr),
+                        None => return,
+                    }
+                }
+            };
+            (state, resp.etag)
+        }
+        // API error (401/403/404 etc) or network error - don't negative cache
+        // Note: 404 can mean "no access" for private repos, not just "no PR"
+        Err(e) => {
+            let error = match e {
+                HttpError::Status(code @ (403 | 404)) if lacks_repo_scope(api, &headers, tls) => {
+                    PrError::MissingScope(code)
+                }
+                e => PrError::http(&e),
+            };
+            let entry = PrCacheEntry::failed(now, branch, Provider::Api, error, previous.as_ref());
+            save_pr_entry(git_dir, branch, &entry);
+            return;
+        }
+    };
+
+    if source.deployments
+        && let PrState::Found(pr) = &mut state
+    {
+        pr.deployment = latest_deployment(api, owner, repo, &encoded_branch, &headers, tls);
+    }
+
+    let entry = PrCacheEntry {
+        branch: branch.to_string(),
+        provider: Some(Provider::Api),
+        fetched_at: now,
+        attempted_at: now,
+        etag,
+        state,
+    };
+    save_pr_entry(git_dir, branch, &entry);
+}
+
+/// Whether the token is a classic one without the `repo` scope, which private
+/// repos need; fine-grained and app tokens report no scopes, so they never are
+fn lacks_repo_scope(api: &str, headers: &[(&str, &str)], tls: &sys::TlsOptions) -> bool {
+    // Doesn't count against the rate limit
+    let url = format!("{api}/rate_limit");
+    sys::http_get_response(&url, headers, None, tls)
+        .ok()
+        .and_then(|resp| resp.oauth_scopes)
+        .is_some_and(|scopes| !has_repo_scope(&scopes))
+}
+
+/// Whether an `X-OAuth-Scopes` list includes `repo`
+fn has_repo_scope(scopes: &str) -> bool {
+    scopes.split(',').any(|scope| scope.trim() == "repo")
+}
+
+/// Details and check status of PR `number`
+/// `summary` is its entry in the PR list; without one, the detail response stands in
+fn pr_info(
+    api: &str,
+    owner: &str,
+    repo: &str,
+    number: u64,
+    summary: Option<&serde_json::Value>,
+    headers: &[(&str, &str)],
+    tls: &sys::TlsOptions,
+) -> Option<PrInfo> {
+    // Fetch additional PR details (comments, changed files)
+    let detail_url = format!("{api}/repos/{owner}/{repo}/pulls/{number}");
+    let detail: serde_json::Value = api_get(&detail_url, headers, tls)
+        .ok()
+        .and_then(|resp| serde_json::from_str(&resp.body).ok())
+        .unwrap_or_default();
+    let summary = summary.unwrap_or(&detail);
+    let comments_count =
+        detail["comments"].as_u64().unwrap_or(0) + detail["review_comments"].as_u64().unwrap_or(0);
+    let changed_files = detail["changed_files"].as_u64().unwrap_or(0);
+
+    // Fetch check runs status
+    let checks_url = format!(
+        "{api}/repos/{owner}/{repo}/commits/{}/check-runs",
+        summary["head"]["sha"].as_str().unwrap_or("")
+    );
+    let check_rollup: Vec<serde_json::Value> = api_get(&checks_url, headers, tls)
+        .ok()
+        .and_then(|resp| serde_json::from_str::<serde_json::Value>(&resp.body).ok())
+        .and_then(|checks| {
+            checks["check_runs"].as_array().map(|runs| {
+                runs.iter()
+                    .map(|run| serde_json::json!({ "conclusion": run["conclusion"] }))
+                    .collect()
+            })
+        })
+        .unwrap_or_default();
+
+    // Latest review per reviewer, summarized like gh's reviewDecision
+    let reviews_url = format!("{api}/repos/{owner}/{repo}/pulls/{number}/reviews?per_page=100");
+    let reviews: Vec<serde_json::Value> = api_get(&reviews_url, headers, tls)
+        .ok()
+        .and_then(|resp| serde_json::from_str(&resp.body).ok())
+        .unwrap_or_default();
+    let review_decision = review_decision(&reviews);
+
+    // detail.mergeable is null while GitHub is still computing it
+    let mergeable = match detail["mergeable"].as_bool() {
+        Some(true) => "MERGEABLE",
+        Some(false) => "CONFLICTING",
+        None => "UNKNOWN",
+    };
+
+    // Same shape as gh output - commentsCount (number) instead of the comments array
+    let gh_json = serde_json::json!({
+        "number": number,
+        "state": summary["state"],
+        "url": summary["html_url"],
+        "commentsCount": comments_count,
+        "changedFiles": changed_files,
+        "statusCheckRollup": check_rollup,
+        "createdAt": summary["created_at"],
+        "autoMergeRequest": summary["auto_merge"],
+        "reviewDecision": review_decision,
+        "labels": summary["labels"],
+        "mergeable": mergeable
+    });
+    parse_pr_json(&gh_json.to_string())
+}
+
+/// Newest deployment of `git_ref` (URL-encoded) and the state of its latest status
+fn latest_deployment(
+    api: &str,
+    owner: &str,
+    repo: &str,
+    git_ref: &str,
+    headers: &[(&str, &str)],
+    tls: &sys::TlsOptions,
+) -> Option<Deployment> {
+    let url = format!("{api}/repos/{owner}/{repo}/deployments?ref={git_ref}&per_page=1");
+    let deployments: serde_json::Value =
+        serde_json::from_str(&api_get(&url, headers, tls).ok()?.body).ok()?;
+    let deployment = deployments.get(0)?;
+    let id = deployment["id"].as_u64()?;
+    let environment = deployment["environment"].as_str()?.to_string();
+
+    // Statuses are newest first; a deployment without one hasn't started
+    let url = format!("{api}/repos/{owner}/{repo}/deployments/{id}/statuses?per_page=1");
+    let statuses: serde_json::Value = api_get(&url, headers, tls)
+        .ok()
+        .and_then(|resp| serde_json::from_str(&resp.body).ok())
+        .unwrap_or_default();
+    let state = statuses[0]["state"]
+        .as_str()
+        .unwrap_or("pending")
+        .to_string();
+    Some(Deployment { environment, state })
+}
+
+/// APPROVED or CHANGES_REQUESTED from each reviewer's latest decisive review
+/// Branch protection isn't visible here, so "review required" is never reported
+fn review_decision(reviews: &[serde_json::Value]) -> &'static str {
+    let mut latest: Vec<(&str, &str)> = Vec::new();
+    for review in reviews {
+        let (Some(user), Some(state)) =
+            (review["user"]["login"].as_str(), review["state"].as_str())
+        else {
+            continue;
+        };
+        // Comments don't change a reviewer's verdict; a dismissal clears it
+        if !matches!(state, "APPROVED" | "CHANGES_REQUESTED" | "DISMISSED") {
+            continue;
+        }
+        latest.retain(|(u, _)| *u != user);
+        latest.push((user, state));
+    }
+    if latest.iter().any(|(_, s)| *s == "CHANGES_REQUESTED") {
+        "CHANGES_REQUESTED"
+    } else if latest.iter().any(|(_, s)| *s == "APPROVED") {
+        "APPROVED"
+    } else {
+        ""
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A fresh repo with `config` appended to its `.git/config`
+    fn repo_with_config(config: &str) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        gix::init(dir.path()).unwrap();
+        let path = dir.path().join(".git/config");
+        let base = fs::read_to_string(&path).unwrap();
+        fs::write(&path, base + config).unwrap();
+        dir
+    }
+
+    fn resolve(dir: &tempfile::TempDir, branch: &str, preferred: Option<&str>) -> Option<PrTarget> {
+        let repo = gix::open(dir.path()).unwrap();
+        resolve_target(&repo, branch, preferred)
+    }
+
+    fn target(owner: &str, repo: &str, head_owner: &str) -> Option<PrTarget> {
+        Some(PrTarget {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            head_owner: head_owner.to_string(),
+        })
+    }
+
+    #[test]
+    fn resolve_target_origin() {
+        let dir = repo_with_config("[remote \"origin\"]\n\turl=git@github.com:owner/repo.git\n");
+        assert_eq!(
+            resolve(&dir, "main", None),
+            target("owner", "repo", "owner")
+        );
+    }
+
+    #[test]
+    fn resolve_target_falls_back_to_other_github_remotes() {
+        let dir = repo_with_config(
+            "[remote \"upstream\"]\n\turl = https://github.com/other/repo\n\
+             [remote \"origin\"]\n\turl = https://gitlab.com/owner/repo\n",
+        );
+        assert_eq!(
+            resolve(&dir, "main", None),
+            target("other", "repo", "other")
+        );
+
+        let dir = repo_with_config("[remote \"origin\"]\n\turl = https://gitlab.com/o/r\n");
+        assert_eq!(resolve(&dir, "main", None), None);
+    }
+
+    #[test]
+    fn resolve_target_prefers_configured_remote_and_fork_head() {
+        let dir = repo_with_config(
+            "[remote \"origin\"]\n\turl = git@github.com:me/app.git\n\
+             [remote \"upstream\"]\n\turl = git@github.com:org/app.git\n\
+             [branch \"feature\"]\n\tremote = origin\n\tmerge = refs/heads/feature\n",
+        );
+        // The PR lives upstream, opened from the fork on origin
+        let fork = resolve(&dir, "feature", Some("upstream"));
+        assert_eq!(fork, target("org", "app", "me"));
+        assert_eq!(fork.unwrap().head("feature"), "me:feature");
+        assert_eq!(resolve(&dir, "feature", None), target("me", "app", "me"));
+        // An unknown preferred remote falls back to origin
+        assert_eq!(
+            resolve(&dir, "other", Some("nope")),
+            target("me", "app", "me")
+        );
+    }
+
+    #[test]
+    fn resolve_target_triangular_push_remote() {
+        // Fetch from upstream, push to the fork
+        let dir = repo_with_config(
+            "[remote \"origin\"]\n\turl = git@github.com:me/app.git\n\
+             [remote \"upstream\"]\n\turl = git@github.com:org/app.git\n\
+             [branch \"feature\"]\n\tremote = upstream\n\tpushRemote = origin\n",
+        );
+        assert_eq!(resolve(&dir, "feature", None), target("org", "app", "me"));
+
+        // remote.pushDefault applies to every branch without a pushRemote
+        let dir = repo_with_config(
+            "[remote \"origin\"]\n\turl = git@github.com:org/app.git\n\
+             [remote \"fork\"]\n\turl = git@github.com:me/app.git\n\
+             [remote]\n\tpushDefault = fork\n\
+             [branch \"feature\"]\n\tremote = origin\n",
+        );
+        assert_eq!(resolve(&dir, "feature", None), target("org", "app", "me"));
+        assert_eq!(resolve(&dir, "new", None), target("org", "app", "me"));
+    }
+
+    #[test]
+    fn resolve_target_insteadof_alias() {
+        let dir = repo_with_config(
+            "[remote \"origin\"]\n\turl = gh:owner/repo\n\
+             [url \"git@github.com:\"]\n\tinsteadOf = gh:\n",
+        );
+        assert_eq!(
+            resolve(&dir, "main", None),
+            target("owner", "repo", "owner")
+        );
+    }
+
+    #[test]
+    fn resolve_target_through_include() {
+        let dir = repo_with_config("[include]\n\tpath = remotes.inc\n");
+        fs::write(
+            dir.path().join(".git/remotes.inc"),
+            "[remote \"origin\"]\n\turl = https://github.com/owner/repo.git\n",
+        )
+        .unwrap();
+        assert_eq!(
+            resolve(&dir, "main", None),
+            target("owner", "repo", "owner")
+        );
+    }
+
+    #[test]
+    fn review_decision_uses_latest_review_per_reviewer() {
+        let reviews: Vec<serde_json::Value> = serde_json::from_str(
+            r#"[{"user": {"login": "a"}, "state": "CHANGES_REQUESTED"},
+                {"user": {"login": "b"}, "state": "APPROVED"},
+                {"user": {"login": "a"}, "state": "COMMENTED"}]"#,
+        )
+        .unwrap();
+        assert_eq!(review_decision(&reviews), "CHANGES_REQUESTED");
+
+        let reviews: Vec<serde_json::Value> = serde_json::from_str(
+            r#"[{"user": {"login": "a"}, "state": "CHANGES_REQUESTED"},
+                {"user": {"login": "b"}, "state": "APPROVED"},
+                {"user": {"login": "a"}, "state": "APPROVED"}]"#,
+        )
+        .unwrap();
+        assert_eq!(review_decision(&reviews), "APPROVED");
+        assert_eq!(review_decision(&[]), "");
+    }
+
+    #[test]
+    fn api_base_url_from_config() {
+        assert_eq!(
+            api_base_url(Some("http://127.0.0.1:8080/")),
+            "http://127.0.0.1:8080"
+        );
+    }
+
+    #[test]
+    fn repo_scope_detection() {
+        assert!(has_repo_scope("read:org, repo, workflow"));
+        assert!(has_repo_scope("repo"));
+        assert!(!has_repo_scope("public_repo, read:org"));
+        assert!(!has_repo_scope(""));
+    }
+
+    #[test]
+    fn token_cache_round_trip_and_expiry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("github-token.cache");
+        write_token_cache(&path, 1_000, Some("ghp_abc"));
+        assert_eq!(
+            read_token_cache(&path, 999),
+            Some(Some("ghp_abc".to_string()))
+        );
+        assert_eq!(read_token_cache(&path, 1_000), None);
+        // Helper without credentials: a cached "no token"
+        write_token_cache(&path, 1_000, None);
+        assert_eq!(read_token_cache(&path, 999), Some(None));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn resolve_target_without_remotes() {
+        let dir = repo_with_config("");
+        assert_eq!(resolve(&dir, "main", None), None);
+    }
+}
@@ -0,0 +1,434 @@
+//! Pull request data for the current branch
+//!
+//! Data is read from a per-branch cache and refreshed in the background,
+//! via `gh` when available or the GitHub REST API otherwise. Azure DevOps
+//! repos and configured Gitea or Forgejo instances use their REST APIs.
+
+mod azure;
+mod cache;
+mod gitea;
+mod github;
+
+use crate::cache::{get_cache_dir, unique_hex};
+use crate::config::GiteaConfig;
+use crate::git::GitRepo;
+use crate::sys::{self, TlsOptions};
+use crate::{memo, percent_encode, shell_escape, wsl};
+pub(crate) use cache::PrError;
+#[cfg(feature = "bench")]
+pub(crate) use cache::{PrCacheEntry, PrState as PrCacheState, Provider, parse_pr_json};
+use cache::{
+    PrCacheResult, PrState, error_prefix, get_gh_output_path, load_pr_cache, pr_cache_is_warm,
+    read_pr_entry, record_active_branch, save_pr_entry,
+};
+use github::{DEFAULT_API_URL, refresh_pr_native};
+pub(crate) use github::{PrTarget, api_base_url, get_github_token, resolve_target};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+static GH_BINARY: OnceLock<Option<&'static str>> = OnceLock::new();
+
+const PR_REFRESH_THROTTLE: u64 = 30; // minimum seconds between refresh attempts
+
+/// Tries per request before a background gh refresh gives up on a transient failure
+const PR_FETCH_ATTEMPTS: u32 = 3;
+
+/// Wait before retry `attempt` (0-based): 250ms doubling, plus up to 50% jitter
+/// so renders that failed together don't retry together
+fn backoff_delay(attempt: u32) -> Duration {
+    let base = 250u64 << attempt.min(6);
+    let nanos = sys::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| u64::from(d.subsec_nanos()));
+    Duration::from_millis(base + nanos % (base / 2 + 1))
+}
+
+/// Pull request data - parsed from gh JSON output or the native API cache
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrInfo {
+    pub number: u32,
+    pub state: String,
+    pub url: String,
+    pub comments: u32,
+    pub changed_files: u32,
+    pub check_status: String, // "passed", "failed", "pending", ""
+    /// UNIX time the PR was opened; 0 if unknown
+    pub created_at: u64,
+    /// Auto-merge is enabled and waiting on requirements
+    pub auto_merge: bool,
+    pub review_decision: String, // "approved", "changes_requested", "review_required", ""
+    pub labels: Vec<String>,
+    pub mergeable: String, // "mergeable", "conflicting", ""
+    /// Latest deployment of the branch, when the `deployment` component asks for it
+    pub deployment: Option<Deployment>,
+    /// Served from an expired cache because the network is off
+    #[serde(skip)]
+    pub stale: bool,
+}
+
+/// A GitHub deployment and the state of its latest status
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deployment {
+    pub environment: String,
+    pub state: String, // "success", "failure", "error", "pending", "in_progress", ...
+}
+
+/// Where PR lookups go
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PrSource {
+    /// REST API base URL
+    pub(crate) api: String,
+    /// Remote holding the repo PRs are opened against, if not origin
+    pub(crate) remote: Option<String>,
+    /// Only read the cache; never run gh or call the API
+    pub(crate) offline: bool,
+    /// CA bundle and verification for the native client
+    pub(crate) tls: TlsOptions,
+    /// Also look up the branch's latest deployment
+    pub(crate) deployments: bool,
+    /// Self-hosted Gitea or Forgejo instances
+    pub(crate) gitea: Option<GiteaConfig>,
+    /// Seconds a credential helper token is reused
+    pub(crate) token_cache_secs: u64,
+}
+
+/// The gh CLI to run, if one works (cached)
+/// Under WSL, the Windows `gh.exe` is used when there is no Linux gh
+fn gh_binary() -> Option<&'static str> {
+    if sys::sandboxed() {
+        return None;
+    }
+    *GH_BINARY.get_or_init(|| {
+        let candidates: &[&'static str] = if wsl::is_wsl() {
+            &["gh", "gh.exe"]
+        } else {
+            &["gh"]
+        };
+        candidates.iter().copied().find(|gh| {
+            Command::new(gh)
+                .arg("--version")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|s| s.success())
+        })
+    })
+}
+
+/// Spawn background process to refresh PR cache using gh CLI
+/// Uses atomic writes: write to temp file, then rename
+/// Distinguishes "no PR" from gh errors to avoid false negative caching
+/// Only available on Unix (requires sh shell)
+#[cfg(unix)]
+fn spawn_pr_refresh_gh(
+    gh: &str,
+    git_dir: &str,
+    work_dir: &str,
+    branch: &str,
+    target: &PrTarget,
+    deployments: bool,
+) {
+    let output_path = get_gh_output_path(git_dir, branch);
+    let output_path_str = output_path.to_string_lossy();
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    // Create temp files with random suffix in secure cache directory
+    let random_suffix = unique_hex();
+    let temp_cache = get_cache_dir().join(format!("pr-tmp-{random_suffix}.cache"));
+    let temp_cache_str = temp_cache.to_string_lossy();
+    let script_path = get_cache_dir().join(format!("pr-refresh-{random_suffix}.sh"));
+
+    // Delays between attempts, then a "done" sentinel
+    let delays: Vec<String> = (0..PR_FETCH_ATTEMPTS - 1)
+        .map(|attempt| format!("{:.3}", backoff_delay(attempt).as_secs_f64()))
+        .chain(std::iter::once("done".to_string()))
+        .collect();
+
+    // Newest deployment of the branch, spliced into the PR JSON as "deployment"
+    // (gh's --jq emits JSON-quoted strings, so environment names can't break it)
+    let deployment = if deployments {
+        let api_repo = format!("repos/{}/{}/deployments", target.owner, target.repo);
+        format!(
+            r#"
+            dep=$({gh} api {list} --jq '.[0] | select(.) | "\(.id) \(.environment | tojson)"' 2>/dev/null)
+            if [ -n "$dep" ]; then
+                dep_state=$({gh} api {statuses_prefix}"${{dep%% *}}"{statuses_suffix} --jq '.[0].state // "pending" | tojson' 2>/dev/null)
+                json="${{json%?}},\"deployment\":{{\"environment\":${{dep#* }},\"state\":${{dep_state:-\"pending\"}}}}}}"
+            fi"#,
+            gh = shell_escape(gh),
+            list = shell_escape(&format!(
+                "{api_repo}?ref={}&per_page=1",
+                percent_encode(branch)
+            )),
+            statuses_prefix = shell_escape(&format!("{api_repo}/")),
+            statuses_suffix = shell_escape("/statuses?per_page=1"),
+        )
+    } else {
+        String::new()
+    };
+
+    // Script logic:
+    // 1. Run gh pr view and capture stdout/stderr separately
+    // 2. If gh succeeds with JSON output -> write PR data
+    // 3. If gh fails with "no pull requests" message -> write NO_PR (legitimate no PR)
+    // 4. If gh fails for other reasons -> retry after a backoff delay, up to
+    //    PR_FETCH_ATTEMPTS times, then write ERROR with a retry time (don't negative cache)
+    // 5. Atomic rename temp file to the output file, which the next render
+    //    folds into the cache
+    // Uses trap with $0 for cleanup to avoid quoting issues with shell_escape
+    let script = format!(
+        r#"#!/bin/sh
+trap 'rm -f "$0"' EXIT
+cd {work_dir} || exit 1
+for delay in {delays}; do
+    # Capture stdout and stderr separately to detect "no PR" vs other errors
+    json=$({gh} pr view {pr} --repo {repo} --json number,state,url,comments,changedFiles,statusCheckRollup,createdAt,autoMergeRequest,reviewDecision,labels,mergeable 2>/dev/null)
+    if [ $? -eq 0 ]; then
+        if [ -n "$json" ]; then
+            # Success with JSON output - PR exists{deployment}
+            printf '%s\n%s\n%s' {timestamp} {branch} "$json" > {temp_cache}
+            mv -f {temp_cache} {output_path}
+        fi
+        exit 0
+    fi
+    # gh failed - check if it's "no PR" error by running again and capturing stderr only
+    # Use file descriptor swap: redirect stdout to /dev/null first, then capture stderr
+    err=$({gh} pr view {pr} --repo {repo} 2>&1 1>/dev/null)
+    case "$err" in
+        *"no pull requests"*|*"no open pull requests"*|*"Could not resolve to a PullRequest"*)
+            # Legitimate "no PR" - negative cache
+            printf '%s\n%s\nNO_PR' {timestamp} {branch} > {temp_cache}
+            mv -f {temp_cache} {output_path}
+            exit 0
+            ;;
+    esac
+    [ "$delay" = done ] || sleep "$delay"
+done
+# Other error (auth, network, etc) - don't negative cache
+err=$(printf '%s' "$err" | tr '\r\n' '  ')
+printf '%s\n%s\n%s%s' {timestamp} {branch} {error_prefix} "$err" > {temp_cache}
+mv -f {temp_cache} {output_path}
+"#,
+        gh = shell_escape(gh),
+        work_dir = shell_escape(work_dir),
+        delays = delays.join(" "),
+        pr = shell_escape(&target.head(branch)),
+        repo = shell_escape(&format!("{}/{}", target.owner, target.repo)),
+        timestamp = now,
+        branch = shell_escape(branch),
+        error_prefix = shell_escape(&error_prefix(now)),
+        temp_cache = shell_escape(&temp_cache_str),
+        output_path = shell_escape(&output_path_str),
+    );
+
+    if fs::write(&script_path, &script).is_err() {
+        return;
+    }
+
+    // Set executable permission
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(&script_path, fs::Permissions::from_mode(0o700));
+    }
+
+    let _ = Command::new("sh")
+        .arg(&script_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+}
+
+/// Dispatch PR refresh to appropriate implementation
+/// Returns true if refresh was synchronous (cache can be re-read immediately)
+/// A non-default API URL always uses the native client, since gh talks to github.com
+fn spawn_pr_refresh(git: &GitRepo, branch: &str, source: &PrSource) -> bool {
+    let git_dir = git.git_dir.as_str();
+    // Only proceed if this is a GitHub, Azure DevOps, or Gitea repo
+    let remote = source.remote.as_deref();
+    let Some(target) = resolve_target(&git.repo, branch, remote) else {
+        if let Some(target) = azure::resolve_target(&git.repo, branch, remote) {
+            azure::refresh_pr_azure(git_dir, branch, source, &target);
+            return true;
+        }
+        if let Some(config) = &source.gitea
+            && let Some(target) = gitea::resolve_target(&git.repo, branch, remote, config)
+        {
+            gitea::refresh_pr_gitea(git_dir, branch, source, config, &target);
+            return true;
+        }
+        return false;
+    };
+
+    // On Unix, prefer gh if available (handles auth, rate limits better)
+    #[cfg(unix)]
+    if source.api == DEFAULT_API_URL
+        && let Some(gh) = gh_binary()
+    {
+        spawn_pr_refresh_gh(
+            gh,
+            git_dir,
+            &git.work_dir,
+            branch,
+            &target,
+            source.deployments,
+        );
+        return false; // Background process, cache not ready yet
+    }
+
+    // Fallback to native HTTP (works on all platforms, no gh required)
+    refresh_pr_native(git_dir, branch, source, &target);
+    true // Synchronous, cache is ready
+}
+
+/// Check if we should skip refresh (attempted within the throttle window)
+/// After a failure the window is the entry's own backoff, so a transient
+/// error is retried within seconds without every render retrying it
+fn should_skip_refresh(git_dir: &str, branch: &str) -> bool {
+    read_pr_entry(git_dir, branch).is_some_and(|entry| {
+        let window = match entry.state {
+            PrState::Failed { retry_at, .. } => retry_at.saturating_sub(entry.fetched_at),
+            _ => PR_REFRESH_THROTTLE,
+        };
+        sys::now_secs().saturating_sub(entry.attempted_at) < window
+    })
+}
+
+/// Mark that we've attempted a refresh, in the branch's entry
+fn mark_refresh_attempt(git_dir: &str, branch: &str) {
+    let now = sys::now_secs();
+    let entry = match read_pr_entry(git_dir, branch) {
+        Some(entry) => cache::PrCacheEntry {
+            attempted_at: now,
+            ..entry
+        },
+        None => cache::PrCacheEntry::pending(now, branch),
+    };
+    save_pr_entry(git_dir, branch, &entry);
+}
+
+/// Get PR data - checks cache first, triggers refresh if needed
+/// On Unix with gh CLI: spawns background process (non-blocking)
+/// On other platforms or without gh: runs synchronous HTTP refresh (may block ~500ms)
+/// Offline: never refreshes, and returns expired data marked stale
+/// Long-running processes reuse a hit for a few seconds without reading the cache
+pub(crate) fn get_pr_data(git: &GitRepo, source: &PrSource) -> Option<PrInfo> {
+    trace_span!("pr");
+    if let Some(data) = memo::pr(git) {
+        return Some(data);
+    }
+    // Single cache read handles all states
+    match load_pr_cache(&git.git_dir, &git.branch) {
+        PrCacheResult::Hit(data) => {
+            memo::remember_pr(git, &data);
+            return Some(data);
+        }
+        PrCacheResult::NoPr => return None, // Negative cache hit - no PR exists
+        PrCacheResult::Stale(expired) if source.offline => {
+            return expired.map(|pr| PrInfo { stale: true, ..pr });
+        }
+        PrCacheResult::Backoff(last) => {
+            return last.map(|pr| PrInfo { stale: true, ..pr });
+        }
+        PrCacheResult::Stale(_) => {} // Continue to refresh
+    }
+
+    // Throttle refresh attempts to avoid process storms
+    if should_skip_refresh(&git.git_dir, &git.branch) {
+        return None;
+    }
+
+    // Mark that we're attempting a refresh
+    mark_refresh_attempt(&git.git_dir, &git.branch);
+
+    // Trigger refresh - returns true if synchronous (native path)
+    let was_synchronous = spawn_pr_refresh(git, &git.branch, source);
+
+    // If refresh was synchronous, re-read cache to return data immediately
+    // This avoids blocking on HTTP but still not showing PR data until next render
+    if was_synchronous && let PrCacheResult::Hit(data) = load_pr_cache(&git.git_dir, &git.branch) {
+        return Some(data);
+    }
+
+    None
+}
+
+/// Whether `get_pr_data` has an answer without refreshing: offline, remembered
+/// in memory, or a current cache entry
+pub(crate) fn pr_is_cached(git: &GitRepo, source: &PrSource) -> bool {
+    source.offline || memo::pr(git).is_some() || pr_cache_is_warm(&git.git_dir, &git.branch)
+}
+
+/// Record the branch as active and refresh the previously active branch's expired
+/// entry, so switching back to it is a cache hit
+/// Recording may evict the entries of the least recently active branches
+pub(crate) fn prefetch_previous(git: &GitRepo, source: &PrSource) {
+    let Some(previous) = record_active_branch(&git.git_dir, &git.branch) else {
+        return;
+    };
+    if source.offline
+        || !matches!(
+            load_pr_cache(&git.git_dir, &previous),
+            PrCacheResult::Stale(_)
+        )
+        || should_skip_refresh(&git.git_dir, &previous)
+    {
+        return;
+    }
+    mark_refresh_attempt(&git.git_dir, &previous);
+    spawn_pr_refresh(git, &previous, source);
+}
+
+/// Error of the branch's last PR refresh, if it failed
+pub(crate) fn last_error(git: &GitRepo) -> Option<PrError> {
+    match read_pr_entry(&git.git_dir, &git.branch)?.state {
+        PrState::Failed { error, .. } => Some(error),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refresh_attempt_throttles() {
+        let git_dir = format!("/nonexistent/throttle-test-{}", unique_hex());
+        assert!(!should_skip_refresh(&git_dir, "main"));
+        mark_refresh_attempt(&git_dir, "main");
+        assert!(should_skip_refresh(&git_dir, "main"));
+        assert!(!should_skip_refresh(&git_dir, "other"));
+        let _ = fs::remove_file(cache::get_pr_cache_path(&git_dir, "main"));
+    }
+
+    #[test]
+    fn refresh_skipped_for_non_github_remote() {
+        let dir = tempfile::tempdir().unwrap();
+        gix::init(dir.path()).unwrap();
+        let config = dir.path().join(".git/config");
+        let base = fs::read_to_string(&config).unwrap();
+        fs::write(
+            &config,
+            base + "[remote \"origin\"]\n\turl = https://gitlab.com/o/r\n",
+        )
+        .unwrap();
+        let git = crate::git::get_git_repo(dir.path().to_str().unwrap()).unwrap();
+        let source = PrSource {
+            api: DEFAULT_API_URL.to_string(),
+            remote: None,
+            offline: false,
+            tls: TlsOptions::default(),
+            deployments: false,
+            gitea: None,
+            token_cache_secs: 0,
+        };
+        assert!(!spawn_pr_refresh(&git, &git.branch, &source));
+    }
+}
@@ -0,0 +1,2756 @@
+//! Segment data model and config-driven rendering
+
+use crate::cache::{self, atomic_rename, get_cache_dir, unique_hex};
+use crate::ci::{self, CiStatus};
+use crate::collect::{Collected, DEFAULT_COLD_DEADLINE_MS, DEFAULT_DEADLINE_MS, GitStats, collect};
+use crate::config::{
+    BudgetConfig, COMPONENTS, Config, CustomSegment, DurationFormat, PathMode, PrVerbosity,
+    ProgressSource, Theme,
+};
+use crate::gerrit::{self, GerritChange};
+use crate::git::{self, GitInfo, GitRepo, Signing, get_git_repo};
+use crate::history;
+use crate::input::ClaudeInput;
+use crate::lfs::{self, LfsStatus};
+use crate::notify;
+use crate::package;
+use crate::pr::{self, PrError, PrInfo, PrSource, api_base_url, resolve_target};
+use crate::repo_status::{self, RepoSource};
+use crate::rules::{self, Value};
+use crate::stats;
+use crate::sys;
+use crate::transcript::{self, TranscriptStats};
+use crate::update::{self, RELEASES_URL};
+use crate::vcs::{self, VcsInfo};
+use crate::{
+    abbreviate_path_with, display_os, escape_controls, get_home, glob_match, hash_path,
+    percent_encode,
+};
+#[cfg(feature = "plugins")]
+use crate::{config::get_plugins_dirs, plugins};
+use std::borrow::Cow;
+use std::env;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+static HOSTNAME: OnceLock<Option<String>> = OnceLock::new();
+
+/// Components that need the PR lookup
+const PR_COMPONENTS: &[&str] = &[
+    "pr_number",
+    "pr_state",
+    "pr_comments",
+    "pr_files",
+    "pr_checks",
+    "pr_age",
+    "pr_auto_merge",
+    "pr_reviews",
+    "pr_labels",
+    "pr_mergeable",
+    "pr_access",
+];
+
+/// Added to the PR row by `"pr_verbosity": "detailed"`
+const PR_DETAIL_COMPONENTS: &[&str] = &["pr_reviews", "pr_labels", "pr_age", "pr_mergeable"];
+
+/// Default time budget per Lua plugin
+#[cfg(feature = "plugins")]
+const PLUGIN_TIMEOUT_MS: u64 = 50;
+
+// Tokyo Night Colors (bright)
+const RESET: &str = "\x1b[0m";
+const TN_BLUE: Color = Color::rgb(122, 162, 247);
+const TN_CYAN: Color = Color::rgb(125, 207, 255);
+const TN_PURPLE: Color = Color::rgb(187, 154, 247);
+const TN_MAGENTA: Color = Color::rgb(157, 124, 216);
+const TN_GREEN: Color = Color::rgb(158, 206, 106);
+const TN_ORANGE: Color = Color::rgb(255, 158, 100);
+const TN_TEAL: Color = Color::rgb(42, 195, 222);
+const TN_GRAY: Color = Color::rgb(120, 140, 180);
+const TN_RED: Color = Color::rgb(247, 118, 142);
+
+/// Okabe-Ito colors, lightened for dark backgrounds
+const CB_BLUE: Color = Color::rgb(86, 180, 233);
+const CB_VERMILLION: Color = Color::rgb(230, 120, 50);
+const CB_YELLOW: Color = Color::rgb(240, 228, 66);
+const CB_GRAY: Color = Color::rgb(190, 190, 190);
+
+const SEP: &str = "\x1b[38;2;86;95;137m • \x1b[0m";
+
+// OSC 8 hyperlink escape sequences (using BEL terminator for broader compatibility)
+const OSC8_START: &str = "\x1b]8;;";
+const OSC8_MID: &str = "\x07";
+const OSC8_END: &str = "\x1b]8;;\x07";
+/// OSC 2 sets the window title, ended by BEL like the links
+const OSC_TITLE_START: &str = "\x1b]2;";
+/// OSC 9;4 sets the taskbar/tab progress (ConEmu, Windows Terminal, WezTerm)
+const OSC_PROGRESS_START: &str = "\x1b]9;4;";
+
+/// Context left (percent) below which the progress turns to a warning
+const PROGRESS_WARN_REMAINING: f64 = 20.0;
+/// Minutes without activity before the idle component shows by default
+const IDLE_AFTER_MINUTES: u64 = 10;
+/// Age an earlier visit needs before the last_visit component shows it
+const LAST_VISIT_AFTER_SECS: u64 = 12 * 60 * 60;
+/// Remaining context percentage that triggers `compact_warning` by default
+const COMPACT_WARNING: f64 = 15.0;
+
+const TERM_WIDTH: usize = 50;
+
+/// Columns taken by SEP
+const SEP_WIDTH: usize = 3;
+/// Narrowest a segment is truncated to; past that it is dropped instead
+const MIN_SEGMENT_WIDTH: usize = 8;
+
+/// Built-in elision priorities: higher is kept longer when a row is too wide
+const PRIORITIES: &[(&str, i32)] = &[
+    ("branch", 100),
+    ("no_git", 100),
+    ("pr_number", 90),
+    ("model", 85),
+    ("context", 80),
+    ("project", 75),
+    ("worktree", 70),
+    ("pr_checks", 65),
+    ("conflicts", 95),
+    ("files", 60),
+    ("ahead_behind", 60),
+    ("lines", 55),
+    ("hostname", 30),
+    ("dirs", 20),
+    ("path", 10),
+];
+/// Priority of components missing from PRIORITIES and the config
+const DEFAULT_PRIORITY: i32 = 50;
+
+/// Built-in context limits by model id glob; the first match wins
+const CONTEXT_LIMITS: &[(&str, u64)] = &[("*[1m]", 1_000_000), ("claude-*", 200_000)];
+
+/// Placeholder for data that missed the collection deadline
+const PENDING: &str = "…";
+
+/// Check if we're inside an SSH session by looking for SSH-related env vars
+fn is_ssh_session() -> bool {
+    sys::var("SSH_CONNECTION").is_some() || sys::var("SSH_CLIENT").is_some()
+}
+
+/// Get the system hostname via libc gethostname() (cached via OnceLock)
+/// Strips the `.local` suffix (used by mDNS/Bonjour on Unix systems)
+fn get_hostname() -> Option<&'static String> {
+    HOSTNAME
+        .get_or_init(|| {
+            #[cfg(unix)]
+            {
+                let mut buf = [0u8; 256];
+                let ret =
+                    unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+                if ret == 0 {
+                    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+                    std::str::from_utf8(&buf[..len]).ok().and_then(|name| {
+                        let trimmed = name.strip_suffix(".local").unwrap_or(name);
+                        if trimmed.is_empty() {
+                            None
+                        } else {
+                            Some(trimmed.to_string())
+                        }
+                    })
+                } else {
+                    None
+                }
+            }
+            #[cfg(not(unix))]
+            {
+                None
+            }
+        })
+        .as_ref()
+}
+
+pub(crate) fn format_tokens(n: u64) -> String {
+    if n >= 1_000_000 {
+        let tenths = n / 100_000;
+        let whole = tenths / 10;
+        let frac = tenths % 10;
+        format!("{whole}.{frac}M")
+    } else if n >= 1_000 {
+        format!("{}K", n / 1_000)
+    } else {
+        format!("{n}")
+    }
+}
+
+// ============================================================================
+// Custom command segments
+// ============================================================================
+
+/// Resolve a color name or `#rrggbb` hex string to a color
+fn parse_color(name: &str) -> Option<Color> {
+    let named = match name.to_ascii_lowercase().as_str() {
+        "blue" => TN_BLUE,
+        "cyan" => TN_CYAN,
+        "purple" => TN_PURPLE,
+        "magenta" => TN_MAGENTA,
+        "green" => TN_GREEN,
+        "orange" => TN_ORANGE,
+        "teal" => TN_TEAL,
+        "gray" | "grey" => TN_GRAY,
+        "red" => TN_RED,
+        _ => {
+            let hex = name.strip_prefix('#')?;
+            if hex.len() != 6 {
+                return None;
+            }
+            let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+            return Some(Color::rgb(channel(0)?, channel(2)?, channel(4)?));
+        }
+    };
+    Some(named)
+}
+
+fn get_custom_cache_path(name: &str, segment: &CustomSegment, dir: &str) -> PathBuf {
+    let key = format!("{name}\0{}\0{dir}", segment.command);
+    get_cache_dir().join(format!("custom-{:016x}.cache", hash_path(&key)))
+}
+
+/// Run a shell command in `dir`, returning stdout or None on failure or timeout
+/// The child is killed if it doesn't finish within `timeout`
+pub(crate) fn run_command_with_timeout(
+    command: &str,
+    dir: &str,
+    timeout: Duration,
+) -> Option<String> {
+    #[cfg(unix)]
+    let mut cmd = Command::new("sh");
+    #[cfg(unix)]
+    cmd.arg("-c").arg(command);
+    #[cfg(not(unix))]
+    let mut cmd = Command::new("cmd");
+    #[cfg(not(unix))]
+    cmd.arg("/C").arg(command);
+
+    let mut child = cmd
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    // Drain stdout on a thread so a chatty command can't block on a full pipe
+    let mut stdout = child.stdout.take()?;
+    let reader = std::thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = stdout.read_to_string(&mut buf);
+        buf
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(2)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+
+    let output = reader.join().ok()?;
+    status.success().then_some(output)
+}
+
+/// Directory a render is for: `cwd` if set, else the input's, else the process's,
+/// mapped through `path_map` so container and remote paths point at the
+/// checkout on this machine
+pub(crate) fn render_dir(cwd: Option<String>, data: &ClaudeInput, config: &Config) -> String {
+    let dir = cwd
+        .or_else(|| data.cwd.clone())
+        .or_else(|| data.workspace.current_dir.clone())
+        .or_else(|| data.workspace.project_dir.clone())
+        .unwrap_or_else(|| {
+            env::current_dir()
+                .map(|d| display_os(d.as_os_str()))
+                .unwrap_or_else(|_| ".".to_string())
+        });
+    config.map_path(&dir)
+}
+
+/// Cached output of a custom command: its UNIX timestamp and first line
+fn read_custom_cache(path: &Path) -> Option<(u64, String)> {
+    // Cache file format: 1st line UNIX timestamp, then the command's output
+    let content = fs::read_to_string(path).ok()?;
+    let (timestamp, output) = content.split_once('\n')?;
+    let text = output.lines().next().unwrap_or("").trim().to_string();
+    Some((timestamp.parse().ok()?, text))
+}
+
+fn write_custom_cache(path: &Path, now: u64, text: &str) {
+    let temp_path = get_cache_dir().join(format!("custom-tmp-{}.cache", unique_hex()));
+    if fs::write(&temp_path, format!("{now}\n{text}")).is_ok() {
+        let _ = atomic_rename(&temp_path, path);
+    }
+}
+
+/// Run a custom command detached; it writes the cache itself if it succeeds,
+/// so a command slower than `timeout` still lands for the next render
+/// Returns true if it finished within `timeout`
+#[cfg(unix)]
+fn refresh_custom(
+    command: &str,
+    dir: &str,
+    cache_path: &Path,
+    now: u64,
+    timeout: Duration,
+) -> bool {
+    use crate::shell_escape;
+    let temp_path = get_cache_dir().join(format!("custom-tmp-{}.cache", unique_hex()));
+    let script = format!(
+        r#"trap 'rm -f {temp}' EXIT
+printf '%s\n' {now} > {temp} || exit 1
+sh -c {command} >> {temp} 2>/dev/null && mv -f {temp} {cache}
+"#,
+        temp = shell_escape(&temp_path.to_string_lossy()),
+        command = shell_escape(command),
+        cache = shell_escape(&cache_path.to_string_lossy()),
+    );
+    let Ok(mut child) = Command::new("sh")
+        .arg("-c")
+        .arg(script)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return false;
+    };
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => return true,
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(2)),
+            Ok(None) => {
+                // Reap it when it exits, for long-lived processes; a render exits first
+                std::thread::spawn(move || child.wait());
+                return false;
+            }
+            Err(_) => return false,
+        }
+    }
+}
+
+/// Without `sh` the command runs in the render and is killed after `timeout`
+#[cfg(not(unix))]
+fn refresh_custom(
+    command: &str,
+    dir: &str,
+    cache_path: &Path,
+    now: u64,
+    timeout: Duration,
+) -> bool {
+    let Some(output) = run_command_with_timeout(command, dir, timeout) else {
+        return false;
+    };
+    write_custom_cache(cache_path, now, output.lines().next().unwrap_or("").trim());
+    true
+}
+
+/// Get a custom segment's text, using the cache when fresh
+/// The entry is claimed before the command runs, so a slow or failing command
+/// isn't rerun until the TTL passes; until it finishes, the last output is shown
+fn custom_segment_text(name: &str, segment: &CustomSegment, dir: &str) -> Option<String> {
+    let cache_path = get_custom_cache_path(name, segment, dir);
+    let now = sys::now_secs();
+
+    let cached = read_custom_cache(&cache_path);
+    if let Some((timestamp, text)) = &cached
+        && now.saturating_sub(*timestamp) < segment.ttl
+    {
+        return Some(text.clone()).filter(|t| !t.is_empty());
+    }
+
+    let stale = cached.map(|(_, text)| text).unwrap_or_default();
+    write_custom_cache(&cache_path, now, &stale);
+    let timeout = Duration::from_millis(segment.timeout_ms);
+    let text = if refresh_custom(&segment.command, dir, &cache_path, now, timeout) {
+        read_custom_cache(&cache_path).map_or(stale, |(_, text)| text)
+    } else {
+        stale
+    };
+
+    Some(text).filter(|t| !t.is_empty())
+}
+
+fn render_custom(name: &str, segment: &CustomSegment, ctx: &StatusLine) -> Option<Vec<Span>> {
+    let text = custom_segment_text(name, segment, &ctx.current_dir)?;
+    let color = segment.color.as_deref().and_then(parse_color);
+    Some(vec![Span::new(text, color.unwrap_or(TN_GRAY))])
+}
+
+/// Render a Lua plugin segment; errors are reported on stderr and the segment is skipped
+#[cfg(feature = "plugins")]
+fn render_plugin(name: &str, ctx: &StatusLine) -> Option<Vec<Span>> {
+    let plugin_ctx = serde_json::json!({
+        "input": ctx.data,
+        "cwd": ctx.current_dir,
+        "project": ctx.project_name,
+        "package": ctx.package,
+        "git": {
+            "branch": ctx.branch(),
+            "worktree": ctx.worktree(),
+            "files": ctx.git_stats.files,
+            "lines_added": ctx.git_stats.lines.map(|(a, _)| a),
+            "lines_deleted": ctx.git_stats.lines.map(|(_, d)| d),
+            "ahead": ctx.git_stats.ahead_behind.map(|(a, _)| a),
+            "behind": ctx.git_stats.ahead_behind.map(|(_, b)| b),
+            "conflicts": ctx.git_stats.conflicts,
+        },
+        "pr": ctx.pr_data.as_ref().map(|pr| serde_json::json!({
+            "number": pr.number,
+            "state": pr.state,
+            "url": pr.url,
+            "comments": pr.comments,
+            "changed_files": pr.changed_files,
+            "check_status": pr.check_status,
+            "created_at": pr.created_at,
+            "auto_merge": pr.auto_merge,
+            "review_decision": pr.review_decision,
+            "labels": pr.labels,
+            "mergeable": pr.mergeable,
+            "deployment": pr.deployment.as_ref().map(|d| serde_json::json!({
+                "environment": d.environment,
+                "state": d.state,
+            })),
+        })),
+    });
+    let timeout_ms = ctx.config.plugin_timeout_ms.unwrap_or(PLUGIN_TIMEOUT_MS);
+    let budget = Duration::from_millis(timeout_ms);
+
+    match plugins::render(&get_plugins_dirs(), name, &plugin_ctx, budget) {
+        Ok(Some(segment)) => {
+            let color = segment.color.as_deref().and_then(parse_color);
+            Some(vec![Span::new(segment.text, color.unwrap_or(TN_GRAY))])
+        }
+        Ok(None) => None,
+        Err(e) => {
+            eprintln!("cc-statusline: plugin '{name}': {e}");
+            None
+        }
+    }
+}
+
+/// Without the plugins feature, unknown components are ignored silently
+#[cfg(not(feature = "plugins"))]
+fn render_plugin(_name: &str, _ctx: &StatusLine) -> Option<Vec<Span>> {
+    None
+}
+
+// ============================================================================
+// Segment data model
+// ============================================================================
+
+/// 24-bit foreground color, written as an ANSI truecolor escape
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\x1b[38;2;{};{};{}m", self.r, self.g, self.b)
+    }
+}
+
+impl Theme {
+    /// The theme's color for a built-in good (green), bad (red), or pending (orange) color
+    fn color(self, color: Color) -> Color {
+        let (good, bad, pending) = match self {
+            Theme::TokyoNight => return color,
+            Theme::Deuteranopia => (CB_BLUE, CB_VERMILLION, CB_GRAY),
+            Theme::Protanopia => (CB_BLUE, CB_YELLOW, CB_GRAY),
+        };
+        match color {
+            TN_GREEN => good,
+            TN_RED => bad,
+            TN_ORANGE => pending,
+            other => other,
+        }
+    }
+}
+
+/// A run of text with an optional color and OSC 8 hyperlink
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub color: Option<Color>,
+    pub link: Option<String>,
+}
+
+impl Span {
+    /// Colored text
+    pub fn new(text: impl Into<String>, color: Color) -> Self {
+        Self {
+            text: text.into(),
+            color: Some(color),
+            link: None,
+        }
+    }
+
+    /// Uncolored text
+    pub fn plain(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            color: None,
+            link: None,
+        }
+    }
+
+    /// Wrap the span in a hyperlink to `url`
+    #[must_use]
+    pub fn with_link(mut self, url: impl Into<String>) -> Self {
+        self.link = Some(url.into());
+        self
+    }
+    /// Escape control characters in the text and percent-encode them in the link
+    fn sanitize(&mut self) {
+        if let Cow::Owned(text) = escape_controls(&self.text) {
+            self.text = text;
+        }
+        if let Some(link) = self
+            .link
+            .as_mut()
+            .filter(|l| l.chars().any(char::is_control))
+        {
+            *link = link
+                .chars()
+                .map(|c| {
+                    let mut buf = [0; 4];
+                    if c.is_control() {
+                        percent_encode(c.encode_utf8(&mut buf))
+                    } else {
+                        c.to_string()
+                    }
+                })
+                .collect();
+        }
+    }
+}
+
+/// Writes the span with ANSI escapes, as it appears in the terminal
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(link) = &self.link {
+            write!(f, "{OSC8_START}{link}{OSC8_MID}")?;
+        }
+        match self.color {
+            Some(color) => write!(f, "{color}{}{RESET}", self.text)?,
+            None => f.write_str(&self.text)?,
+        }
+        if self.link.is_some() {
+            f.write_str(OSC8_END)?;
+        }
+        Ok(())
+    }
+}
+
+/// One rendered component: its name from the config and the spans it produced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub name: String,
+    pub spans: Vec<Span>,
+}
+
+impl Segment {
+    /// Text content without colors or links
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|s| s.text.as_str()).collect()
+    }
+}
+
+impl Segment {
+    /// Columns the text takes in the terminal
+    pub fn width(&self) -> usize {
+        self.spans.iter().map(|s| text_width(&s.text)).sum()
+    }
+
+    /// Cut the text to `width` columns, ending it with "…"
+    fn truncate(&mut self, width: usize) {
+        let mut left = width.saturating_sub(1);
+        let mut kept = Vec::new();
+        for mut span in self.spans.drain(..) {
+            let span_width = text_width(&span.text);
+            if span_width <= left {
+                left -= span_width;
+                kept.push(span);
+                continue;
+            }
+            let mut text = String::new();
+            for c in span.text.chars() {
+                let w = char_width(c);
+                if w > left {
+                    break;
+                }
+                left -= w;
+                text.push(c);
+            }
+            text.push('…');
+            span.text = text;
+            kept.push(span);
+            break;
+        }
+        self.spans = kept;
+    }
+}
+
+/// Terminal columns of `c`: 2 for East Asian wide characters and emoji, 0 for joiners
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x200B..=0x200D | 0xFE00..=0xFE0F => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F680..=0x1F6FF
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+/// Terminal columns of `text`, which has no escape sequences
+fn text_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// Writes all spans with ANSI escapes
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.spans.iter().try_for_each(|span| write!(f, "{span}"))
+    }
+}
+
+// ============================================================================
+// Config-driven rendering
+// ============================================================================
+
+/// A status line for one input, with git and PR data collected up front
+///
+/// Build with [`StatusLine::builder`], then [`render`](StatusLine::render) the
+/// ANSI rows or walk the [`rows`](StatusLine::rows) data model directly.
+pub struct StatusLine {
+    data: ClaudeInput,
+    config: Config,
+    git: Option<GitRepo>,
+    // Sapling or SVN checkout, when there is no git repo
+    vcs: Option<VcsInfo>,
+    current_dir: String,
+    // Cached computed values
+    project_name: String,
+    // Monorepo sub-package containing the cwd (package component only)
+    package: Option<String>,
+    display_cwd: String,
+    // Repo name when display_cwd is relative to the repo root (repo path mode)
+    path_root: Option<String>,
+    hostname: Option<&'static String>,
+    // Git stats (computed lazily via Option)
+    git_stats: GitStats,
+    stats_pending: bool,
+    // PR data (computed lazily)
+    pr_data: Option<PrInfo>,
+    pr_pending: bool,
+    // Why the last PR refresh failed (pr_access component or debug logging only)
+    pr_error: Option<PrError>,
+    // Newer release from the daily update check (update component only)
+    latest_version: Option<String>,
+    // Latest release tag and commits on HEAD since it (release component only)
+    release: Option<(String, Option<u32>)>,
+    // Open Dependabot alerts, like "3" or "100+" (vulns component only)
+    alerts: Option<String>,
+    // Unread GitHub notifications, like "3" or "50+" (notifications component only)
+    notifications: Option<String>,
+    // Latest build on the configured CI service (`ci` config only)
+    ci: Option<CiStatus>,
+    // Gerrit change of HEAD's Change-Id (change component only)
+    gerrit_change: Option<GerritChange>,
+    // Whether HEAD is signed and signing is configured (signed component only)
+    signing: Option<Signing>,
+    // Missing LFS objects or git-lfs itself (lfs component only)
+    lfs: Option<LfsStatus>,
+    // Transcript totals (transcript, tools, and last_tool components only)
+    transcript: Option<TranscriptStats>,
+    // Cost and tokens used in the budget period (budget component only)
+    budget_usage: Option<(f64, u64)>,
+    // Seconds since the session's usage last changed (idle component only)
+    idle_secs: Option<u64>,
+    // Unix seconds an earlier session was last in this project (last_visit component only)
+    last_visit: Option<u64>,
+    // Unix seconds of the HEAD commit, when there are unpushed commits (`unpushed_after` only)
+    head_commit_at: Option<u64>,
+    // Collectors that missed the deadline
+    background: Vec<JoinHandle<()>>,
+}
+
+/// Builder for [`StatusLine`]
+pub struct StatusLineBuilder {
+    data: ClaudeInput,
+    cwd: Option<String>,
+    config: Option<Config>,
+}
+
+impl StatusLineBuilder {
+    /// Directory to inspect for git state; defaults to the input's cwd,
+    /// then workspace dirs, then the process working directory
+    #[must_use]
+    pub fn cwd(mut self, dir: impl Into<String>) -> Self {
+        self.cwd = Some(dir.into());
+        self
+    }
+
+    /// Config to render with; defaults to the built-in rows (the config file is not read)
+    #[must_use]
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Discover the git repo and collect the data the config needs
+    pub fn build(self) -> StatusLine {
+        trace_span!("build");
+        let data = self.data;
+        let config = self.config.unwrap_or_default();
+        let current_dir = render_dir(self.cwd, &data, &config);
+
+        // Skip filesystem detection if JSON provides git.branch
+        let git = if data.git.branch.is_some() {
+            None
+        } else {
+            get_git_repo(&current_dir)
+        };
+
+        let vcs = if git.is_none() && data.git.branch.is_none() {
+            vcs::detect(&current_dir)
+        } else {
+            None
+        };
+
+        let project_name = data
+            .workspace
+            .project_dir
+            .as_ref()
+            .and_then(|p| Path::new(p).file_name())
+            .map(display_os)
+            .unwrap_or_default();
+
+        let package = git
+            .as_ref()
+            .filter(|_| config.uses_any(&["package"]) || config.conditions_reference(&["package"]))
+            .and_then(|g| package::package_name(&current_dir, &g.work_dir));
+
+        let repo_relative = git
+            .as_ref()
+            .filter(|_| config.path_mode == Some(PathMode::Repo))
+            .and_then(|g| {
+                let root = Path::new(&g.work_dir);
+                let rel = Path::new(&current_dir).strip_prefix(root).ok()?;
+                let name = display_os(root.file_name()?);
+                Some((name, display_os(rel.as_os_str())))
+            });
+        let home = get_home();
+        let (display_cwd, path_root) = match repo_relative {
+            Some((name, rel)) => (rel, Some(name)),
+            None if !home.is_empty() && current_dir.starts_with(&*home) => {
+                (format!("~{}", &current_dir[home.len()..]), None)
+            }
+            None => (current_dir.clone(), None),
+        };
+
+        let hostname = if is_ssh_session() {
+            get_hostname()
+        } else {
+            None
+        };
+
+        // Collect git stats and PR data concurrently, unless JSON overrides them
+        // or the directory is ignored (branch-only mode)
+        let ignored = config.is_ignored(&current_dir)
+            || git.as_ref().is_some_and(|g| config.is_ignored(&g.work_dir));
+        let want_stats = !ignored
+            && data.git.branch.is_none()
+            && (config.uses_any(&["files", "lines", "ahead_behind", "conflicts", "index_stats"])
+                || config.conditions_reference(&[
+                    "files",
+                    "conflicts",
+                    "lines_added",
+                    "lines_deleted",
+                    "ahead",
+                    "behind",
+                ]));
+        let want_pr = !ignored
+            && data.pr.number.is_none()
+            && (config.uses_any(PR_COMPONENTS)
+                || config.uses_any(&["deployment"])
+                || config.notify_checks
+                || config.conditions_reference(&["pr", "pr_state", "checks"]));
+        let started = Instant::now();
+        let deadline =
+            started + Duration::from_millis(config.deadline_ms.unwrap_or(DEFAULT_DEADLINE_MS));
+        let cold_deadline = started
+            + Duration::from_millis(config.cold_deadline_ms.unwrap_or(DEFAULT_COLD_DEADLINE_MS));
+        let offline = config.is_offline();
+        let pr_source = want_pr.then(|| PrSource {
+            api: api_base_url(config.github_api_url.as_deref()),
+            remote: config.pr_remote.clone(),
+            offline,
+            tls: config.tls_options(),
+            deployments: config.uses_any(&["deployment"]),
+            gitea: config.gitea.clone(),
+            token_cache_secs: config.token_cache_secs(),
+        });
+        let mut collected = match &git {
+            Some(g) if want_stats || want_pr => collect(
+                g,
+                want_stats,
+                config.git_mode.unwrap_or_default().for_slow_fs(g.slow_fs),
+                config.max_files,
+                pr_source.as_ref(),
+                deadline,
+                cold_deadline,
+            ),
+            _ => Collected::default(),
+        };
+
+        if data.git.branch.is_some() {
+            // Using JSON input
+            collected.git_stats = GitStats {
+                files: Some(data.git.changed_files.unwrap_or(0)),
+                lines: data.git.lines_added.or(data.git.lines_deleted).map(|_| {
+                    (
+                        data.git.lines_added.unwrap_or(0),
+                        data.git.lines_deleted.unwrap_or(0),
+                    )
+                }),
+                ahead_behind: Some((data.git.ahead.unwrap_or(0), data.git.behind.unwrap_or(0))),
+                conflicts: None,
+                index: None,
+                files_capped: false,
+                dirty_only: false,
+            };
+        } else if !ignored && let Some(v) = &vcs {
+            collected.git_stats.files = v.changed_files;
+        }
+        let pr_error = git
+            .as_ref()
+            .filter(|_| {
+                pr_source.is_some()
+                    && (config.uses_any(&["pr_access"])
+                        || sys::var("CC_STATUSLINE_DEBUG").is_some())
+            })
+            .and_then(pr::last_error);
+        collected
+            .background
+            .extend(cache::prune_if_due(config.cache_max_bytes()));
+
+        let mut latest_version = None;
+        if config.uses_any(&["update"]) {
+            let (newer, refresh) = update::check_cached(offline, &config.tls_options());
+            latest_version = newer;
+            collected.background.extend(refresh);
+        }
+
+        let repo_source = git
+            .as_ref()
+            .filter(|_| {
+                !ignored
+                    && (config.uses_any(&["release", "vulns"])
+                        || config.uses_any(&["notifications"]) && config.notifications_repo_only)
+            })
+            .and_then(|g| {
+                Some(RepoSource {
+                    api: api_base_url(config.github_api_url.as_deref()),
+                    target: resolve_target(&g.repo, &g.branch, config.pr_remote.as_deref())?,
+                    offline,
+                    tls: config.tls_options(),
+                    token_cache_secs: config.token_cache_secs(),
+                })
+            });
+        let (mut release, mut alerts) = (None, None);
+        if let (Some(g), Some(source)) = (&git, &repo_source) {
+            if config.uses_any(&["release"]) {
+                let (tag, refresh) = repo_status::latest_release(source);
+                release = tag.map(|tag| {
+                    let ahead = git::commits_since_tag(&g.repo, &tag);
+                    (tag, ahead)
+                });
+                collected.background.extend(refresh);
+            }
+            if config.uses_any(&["vulns"]) {
+                let (count, refresh) = repo_status::open_alerts(source);
+                alerts = count;
+                collected.background.extend(refresh);
+            }
+        }
+        let mut notifications = None;
+        if config.uses_any(&["notifications"]) {
+            let repo = repo_source.as_ref().map(|s| &s.target);
+            if repo.is_some() || !config.notifications_repo_only {
+                let api = api_base_url(config.github_api_url.as_deref());
+                let repo = repo.filter(|_| config.notifications_repo_only);
+                let (count, refresh) = repo_status::unread_notifications(
+                    &api,
+                    repo,
+                    offline,
+                    &config.tls_options(),
+                    config.token_cache_secs(),
+                );
+                notifications = count;
+                collected.background.extend(refresh);
+            }
+        }
+
+        let mut ci = None;
+        if let (Some(g), Some(ci_config)) = (&git, &config.ci)
+            && !ignored
+            && (config.uses_any(&["pr_checks", "pr_number"])
+                || config.notify_checks
+                || config.conditions_reference(&["checks"]))
+        {
+            let (status, refresh) =
+                ci::build_status(ci_config, &g.branch_ref, offline, &config.tls_options());
+            ci = status;
+            collected.background.extend(refresh);
+        }
+
+        let signing = git
+            .as_ref()
+            .filter(|_| {
+                !ignored
+                    && (config.uses_any(&["signed"]) || config.conditions_reference(&["signed"]))
+            })
+            .and_then(|g| git::head_signing(&g.repo));
+
+        let lfs = git
+            .as_ref()
+            .filter(|_| !ignored && config.uses_any(&["lfs"]))
+            .and_then(lfs::status);
+
+        let mut gerrit_change = None;
+        if let (Some(g), Some(gerrit_config)) = (&git, &config.gerrit)
+            && !ignored
+            && config.uses_any(&["change"])
+            && let Some(change_id) = git::head_change_id(&g.repo)
+        {
+            let (change, refresh) =
+                gerrit::change_status(gerrit_config, &change_id, offline, &config.tls_options());
+            gerrit_change = change;
+            collected.background.extend(refresh);
+        }
+
+        let transcript = data
+            .transcript_path
+            .as_deref()
+            .filter(|_| {
+                config.uses_any(&["transcript", "tools", "last_tool"])
+                    || config.conditions_reference(&[
+                        "turns",
+                        "transcript_size",
+                        "tool_calls",
+                        "last_tool",
+                    ])
+            })
+            .and_then(|p| transcript::stats(Path::new(p)));
+
+        if data.pr.number.is_some() {
+            // Using JSON input
+            collected.pr_data = Some(PrInfo {
+                number: data.pr.number.unwrap_or(0),
+                state: data.pr.state.clone().unwrap_or_default(),
+                url: data.pr.url.clone().unwrap_or_default(),
+                comments: data.pr.comments.unwrap_or(0),
+                changed_files: data.pr.changed_files.unwrap_or(0),
+                check_status: data.pr.check_status.clone().unwrap_or_default(),
+                created_at: data.pr.created_at.unwrap_or(0),
+                auto_merge: data.pr.auto_merge.unwrap_or(false),
+                review_decision: data.pr.review_decision.clone().unwrap_or_default(),
+                labels: data.pr.labels.clone().unwrap_or_default(),
+                mergeable: data.pr.mergeable.clone().unwrap_or_default(),
+                deployment: data.pr.deployment.clone(),
+                stale: false,
+            });
+        } else if !ignored && let Some(pr) = vcs.as_ref().and_then(|v| v.pr.clone()) {
+            // Sapling links the PR to the commit, so no lookup is needed
+            collected.pr_data = Some(pr);
+        }
+        let head_commit_at = git
+            .as_ref()
+            .filter(|_| config.unpushed_after.is_some())
+            .filter(|_| {
+                collected
+                    .git_stats
+                    .ahead_behind
+                    .is_some_and(|(ahead, _)| ahead > 0)
+            })
+            .map(GitRepo::last_commit_at)
+            .filter(|&at| at > 0);
+
+        let mut status = StatusLine {
+            data,
+            config,
+            git,
+            vcs,
+            current_dir,
+            project_name,
+            package,
+            display_cwd,
+            path_root,
+            hostname,
+            git_stats: collected.git_stats,
+            stats_pending: collected.stats_pending,
+            pr_data: collected.pr_data,
+            pr_pending: collected.pr_pending,
+            pr_error,
+            latest_version,
+            release,
+            alerts,
+            notifications,
+            ci,
+            gerrit_change,
+            signing,
+            lfs,
+            transcript,
+            budget_usage: None,
+            idle_secs: None,
+            last_visit: None,
+            head_commit_at,
+            background: collected.background,
+        };
+        if status.config.notify_checks
+            && let (Some(g), Some(pr), Some((checks, _))) =
+                (&status.git, &status.pr_data, status.checks())
+            && !pr.stale
+        {
+            notify::check_transition(&g.git_dir, &g.branch, &format!("#{}", pr.number), checks);
+        }
+        if status.config.notify_compact
+            && let (Some(session_id), Some(remaining)) = (
+                &status.data.session_id,
+                status.data.context_window.remaining_percentage,
+            )
+        {
+            notify::compact_transition(session_id, status.compact_soon(), remaining);
+        }
+        let budget = status
+            .config
+            .budget
+            .as_ref()
+            .filter(|_| status.config.uses_any(&["budget"]));
+        if let Some(budget) = budget {
+            status.budget_usage = Some(stats::period_usage(&status.data, budget.period));
+        }
+        if status.config.uses_any(&["idle"]) {
+            status.idle_secs = stats::idle_secs(&status.data);
+        }
+        if status.config.uses_any(&["last_visit"]) {
+            status.last_visit =
+                history::previous_visit(status.project_dir(), status.data.session_id.as_deref());
+        }
+        let recording = status.record_usage();
+        status.background.extend(recording);
+        status
+    }
+}
+
+impl StatusLine {
+    pub fn builder(input: ClaudeInput) -> StatusLineBuilder {
+        StatusLineBuilder {
+            data: input,
+            cwd: None,
+            config: None,
+        }
+    }
+
+    /// Why the last PR lookup failed, with a hint for access problems
+    pub fn pr_problem(&self) -> Option<String> {
+        self.pr_error.as_ref().map(ToString::to_string)
+    }
+
+    /// Rendered segments per row; rows with nothing to show are omitted
+    pub fn rows(&self) -> Vec<Vec<Segment>> {
+        self.config
+            .rows
+            .iter()
+            .map(|row| {
+                self.row_components(row)
+                    .iter()
+                    .filter(|name| self.condition_allows(name))
+                    .filter_map(|name| {
+                        let mut spans = match self.config.custom.get(name) {
+                            Some(segment) => render_custom(name, segment, self),
+                            None if COMPONENTS.contains(&name.as_str()) => {
+                                render_component(name, self)
+                            }
+                            None => render_plugin(name, self),
+                        }?;
+                        // Branches, PR fields, input, and command output are untrusted
+                        for span in &mut spans {
+                            span.sanitize();
+                        }
+                        if let Some(theme) = self.config.theme {
+                            for span in &mut spans {
+                                span.color = span.color.map(|c| theme.color(c));
+                            }
+                        }
+                        Some(Segment {
+                            name: name.clone(),
+                            spans,
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .map(|mut row| {
+                if let Some(width) = self.max_width() {
+                    self.fit(&mut row, width);
+                }
+                row
+            })
+            .filter(|row| !row.is_empty())
+            .collect()
+    }
+
+    /// Columns rows must fit in: `max_width`, else `COLUMNS`
+    fn max_width(&self) -> Option<usize> {
+        self.config.max_width.or_else(|| {
+            sys::var("COLUMNS")
+                .and_then(|c| c.trim().parse().ok())
+                .filter(|c| *c > 0)
+        })
+    }
+
+    /// Whether remaining context is below the `compact_warning` threshold
+    fn compact_soon(&self) -> bool {
+        let threshold = self.config.compact_warning.unwrap_or(COMPACT_WARNING);
+        self.data
+            .context_window
+            .remaining_percentage
+            .is_some_and(|remaining| remaining < threshold)
+    }
+
+    /// Context limit of the model: the config's, the input's, else the built-in one
+    /// The most specific (longest) matching `context_limits` pattern wins
+    fn context_limit(&self) -> Option<u64> {
+        let id = self.data.model.id.as_deref().unwrap_or_default();
+        self.config
+            .context_limits
+            .iter()
+            .filter(|(pattern, _)| glob_match(pattern, id))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, limit)| *limit)
+            .or(self.data.context_window.context_window_size)
+            .or_else(|| {
+                CONTEXT_LIMITS
+                    .iter()
+                    .find(|(pattern, _)| glob_match(pattern, id))
+                    .map(|(_, limit)| *limit)
+            })
+            .filter(|&limit| limit > 0)
+    }
+
+    /// Elision priority of a component: the config's, else the built-in one
+    fn priority(&self, name: &str) -> i32 {
+        self.config
+            .priorities
+            .get(name)
+            .copied()
+            .unwrap_or_else(|| {
+                PRIORITIES
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .map_or(DEFAULT_PRIORITY, |(_, p)| *p)
+            })
+    }
+
+    /// Truncate or drop the lowest-priority segments (rightmost first on ties)
+    /// until `row` fits in `width`, so the terminal never wraps mid-escape
+    fn fit(&self, row: &mut Vec<Segment>, width: usize) {
+        loop {
+            let total = row.iter().map(Segment::width).sum::<usize>()
+                + SEP_WIDTH * row.len().saturating_sub(1);
+            if total <= width {
+                return;
+            }
+            let Some(lowest) =
+                (0..row.len()).min_by_key(|&i| (self.priority(&row[i].name), std::cmp::Reverse(i)))
+            else {
+                return;
+            };
+            let over = total - width;
+            let segment_width = row[lowest].width();
+            if segment_width >= over + MIN_SEGMENT_WIDTH {
+                row[lowest].truncate(segment_width - over);
+                return;
+            }
+            if row.len() == 1 {
+                row[0].truncate(width.max(1));
+                return;
+            }
+            row.remove(lowest);
+        }
+    }
+
+    /// Component names of `row` after applying `pr_verbosity`
+    /// Compact keeps only `pr_number`; detailed adds the detail components after the last PR one
+    fn row_components<'a>(&self, row: &'a [String]) -> Cow<'a, [String]> {
+        let is_pr = |name: &String| PR_COMPONENTS.contains(&name.as_str());
+        match self.config.pr_verbosity.unwrap_or_default() {
+            PrVerbosity::Normal => Cow::Borrowed(row),
+            PrVerbosity::Compact => row
+                .iter()
+                .filter(|name| !is_pr(name) || *name == "pr_number")
+                .cloned()
+                .collect(),
+            PrVerbosity::Detailed => {
+                let mut names = row.to_vec();
+                if let Some(last) = row.iter().rposition(is_pr) {
+                    let extra = PR_DETAIL_COMPONENTS
+                        .iter()
+                        .filter(|d| !row.iter().any(|name| name == *d))
+                        .map(ToString::to_string);
+                    names.splice(last + 1..last + 1, extra);
+                }
+                Cow::Owned(names)
+            }
+        }
+    }
+
+    /// Write all rows as ANSI-colored lines
+    pub fn render<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        trace_span!("render");
+        if let Some(title) = self.window_title() {
+            write!(out, "{OSC_TITLE_START}{title}{OSC8_MID}")?;
+        }
+        if let Some((state, value)) = self.progress() {
+            write!(out, "{OSC_PROGRESS_START}{state};{value}{OSC8_MID}")?;
+        }
+        // Straight into the writer; no line is built up first
+        for row in self.rows() {
+            for (i, segment) in row.iter().enumerate() {
+                if i > 0 {
+                    out.write_all(SEP.as_bytes())?;
+                }
+                write!(out, "{segment}")?;
+            }
+            out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// OSC 9;4 state and percent for the configured `progress`
+    /// States: 0 clear, 1 normal, 2 error, 3 indeterminate, 4 warning
+    pub fn progress(&self) -> Option<(u8, u8)> {
+        Some(match self.config.progress? {
+            ProgressSource::Context => match self.data.context_window.remaining_percentage {
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                Some(remaining) => {
+                    let used = (100.0 - remaining).clamp(0.0, 100.0).round() as u8;
+                    let state = if remaining < PROGRESS_WARN_REMAINING {
+                        4
+                    } else {
+                        1
+                    };
+                    (state, used)
+                }
+                None => (0, 0),
+            },
+            ProgressSource::Checks => match self.checks().map(|(status, _)| status) {
+                Some("pending") => (3, 0),
+                Some("failed") => (2, 100),
+                _ => (0, 0),
+            },
+        })
+    }
+
+    /// `window_title` with each `{name}` replaced by that condition variable
+    /// Unknown or missing variables are left empty; control characters are dropped
+    pub fn window_title(&self) -> Option<String> {
+        let template = self.config.window_title.as_deref()?;
+        let mut title = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            title.push_str(&rest[..start]);
+            let Some(len) = rest[start..].find('}') else {
+                rest = &rest[start..];
+                break;
+            };
+            match rules::Context::var(self, &rest[start + 1..start + len]) {
+                Value::Str(s) => title.push_str(&s),
+                Value::Number(n) => {
+                    let _ = write!(title, "{n}");
+                }
+                Value::Bool(b) => {
+                    let _ = write!(title, "{b}");
+                }
+                Value::Null => {}
+            }
+            rest = &rest[start + len + 1..];
+        }
+        title.push_str(rest);
+        title.retain(|c| !c.is_control());
+        Some(title)
+    }
+
+    /// Git state from the input JSON or the repository at the working directory
+    pub fn git_info(&self) -> Option<GitInfo> {
+        Some(GitInfo {
+            branch: self.branch()?.to_string(),
+            worktree: self.worktree().map(str::to_string),
+            changed_files: self.git_stats.files,
+            lines_added: self.git_stats.lines.map(|(a, _)| a),
+            lines_deleted: self.git_stats.lines.map(|(_, d)| d),
+            ahead: self.git_stats.ahead_behind.map(|(a, _)| a),
+            behind: self.git_stats.ahead_behind.map(|(_, b)| b),
+        })
+    }
+
+    /// Pull request for the current branch, if known
+    pub fn pr_info(&self) -> Option<&PrInfo> {
+        self.pr_data.as_ref()
+    }
+
+    /// Wait for collectors that missed the render deadline
+    /// Short-lived callers should call this after output is flushed so slow
+    /// lookups still finish writing their caches before the process exits
+    pub fn wait_for_background(&mut self) {
+        for handle in self.background.drain(..) {
+            let _ = handle.join();
+        }
+    }
+
+    /// Hand over collectors that missed the render deadline, for a long-lived
+    /// caller to reap as they finish instead of waiting on them
+    pub fn take_background(&mut self) -> Vec<JoinHandle<()>> {
+        std::mem::take(&mut self.background)
+    }
+
+    /// Append the session's usage and the project visit on their own thread, which
+    /// joins the collectors waited on after the output is released; both read and
+    /// rewrite files, so they stay off the render path
+    fn record_usage(&self) -> Option<JoinHandle<()>> {
+        // One at a time, so a long-lived process's renders don't lose each other's writes
+        static RECORDING: Mutex<()> = Mutex::new(());
+
+        let budget = self.config.budget.is_some() && self.config.uses_any(&["budget"]);
+        let usage = (self.config.session_stats || budget).then(|| self.data.clone());
+        let visit = (self.config.project_history || self.config.uses_any(&["last_visit"]))
+            .then(|| (self.project_dir().to_string(), self.data.session_id.clone()));
+        if usage.is_none() && visit.is_none() {
+            return None;
+        }
+        Some(std::thread::spawn(move || {
+            let _serial = RECORDING.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(data) = usage {
+                stats::record(&data);
+            }
+            if let Some((dir, session)) = visit {
+                history::record(&dir, session.as_deref());
+            }
+        }))
+    }
+
+    /// Directory project history is kept under: the work tree, else the current dir
+    fn project_dir(&self) -> &str {
+        self.git.as_ref().map_or(&self.current_dir, |g| &g.work_dir)
+    }
+
+    /// Check status and its page: the CI service's latest build, else the PR's checks
+    fn checks(&self) -> Option<(&str, Option<String>)> {
+        if let Some(ci) = &self.ci {
+            return Some((ci.status.as_str(), ci.url.clone()));
+        }
+        let pr = self.pr_data.as_ref()?;
+        let url = Some(format!("{}/checks", pr.url)).filter(|_| !pr.url.is_empty());
+        Some((pr.check_status.trim(), url))
+    }
+
+    fn branch(&self) -> Option<&str> {
+        self.data
+            .git
+            .branch
+            .as_deref()
+            .or_else(|| self.git.as_ref().map(|g| g.branch.as_str()))
+            .or_else(|| self.vcs.as_ref().map(VcsInfo::label))
+    }
+
+    fn worktree(&self) -> Option<&str> {
+        self.data
+            .git
+            .worktree
+            .as_deref()
+            .or_else(|| self.git.as_ref().and_then(|g| g.worktree.as_deref()))
+    }
+
+    /// Check a component's condition rule; components without a rule always render
+    /// Invalid rules were reported when the config loaded and are treated as true
+    fn condition_allows(&self, name: &str) -> bool {
+        match self.config.parsed_conditions().get(name) {
+            Some(Some(expr)) => expr.eval(self),
+            _ => true,
+        }
+    }
+}
+
+/// Expose collected data to `conditions` rules
+impl rules::Context for StatusLine {
+    fn var(&self, name: &str) -> Value {
+        let num = |n: Option<f64>| n.map_or(Value::Null, Value::Number);
+        let text = |s: Option<&str>| s.map_or(Value::Null, |s| Value::Str(s.to_string()));
+        let cw = &self.data.context_window;
+        #[allow(clippy::cast_precision_loss)] // counts are far below 2^52
+        match name {
+            "tokens" => match (cw.total_input_tokens, cw.total_output_tokens) {
+                (None, None) => Value::Null,
+                (i, o) => Value::Number((i.unwrap_or(0) + o.unwrap_or(0)) as f64),
+            },
+            "input_tokens" => num(cw.total_input_tokens.map(|n| n as f64)),
+            "output_tokens" => num(cw.total_output_tokens.map(|n| n as f64)),
+            "context" => num(cw.remaining_percentage),
+            "cost" => num(self.data.cost.total_cost_usd),
+            "duration" => num(self
+                .data
+                .cost
+                .total_duration_ms
+                .map(|ms| (ms / 1000) as f64)),
+            "api_duration" => num(self
+                .data
+                .cost
+                .total_api_duration_ms
+                .map(|ms| (ms / 1000) as f64)),
+            "turns" => num(self.transcript.as_ref().map(|t| f64::from(t.turns))),
+            "transcript_size" => num(self.transcript.as_ref().map(|t| t.bytes as f64)),
+            "tool_calls" => num(self.transcript.as_ref().map(|t| f64::from(t.tool_calls))),
+            "last_tool" => text(
+                self.transcript
+                    .as_ref()
+                    .and_then(|t| t.last_tool.as_deref()),
+            ),
+            "files" => num(self.git_stats.files.map(f64::from)),
+            "conflicts" => num(self.git_stats.conflicts.map(f64::from)),
+            "lines_added" => num(self.git_stats.lines.map(|(a, _)| f64::from(a))),
+            "lines_deleted" => num(self.git_stats.lines.map(|(_, d)| f64::from(d))),
+            "ahead" => num(self.git_stats.ahead_behind.map(|(a, _)| f64::from(a))),
+            "behind" => num(self.git_stats.ahead_behind.map(|(_, b)| f64::from(b))),
+            "branch" => text(self.branch()),
+            "worktree" => text(self.worktree()),
+            "model" => text(self.data.model.display_name.as_deref()),
+            "style" => text(self.data.output_style.name.as_deref()),
+            "project" => text(Some(self.project_name.as_str()).filter(|p| !p.is_empty())),
+            "package" => text(self.package.as_deref()),
+            "path" => Value::Str(self.current_dir.clone()),
+            "dirs" => Value::Number(self.data.workspace.added_dirs.len() as f64),
+            "ssh" => Value::Bool(self.hostname.is_some()),
+            "signed" => self
+                .signing
+                .map_or(Value::Null, |s| Value::Bool(s.head_signed)),
+            "pr" => num(self.pr_data.as_ref().map(|p| f64::from(p.number))),
+            "pr_state" => text(self.pr_data.as_ref().map(|p| p.state.as_str())),
+            "checks" => text(self.checks().map(|(status, _)| status)),
+            _ => Value::Null,
+        }
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        let home = get_home();
+        let path = match path.strip_prefix("~/") {
+            Some(rest) if !home.is_empty() => Path::new(&*home).join(rest),
+            _ => Path::new(&self.current_dir).join(path),
+        };
+        path.exists()
+    }
+}
+
+/// Coarse age in the largest whole unit, like `45m`, `2h`, or `3d`
+fn format_age(secs: u64) -> String {
+    match secs {
+        ..3600 => format!("{}m", secs / 60),
+        3600..86_400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86_400),
+    }
+}
+
+/// A single-span segment
+fn one(text: impl Into<String>, color: Color) -> Option<Vec<Span>> {
+    Some(vec![Span::new(text, color)])
+}
+
+/// `$8.20/$15` and `1.2M/2.0M` for the configured limits, red once exceeded
+fn budget_spans(budget: &BudgetConfig, cost: f64, tokens: u64) -> Option<Vec<Span>> {
+    let over = |exceeded: bool| if exceeded { TN_RED } else { TN_GRAY };
+    let mut spans = Vec::new();
+    if let Some(limit) = budget.cost {
+        let limit_text = if limit.fract() == 0.0 {
+            format!("${limit:.0}")
+        } else {
+            format!("${limit:.2}")
+        };
+        spans.push(Span::new(
+            format!("${cost:.2}/{limit_text}"),
+            over(cost > limit),
+        ));
+    }
+    if let Some(limit) = budget.tokens {
+        if !spans.is_empty() {
+            spans.push(Span::plain(" "));
+        }
+        spans.push(Span::new(
+            format!("{}/{}", format_tokens(tokens), format_tokens(limit)),
+            over(tokens > limit),
+        ));
+    }
+    (!spans.is_empty()).then_some(spans)
+}
+
+/// Green `+added` and red `-deleted`, leaving out zeros
+fn line_counts(added: u64, deleted: u64, separator: &str) -> Option<Vec<Span>> {
+    let mut spans = Vec::new();
+    if added > 0 {
+        spans.push(Span::new(format!("+{added}"), TN_GREEN));
+    }
+    if deleted > 0 {
+        if added > 0 {
+            spans.push(Span::plain(separator));
+        }
+        spans.push(Span::new(format!("-{deleted}"), TN_RED));
+    }
+    (!spans.is_empty()).then_some(spans)
+}
+
+/// `ms` as `1h 4m`, or `1h4m32s` with seconds
+pub(crate) fn format_duration(ms: u64, format: DurationFormat) -> String {
+    let total_secs = ms / 1000;
+    let (hours, mins, secs) = (total_secs / 3600, total_secs / 60 % 60, total_secs % 60);
+    match format {
+        DurationFormat::Minutes if hours > 0 => format!("{hours}h {mins}m"),
+        DurationFormat::Minutes => format!("{mins}m"),
+        DurationFormat::Seconds if hours > 0 => format!("{hours}h{mins}m{secs}s"),
+        DurationFormat::Seconds if mins > 0 => format!("{mins}m{secs}s"),
+        DurationFormat::Seconds => format!("{secs}s"),
+    }
+}
+
+/// `text` after its state glyph (✓ ✗ ◐) when `status_glyphs` is on
+fn with_glyph(ctx: &StatusLine, glyph: &str, text: &str) -> String {
+    if ctx.config.status_glyphs {
+        format!("{glyph} {text}")
+    } else {
+        text.to_string()
+    }
+}
+
+/// Render a single built-in component, returning its spans or None if no data
+fn render_component(name: &str, ctx: &StatusLine) -> Option<Vec<Span>> {
+    match name {
+        "hostname" => ctx.hostname.and_then(|h| one(h.as_str(), TN_GREEN)),
+
+        "project" => {
+            let style = ctx.config.project_style(&ctx.current_dir);
+            let name = style
+                .and_then(|s| s.label.as_deref())
+                .unwrap_or(&ctx.project_name);
+            if name.is_empty() {
+                return None;
+            }
+            let color = style
+                .and_then(|s| s.color.as_deref())
+                .and_then(parse_color)
+                .unwrap_or(TN_BLUE);
+            match style.and_then(|s| s.emoji.as_deref()) {
+                Some(emoji) => one(format!("{emoji} {name}"), color),
+                None => one(name, color),
+            }
+        }
+
+        "path" => {
+            // Use a conservative width for path abbreviation
+            // Since config allows placing path on any row, we can't know what other
+            // components share the row. Use ~60% of terminal width as a reasonable default.
+            let path_width = (TERM_WIDTH * 3 / 5).max(20);
+            let style = ctx.config.path_style.clone().unwrap_or_default();
+            let abbreviate = |width| {
+                abbreviate_path_with(
+                    &ctx.display_cwd,
+                    width,
+                    &style,
+                    Some(Path::new(&ctx.current_dir)),
+                )
+            };
+            match &ctx.path_root {
+                // The repo name is kept whole; only the part below it is abbreviated
+                Some(root) if ctx.display_cwd.is_empty() => one(root.as_str(), TN_CYAN),
+                Some(root) => {
+                    let width = path_width.saturating_sub(root.len() + 2).max(10);
+                    one(format!("{root}//{}", abbreviate(width)), TN_CYAN)
+                }
+                None => one(abbreviate(path_width), TN_CYAN),
+            }
+        }
+
+        // Directories added to the session beyond the current one
+        "dirs" => match ctx.data.workspace.added_dirs.len() {
+            0 => None,
+            1 => one("+1 dir", TN_GRAY),
+            n => one(format!("+{n} dirs"), TN_GRAY),
+        },
+
+        "package" => ctx
+            .package
+            .as_deref()
+            .and_then(|p| one(format!("pkg: {p}"), TN_BLUE)),
+
+        // Repos on network filesystems run in a degraded git mode
+        "branch" => ctx.branch().map(|b| {
+            let mut spans = if ctx.config.is_protected(&ctx.current_dir, b) {
+                vec![Span::new(format!("⚠ {b}"), TN_ORANGE)]
+            } else {
+                vec![Span::new(b, TN_PURPLE)]
+            };
+            if ctx.git.as_ref().is_some_and(|g| g.slow_fs) {
+                spans.push(Span::new(" (slow fs)", TN_GRAY));
+            }
+            spans
+        }),
+
+        // Shows "no git" when there's no branch (not in a git repo)
+        "no_git" => {
+            if ctx.branch().is_none() {
+                one("no git", TN_GRAY)
+            } else {
+                None
+            }
+        }
+
+        "worktree" => ctx.worktree().and_then(|wt| one(wt, TN_MAGENTA)),
+
+        // Stats that missed the deadline show a placeholder on the files segment
+        "files" if ctx.stats_pending => one(PENDING, TN_GRAY),
+        "files" => {
+            let files = ctx.git_stats.files.unwrap_or(0);
+            if files == 0 {
+                None
+            } else if ctx.git_stats.dirty_only {
+                one("●", TN_GRAY)
+            } else if ctx.git_stats.files_capped {
+                one(format!("{files}+ files"), TN_GRAY)
+            } else {
+                one(format!("{files} files"), TN_GRAY)
+            }
+        }
+
+        // Unmerged paths, apart from the changed-files count
+        "conflicts" => match ctx.git_stats.conflicts? {
+            0 => None,
+            1 => one("✖ 1 conflict", TN_RED),
+            n => one(format!("✖ {n} conflicts"), TN_RED),
+        },
+
+        // Index size and how long the last scan took, e.g. "182K files · 14ms"
+        "index_stats" => {
+            // Caches written before the count was recorded read 0 entries
+            let (entries, ms) = ctx.git_stats.index.filter(|&(entries, _)| entries > 0)?;
+            one(
+                format!("{} files · {ms}ms", format_tokens(u64::from(entries))),
+                TN_GRAY,
+            )
+        }
+
+        // Line counts are only collected in full git mode
+        "lines" => {
+            let (added, deleted) = ctx.git_stats.lines.unwrap_or((0, 0));
+            line_counts(u64::from(added), u64::from(deleted), " ")
+        }
+
+        "ahead_behind" => {
+            let (ahead, behind) = ctx.git_stats.ahead_behind.unwrap_or((0, 0));
+            if ahead > 0 || behind > 0 {
+                let mut spans = Vec::new();
+                if ahead > 0 {
+                    let unpushed = ctx
+                        .head_commit_at
+                        .map(|at| sys::now_secs().saturating_sub(at))
+                        .filter(|&age| {
+                            ctx.config
+                                .unpushed_after
+                                .is_some_and(|minutes| age >= minutes * 60)
+                        });
+                    match unpushed {
+                        Some(age) => spans.push(Span::new(
+                            format!("↑{ahead} ({} unpushed)", format_age(age)),
+                            TN_ORANGE,
+                        )),
+                        None => spans.push(Span::new(format!("↑{ahead}"), TN_GRAY)),
+                    }
+                }
+                if behind > 0 {
+                    if ahead > 0 {
+                        spans.push(Span::plain(" "));
+                    }
+                    spans.push(Span::new(format!("↓{behind}"), TN_GRAY));
+                }
+                Some(spans)
+            } else {
+                None
+            }
+        }
+
+        "pr_number" if ctx.pr_pending => one(PENDING, TN_GRAY),
+        "pr_number" => {
+            let pr = ctx.pr_data.as_ref()?;
+            let span = Span::new(format!("#{}", pr.number), TN_CYAN);
+            let mut spans = if pr.url.is_empty() {
+                vec![span]
+            } else {
+                vec![span.with_link(pr.url.as_str())]
+            };
+            // Compact rows fold the check status into a mark
+            if ctx.config.pr_verbosity == Some(PrVerbosity::Compact) {
+                let mark = match ctx.checks().map_or("", |(status, _)| status) {
+                    "passed" => Some(("✓", TN_GREEN)),
+                    "failed" => Some(("✗", TN_RED)),
+                    "pending" if ctx.config.status_glyphs => Some(("◐", TN_ORANGE)),
+                    "pending" => Some(("●", TN_ORANGE)),
+                    _ => None,
+                };
+                if let Some((mark, color)) = mark {
+                    spans.push(Span::plain(" "));
+                    spans.push(Span::new(mark, color));
+                }
+            }
+            // Expired cache shown while offline
+            if pr.stale {
+                spans.push(Span::new(" (stale)", TN_GRAY));
+            }
+            Some(spans)
+        }
+
+        "pr_state" => {
+            let pr = ctx.pr_data.as_ref()?;
+            if pr.state.is_empty() {
+                return None;
+            }
+            let state_lower = pr.state.to_lowercase();
+            let color = match state_lower.as_str() {
+                "open" => TN_GREEN,
+                "merged" => TN_PURPLE,
+                "closed" => TN_RED,
+                _ => TN_GRAY,
+            };
+            one(state_lower, color)
+        }
+
+        "pr_comments" => {
+            let pr = ctx.pr_data.as_ref()?;
+            if pr.comments > 0 {
+                let label = if pr.comments == 1 {
+                    "comment"
+                } else {
+                    "comments"
+                };
+                one(format!("{} {label}", pr.comments), TN_GRAY)
+            } else {
+                None
+            }
+        }
+
+        "pr_files" => {
+            let pr = ctx.pr_data.as_ref()?;
+            if pr.changed_files > 0 {
+                let label = if pr.changed_files == 1 {
+                    "file"
+                } else {
+                    "files"
+                };
+                one(format!("{} {label}", pr.changed_files), TN_GRAY)
+            } else {
+                None
+            }
+        }
+
+        "pr_checks" => {
+            let (status, url) = ctx.checks()?;
+            let (glyph, text, color) = match status {
+                "passed" => ("✓", "checks passed", TN_GREEN),
+                "failed" => ("✗", "checks failed", TN_RED),
+                "pending" => ("◐", "checks pending", TN_ORANGE),
+                _ => return None,
+            };
+            let span = Span::new(with_glyph(ctx, glyph, text), color);
+            match url {
+                Some(url) => Some(vec![span.with_link(url)]),
+                None => Some(vec![span]),
+            }
+        }
+
+        // Dim marker when the token can't see the repo's PRs
+        "pr_access" => ctx
+            .pr_error
+            .as_ref()
+            .filter(|e| ctx.pr_data.is_none() && e.is_access_denied())
+            .and_then(|_| one("pr: no access", TN_GRAY)),
+
+        // Time since an open PR was created, e.g. "opened 3d ago"
+        "pr_age" => {
+            let pr = ctx.pr_data.as_ref()?;
+            if pr.created_at == 0 || !pr.state.eq_ignore_ascii_case("open") {
+                return None;
+            }
+            let age = format_age(sys::now_secs().saturating_sub(pr.created_at));
+            one(format!("opened {age} ago"), TN_GRAY)
+        }
+
+        "pr_auto_merge" => {
+            let pr = ctx.pr_data.as_ref()?;
+            (pr.auto_merge && pr.state.eq_ignore_ascii_case("open"))
+                .then(|| vec![Span::new("auto-merge armed", TN_GREEN)])
+        }
+
+        "pr_reviews" => {
+            let pr = ctx.pr_data.as_ref()?;
+            let (glyph, text, color) = match pr.review_decision.as_str() {
+                "approved" => ("✓", "approved", TN_GREEN),
+                "changes_requested" => ("✗", "changes requested", TN_RED),
+                "review_required" => ("◐", "review required", TN_ORANGE),
+                _ => return None,
+            };
+            one(with_glyph(ctx, glyph, text), color)
+        }
+
+        // Up to three labels, then a count of the rest
+        "pr_labels" => {
+            let pr = ctx.pr_data.as_ref()?;
+            if pr.labels.is_empty() {
+                return None;
+            }
+            let mut text = pr
+                .labels
+                .iter()
+                .take(3)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ");
+            if pr.labels.len() > 3 {
+                let _ = write!(text, " +{}", pr.labels.len() - 3);
+            }
+            one(text, TN_GRAY)
+        }
+
+        "pr_mergeable" => {
+            let pr = ctx.pr_data.as_ref()?;
+            if !pr.state.eq_ignore_ascii_case("open") {
+                return None;
+            }
+            match pr.mergeable.as_str() {
+                "mergeable" => one(with_glyph(ctx, "✓", "mergeable"), TN_GREEN),
+                "conflicting" => one(with_glyph(ctx, "✗", "conflicts"), TN_RED),
+                _ => None,
+            }
+        }
+
+        // Latest deployment of the branch, e.g. "🚀 staging ✓"
+        "deployment" => {
+            let deployment = ctx.pr_data.as_ref()?.deployment.as_ref()?;
+            let (mark, color) = match deployment.state.as_str() {
+                "success" => ("✓", TN_GREEN),
+                "failure" | "error" => ("✗", TN_RED),
+                "inactive" => ("inactive", TN_GRAY),
+                _ if ctx.config.status_glyphs => ("◐", TN_ORANGE),
+                _ => ("pending", TN_ORANGE),
+            };
+            Some(vec![
+                Span::new(format!("🚀 {}", deployment.environment), TN_GRAY),
+                Span::plain(" "),
+                Span::new(mark, color),
+            ])
+        }
+
+        // Latest release and how far HEAD has moved past it, e.g. "v3.2.0 +14 commits"
+        "release" => {
+            let (tag, ahead) = ctx.release.as_ref()?;
+            let mut spans = vec![Span::new(tag.as_str(), TN_BLUE)];
+            match ahead {
+                Some(0) | None => {}
+                Some(1) => spans.push(Span::new(" +1 commit", TN_GRAY)),
+                Some(n) => spans.push(Span::new(format!(" +{n} commits"), TN_GRAY)),
+            }
+            Some(spans)
+        }
+
+        // Open Dependabot alerts, e.g. "⚠ 3 vulns"
+        "vulns" => {
+            let count = ctx.alerts.as_deref().filter(|c| *c != "0")?;
+            let label = if count == "1" { "vuln" } else { "vulns" };
+            one(format!("⚠ {count} {label}"), TN_RED)
+        }
+
+        // Unread GitHub notifications, e.g. "🔔 3"
+        "notifications" => {
+            let count = ctx.notifications.as_deref().filter(|c| *c != "0")?;
+            one(format!("🔔 {count}"), TN_ORANGE)
+        }
+
+        // Signed HEAD, or a warning: red when `commit.gpgsign` is off too
+        "signed" => match ctx.signing? {
+            Signing {
+                head_signed: true, ..
+            } => one("🔏", TN_GREEN),
+            Signing { configured, .. } => {
+                one("⚠ unsigned", if configured { TN_ORANGE } else { TN_RED })
+            }
+        },
+
+        "lfs" => match ctx.lfs? {
+            LfsStatus::NotInstalled => one("⚠ git-lfs missing", TN_RED),
+            LfsStatus::Missing(n) => one(format!("lfs: {n} not pulled"), TN_ORANGE),
+        },
+
+        // Gerrit change of HEAD with its review scores, e.g. "4711 CR+2 V+1"
+        "change" => {
+            let change = ctx.gerrit_change.as_ref()?;
+            let mut spans =
+                vec![Span::new(change.number.to_string(), TN_CYAN).with_link(change.url.as_str())];
+            let scores = change.scores();
+            if !scores.is_empty() {
+                let blocked = [change.code_review, change.verified]
+                    .iter()
+                    .any(|s| s.is_some_and(|s| s < 0));
+                let color = if blocked {
+                    TN_RED
+                } else if change.code_review.is_some_and(|s| s >= 2) {
+                    TN_GREEN
+                } else {
+                    TN_GRAY
+                };
+                spans.push(Span::plain(" "));
+                spans.push(Span::new(scores, color));
+            }
+            Some(spans)
+        }
+
+        "model" => {
+            if let Some(model) = &ctx.data.model.display_name
+                && model != "Unknown"
+            {
+                return one(model.as_str(), TN_ORANGE);
+            }
+            None
+        }
+
+        "context" => {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let pct = ctx
+                .data
+                .context_window
+                .remaining_percentage
+                .unwrap_or(100.0) as u32;
+            if pct < 100 {
+                one(format!("{pct}%"), TN_TEAL)
+            } else {
+                None
+            }
+        }
+
+        "budget" => {
+            let (budget, (cost, tokens)) = (ctx.config.budget.as_ref()?, ctx.budget_usage?);
+            budget_spans(budget, cost, tokens)
+        }
+
+        "idle" => {
+            let idle = ctx.idle_secs?;
+            let threshold = ctx.config.idle_after.unwrap_or(IDLE_AFTER_MINUTES) * 60;
+            (idle >= threshold).then(|| {
+                let text = format_duration(idle * 1000, DurationFormat::Minutes);
+                vec![Span::new(format!("idle {text}"), TN_GRAY)]
+            })
+        }
+
+        // Time since an earlier session was here, e.g. "last here 3d ago"
+        "last_visit" => {
+            let age = sys::now_secs().saturating_sub(ctx.last_visit?);
+            if age < LAST_VISIT_AFTER_SECS {
+                return None;
+            }
+            one(format!("last here {} ago", format_age(age)), TN_GRAY)
+        }
+
+        "compact_warning" if ctx.compact_soon() => one("⚠ compact soon", TN_ORANGE),
+
+        "style" => {
+            if let Some(mode) = &ctx.data.output_style.name
+                && mode != "default"
+            {
+                return one(mode.as_str(), TN_BLUE);
+            }
+            None
+        }
+
+        "duration" => {
+            let ms = ctx.data.cost.total_duration_ms.unwrap_or(0);
+            if ms == 0 {
+                return None;
+            }
+            let format = ctx.config.duration_format.unwrap_or_default();
+            let wall = format_duration(ms, format);
+            match ctx.data.cost.total_api_duration_ms {
+                Some(api) if ctx.config.api_duration => one(
+                    format!("api {} / wall {wall}", format_duration(api, format)),
+                    TN_GRAY,
+                ),
+                _ => one(wall, TN_GRAY),
+            }
+        }
+
+        "session_lines" => line_counts(
+            ctx.data.cost.total_lines_added.unwrap_or(0),
+            ctx.data.cost.total_lines_removed.unwrap_or(0),
+            "/",
+        ),
+
+        "cost" => {
+            let usd = ctx.data.cost.total_cost_usd.unwrap_or(0.0);
+            if usd > 0.0 {
+                one(format!("${usd:.2}"), TN_GRAY)
+            } else {
+                None
+            }
+        }
+
+        "tokens" if ctx.config.token_limit => {
+            let cw = &ctx.data.context_window;
+            let limit = ctx.context_limit()?;
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let used = match cw.remaining_percentage {
+                Some(remaining) => (limit as f64 * (100.0 - remaining) / 100.0).max(0.0) as u64,
+                None => cw.total_input_tokens?,
+            };
+            one(
+                format!("{}/{}", format_tokens(used), format_tokens(limit)),
+                TN_GRAY,
+            )
+        }
+
+        "tokens" => {
+            let input = ctx.data.context_window.total_input_tokens.unwrap_or(0);
+            let output = ctx.data.context_window.total_output_tokens.unwrap_or(0);
+            if input > 0 || output > 0 {
+                one(
+                    format!("{}/{}", format_tokens(input), format_tokens(output)),
+                    TN_GRAY,
+                )
+            } else {
+                None
+            }
+        }
+
+        // Prompts so far and transcript size, e.g. "12 turns 340KB"
+        "transcript" => ctx
+            .transcript
+            .as_ref()
+            .filter(|t| t.turns > 0)
+            .and_then(|t| {
+                one(
+                    format!("{} turns {}B", t.turns, format_tokens(t.bytes)),
+                    TN_GRAY,
+                )
+            }),
+
+        "tools" => ctx
+            .transcript
+            .as_ref()
+            .filter(|t| t.tool_calls > 0)
+            .and_then(|t| one(format!("{} tools", t.tool_calls), TN_GRAY)),
+
+        "last_tool" => ctx
+            .transcript
+            .as_ref()
+            .and_then(|t| t.last_tool.as_deref())
+            .and_then(|name| one(name, TN_TEAL)),
+
+        // Dim marker linking to the release page when a newer version exists
+        "update" => ctx
+            .latest_version
+            .as_ref()
+            .map(|_| vec![Span::new("⬆ update", TN_GRAY).with_link(RELEASES_URL)]),
+
+        _ => None, // Unknown component - ignore silently for forward compatibility
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // =========================================================================
+    // format_tokens tests
+    // =========================================================================
+
+    #[test]
+    fn tokens_small() {
+        assert_eq!(format_tokens(42), "42");
+    }
+
+    #[test]
+    fn tokens_thousands() {
+        assert_eq!(format_tokens(5_432), "5K");
+    }
+
+    #[test]
+    fn tokens_exact_thousand() {
+        assert_eq!(format_tokens(1_000), "1K");
+    }
+
+    #[test]
+    fn tokens_millions() {
+        assert_eq!(format_tokens(2_500_000), "2.5M");
+    }
+
+    #[test]
+    fn tokens_exact_million() {
+        assert_eq!(format_tokens(1_000_000), "1.0M");
+    }
+
+    #[test]
+    fn tokens_zero() {
+        assert_eq!(format_tokens(0), "0");
+    }
+
+    #[test]
+    fn tokens_large_millions() {
+        assert_eq!(format_tokens(15_700_000), "15.7M");
+    }
+
+    // =========================================================================
+    // Custom segment tests
+    // =========================================================================
+
+    #[test]
+    fn parse_color_named_and_hex() {
+        assert_eq!(parse_color("cyan"), Some(TN_CYAN));
+        assert_eq!(parse_color("Grey"), Some(TN_GRAY));
+        assert_eq!(parse_color("#ff0080"), Some(Color::rgb(255, 0, 128)));
+        assert_eq!(parse_color("#fff"), None);
+        assert_eq!(parse_color("#gg0000"), None);
+        assert_eq!(parse_color("chartreuse"), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn command_output_and_timeout() {
+        let dir = env::temp_dir();
+        let dir = dir.to_str().unwrap();
+        let output = run_command_with_timeout("echo hello", dir, Duration::from_secs(5));
+        assert_eq!(output.as_deref(), Some("hello\n"));
+
+        let output = run_command_with_timeout("sleep 5", dir, Duration::from_millis(20));
+        assert_eq!(output, None);
+
+        let output = run_command_with_timeout("exit 3", dir, Duration::from_secs(5));
+        assert_eq!(output, None);
+    }
+
+    // =========================================================================
+    // Segment and StatusLine tests
+    // =========================================================================
+
+    #[test]
+    fn color_display_matches_escape() {
+        assert_eq!(TN_CYAN.to_string(), "\x1b[38;2;125;207;255m");
+    }
+
+    #[test]
+    fn span_display_with_link() {
+        let span = Span::new("#42", TN_CYAN).with_link("https://x/pull/42");
+        assert_eq!(
+            span.to_string(),
+            format!("{OSC8_START}https://x/pull/42{OSC8_MID}{TN_CYAN}#42{RESET}{OSC8_END}")
+        );
+        assert_eq!(Span::plain(" ").to_string(), " ");
+    }
+
+    #[test]
+    fn status_line_rows_from_json_input() {
+        let input = ClaudeInput::from_json(
+            r#"{"cwd": "/tmp", "model": {"display_name": "Opus"},
+                "git": {"branch": "main", "ahead": 2, "behind": 1,
+                        "lines_added": 5, "lines_deleted": 3},
+                "pr": {"number": 7, "state": "OPEN"}}"#,
+        );
+        let config = Config {
+            rows: vec![
+                vec!["model".into(), "branch".into(), "ahead_behind".into()],
+                vec!["pr_number".into(), "pr_comments".into()],
+                vec!["files".into()],
+                vec!["lines".into()],
+            ],
+            ..Config::default()
+        };
+        let status = StatusLine::builder(input).config(config).build();
+
+        let rows = status.rows();
+        assert_eq!(rows.len(), 3, "empty rows are omitted");
+        assert_eq!(rows[2][0].text(), "+5 -3");
+        let names: Vec<&str> = rows[0].iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["model", "branch", "ahead_behind"]);
+        assert_eq!(rows[0][2].text(), "↑2 ↓1");
+        assert_eq!(rows[1][0].text(), "#7");
+
+        let git = status.git_info().unwrap();
+        assert_eq!(git.branch, "main");
+        assert_eq!((git.ahead, git.behind), (Some(2), Some(1)));
+        assert_eq!(status.pr_info().unwrap().number, 7);
+    }
+
+    #[test]
+    fn protected_branch_is_a_warning() {
+        let config = Config {
+            rows: vec![vec!["branch".into()]],
+            protected_branches: vec!["main".into(), "release/*".into()],
+            ..Config::default()
+        };
+        let branch = |name: &str| {
+            let input = ClaudeInput::from_json(&format!(
+                r#"{{"cwd": "/tmp", "git": {{"branch": "{name}"}}}}"#
+            ));
+            let rows = StatusLine::builder(input)
+                .config(config.clone())
+                .build()
+                .rows();
+            rows[0][0].spans[0].clone()
+        };
+        let main = branch("main");
+        assert_eq!(
+            (main.text.as_str(), main.color),
+            ("⚠ main", Some(TN_ORANGE))
+        );
+        assert_eq!(branch("release/2.0").text, "⚠ release/2.0");
+        assert_eq!(branch("feature").text, "feature");
+    }
+
+    #[test]
+    fn pr_age_and_auto_merge_segments() {
+        let created_at = sys::now_secs() - 3 * 86_400 - 60;
+        let input = ClaudeInput::from_json(&format!(
+            r#"{{"cwd": "/tmp", "git": {{"branch": "main"}},
+                "pr": {{"number": 7, "state": "OPEN", "created_at": {created_at}, "auto_merge": true}}}}"#
+        ));
+        let config = Config {
+            rows: vec![vec!["pr_age".into(), "pr_auto_merge".into()]],
+            ..Config::default()
+        };
+        let rows = StatusLine::builder(input)
+            .config(config.clone())
+            .build()
+            .rows();
+        assert_eq!(rows[0][0].text(), "opened 3d ago");
+        assert_eq!(rows[0][1].text(), "auto-merge armed");
+
+        // Neither applies once the PR is merged
+        let input = ClaudeInput::from_json(&format!(
+            r#"{{"cwd": "/tmp", "git": {{"branch": "main"}},
+                "pr": {{"number": 7, "state": "MERGED", "created_at": {created_at}, "auto_merge": true}}}}"#
+        ));
+        assert!(
+            StatusLine::builder(input)
+                .config(config)
+                .build()
+                .rows()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn progress_from_context_and_checks() {
+        let status = |input: &str, progress| {
+            let config = Config {
+                rows: vec![vec!["context".into()]],
+                progress: Some(progress),
+                ..Config::default()
+            };
+            StatusLine::builder(ClaudeInput::from_json(input))
+                .config(config)
+                .build()
+        };
+        let context = |remaining: u32| {
+            format!(
+                r#"{{"cwd": "/tmp", "context_window": {{"remaining_percentage": {remaining}}}}}"#
+            )
+        };
+        let checks = |check: &str| {
+            format!(
+                r#"{{"cwd": "/tmp", "git": {{"branch": "main"}},
+                    "pr": {{"number": 7, "check_status": "{check}"}}}}"#
+            )
+        };
+
+        assert_eq!(
+            status(&context(70), ProgressSource::Context).progress(),
+            Some((1, 30))
+        );
+        assert_eq!(
+            status(&context(10), ProgressSource::Context).progress(),
+            Some((4, 90))
+        );
+        assert_eq!(
+            status(r#"{"cwd": "/tmp"}"#, ProgressSource::Context).progress(),
+            Some((0, 0))
+        );
+        assert_eq!(
+            status(&checks("pending"), ProgressSource::Checks).progress(),
+            Some((3, 0))
+        );
+        assert_eq!(
+            status(&checks("failed"), ProgressSource::Checks).progress(),
+            Some((2, 100))
+        );
+        assert_eq!(
+            status(&checks("passed"), ProgressSource::Checks).progress(),
+            Some((0, 0))
+        );
+
+        let mut out = Vec::new();
+        status(&context(70), ProgressSource::Context)
+            .render(&mut out)
+            .unwrap();
+        assert!(
+            String::from_utf8(out)
+                .unwrap()
+                .starts_with("\x1b]9;4;1;30\x07")
+        );
+    }
+
+    #[test]
+    fn window_title_from_template() {
+        let input = r#"{"cwd": "/tmp/app", "workspace": {"project_dir": "/tmp/app"},
+            "git": {"branch": "main"}}"#;
+        let config = Config {
+            rows: vec![vec!["branch".into()]],
+            window_title: Some("{project}:{branch}{nope} ({files})".into()),
+            ..Config::default()
+        };
+        let status = StatusLine::builder(ClaudeInput::from_json(input))
+            .config(config)
+            .build();
+        assert_eq!(status.window_title().as_deref(), Some("app:main (0)"));
+
+        let mut out = Vec::new();
+        status.render(&mut out).unwrap();
+        assert!(
+            String::from_utf8(out)
+                .unwrap()
+                .starts_with("\x1b]2;app:main (0)\x07")
+        );
+    }
+
+    #[test]
+    fn rows_fit_max_width_by_priority() {
+        let input = r#"{"cwd": "/tmp", "model": {"display_name": "Opus"},
+            "workspace": {"project_dir": "/srv/statusline-project"}, "git": {"branch": "feature"}}"#;
+        let render = |max_width, priorities: &[(&str, i32)]| {
+            let config = Config {
+                rows: vec![vec!["project".into(), "branch".into(), "model".into()]],
+                max_width: Some(max_width),
+                priorities: priorities
+                    .iter()
+                    .map(|(n, p)| (n.to_string(), *p))
+                    .collect(),
+                ..Config::default()
+            };
+            StatusLine::builder(ClaudeInput::from_json(input))
+                .config(config)
+                .build()
+                .rows()[0]
+                .iter()
+                .map(Segment::text)
+                .collect::<Vec<_>>()
+        };
+
+        // "statusline-project • feature • Opus" is 35 columns
+        assert_eq!(render(35, &[]), ["statusline-project", "feature", "Opus"]);
+        // Project has the lowest priority, so it's truncated first
+        assert_eq!(render(31, &[]), ["statusline-pr…", "feature", "Opus"]);
+        // ...and dropped when it would shrink below the minimum width
+        assert_eq!(render(20, &[]), ["feature", "Opus"]);
+        assert_eq!(
+            render(31, &[("model", 1)]),
+            ["statusline-project", "feature"]
+        );
+        assert_eq!(render(4, &[]), ["fea…"]);
+    }
+
+    #[test]
+    fn segment_width_counts_wide_characters() {
+        assert_eq!(text_width("🔔 3"), 4);
+        assert_eq!(text_width("日本"), 4);
+        let mut segment = Segment {
+            name: "x".into(),
+            spans: vec![Span::plain("ab"), Span::new("cdef", TN_CYAN)],
+        };
+        segment.truncate(4);
+        assert_eq!(segment.text(), "abc…");
+        assert_eq!(segment.spans[1].color, Some(TN_CYAN));
+    }
+
+    #[test]
+    fn duration_formats() {
+        assert_eq!(format_duration(272_000, DurationFormat::Minutes), "4m");
+        assert_eq!(format_duration(272_000, DurationFormat::Seconds), "4m32s");
+        assert_eq!(format_duration(45_999, DurationFormat::Seconds), "45s");
+        assert_eq!(format_duration(3_872_000, DurationFormat::Minutes), "1h 4m");
+        assert_eq!(
+            format_duration(3_872_000, DurationFormat::Seconds),
+            "1h4m32s"
+        );
+
+        let input = r#"{"cost": {"total_duration_ms": 272000, "total_api_duration_ms": 72000}}"#;
+        let config = Config {
+            rows: vec![vec!["duration".into()]],
+            duration_format: Some(DurationFormat::Seconds),
+            api_duration: true,
+            ..Config::default()
+        };
+        let status = StatusLine::builder(ClaudeInput::from_json(input))
+            .config(config)
+            .build();
+        assert_eq!(status.rows()[0][0].text(), "api 1m12s / wall 4m32s");
+    }
+
+    #[test]
+    fn session_lines_from_cost() {
+        let render = |input| {
+            let config = Config {
+                rows: vec![vec!["session_lines".into()]],
+                ..Config::default()
+            };
+            StatusLine::builder(ClaudeInput::from_json(input))
+                .config(config)
+                .build()
+                .rows()
+                .iter()
+                .flatten()
+                .map(Segment::text)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            render(r#"{"cost": {"total_lines_added": 245, "total_lines_removed": 89}}"#),
+            ["+245/-89"]
+        );
+        assert_eq!(render(r#"{"cost": {"total_lines_removed": 3}}"#), ["-3"]);
+        assert!(render(r#"{"cost": {}}"#).is_empty());
+    }
+
+    #[test]
+    fn tokens_against_context_limit() {
+        let render = |input, limits: &[(&str, u64)]| {
+            let config = Config {
+                rows: vec![vec!["tokens".into()]],
+                token_limit: true,
+                context_limits: limits.iter().map(|(m, l)| (m.to_string(), *l)).collect(),
+                ..Config::default()
+            };
+            StatusLine::builder(ClaudeInput::from_json(input))
+                .config(config)
+                .build()
+                .rows()
+                .iter()
+                .flatten()
+                .map(Segment::text)
+                .collect::<Vec<_>>()
+        };
+        let sonnet = r#"{"model": {"id": "claude-sonnet-4-5"},
+            "context_window": {"remaining_percentage": 44.0, "total_input_tokens": 500000}}"#;
+        assert_eq!(render(sonnet, &[]), ["112K/200K"]);
+        assert_eq!(
+            render(
+                sonnet,
+                &[("claude-*", 100_000), ("claude-sonnet-*", 400_000)]
+            ),
+            ["224K/400K"]
+        );
+        assert_eq!(
+            render(
+                r#"{"model": {"id": "claude-opus-4-1[1m]"}, "context_window": {"total_input_tokens": 150000}}"#,
+                &[]
+            ),
+            ["150K/1.0M"]
+        );
+        // Unknown models without a size in the input show nothing
+        assert!(render(r#"{"model": {"id": "gpt-5"}}"#, &[]).is_empty());
+    }
+
+    #[test]
+    fn compact_warning_below_threshold() {
+        let render = |remaining, threshold| {
+            let input = format!(r#"{{"context_window": {{"remaining_percentage": {remaining}}}}}"#);
+            let config = Config {
+                rows: vec![vec!["compact_warning".into()]],
+                compact_warning: threshold,
+                ..Config::default()
+            };
+            StatusLine::builder(ClaudeInput::from_json(&input))
+                .config(config)
+                .build()
+                .rows()
+                .iter()
+                .flatten()
+                .map(Segment::text)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(render(12.5, None), ["⚠ compact soon"]);
+        assert!(render(15.0, None).is_empty());
+        assert_eq!(render(25.0, Some(30.0)), ["⚠ compact soon"]);
+    }
+
+    #[test]
+    fn budget_turns_red_when_exceeded() {
+        let budget = |cost, tokens| BudgetConfig {
+            period: crate::config::BudgetPeriod::Day,
+            cost,
+            tokens,
+        };
+        let spans = budget_spans(&budget(Some(15.0), None), 8.2, 0).unwrap();
+        assert_eq!(spans[0].text, "$8.20/$15");
+        assert_eq!(spans[0].color, Some(TN_GRAY));
+
+        let spans = budget_spans(&budget(Some(12.5), Some(2_000_000)), 13.0, 1_200_000).unwrap();
+        assert_eq!(spans[0].text, "$13.00/$12.50");
+        assert_eq!(spans[0].color, Some(TN_RED));
+        assert_eq!(spans[2].text, "1.2M/2.0M");
+        assert_eq!(spans[2].color, Some(TN_GRAY));
+
+        assert!(budget_spans(&budget(None, None), 1.0, 1).is_none());
+    }
+
+    #[test]
+    fn color_blind_theme_and_glyphs() {
+        let input = r#"{"cwd": "/tmp", "git": {"branch": "main"},
+            "pr": {"number": 42, "state": "OPEN", "check_status": "failed",
+                   "review_decision": "review_required", "mergeable": "mergeable"}}"#;
+        let config = Config {
+            rows: vec![vec![
+                "pr_checks".into(),
+                "pr_reviews".into(),
+                "pr_mergeable".into(),
+            ]],
+            theme: Some(Theme::Deuteranopia),
+            status_glyphs: true,
+            ..Config::default()
+        };
+        let rows = StatusLine::builder(ClaudeInput::from_json(input))
+            .config(config)
+            .build()
+            .rows();
+        let texts: Vec<String> = rows[0].iter().map(Segment::text).collect();
+        assert_eq!(
+            texts,
+            ["✗ checks failed", "◐ review required", "✓ mergeable"]
+        );
+        let colors: Vec<_> = rows[0].iter().map(|s| s.spans[0].color).collect();
+        assert_eq!(colors, [Some(CB_VERMILLION), Some(CB_GRAY), Some(CB_BLUE)]);
+    }
+
+    #[test]
+    fn pr_verbosity_levels() {
+        let input = r#"{"cwd": "/tmp", "git": {"branch": "main"},
+            "pr": {"number": 42, "state": "OPEN", "comments": 2, "check_status": "passed",
+                   "review_decision": "approved", "labels": ["a", "b", "c", "d"],
+                   "mergeable": "conflicting"}}"#;
+        let render = |verbosity| {
+            let config = Config {
+                rows: vec![
+                    vec!["pr_number".into(), "pr_comments".into(), "pr_checks".into()],
+                    vec!["branch".into()],
+                ],
+                pr_verbosity: Some(verbosity),
+                ..Config::default()
+            };
+            StatusLine::builder(ClaudeInput::from_json(input))
+                .config(config)
+                .build()
+                .rows()
+                .iter()
+                .map(|row| row.iter().map(Segment::text).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(render(PrVerbosity::Compact), [vec!["#42 ✓"], vec!["main"]]);
+        assert_eq!(
+            render(PrVerbosity::Normal)[0],
+            ["#42", "2 comments", "checks passed"]
+        );
+        assert_eq!(
+            render(PrVerbosity::Detailed)[0],
+            [
+                "#42",
+                "2 comments",
+                "checks passed",
+                "approved",
+                "a, b, c +1",
+                "conflicts"
+            ]
+        );
+    }
+
+    #[test]
+    fn deployment_segment_states() {
+        let render = |state: &str| {
+            let input = ClaudeInput::from_json(&format!(
+                r#"{{"cwd": "/tmp", "git": {{"branch": "main"}}, "pr": {{"number": 7,
+                    "deployment": {{"environment": "staging", "state": "{state}"}}}}}}"#
+            ));
+            let config = Config {
+                rows: vec![vec!["deployment".into()]],
+                ..Config::default()
+            };
+            StatusLine::builder(input).config(config).build().rows()[0][0].text()
+        };
+        assert_eq!(render("success"), "🚀 staging ✓");
+        assert_eq!(render("error"), "🚀 staging ✗");
+        assert_eq!(render("in_progress"), "🚀 staging pending");
+    }
+
+    #[test]
+    fn status_line_pending_data_shows_placeholder() {
+        let config = Config {
+            rows: vec![vec![
+                "files".into(),
+                "ahead_behind".into(),
+                "pr_number".into(),
+                "pr_state".into(),
+            ]],
+            ..Config::default()
+        };
+        let mut status = StatusLine::builder(ClaudeInput::default())
+            .cwd("/")
+            .config(config)
+            .build();
+        status.stats_pending = true;
+        status.pr_pending = true;
+
+        let rows = status.rows();
+        let texts: Vec<String> = rows[0].iter().map(Segment::text).collect();
+        assert_eq!(texts, [PENDING, PENDING]);
+        status.wait_for_background();
+    }
+
+    #[test]
+    fn status_line_update_marker() {
+        // Set rows after build so the test never starts a release check
+        let mut status = StatusLine::builder(ClaudeInput::default())
+            .cwd("/")
+            .config(Config {
+                rows: Vec::new(),
+                ..Config::default()
+            })
+            .build();
+        status.config.rows = vec![vec!["update".into()]];
+        assert!(status.rows().is_empty());
+
+        status.latest_version = Some("9.9.9".into());
+        let rows = status.rows();
+        assert_eq!(rows[0][0].text(), "⬆ update");
+        assert_eq!(rows[0][0].spans[0].link.as_deref(), Some(RELEASES_URL));
+    }
+
+    #[test]
+    fn status_line_added_dirs() {
+        let input =
+            ClaudeInput::from_json(r#"{"cwd": "/tmp", "workspace": {"added_dirs": ["/a", "/b"]}}"#);
+        let config = Config {
+            rows: vec![vec!["dirs".into()]],
+            ..Config::default()
+        };
+        let status = StatusLine::builder(input).config(config).build();
+        assert_eq!(status.rows()[0][0].text(), "+2 dirs");
+    }
+
+    #[test]
+    fn status_line_transcript_segment() {
+        let mut status = StatusLine::builder(ClaudeInput::default())
+            .cwd("/")
+            .config(Config {
+                rows: vec![vec!["transcript".into()]],
+                ..Config::default()
+            })
+            .build();
+        assert!(status.rows().is_empty());
+
+        status.config.rows = vec![vec![
+            "transcript".into(),
+            "tools".into(),
+            "last_tool".into(),
+        ]];
+        status.transcript = Some(TranscriptStats {
+            bytes: 340_512,
+            turns: 12,
+            tool_calls: 37,
+            last_tool: Some("Bash".into()),
+            offset: 340_512,
+        });
+        let texts: Vec<String> = status.rows()[0].iter().map(Segment::text).collect();
+        assert_eq!(texts, ["12 turns 340KB", "37 tools", "Bash"]);
+    }
+
+    #[test]
+    fn status_line_render_joins_segments() {
+        let input = ClaudeInput::from_json(r#"{"cwd": "/tmp", "git": {"branch": "dev"}}"#);
+        let config = Config {
+            rows: vec![vec!["branch".into(), "no_git".into(), "worktree".into()]],
+            ..Config::default()
+        };
+        let status = StatusLine::builder(input).config(config).build();
+        let mut out = Vec::new();
+        status.render(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("{TN_PURPLE}dev{RESET}\n")
+        );
+    }
+
+    #[test]
+    fn control_characters_in_input_are_escaped() {
+        let input = ClaudeInput::from_json(
+            r#"{"cwd": "/tmp", "git": {"branch": "main\u001b]0;owned\u0007"}}"#,
+        );
+        let config = Config {
+            rows: vec![vec!["branch".into()]],
+            ..Config::default()
+        };
+        let status = StatusLine::builder(input).config(config).build();
+        let mut out = Vec::new();
+        status.render(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("{TN_PURPLE}main\\x1B]0;owned\\x07{RESET}\n")
+        );
+
+        let mut span = Span::plain("x").with_link("https://e.com/\x1b\\");
+        span.sanitize();
+        assert_eq!(span.link.as_deref(), Some("https://e.com/%1B\\"));
+    }
+}
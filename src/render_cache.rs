@@ -0,0 +1,163 @@
+//! The last rendered output, reused while nothing it depends on has changed
+//!
+//! Claude Code reruns the status line on every update, often with the same
+//! input. With `render_cache` set, the output is stored with a hash of the
+//! raw input, profile, config file mtime, environment, working directory, and
+//! the state of the repo the render would use: its `HEAD` and index mtimes,
+//! and, when working-tree counts are shown, the filesystem monitor's changed
+//! paths. A later run with the same hash, within the configured seconds,
+//! prints the stored bytes without building anything. When the repo state
+//! can't be read there is no hash, and the cache is skipped.
+//! The time limit bounds how stale clocks, ages, and background PR refreshes
+//! can get, since none of those are in the hash.
+
+use crate::cache::{atomic_rename, get_cache_dir, unique_hex};
+use crate::config::{Config, get_config_path};
+use crate::fsmonitor;
+use crate::git::get_git_repo;
+use crate::input::ClaudeInput;
+use crate::render::render_dir;
+use crate::sys;
+use std::cell::OnceCell;
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Key hash, write time, and seconds the entry is good for
+const HEADER_LEN: usize = 8 + 8 + 8;
+
+/// Components whose values change with working-tree edits alone
+const WORKTREE_COMPONENTS: &[&str] = &["files", "lines"];
+
+/// Where a render's output is stored and what it must match
+/// The hash is only computed once a stored entry turns up, so runs without
+/// the cache enabled pay for no more than a missing file
+pub struct RenderKey<'a> {
+    raw: &'a str,
+    /// The render's directory, after `path_map`
+    dir: String,
+    profile: Option<&'a str>,
+    /// The input names the branch, so the render doesn't open a repo
+    git_from_input: bool,
+    /// Working-tree counts are shown, so edits outside the index matter
+    worktree: bool,
+    path: PathBuf,
+    hash: OnceCell<Option<u64>>,
+}
+
+impl<'a> RenderKey<'a> {
+    /// Key for rendering `raw` (parsed as `input`) with the given profile and its config
+    pub fn new(
+        raw: &'a str,
+        input: &ClaudeInput,
+        profile: Option<&'a str>,
+        config: &Config,
+    ) -> Self {
+        let dir = render_dir(None, input, config);
+        // One entry per session, so concurrent sessions don't evict each other
+        let mut owner = DefaultHasher::new();
+        input.session_id.as_deref().unwrap_or(&dir).hash(&mut owner);
+        Self {
+            raw,
+            profile,
+            git_from_input: input.git.branch.is_some(),
+            worktree: config.uses_any(WORKTREE_COMPONENTS)
+                || config.conditions_reference(WORKTREE_COMPONENTS),
+            path: get_cache_dir().join(format!("render-{:016x}.cache", owner.finish())),
+            dir,
+            hash: OnceCell::new(),
+        }
+    }
+
+    /// None when the repo's state can't be read, so its output can't be reused
+    fn hash(&self) -> Option<u64> {
+        *self.hash.get_or_init(|| {
+            let mut hasher = DefaultHasher::new();
+            self.raw.hash(&mut hasher);
+            self.profile.hash(&mut hasher);
+            mtime(&get_config_path()).hash(&mut hasher);
+            let mut vars: Vec<_> = env::vars_os().collect();
+            vars.sort();
+            vars.hash(&mut hasher);
+            env::current_dir().ok().hash(&mut hasher);
+            self.dir.hash(&mut hasher);
+            // The same repo the render collects from, honoring GIT_DIR
+            if !self.git_from_input
+                && let Some(git) = get_git_repo(&self.dir)
+            {
+                let git_dir = Path::new(&git.git_dir);
+                mtime(&git_dir.join("HEAD"))?.hash(&mut hasher);
+                mtime(&git_dir.join("index"))?.hash(&mut hasher);
+                if self.worktree && git.repo.work_dir().is_some() {
+                    // Without a monitor, edits outside the index can't be seen cheaply
+                    fsmonitor::query(&git.repo)?.fingerprint().hash(&mut hasher);
+                }
+            }
+            Some(hasher.finish())
+        })
+    }
+}
+
+fn mtime(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// The stored output, if it was rendered for the same key and hasn't expired
+pub fn load(key: &RenderKey<'_>) -> Option<Vec<u8>> {
+    let mut data = fs::read(&key.path).ok()?;
+    let field = |i: usize| {
+        let bytes = data.get(i * 8..(i + 1) * 8)?;
+        Some(u64::from_le_bytes(bytes.try_into().ok()?))
+    };
+    let (hash, written_at, secs) = (field(0)?, field(1)?, field(2)?);
+    if Some(hash) != key.hash() || sys::now_secs().saturating_sub(written_at) >= secs {
+        return None;
+    }
+    Some(data.split_off(HEADER_LEN))
+}
+
+/// Store `output` for `secs` seconds
+/// Nothing is stored when the repo's state can't be read
+pub fn save(key: &RenderKey<'_>, secs: u64, output: &[u8]) {
+    let Some(hash) = key.hash() else {
+        return;
+    };
+    let mut data = Vec::with_capacity(HEADER_LEN + output.len());
+    data.extend_from_slice(&hash.to_le_bytes());
+    data.extend_from_slice(&sys::now_secs().to_le_bytes());
+    data.extend_from_slice(&secs.to_le_bytes());
+    data.extend_from_slice(output);
+    let temp_path = key
+        .path
+        .with_file_name(format!("render-tmp-{}.cache", unique_hex()));
+    if fs::write(&temp_path, data).is_ok() {
+        let _ = atomic_rename(&temp_path, &key.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_match_their_key_until_they_expire() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = |hash| RenderKey {
+            raw: "{}",
+            dir: String::new(),
+            profile: None,
+            git_from_input: false,
+            worktree: false,
+            path: dir.path().join("render.cache"),
+            hash: OnceCell::from(Some(hash)),
+        };
+        save(&key(7), 5, b"status");
+        assert_eq!(load(&key(7)).as_deref(), Some(&b"status"[..]));
+        assert_eq!(load(&key(8)), None);
+        save(&key(7), 0, b"status");
+        assert_eq!(load(&key(7)), None);
+    }
+}
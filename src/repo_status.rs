@@ -0,0 +1,195 @@
+//! GitHub data that changes slowly: the repo's latest release and open
+//! Dependabot alerts, and unread notifications
+//!
+//! Like the update check, components only read a per-repo cache; a stale
+//! entry is refreshed on a background thread at most once per TTL.
+
+use crate::cache::{atomic_rename, get_cache_dir, unique_hex};
+use crate::hash_path;
+use crate::pr::{PrTarget, get_github_token};
+use crate::sys::{self, TlsOptions};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const RELEASE_TTL: u64 = 6 * 60 * 60; // seconds
+const ALERTS_TTL: u64 = 4 * 60 * 60; // seconds
+/// Alerts fetched per lookup; a full page shows as "100+"
+const ALERTS_PAGE: usize = 100;
+const NOTIFICATIONS_TTL: u64 = 30 * 60; // seconds
+/// Notifications fetched per lookup; a full page shows as "50+"
+const NOTIFICATIONS_PAGE: usize = 50;
+
+/// Repo whose data is shown, and how to reach its API
+#[derive(Debug, Clone)]
+pub(crate) struct RepoSource {
+    pub(crate) api: String,
+    pub(crate) target: PrTarget,
+    /// Only read the cache
+    pub(crate) offline: bool,
+    pub(crate) tls: TlsOptions,
+    /// Seconds a credential helper token is reused
+    pub(crate) token_cache_secs: u64,
+}
+
+impl RepoSource {
+    /// Cache file for `name` data of this repo
+    fn cache_path(&self, name: &str) -> PathBuf {
+        let key = format!(
+            "{}/repos/{}/{}",
+            self.api, self.target.owner, self.target.repo
+        );
+        get_cache_dir().join(format!("{name}-{:016x}.cache", hash_path(&key)))
+    }
+
+    /// GET `path` under the repo's API URL
+    fn get(&self, path: &str) -> Option<serde_json::Value> {
+        let url = format!(
+            "{}/repos/{}/{}/{path}",
+            self.api, self.target.owner, self.target.repo
+        );
+        api_get(
+            &url,
+            get_github_token(self.token_cache_secs).as_deref(),
+            &self.tls,
+        )
+    }
+}
+
+/// GET `url` from the API, authenticated when a token is given
+fn api_get(url: &str, token: Option<&str>, tls: &TlsOptions) -> Option<serde_json::Value> {
+    let auth = token.map(|token| format!("Bearer {token}"));
+    let mut headers = vec![
+        ("Accept", "application/vnd.github+json"),
+        ("User-Agent", "cc-statusline"),
+        ("X-GitHub-Api-Version", "2022-11-28"),
+    ];
+    if let Some(auth) = &auth {
+        headers.push(("Authorization", auth));
+    }
+    let body = sys::http_get(url, &headers, Some(Duration::from_secs(5)), tls).ok()?;
+    serde_json::from_str(&body).ok()
+}
+
+/// Page length as a count, like "3", or "50+" for a full page
+fn page_count(items: &serde_json::Value, page: usize) -> Option<String> {
+    let count = items.as_array()?.len();
+    Some(if count >= page {
+        format!("{page}+")
+    } else {
+        count.to_string()
+    })
+}
+
+/// Parse cache content: "<fetched_at>\n<value>" (value is empty if there was none)
+fn parse_cache(content: &str) -> Option<(u64, String)> {
+    let (fetched_at, value) = content.split_once('\n').unwrap_or((content, ""));
+    Some((fetched_at.parse().ok()?, value.trim().to_string()))
+}
+
+fn save_cache(path: &Path, value: &str) {
+    let temp_path = get_cache_dir().join(format!("repo-tmp-{}", unique_hex()));
+    if fs::write(&temp_path, format!("{}\n{value}", sys::now_secs())).is_ok() {
+        let _ = atomic_rename(&temp_path, path);
+    }
+}
+
+/// Cached value, refreshed by `fetch` on the returned thread when older than
+/// `ttl` (unless offline); a failed fetch keeps the old value for another TTL
+pub(crate) fn cached(
+    path: PathBuf,
+    ttl: u64,
+    offline: bool,
+    fetch: impl FnOnce() -> Option<String> + Send + 'static,
+) -> (Option<String>, Option<JoinHandle<()>>) {
+    let (fetched_at, value) = fs::read_to_string(&path)
+        .ok()
+        .and_then(|c| parse_cache(&c))
+        .unwrap_or_default();
+
+    let expired = sys::now_secs().saturating_sub(fetched_at) >= ttl;
+    let refresh = (expired && !offline).then(|| {
+        // Record the attempt first so concurrent renders don't all hit the API
+        save_cache(&path, &value);
+        thread::spawn(move || {
+            if let Some(fresh) = fetch() {
+                save_cache(&path, &fresh);
+            }
+        })
+    });
+    ((!value.is_empty()).then_some(value), refresh)
+}
+
+/// Tag of the repo's latest release
+pub(crate) fn latest_release(source: &RepoSource) -> (Option<String>, Option<JoinHandle<()>>) {
+    let path = source.cache_path("release");
+    let fetcher = source.clone();
+    cached(path, RELEASE_TTL, source.offline, move || {
+        let release = fetcher.get("releases/latest")?;
+        Some(release["tag_name"].as_str().unwrap_or_default().to_string())
+    })
+}
+
+/// Open Dependabot alerts, like "3" or "100+"
+/// Needs a token that can read security alerts; without one nothing is shown
+pub(crate) fn open_alerts(source: &RepoSource) -> (Option<String>, Option<JoinHandle<()>>) {
+    let path = source.cache_path("alerts");
+    let fetcher = source.clone();
+    cached(path, ALERTS_TTL, source.offline, move || {
+        let alerts = fetcher.get(&format!(
+            "dependabot/alerts?state=open&per_page={ALERTS_PAGE}"
+        ))?;
+        page_count(&alerts, ALERTS_PAGE)
+    })
+}
+
+/// Unread notifications, like "3" or "50+", only for `repo` when given
+/// Needs a token; without one nothing is shown
+pub(crate) fn unread_notifications(
+    api: &str,
+    repo: Option<&PrTarget>,
+    offline: bool,
+    tls: &TlsOptions,
+    token_cache_secs: u64,
+) -> (Option<String>, Option<JoinHandle<()>>) {
+    let Some(token) = get_github_token(token_cache_secs) else {
+        return (None, None);
+    };
+    let url = match repo {
+        Some(t) => format!("{api}/repos/{}/{}/notifications", t.owner, t.repo),
+        None => format!("{api}/notifications"),
+    };
+    let path = get_cache_dir().join(format!("notifications-{:016x}.cache", hash_path(&url)));
+    let tls = tls.clone();
+    cached(path, NOTIFICATIONS_TTL, offline, move || {
+        let url = format!("{url}?per_page={NOTIFICATIONS_PAGE}");
+        page_count(&api_get(&url, Some(&token), &tls)?, NOTIFICATIONS_PAGE)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repo_cache_parsing() {
+        assert_eq!(
+            parse_cache("1700000000\nv3.2.0"),
+            Some((1_700_000_000, "v3.2.0".to_string()))
+        );
+        assert_eq!(
+            parse_cache("1700000000"),
+            Some((1_700_000_000, String::new()))
+        );
+        assert_eq!(parse_cache("garbage\nv1"), None);
+    }
+
+    #[test]
+    fn counts_cap_at_page_size() {
+        let items = |n: usize| serde_json::Value::Array(vec![serde_json::Value::Null; n]);
+        assert_eq!(page_count(&items(3), 50).as_deref(), Some("3"));
+        assert_eq!(page_count(&items(50), 50).as_deref(), Some("50+"));
+        assert_eq!(page_count(&serde_json::json!({"message": "x"}), 50), None);
+    }
+}
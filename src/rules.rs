@@ -0,0 +1,439 @@
+//! Conditional segment rules
+//!
+//! A tiny expression language used by the `conditions` config key to decide
+//! whether a component is rendered. Expressions are evaluated against named
+//! values from the collected data model (tokens, cost, branch, ...).
+//!
+//! Grammar:
+//! ```text
+//! expr       := and ("||" and)*
+//! and        := unary ("&&" unary)*
+//! unary      := "!" unary | primary
+//! primary    := "(" expr ")" | call | operand (op operand)?
+//! call       := "exists" "(" string ")"
+//! operand    := ident | number | string | "true" | "false"
+//! op         := "==" | "!=" | "<" | "<=" | ">" | ">="
+//! ```
+//!
+//! Examples: `tokens >= 1000`, `cost > 0.5 && branch != "main"`, `exists("k8s")`
+
+use std::fmt;
+
+/// A value produced by a variable lookup or literal
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(String),
+}
+
+impl Value {
+    /// Truthiness: null, false, 0, and "" are false
+    pub fn truthy(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Bool(b) => *b,
+            Value::Number(n) => *n != 0.0,
+            Value::Str(s) => !s.is_empty(),
+        }
+    }
+}
+
+/// Source of variable values and filesystem checks for evaluation
+pub trait Context {
+    /// Look up a variable by name, returning `Value::Null` if unknown
+    fn var(&self, name: &str) -> Value;
+    /// Check whether a path (relative to the current directory) exists
+    fn exists(&self, path: &str) -> bool;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Var(String),
+    Lit(Value),
+}
+
+/// Parsed rule expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Cmp(Operand, CmpOp, Operand),
+    Truthy(Operand),
+    Exists(String),
+}
+
+/// Error returned when a rule fails to parse
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    Str(String),
+    Op(CmpOp),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+fn tokenize(src: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let bytes = src.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let err = |message: &str, position| ParseError {
+        message: message.to_string(),
+        position,
+    };
+
+    while i < bytes.len() {
+        let start = i;
+        let b = bytes[i];
+        match b {
+            b' ' | b'\t' | b'\n' | b'\r' => {
+                i += 1;
+                continue;
+            }
+            b'(' => {
+                tokens.push((Token::LParen, start));
+                i += 1;
+            }
+            b')' => {
+                tokens.push((Token::RParen, start));
+                i += 1;
+            }
+            b'&' if bytes.get(i + 1) == Some(&b'&') => {
+                tokens.push((Token::And, start));
+                i += 2;
+            }
+            b'|' if bytes.get(i + 1) == Some(&b'|') => {
+                tokens.push((Token::Or, start));
+                i += 2;
+            }
+            b'=' if bytes.get(i + 1) == Some(&b'=') => {
+                tokens.push((Token::Op(CmpOp::Eq), start));
+                i += 2;
+            }
+            b'!' if bytes.get(i + 1) == Some(&b'=') => {
+                tokens.push((Token::Op(CmpOp::Ne), start));
+                i += 2;
+            }
+            b'!' => {
+                tokens.push((Token::Not, start));
+                i += 1;
+            }
+            b'<' | b'>' => {
+                let has_eq = bytes.get(i + 1) == Some(&b'=');
+                let op = match (b, has_eq) {
+                    (b'<', false) => CmpOp::Lt,
+                    (b'<', true) => CmpOp::Le,
+                    (_, false) => CmpOp::Gt,
+                    (_, true) => CmpOp::Ge,
+                };
+                tokens.push((Token::Op(op), start));
+                i += if has_eq { 2 } else { 1 };
+            }
+            b'"' | b'\'' => {
+                let quote = b;
+                i += 1;
+                let content_start = i;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += 1;
+                }
+                if i >= bytes.len() {
+                    return Err(err("unterminated string", start));
+                }
+                tokens.push((Token::Str(src[content_start..i].to_string()), start));
+                i += 1;
+            }
+            b'0'..=b'9' | b'.' | b'-' => {
+                i += 1;
+                while i < bytes.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'.') {
+                    i += 1;
+                }
+                let n: f64 = src[start..i]
+                    .parse()
+                    .map_err(|_| err("invalid number", start))?;
+                tokens.push((Token::Number(n), start));
+            }
+            b'a'..=b'z' | b'A'..=b'Z' | b'_' => {
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                tokens.push((Token::Ident(src[start..i].to_string()), start));
+            }
+            _ => return Err(err("unexpected character", start)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |(_, p)| *p)
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            message: message.to_string(),
+            position: self.position(),
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).map(|(t, _)| t.clone());
+        self.pos += 1;
+        t
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            let right = self.parse_unary()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let inner = self.parse_or()?;
+            if self.next() != Some(Token::RParen) {
+                self.pos -= 1;
+                return Err(self.error("expected ')'"));
+            }
+            return Ok(inner);
+        }
+
+        if let Some(Token::Ident(name)) = self.peek()
+            && name == "exists"
+            && matches!(self.tokens.get(self.pos + 1), Some((Token::LParen, _)))
+        {
+            self.pos += 2;
+            let Some(Token::Str(path)) = self.next() else {
+                self.pos -= 1;
+                return Err(self.error("exists() expects a string argument"));
+            };
+            if self.next() != Some(Token::RParen) {
+                self.pos -= 1;
+                return Err(self.error("expected ')'"));
+            }
+            return Ok(Expr::Exists(path));
+        }
+
+        let left = self.parse_operand()?;
+        if let Some(Token::Op(op)) = self.peek() {
+            let op = *op;
+            self.pos += 1;
+            let right = self.parse_operand()?;
+            return Ok(Expr::Cmp(left, op, right));
+        }
+        Ok(Expr::Truthy(left))
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand, ParseError> {
+        match self.next() {
+            Some(Token::Ident(name)) => Ok(match name.as_str() {
+                "true" => Operand::Lit(Value::Bool(true)),
+                "false" => Operand::Lit(Value::Bool(false)),
+                "null" => Operand::Lit(Value::Null),
+                _ => Operand::Var(name),
+            }),
+            Some(Token::Number(n)) => Ok(Operand::Lit(Value::Number(n))),
+            Some(Token::Str(s)) => Ok(Operand::Lit(Value::Str(s))),
+            _ => {
+                self.pos -= 1;
+                Err(self.error("expected a value"))
+            }
+        }
+    }
+}
+
+/// Parse a rule expression
+pub fn parse(src: &str) -> Result<Expr, ParseError> {
+    let tokens = tokenize(src)?;
+    if tokens.is_empty() {
+        return Err(ParseError {
+            message: "empty expression".to_string(),
+            position: 0,
+        });
+    }
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end: src.len(),
+    };
+    let expr = parser.parse_or()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(parser.error("unexpected trailing input"));
+    }
+    Ok(expr)
+}
+
+fn resolve(operand: &Operand, ctx: &impl Context) -> Value {
+    match operand {
+        Operand::Var(name) => ctx.var(name),
+        Operand::Lit(v) => v.clone(),
+    }
+}
+
+fn compare(left: &Value, op: CmpOp, right: &Value) -> bool {
+    use std::cmp::Ordering;
+    let ordering = match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+        (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        _ => None,
+    };
+    match (op, ordering) {
+        (CmpOp::Eq, o) => o == Some(Ordering::Equal),
+        (CmpOp::Ne, o) => o != Some(Ordering::Equal),
+        (CmpOp::Lt, Some(o)) => o == Ordering::Less,
+        (CmpOp::Le, Some(o)) => o != Ordering::Greater,
+        (CmpOp::Gt, Some(o)) => o == Ordering::Greater,
+        (CmpOp::Ge, Some(o)) => o != Ordering::Less,
+        // Ordering comparisons between mismatched types (or with null) are false
+        (_, None) => false,
+    }
+}
+
+impl Expr {
+    /// Evaluate the expression against a context
+    pub fn eval(&self, ctx: &impl Context) -> bool {
+        match self {
+            Expr::Or(a, b) => a.eval(ctx) || b.eval(ctx),
+            Expr::And(a, b) => a.eval(ctx) && b.eval(ctx),
+            Expr::Not(e) => !e.eval(ctx),
+            Expr::Cmp(l, op, r) => compare(&resolve(l, ctx), *op, &resolve(r, ctx)),
+            Expr::Truthy(o) => resolve(o, ctx).truthy(),
+            Expr::Exists(path) => ctx.exists(path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestCtx;
+
+    impl Context for TestCtx {
+        fn var(&self, name: &str) -> Value {
+            match name {
+                "tokens" => Value::Number(1500.0),
+                "cost" => Value::Number(0.25),
+                "branch" => Value::Str("main".to_string()),
+                "ssh" => Value::Bool(false),
+                _ => Value::Null,
+            }
+        }
+
+        fn exists(&self, path: &str) -> bool {
+            path == "k8s"
+        }
+    }
+
+    fn eval(src: &str) -> bool {
+        parse(src).expect("should parse").eval(&TestCtx)
+    }
+
+    #[test]
+    fn numeric_comparisons() {
+        assert!(eval("tokens >= 1000"));
+        assert!(!eval("tokens < 1000"));
+        assert!(!eval("cost > 0.5"));
+        assert!(eval("cost <= .25"));
+    }
+
+    #[test]
+    fn string_comparisons() {
+        assert!(eval("branch == \"main\""));
+        assert!(eval("branch != 'develop'"));
+    }
+
+    #[test]
+    fn boolean_logic_and_precedence() {
+        assert!(eval("cost > 1 || tokens > 1000 && branch == 'main'"));
+        assert!(!eval("(cost > 1 || tokens > 1000) && ssh"));
+        assert!(eval("!ssh"));
+    }
+
+    #[test]
+    fn exists_function() {
+        assert!(eval("exists(\"k8s\")"));
+        assert!(!eval("exists('helm')"));
+    }
+
+    #[test]
+    fn unknown_variables_are_null() {
+        assert!(!eval("missing"));
+        assert!(!eval("missing > 0"));
+        assert!(eval("missing == null"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse("").is_err());
+        assert!(parse("tokens >=").is_err());
+        assert!(parse("(tokens > 1").is_err());
+        assert!(parse("tokens > 1 )").is_err());
+        assert!(parse("exists(k8s)").is_err());
+        assert!(parse("'unterminated").is_err());
+        assert!(parse("tokens # 1").is_err());
+    }
+}
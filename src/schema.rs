@@ -0,0 +1,295 @@
+//! Claude Code status line input schema and diagnostics
+//!
+//! Used by `--validate-input` and debug logging to spot upstream schema drift:
+//! fields Claude Code started sending, stopped sending, or renamed.
+
+use serde_json::Value;
+use std::fmt;
+
+/// Kind of JSON value expected at a schema path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    String,
+    Number,
+    Bool,
+    Array,
+    /// Object whose fields are checked individually (or not at all if none are listed)
+    Object,
+}
+
+impl fmt::Display for FieldKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::String => "string",
+            Self::Number => "number",
+            Self::Bool => "boolean",
+            Self::Array => "array",
+            Self::Object => "object",
+        })
+    }
+}
+
+/// Known input fields: (path, kind, always sent by Claude Code)
+/// `git` and `pr` are our own overrides for screenshots and tests
+const SCHEMA: &[(&str, FieldKind, bool)] = &[
+    ("hook_event_name", FieldKind::String, false),
+    ("session_id", FieldKind::String, false),
+    ("transcript_path", FieldKind::String, false),
+    ("cwd", FieldKind::String, true),
+    ("version", FieldKind::String, false),
+    ("exceeds_200k_tokens", FieldKind::Bool, false),
+    ("model", FieldKind::Object, true),
+    ("model.id", FieldKind::String, false),
+    ("model.display_name", FieldKind::String, true),
+    ("workspace", FieldKind::Object, true),
+    ("workspace.current_dir", FieldKind::String, true),
+    ("workspace.project_dir", FieldKind::String, true),
+    ("workspace.added_dirs", FieldKind::Array, false),
+    ("output_style", FieldKind::Object, false),
+    ("output_style.name", FieldKind::String, false),
+    ("cost", FieldKind::Object, false),
+    ("cost.total_cost_usd", FieldKind::Number, false),
+    ("cost.total_duration_ms", FieldKind::Number, false),
+    ("cost.total_api_duration_ms", FieldKind::Number, false),
+    ("cost.total_lines_added", FieldKind::Number, false),
+    ("cost.total_lines_removed", FieldKind::Number, false),
+    ("context_window", FieldKind::Object, false),
+    (
+        "context_window.total_input_tokens",
+        FieldKind::Number,
+        false,
+    ),
+    (
+        "context_window.total_output_tokens",
+        FieldKind::Number,
+        false,
+    ),
+    (
+        "context_window.context_window_size",
+        FieldKind::Number,
+        false,
+    ),
+    ("context_window.used_percentage", FieldKind::Number, false),
+    (
+        "context_window.remaining_percentage",
+        FieldKind::Number,
+        false,
+    ),
+    ("context_window.current_usage", FieldKind::Object, false),
+    ("git", FieldKind::Object, false),
+    ("git.branch", FieldKind::String, false),
+    ("git.worktree", FieldKind::String, false),
+    ("git.changed_files", FieldKind::Number, false),
+    ("git.lines_added", FieldKind::Number, false),
+    ("git.lines_deleted", FieldKind::Number, false),
+    ("git.ahead", FieldKind::Number, false),
+    ("git.behind", FieldKind::Number, false),
+    ("pr", FieldKind::Object, false),
+    ("pr.number", FieldKind::Number, false),
+    ("pr.state", FieldKind::String, false),
+    ("pr.url", FieldKind::String, false),
+    ("pr.comments", FieldKind::Number, false),
+    ("pr.changed_files", FieldKind::Number, false),
+    ("pr.check_status", FieldKind::String, false),
+    ("pr.created_at", FieldKind::Number, false),
+    ("pr.auto_merge", FieldKind::Bool, false),
+    ("pr.review_decision", FieldKind::String, false),
+    ("pr.labels", FieldKind::Array, false),
+    ("pr.mergeable", FieldKind::String, false),
+    ("pr.deployment", FieldKind::Object, false),
+    ("pr.deployment.environment", FieldKind::String, false),
+    ("pr.deployment.state", FieldKind::String, false),
+];
+
+/// Field names seen in older or alternate payloads, and their current name
+const RENAMED: &[(&str, &str)] = &[
+    ("model.name", "model.display_name"),
+    ("model.displayName", "model.display_name"),
+    ("outputStyle", "output_style"),
+    ("workspace.cwd", "workspace.current_dir"),
+    ("workspace.additional_directories", "workspace.added_dirs"),
+    ("cost.total_duration", "cost.total_duration_ms"),
+    (
+        "context_window.remaining_percent",
+        "context_window.remaining_percentage",
+    ),
+];
+
+/// A problem found in status line input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputDiagnostic {
+    /// Field not in the schema
+    Unknown(String),
+    /// Field Claude Code always sends is absent
+    Missing(String),
+    /// Field uses an old or alternate name
+    Renamed { found: String, expected: String },
+    /// Field has the wrong JSON type (null is always allowed)
+    WrongType { path: String, expected: FieldKind },
+}
+
+impl fmt::Display for InputDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown(path) => write!(f, "unknown field `{path}`"),
+            Self::Missing(path) => write!(f, "missing field `{path}`"),
+            Self::Renamed { found, expected } => {
+                write!(f, "field `{found}` was renamed to `{expected}`")
+            }
+            Self::WrongType { path, expected } => {
+                write!(f, "field `{path}` should be a {expected}")
+            }
+        }
+    }
+}
+
+fn schema_kind(path: &str) -> Option<FieldKind> {
+    SCHEMA
+        .iter()
+        .find(|(p, _, _)| *p == path)
+        .map(|&(_, k, _)| k)
+}
+
+/// Whether any schema entries are nested under `path`
+fn has_children(path: &str) -> bool {
+    SCHEMA.iter().any(|(p, _, _)| {
+        p.len() > path.len() && p.starts_with(path) && p[path.len()..].starts_with('.')
+    })
+}
+
+fn matches_kind(value: &Value, kind: FieldKind) -> bool {
+    match kind {
+        FieldKind::String => value.is_string(),
+        FieldKind::Number => value.is_number(),
+        FieldKind::Bool => value.is_boolean(),
+        FieldKind::Array => value.is_array(),
+        FieldKind::Object => value.is_object(),
+    }
+}
+
+fn check_object(
+    object: &serde_json::Map<String, Value>,
+    prefix: &str,
+    out: &mut Vec<InputDiagnostic>,
+) {
+    for (key, value) in object {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        if let Some(&(_, expected)) = RENAMED.iter().find(|(old, _)| *old == path) {
+            out.push(InputDiagnostic::Renamed {
+                found: path,
+                expected: expected.to_string(),
+            });
+            continue;
+        }
+        let Some(kind) = schema_kind(&path) else {
+            out.push(InputDiagnostic::Unknown(path));
+            continue;
+        };
+        if value.is_null() {
+            continue;
+        }
+        if !matches_kind(value, kind) {
+            out.push(InputDiagnostic::WrongType {
+                path,
+                expected: kind,
+            });
+        } else if let Value::Object(inner) = value
+            && has_children(&path)
+        {
+            check_object(inner, &path, out);
+        }
+    }
+}
+
+fn lookup<'a>(root: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(root, |v, key| v.get(key))
+}
+
+/// Check status line input against the schema
+/// Returns an error if `json` is not a JSON object; otherwise the problems found, if any
+pub fn validate_input(json: &str) -> serde_json::Result<Vec<InputDiagnostic>> {
+    let root: Value = serde_json::from_str(json)?;
+    let Value::Object(object) = &root else {
+        return Err(serde::de::Error::custom("input must be a JSON object"));
+    };
+
+    let mut out = Vec::new();
+    check_object(object, "", &mut out);
+    for &(path, _, required) in SCHEMA {
+        if !required || lookup(&root, path).is_some() {
+            continue;
+        }
+        // Don't report a child missing when its parent is already missing or renamed
+        let parent_missing = path
+            .rsplit_once('.')
+            .is_some_and(|(parent, _)| lookup(&root, parent).is_none());
+        let renamed = RENAMED.iter().any(|(_, new)| *new == path)
+            && out.iter().any(
+                |d| matches!(d, InputDiagnostic::Renamed { expected, .. } if expected == path),
+            );
+        if !parent_missing && !renamed {
+            out.push(InputDiagnostic::Missing(path.to_string()));
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FULL: &str = r#"{
+        "hook_event_name": "Status",
+        "session_id": "abc",
+        "transcript_path": "/tmp/t.jsonl",
+        "cwd": "/repo",
+        "model": {"id": "claude-opus", "display_name": "Opus"},
+        "workspace": {"current_dir": "/repo", "project_dir": "/repo"},
+        "version": "1.0.80",
+        "output_style": {"name": "default"},
+        "cost": {"total_cost_usd": 0.01, "total_duration_ms": 45000},
+        "context_window": {"remaining_percentage": 40, "current_usage": {"input_tokens": 5}}
+    }"#;
+
+    #[test]
+    fn full_input_is_clean() {
+        assert_eq!(validate_input(FULL).unwrap(), []);
+    }
+
+    #[test]
+    fn reports_unknown_renamed_and_wrong_types() {
+        let diagnostics = validate_input(
+            r#"{"cwd": "/repo", "workspace": {"current_dir": "/repo", "project_dir": "/repo"},
+                "model": {"name": "Opus"}, "cost": {"total_cost_usd": "1"}, "new_thing": 1}"#,
+        )
+        .unwrap();
+        assert!(diagnostics.contains(&InputDiagnostic::Renamed {
+            found: "model.name".into(),
+            expected: "model.display_name".into(),
+        }));
+        assert!(diagnostics.contains(&InputDiagnostic::WrongType {
+            path: "cost.total_cost_usd".into(),
+            expected: FieldKind::Number,
+        }));
+        assert!(diagnostics.contains(&InputDiagnostic::Unknown("new_thing".into())));
+        // The renamed field is not also reported as missing
+        assert!(!diagnostics.contains(&InputDiagnostic::Missing("model.display_name".into())));
+    }
+
+    #[test]
+    fn reports_missing_fields_once() {
+        let diagnostics =
+            validate_input(r#"{"cwd": "/repo", "model": {"display_name": "Opus"}}"#).unwrap();
+        assert_eq!(diagnostics, [InputDiagnostic::Missing("workspace".into())]);
+    }
+
+    #[test]
+    fn rejects_non_objects() {
+        assert!(validate_input("[]").is_err());
+        assert!(validate_input("not json").is_err());
+    }
+}
@@ -0,0 +1,360 @@
+//! Per-session summaries and the `stats` report
+//!
+//! With `session_stats` on (or a budget component), renders append the session's running totals to
+//! `sessions.jsonl` in the data dir, at most once a minute per session. Totals
+//! are cumulative, so a period's usage is each session's latest line minus its
+//! last line from before the period.
+
+use crate::cache::{atomic_rename, get_state_dir, unique_hex};
+use crate::config::{BudgetPeriod, DurationFormat};
+use crate::input::ClaudeInput;
+use crate::render::{format_duration, format_tokens};
+use crate::{display_os, get_home, hash_path, sys};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Minimum seconds between lines for the same session
+const RECORD_INTERVAL: u64 = 60;
+const STATS_FILE: &str = "sessions.jsonl";
+const DAY: u64 = 24 * 60 * 60;
+
+/// Running totals of one session at `time`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionRecord {
+    pub session: String,
+    /// Unix seconds
+    pub time: u64,
+    pub duration_ms: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+    /// Project directory name
+    pub repo: String,
+    pub model: String,
+}
+
+impl SessionRecord {
+    fn from_input(data: &ClaudeInput, session: &str, time: u64) -> Self {
+        let dir = data
+            .workspace
+            .project_dir
+            .as_deref()
+            .or(data.cwd.as_deref())
+            .unwrap_or_default();
+        SessionRecord {
+            session: session.to_string(),
+            time,
+            duration_ms: data.cost.total_duration_ms.unwrap_or(0),
+            input_tokens: data.context_window.total_input_tokens.unwrap_or(0),
+            output_tokens: data.context_window.total_output_tokens.unwrap_or(0),
+            cost_usd: data.cost.total_cost_usd.unwrap_or(0.0),
+            repo: Path::new(dir)
+                .file_name()
+                .map(display_os)
+                .unwrap_or_default(),
+            model: data.model.display_name.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Usage since `baseline`, an earlier line of the same session
+    fn since(&self, baseline: &SessionRecord) -> SessionRecord {
+        SessionRecord {
+            duration_ms: self.duration_ms.saturating_sub(baseline.duration_ms),
+            input_tokens: self.input_tokens.saturating_sub(baseline.input_tokens),
+            output_tokens: self.output_tokens.saturating_sub(baseline.output_tokens),
+            cost_usd: (self.cost_usd - baseline.cost_usd).max(0.0),
+            ..self.clone()
+        }
+    }
+}
+
+/// Uses $XDG_DATA_HOME/cc-statusline or ~/.local/share/cc-statusline
+pub fn data_dir() -> Option<PathBuf> {
+    let base = match sys::var("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let home = get_home();
+            if home.is_empty() {
+                return None;
+            }
+            PathBuf::from(&*home).join(".local").join("share")
+        }
+    };
+    Some(base.join("cc-statusline"))
+}
+
+/// Append the session's totals, unless it was recorded in the last minute
+pub(crate) fn record(data: &ClaudeInput) {
+    let Some(session) = data.session_id.as_deref().filter(|s| !s.is_empty()) else {
+        return;
+    };
+    let Some(dir) = data_dir() else {
+        return;
+    };
+    let now = sys::now_secs();
+    let state_path = get_state_dir().join(format!("session-{:016x}.state", hash_path(session)));
+    let last = fs::read_to_string(&state_path)
+        .ok()
+        .and_then(|s| s.trim().parse::<u64>().ok());
+    if last.is_some_and(|last| now.saturating_sub(last) < RECORD_INTERVAL) {
+        return;
+    }
+    let Ok(mut line) = serde_json::to_string(&SessionRecord::from_input(data, session, now)) else {
+        return;
+    };
+    line.push('\n');
+    // One write per line, so concurrent sessions' appends don't interleave
+    let appended = fs::create_dir_all(&dir).and_then(|()| {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(STATS_FILE))?
+            .write_all(line.as_bytes())
+    });
+    if appended.is_ok() {
+        let temp_path = get_state_dir().join(format!("session-tmp-{}", unique_hex()));
+        if fs::write(&temp_path, now.to_string()).is_ok() {
+            let _ = atomic_rename(&temp_path, &state_path);
+        }
+    }
+}
+
+/// Seconds since the session's usage last changed
+/// Renders with the same cost, tokens, and API time don't count as activity
+pub(crate) fn idle_secs(data: &ClaudeInput) -> Option<u64> {
+    let session = data.session_id.as_deref().filter(|s| !s.is_empty())?;
+    let now = sys::now_secs();
+    let path = get_state_dir().join(format!("idle-{:016x}.state", hash_path(session)));
+    let activity = format!(
+        "{:?} {:?} {:?} {:?}",
+        data.cost.total_cost_usd,
+        data.cost.total_api_duration_ms,
+        data.context_window.total_input_tokens,
+        data.context_window.total_output_tokens
+    );
+    let state = fs::read_to_string(&path).unwrap_or_default();
+    if let Some((since, last)) = state.split_once(' ')
+        && last == activity
+        && let Ok(since) = since.parse::<u64>()
+    {
+        return Some(now.saturating_sub(since));
+    }
+    let temp_path = get_state_dir().join(format!("idle-tmp-{}", unique_hex()));
+    if fs::write(&temp_path, format!("{now} {activity}")).is_ok() {
+        let _ = atomic_rename(&temp_path, &path);
+    }
+    Some(0)
+}
+
+/// Every recorded line, oldest first; unreadable lines are skipped
+pub fn load() -> Vec<SessionRecord> {
+    let Some(contents) = data_dir().and_then(|d| fs::read_to_string(d.join(STATS_FILE)).ok())
+    else {
+        return Vec::new();
+    };
+    let mut records: Vec<SessionRecord> = contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    records.sort_by_key(|r| r.time);
+    records
+}
+
+/// Each session's usage from `since` on, for sessions active since then
+pub fn usage_since(records: &[SessionRecord], since: u64) -> Vec<SessionRecord> {
+    let mut sessions: HashMap<&str, (Option<&SessionRecord>, &SessionRecord)> = HashMap::new();
+    for record in records {
+        let entry = sessions
+            .entry(record.session.as_str())
+            .or_insert((None, record));
+        if record.time < since {
+            entry.0 = Some(record);
+        }
+        if record.time >= entry.1.time {
+            entry.1 = record;
+        }
+    }
+    let mut usage: Vec<SessionRecord> = sessions
+        .into_values()
+        .filter(|(_, latest)| latest.time >= since)
+        .map(|(baseline, latest)| match baseline {
+            Some(baseline) => latest.since(baseline),
+            None => latest.clone(),
+        })
+        .collect();
+    usage.sort_by_key(|r| r.time);
+    usage
+}
+
+/// Cost and tokens used in `period`, with the current session's live totals
+pub(crate) fn period_usage(data: &ClaudeInput, period: BudgetPeriod) -> (f64, u64) {
+    let now = sys::now_secs();
+    let since = match period {
+        BudgetPeriod::Day => start_of_day(now),
+        BudgetPeriod::Week => start_of_week(now),
+    };
+    let mut records = load();
+    if let Some(session) = data.session_id.as_deref().filter(|s| !s.is_empty()) {
+        records.push(SessionRecord::from_input(data, session, now));
+    }
+    usage_since(&records, since)
+        .iter()
+        .fold((0.0, 0), |(cost, tokens), r| {
+            (cost + r.cost_usd, tokens + r.input_tokens + r.output_tokens)
+        })
+}
+
+/// Unix seconds at the local midnight starting the day of `secs`
+pub fn start_of_day(secs: u64) -> u64 {
+    let offset = utc_offset(secs);
+    let local = secs.saturating_add_signed(offset);
+    (local - local % DAY).saturating_add_signed(-offset)
+}
+
+/// Unix seconds at the start of the 7 days ending today
+pub fn start_of_week(secs: u64) -> u64 {
+    start_of_day(secs).saturating_sub(6 * DAY)
+}
+
+#[cfg(unix)]
+fn utc_offset(secs: u64) -> i64 {
+    let Ok(time) = libc::time_t::try_from(secs) else {
+        return 0;
+    };
+    // SAFETY: localtime_r only writes to the tm we pass
+    let mut tm = unsafe { std::mem::zeroed::<libc::tm>() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return 0;
+    }
+    #[allow(clippy::useless_conversion)] // c_long is 32 bits on some targets
+    i64::from(tm.tm_gmtoff)
+}
+
+#[cfg(not(unix))]
+fn utc_offset(_secs: u64) -> i64 {
+    0
+}
+
+/// Usage totals with per-repo and per-model breakdowns
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub sessions: usize,
+    pub duration_ms: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+    /// (name, sessions, cost), most expensive first
+    pub repos: Vec<(String, usize, f64)>,
+    pub models: Vec<(String, usize, f64)>,
+}
+
+impl Summary {
+    pub fn new(usage: &[SessionRecord]) -> Self {
+        let breakdown = |key: fn(&SessionRecord) -> &str| {
+            let mut groups: HashMap<&str, (usize, f64)> = HashMap::new();
+            for record in usage {
+                let group = groups.entry(key(record)).or_default();
+                group.0 += 1;
+                group.1 += record.cost_usd;
+            }
+            let mut groups: Vec<(String, usize, f64)> = groups
+                .into_iter()
+                .map(|(name, (sessions, cost))| (name.to_string(), sessions, cost))
+                .collect();
+            groups.sort_by(|a, b| b.2.total_cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+            groups
+        };
+        Summary {
+            sessions: usage.len(),
+            duration_ms: usage.iter().map(|r| r.duration_ms).sum(),
+            input_tokens: usage.iter().map(|r| r.input_tokens).sum(),
+            output_tokens: usage.iter().map(|r| r.output_tokens).sum(),
+            cost_usd: usage.iter().map(|r| r.cost_usd).sum(),
+            repos: breakdown(|r| &r.repo),
+            models: breakdown(|r| &r.model),
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Sessions  {}", self.sessions)?;
+        writeln!(
+            f,
+            "Duration  {}",
+            format_duration(self.duration_ms, DurationFormat::Minutes)
+        )?;
+        writeln!(
+            f,
+            "Tokens    {} in, {} out",
+            format_tokens(self.input_tokens),
+            format_tokens(self.output_tokens)
+        )?;
+        writeln!(f, "Cost      ${:.2}", self.cost_usd)?;
+        for (title, groups) in [("Repo", &self.repos), ("Model", &self.models)] {
+            let width = groups
+                .iter()
+                .map(|(name, ..)| name.chars().count())
+                .chain([title.len()])
+                .max()
+                .unwrap_or(0);
+            writeln!(f, "\n{title:width$}  Sessions  Cost")?;
+            for (name, sessions, cost) in groups {
+                let name = if name.is_empty() { "-" } else { name };
+                writeln!(f, "{name:width$}  {sessions:<8}  ${cost:.2}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(session: &str, time: u64, cost_usd: f64, repo: &str) -> SessionRecord {
+        SessionRecord {
+            session: session.to_string(),
+            time,
+            input_tokens: time * 10,
+            cost_usd,
+            repo: repo.to_string(),
+            ..SessionRecord::default()
+        }
+    }
+
+    #[test]
+    fn usage_subtracts_lines_before_the_period() {
+        let records = [
+            record("a", 100, 1.0, "app"),
+            record("a", 200, 3.0, "app"),
+            record("b", 150, 2.0, "lib"),
+            record("a", 300, 4.5, "app"),
+            record("c", 50, 9.0, "old"),
+        ];
+        let usage = usage_since(&records, 160);
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].session, "a");
+        assert!((usage[0].cost_usd - 3.5).abs() < 1e-9);
+        assert_eq!(usage[0].input_tokens, 2000);
+
+        let summary = Summary::new(&usage_since(&records, 0));
+        assert_eq!(summary.sessions, 3);
+        assert!((summary.cost_usd - 15.5).abs() < 1e-9);
+        assert_eq!(summary.repos[0].0, "old");
+        assert_eq!(summary.repos[1], ("app".to_string(), 1, 4.5));
+    }
+
+    #[test]
+    fn week_starts_six_days_before_today() {
+        let now = 1_750_000_000;
+        let today = start_of_day(now);
+        assert!(today <= now && now - today < DAY);
+        assert_eq!(start_of_week(now), today - 6 * DAY);
+    }
+}
@@ -0,0 +1,311 @@
+//! Access to the outside world: clock, environment, HTTP, and cache location
+//!
+//! The render pipeline reads these through here rather than calling `std`
+//! or `ureq` directly, so the `testing` feature can substitute fakes.
+
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+#[cfg(feature = "testing")]
+use std::{collections::HashMap, path::PathBuf, sync::RwLock};
+
+/// Fakes installed by `testing::Sandbox`; None outside a sandbox
+#[cfg(feature = "testing")]
+pub(crate) static OVERRIDES: RwLock<Option<Overrides>> = RwLock::new(None);
+
+/// Fake HTTP handler: URL to (status, body)
+#[cfg(feature = "testing")]
+pub(crate) type Responder = Box<dyn Fn(&str) -> (u16, String) + Send + Sync>;
+
+#[cfg(feature = "testing")]
+pub(crate) struct Overrides {
+    pub(crate) now: SystemTime,
+    pub(crate) vars: HashMap<String, String>,
+    pub(crate) http: Option<Responder>,
+    pub(crate) cache_dir: PathBuf,
+}
+
+#[cfg(feature = "testing")]
+fn with_overrides<T>(f: impl FnOnce(&Overrides) -> T) -> Option<T> {
+    let guard = OVERRIDES.read().unwrap_or_else(|e| e.into_inner());
+    guard.as_ref().map(f)
+}
+
+/// Whether fakes are installed; subprocesses that reach outside (gh, git credential) are skipped
+pub(crate) fn sandboxed() -> bool {
+    #[cfg(feature = "testing")]
+    if with_overrides(|_| ()).is_some() {
+        return true;
+    }
+    false
+}
+
+/// Current time
+pub(crate) fn now() -> SystemTime {
+    #[cfg(feature = "testing")]
+    if let Some(now) = with_overrides(|o| o.now) {
+        return now;
+    }
+    SystemTime::now()
+}
+
+/// Current time as Unix seconds
+pub(crate) fn now_secs() -> u64 {
+    now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Environment variable, if set and valid UTF-8
+pub(crate) fn var(name: &str) -> Option<String> {
+    #[cfg(feature = "testing")]
+    if let Some(value) = with_overrides(|o| o.vars.get(name).cloned()) {
+        return value;
+    }
+    std::env::var(name).ok()
+}
+
+/// Environment variables whose names start with `prefix`, skipping non-UTF-8 ones
+pub(crate) fn vars_with_prefix(prefix: &str) -> Vec<(String, String)> {
+    #[cfg(feature = "testing")]
+    if let Some(vars) = with_overrides(|o| {
+        o.vars
+            .iter()
+            .filter(|(name, _)| name.starts_with(prefix))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }) {
+        return vars;
+    }
+    std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .filter(|(name, _)| name.starts_with(prefix))
+        .collect()
+}
+
+/// Cache directory override from the sandbox
+#[cfg(feature = "testing")]
+pub(crate) fn cache_dir_override() -> Option<PathBuf> {
+    with_overrides(|o| o.cache_dir.clone())
+}
+
+/// State directory override from the sandbox, inside its cache directory
+#[cfg(feature = "testing")]
+pub(crate) fn state_dir_override() -> Option<PathBuf> {
+    with_overrides(|o| o.cache_dir.join("state"))
+}
+
+/// Failed HTTP request
+#[derive(Debug)]
+pub(crate) enum HttpError {
+    /// Server answered with a non-success status
+    Status(u16),
+    /// Connection, TLS, or read failure
+    Transport(String),
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Status(code) => write!(f, "HTTP {code}"),
+            Self::Transport(e) => f.write_str(e),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+/// Certificate settings for hosts behind a private CA (GitHub Enterprise, TLS-inspecting proxies)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct TlsOptions {
+    /// PEM bundle trusted in addition to the system roots
+    pub(crate) ca_bundle: Option<String>,
+    /// Accept any certificate; only for internal hosts
+    pub(crate) skip_verify: bool,
+}
+
+impl TlsOptions {
+    fn connector(&self) -> Result<native_tls::TlsConnector, HttpError> {
+        let transport = |e: &dyn fmt::Display| HttpError::Transport(e.to_string());
+        let mut builder = native_tls::TlsConnector::builder();
+        if let Some(path) = &self.ca_bundle {
+            let pem = std::fs::read(Path::new(path)).map_err(|e| transport(&e))?;
+            for cert in pem_certificates(&pem) {
+                builder.add_root_certificate(
+                    native_tls::Certificate::from_pem(cert).map_err(|e| transport(&e))?,
+                );
+            }
+        }
+        builder.danger_accept_invalid_certs(self.skip_verify);
+        builder.build().map_err(|e| transport(&e))
+    }
+}
+
+/// Each certificate block in a PEM bundle
+fn pem_certificates(pem: &[u8]) -> Vec<&[u8]> {
+    const BEGIN: &[u8] = b"-----BEGIN CERTIFICATE-----";
+    const END: &[u8] = b"-----END CERTIFICATE-----";
+    let find = |hay: &[u8], needle: &[u8]| hay.windows(needle.len()).position(|w| w == needle);
+    let mut certs = Vec::new();
+    let mut rest = pem;
+    while let Some(start) = find(rest, BEGIN) {
+        let Some(len) = find(&rest[start..], END) else {
+            break;
+        };
+        let end = start + len + END.len();
+        certs.push(&rest[start..end]);
+        rest = &rest[end..];
+    }
+    certs
+}
+
+/// Proxy for `url` from `HTTPS_PROXY`/`HTTP_PROXY` (then `ALL_PROXY`), unless
+/// `NO_PROXY` lists its host; lowercase names are honored too
+fn proxy_for(url: &str) -> Option<String> {
+    let env = |name: &str| {
+        var(name)
+            .or_else(|| var(&name.to_ascii_lowercase()))
+            .filter(|v| !v.is_empty())
+    };
+    let (scheme, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?']).next()?;
+    let host = authority.rsplit_once(':').map_or(authority, |(h, _)| h);
+    if let Some(no_proxy) = env("NO_PROXY")
+        && bypasses_proxy(&no_proxy, host)
+    {
+        return None;
+    }
+    let name = if scheme == "https" {
+        "HTTPS_PROXY"
+    } else {
+        "HTTP_PROXY"
+    };
+    env(name).or_else(|| env("ALL_PROXY"))
+}
+
+/// Whether a `NO_PROXY` list (`*`, hosts, or `.domain` suffixes) covers `host`
+fn bypasses_proxy(no_proxy: &str, host: &str) -> bool {
+    no_proxy.split(',').map(str::trim).any(|entry| {
+        let entry = entry.rsplit_once(':').map_or(entry, |(h, _)| h);
+        let domain = entry.trim_start_matches('.');
+        entry == "*"
+            || (!domain.is_empty()
+                && (host.eq_ignore_ascii_case(domain)
+                    || host
+                        .to_ascii_lowercase()
+                        .ends_with(&format!(".{}", domain.to_ascii_lowercase()))))
+    })
+}
+
+/// Successful (or not-modified) HTTP response
+#[derive(Debug)]
+pub(crate) struct HttpResponse {
+    pub(crate) status: u16,
+    pub(crate) body: String,
+    /// `ETag` header, to send back as `If-None-Match`
+    pub(crate) etag: Option<String>,
+    /// GitHub's `X-OAuth-Scopes` header: a classic token's scopes
+    pub(crate) oauth_scopes: Option<String>,
+}
+
+/// GET `url` with `headers`, returning the body of a successful response
+/// Goes through the environment's proxy, and trusts the CAs in `tls`
+pub(crate) fn http_get(
+    url: &str,
+    headers: &[(&str, &str)],
+    timeout: Option<Duration>,
+    tls: &TlsOptions,
+) -> Result<String, HttpError> {
+    http_get_response(url, headers, timeout, tls).map(|resp| resp.body)
+}
+
+/// Like [`http_get`], keeping the status and `ETag`; a 304 is not an error
+pub(crate) fn http_get_response(
+    url: &str,
+    headers: &[(&str, &str)],
+    timeout: Option<Duration>,
+    tls: &TlsOptions,
+) -> Result<HttpResponse, HttpError> {
+    #[cfg(feature = "testing")]
+    if let Some(response) = with_overrides(|o| {
+        o.http
+            .as_ref()
+            .map_or((0, "offline".to_string()), |respond| respond(url))
+    }) {
+        return match response {
+            (status @ 200..=399, body) => Ok(HttpResponse {
+                status,
+                body,
+                etag: None,
+                oauth_scopes: None,
+            }),
+            (0, e) => Err(HttpError::Transport(e)),
+            (code, _) => Err(HttpError::Status(code)),
+        };
+    }
+
+    let mut agent = ureq::AgentBuilder::new();
+    if let Some(proxy) = proxy_for(url) {
+        let proxy = ureq::Proxy::new(proxy).map_err(|e| HttpError::Transport(e.to_string()))?;
+        agent = agent.proxy(proxy);
+    }
+    if *tls != TlsOptions::default() {
+        agent = agent.tls_connector(Arc::new(tls.connector()?));
+    }
+    let mut request = agent.build().get(url);
+    for (name, value) in headers {
+        request = request.set(name, value);
+    }
+    if let Some(timeout) = timeout {
+        request = request.timeout(timeout);
+    }
+    match request.call() {
+        Ok(resp) => {
+            let status = resp.status();
+            let etag = resp.header("etag").map(str::to_string);
+            let oauth_scopes = resp.header("x-oauth-scopes").map(str::to_string);
+            let body = resp
+                .into_string()
+                .map_err(|e| HttpError::Transport(e.to_string()))?;
+            Ok(HttpResponse {
+                status,
+                body,
+                etag,
+                oauth_scopes,
+            })
+        }
+        Err(ureq::Error::Status(code, _)) => Err(HttpError::Status(code)),
+        Err(e) => Err(HttpError::Transport(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_proxy_matching() {
+        assert!(bypasses_proxy("*", "api.github.com"));
+        assert!(bypasses_proxy(
+            "localhost, .corp.example",
+            "ghe.corp.example"
+        ));
+        assert!(bypasses_proxy("corp.example", "ghe.corp.example"));
+        assert!(bypasses_proxy("ghe.corp.example:443", "GHE.corp.example"));
+        assert!(!bypasses_proxy("corp.example", "notcorp.example"));
+        assert!(!bypasses_proxy("", "api.github.com"));
+    }
+
+    #[test]
+    fn pem_bundle_split() {
+        let pem = b"junk\n-----BEGIN CERTIFICATE-----\nAAA\n-----END CERTIFICATE-----\n\
+            -----BEGIN CERTIFICATE-----\nBBB\n-----END CERTIFICATE-----\n";
+        let certs = pem_certificates(pem);
+        assert_eq!(certs.len(), 2);
+        assert!(certs[1].starts_with(b"-----BEGIN") && certs[1].ends_with(b"CERTIFICATE-----"));
+        assert!(pem_certificates(b"no certs").is_empty());
+    }
+}
@@ -0,0 +1,113 @@
+//! Deterministic test harness for the render pipeline (testing feature)
+//!
+//! A [`Sandbox`] swaps the clock, environment variables, HTTP, and cache
+//! directory for fakes while it is alive, so full rendered output can be
+//! compared against snapshots:
+//!
+//! ```
+//! use cc_statusline::testing::Sandbox;
+//! use cc_statusline::{ClaudeInput, StatusLine};
+//!
+//! let sandbox = Sandbox::new();
+//! sandbox.set_var("HOME", "/home/dev");
+//! let status = StatusLine::builder(ClaudeInput::from_json(r#"{"cwd": "/home/dev/app"}"#)).build();
+//! ```
+//!
+//! Sandboxes are process-global: creating one blocks until any other sandbox
+//! in the process is dropped, so tests using them run one at a time.
+
+use crate::cache::unique_hex;
+use crate::sys::{OVERRIDES, Overrides};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+static SANDBOX_LOCK: Mutex<()> = Mutex::new(());
+
+/// Fixed starting time for sandboxes: 2024-01-01T00:00:00Z
+pub const DEFAULT_TIME: u64 = 1_704_067_200;
+
+/// Fake clock, environment, HTTP, and cache directory; restored on drop
+///
+/// Inside a sandbox, environment variables read by the pipeline are unset
+/// unless set with [`set_var`](Sandbox::set_var), HTTP requests fail as
+/// offline unless a responder is installed, and the `gh` CLI and git
+/// credential helper are never run.
+pub struct Sandbox {
+    cache_dir: PathBuf,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        let lock = SANDBOX_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let cache_dir = env::temp_dir().join(format!("cc-statusline-sandbox-{}", unique_hex()));
+        let _ = fs::create_dir_all(cache_dir.join("state"));
+        *OVERRIDES.write().unwrap_or_else(|e| e.into_inner()) = Some(Overrides {
+            now: SystemTime::UNIX_EPOCH + Duration::from_secs(DEFAULT_TIME),
+            vars: HashMap::new(),
+            http: None,
+            cache_dir: cache_dir.clone(),
+        });
+        Self {
+            cache_dir,
+            _lock: lock,
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Overrides)) {
+        if let Some(overrides) = OVERRIDES
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_mut()
+        {
+            f(overrides);
+        }
+    }
+
+    /// Set the fake clock to Unix time `secs`
+    pub fn set_time(&self, secs: u64) {
+        self.update(|o| o.now = SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+    }
+
+    /// Move the fake clock forward
+    pub fn advance(&self, by: Duration) {
+        self.update(|o| o.now += by);
+    }
+
+    /// Set an environment variable as seen by the pipeline
+    pub fn set_var(&self, name: &str, value: &str) {
+        self.update(|o| {
+            o.vars.insert(name.to_string(), value.to_string());
+        });
+    }
+
+    /// Answer HTTP GETs with `respond(url) -> (status, body)`; status 0 is a network error
+    pub fn respond_with<F>(&self, respond: F)
+    where
+        F: Fn(&str) -> (u16, String) + Send + Sync + 'static,
+    {
+        self.update(|o| o.http = Some(Box::new(respond)));
+    }
+
+    /// Empty per-sandbox cache directory, removed on drop
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        *OVERRIDES.write().unwrap_or_else(|e| e.into_inner()) = None;
+        let _ = fs::remove_dir_all(&self.cache_dir);
+    }
+}
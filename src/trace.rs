@@ -0,0 +1,32 @@
+//! Chrome trace output (trace feature)
+//!
+//! With `CC_STATUSLINE_TRACE=<file>`, spans for each stage of a run (stdin,
+//! parse, build, discover, git, ahead_behind, pr, render) are written to
+//! `<file>` in the Chrome trace event format, which chrome://tracing and
+//! Perfetto open.
+//! Background lookups show up on their own threads.
+
+use crate::sys;
+use std::fs::File;
+use std::io::BufWriter;
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::prelude::*;
+
+/// Start tracing if `CC_STATUSLINE_TRACE` names a file
+/// The trace is complete once the returned guard is dropped
+pub fn init() -> Option<FlushGuard> {
+    let path = sys::var("CC_STATUSLINE_TRACE").filter(|p| !p.is_empty())?;
+    let file = match File::create(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("cc-statusline: can't write trace to {path}: {e}");
+            return None;
+        }
+    };
+    let (layer, guard) = ChromeLayerBuilder::new()
+        .writer(BufWriter::new(file))
+        .include_args(true)
+        .build();
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer)).ok()?;
+    Some(guard)
+}
@@ -0,0 +1,226 @@
+//! Session transcript statistics from Claude Code's `transcript_path`
+//!
+//! The transcript is JSONL that only ever grows during a session, so the
+//! totals are cached with the byte offset they cover and each run parses
+//! just the lines appended since.
+
+use crate::cache::{atomic_rename, get_cache_dir, unique_hex};
+use crate::hash_path;
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Size and activity of a session transcript
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct TranscriptStats {
+    pub(crate) bytes: u64,
+    /// User prompts, not counting tool results sent back as user entries
+    pub(crate) turns: u32,
+    pub(crate) tool_calls: u32,
+    /// Name of the most recent tool the assistant invoked
+    pub(crate) last_tool: Option<String>,
+    /// End of the last complete line counted
+    pub(crate) offset: u64,
+}
+
+impl TranscriptStats {
+    /// Count one transcript entry
+    fn add_entry(&mut self, entry: &Value) {
+        let content = &entry["message"]["content"];
+        match entry["type"].as_str() {
+            Some("user") => {
+                let tool_result = content
+                    .as_array()
+                    .is_some_and(|items| items.iter().any(|i| i["type"] == "tool_result"));
+                if !tool_result {
+                    self.turns += 1;
+                }
+            }
+            Some("assistant") => {
+                for item in content.as_array().into_iter().flatten() {
+                    if item["type"] == "tool_use" {
+                        self.tool_calls += 1;
+                        self.last_tool = item["name"].as_str().map(str::to_string);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Parse cache content: "<offset>\n<turns>\n<tool_calls>\n<last_tool>"
+    fn parse_cache(content: &str) -> Option<Self> {
+        let mut lines = content.lines();
+        let offset = lines.next()?.parse().ok()?;
+        let turns = lines.next()?.parse().ok()?;
+        let tool_calls = lines.next()?.parse().ok()?;
+        let last_tool = lines.next().filter(|t| !t.is_empty()).map(str::to_string);
+        Some(Self {
+            bytes: offset,
+            turns,
+            tool_calls,
+            last_tool,
+            offset,
+        })
+    }
+
+    fn to_cache(&self) -> String {
+        format!(
+            "{}\n{}\n{}\n{}",
+            self.offset,
+            self.turns,
+            self.tool_calls,
+            self.last_tool.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+fn get_transcript_cache_path(path: &Path) -> PathBuf {
+    get_cache_dir().join(format!(
+        "transcript-{:016x}.cache",
+        hash_path(&path.to_string_lossy())
+    ))
+}
+
+/// Continue counting `path` from `prev`, or from the start if the file shrank
+fn scan(path: &Path, prev: TranscriptStats) -> Option<TranscriptStats> {
+    let mut file = File::open(path).ok()?;
+    let bytes = file.metadata().ok()?.len();
+    let mut stats = if prev.offset <= bytes {
+        prev
+    } else {
+        TranscriptStats::default()
+    };
+    stats.bytes = bytes;
+    if stats.offset == bytes {
+        return Some(stats);
+    }
+
+    file.seek(SeekFrom::Start(stats.offset)).ok()?;
+    let mut reader = BufReader::new(file);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let n = reader.read_until(b'\n', &mut line).ok()?;
+        // Stop before a partial last line; it is counted once complete
+        if n == 0 || line.last() != Some(&b'\n') {
+            break;
+        }
+        stats.offset += n as u64;
+        if let Ok(entry) = serde_json::from_slice::<Value>(&line) {
+            stats.add_entry(&entry);
+        }
+    }
+    Some(stats)
+}
+
+/// Transcript stats, reading only what was appended since the last call
+/// Returns None if the file can't be read
+pub(crate) fn stats(path: &Path) -> Option<TranscriptStats> {
+    let cache_path = get_transcript_cache_path(path);
+    let prev = fs::read_to_string(&cache_path)
+        .ok()
+        .and_then(|c| TranscriptStats::parse_cache(&c))
+        .unwrap_or_default();
+    let unchanged = prev.offset;
+    let stats = scan(path, prev)?;
+    if stats.offset != unchanged {
+        let temp_path = get_cache_dir().join(format!("transcript-tmp-{}", unique_hex()));
+        if fs::write(&temp_path, stats.to_cache()).is_ok() {
+            let _ = atomic_rename(&temp_path, &cache_path);
+        }
+    }
+    Some(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::io::Write;
+
+    const PROMPT: &str = r#"{"type":"user","message":{"role":"user","content":"hi"}}"#;
+    const TOOL_USE: &str = r#"{"type":"assistant","message":{"content":[{"type":"text","text":"ok"},{"type":"tool_use","id":"t1","name":"Bash","input":{}}]}}"#;
+    const TOOL_RESULT: &str =
+        r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"t1"}]}}"#;
+    const EDIT: &str = r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t2","name":"Edit","input":{}}]}}"#;
+
+    fn temp_transcript() -> PathBuf {
+        env::temp_dir().join(format!("cc-statusline-transcript-{}.jsonl", unique_hex()))
+    }
+
+    #[test]
+    fn counts_prompts_and_tool_calls() {
+        let path = temp_transcript();
+        fs::write(
+            &path,
+            format!("{PROMPT}\n{TOOL_USE}\n{TOOL_RESULT}\n{PROMPT}\n"),
+        )
+        .unwrap();
+        let stats = scan(&path, TranscriptStats::default()).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(stats.turns, 2);
+        assert_eq!(stats.tool_calls, 1);
+        assert_eq!(stats.last_tool.as_deref(), Some("Bash"));
+        assert_eq!(stats.bytes, stats.offset);
+    }
+
+    #[test]
+    fn resumes_from_offset_and_skips_partial_line() {
+        let path = temp_transcript();
+        fs::write(&path, format!("{PROMPT}\n{TOOL_USE}\n")).unwrap();
+        let first = scan(&path, TranscriptStats::default()).unwrap();
+
+        // A half-written line is left for the next scan
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "{TOOL_RESULT}\n{}", &EDIT[..20]).unwrap();
+        let second = scan(&path, first.clone()).unwrap();
+        assert_eq!((second.turns, second.tool_calls), (1, 1));
+        assert!(second.offset < second.bytes);
+
+        writeln!(file, "{}", &EDIT[20..]).unwrap();
+        let third = scan(&path, second).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!((third.turns, third.tool_calls), (1, 2));
+        assert_eq!(third.last_tool.as_deref(), Some("Edit"));
+    }
+
+    #[test]
+    fn rescans_when_file_shrinks() {
+        let path = temp_transcript();
+        fs::write(&path, format!("{PROMPT}\n")).unwrap();
+        let stale = TranscriptStats {
+            turns: 40,
+            offset: 1_000_000,
+            ..TranscriptStats::default()
+        };
+        let stats = scan(&path, stale).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(stats.turns, 1);
+    }
+
+    #[test]
+    fn cache_round_trip() {
+        let stats = TranscriptStats {
+            bytes: 120,
+            turns: 3,
+            tool_calls: 7,
+            last_tool: Some("Read".into()),
+            offset: 120,
+        };
+        assert_eq!(TranscriptStats::parse_cache(&stats.to_cache()), Some(stats));
+        assert_eq!(TranscriptStats::parse_cache("garbage"), None);
+    }
+
+    #[test]
+    fn missing_file() {
+        assert_eq!(
+            scan(
+                Path::new("/nonexistent/t.jsonl"),
+                TranscriptStats::default()
+            ),
+            None
+        );
+    }
+}
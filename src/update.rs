@@ -0,0 +1,141 @@
+//! Opt-in check for newer cc-statusline releases
+//!
+//! The latest release tag is fetched from GitHub at most once a day and cached;
+//! the `update` component only ever reads the cache, refreshing it on a
+//! background thread when it is stale.
+
+use crate::Config;
+use crate::cache::{atomic_rename, get_cache_dir, unique_hex};
+use crate::sys::{self, TlsOptions};
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const UPDATE_CHECK_TTL: u64 = 24 * 60 * 60; // seconds
+const LATEST_RELEASE_API: &str =
+    "https://api.github.com/repos/karbassi/cc-status-line/releases/latest";
+/// Page the `update` segment links to
+pub(crate) const RELEASES_URL: &str = "https://github.com/karbassi/cc-status-line/releases/latest";
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+}
+
+fn get_update_cache_path() -> PathBuf {
+    get_cache_dir().join("latest-version.cache")
+}
+
+/// Parse cache content: "<checked_at>\n<version>" (version is empty if the check failed)
+fn parse_update_cache(content: &str) -> Option<(u64, String)> {
+    let mut lines = content.lines();
+    let checked_at = lines.next()?.parse().ok()?;
+    let version = lines.next().unwrap_or_default().trim().to_string();
+    Some((checked_at, version))
+}
+
+fn save_update_cache(version: &str) {
+    let temp_path = get_cache_dir().join(format!("latest-version-tmp-{}", unique_hex()));
+    if fs::write(&temp_path, format!("{}\n{version}", sys::now_secs())).is_ok() {
+        let _ = atomic_rename(&temp_path, &get_update_cache_path());
+    }
+}
+
+/// Fetch the latest release version (without a leading `v`) from GitHub
+fn fetch_latest_version(tls: &TlsOptions) -> io::Result<String> {
+    let body = sys::http_get(
+        LATEST_RELEASE_API,
+        &[
+            ("Accept", "application/vnd.github+json"),
+            ("User-Agent", "cc-statusline"),
+            ("X-GitHub-Api-Version", "2022-11-28"),
+        ],
+        Some(Duration::from_secs(5)),
+        tls,
+    )
+    .map_err(io::Error::other)?;
+    let release: Release = serde_json::from_str(&body)?;
+    Ok(release.tag_name.trim_start_matches('v').to_string())
+}
+
+/// Whether dotted version `latest` is newer than `current`; pre-release suffixes are ignored
+fn is_newer(latest: &str, current: &str) -> bool {
+    fn parts(v: &str) -> Vec<u64> {
+        v.split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|p| p.parse().unwrap_or(0))
+            .collect()
+    }
+    parts(latest) > parts(current)
+}
+
+/// Newer release from the cache, if any; a stale cache is refreshed on the
+/// returned thread unless `offline`
+pub(crate) fn check_cached(
+    offline: bool,
+    tls: &TlsOptions,
+) -> (Option<String>, Option<JoinHandle<()>>) {
+    let cached = fs::read_to_string(get_update_cache_path())
+        .ok()
+        .and_then(|c| parse_update_cache(&c));
+    let (checked_at, version) = cached.unwrap_or_default();
+
+    let expired = sys::now_secs().saturating_sub(checked_at) >= UPDATE_CHECK_TTL;
+    let refresh = (expired && !offline).then(|| {
+        // Record the attempt first so concurrent renders don't all hit the API
+        save_update_cache(&version);
+        let tls = tls.clone();
+        thread::spawn(move || {
+            if let Ok(latest) = fetch_latest_version(&tls) {
+                save_update_cache(&latest);
+            }
+        })
+    });
+
+    let newer =
+        (!version.is_empty() && is_newer(&version, env!("CARGO_PKG_VERSION"))).then_some(version);
+    (newer, refresh)
+}
+
+/// Check GitHub for the latest release now, updating the cache
+/// Returns the latest version when it is newer than this build
+/// Uses the TLS settings from the config file
+pub fn check_latest() -> io::Result<Option<String>> {
+    let latest = fetch_latest_version(&Config::load(None).tls_options())?;
+    save_update_cache(&latest);
+    Ok(is_newer(&latest, env!("CARGO_PKG_VERSION")).then_some(latest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_comparison() {
+        assert!(is_newer("0.1.8", "0.1.7"));
+        assert!(is_newer("0.2.0", "0.1.10"));
+        assert!(is_newer("1.0", "0.9.9"));
+        assert!(!is_newer("0.1.7", "0.1.7"));
+        assert!(!is_newer("0.1.10", "0.2.0"));
+        assert!(!is_newer("0.1.7-rc.1", "0.1.7"));
+    }
+
+    #[test]
+    fn update_cache_parsing() {
+        assert_eq!(
+            parse_update_cache("1700000000\n0.2.0"),
+            Some((1_700_000_000, "0.2.0".to_string()))
+        );
+        // A failed check only records the attempt time
+        assert_eq!(
+            parse_update_cache("1700000000\n"),
+            Some((1_700_000_000, String::new()))
+        );
+        assert_eq!(parse_update_cache("garbage"), None);
+    }
+}
@@ -0,0 +1,275 @@
+//! Non-git version control: Sapling and Subversion
+//!
+//! Used only when no git repo is found. State comes from the VCS's own CLI,
+//! cached per checkout until the files it depends on change.
+
+use crate::cache::{atomic_rename, get_cache_dir, unique_hex};
+use crate::pr::PrInfo;
+use crate::render::run_command_with_timeout;
+use crate::{hash_path, sys};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Time allowed for the VCS CLI on a cache miss
+const VCS_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Sapling template: short commit, active bookmark, and linked GitHub PR
+const SAPLING_TEMPLATE: &str =
+    "{node|short}\\n{activebookmark}\\n{github_pull_request_number}\\n{github_pull_request_url}";
+
+/// Seconds SVN state is reused; file edits don't touch `wc.db`, so the
+/// modified count is refreshed on a timer as well
+const SVN_REFRESH_SECS: u64 = 10;
+
+/// Statuses counted as local changes in `svn status -q` output
+const SVN_CHANGED: &[char] = &['M', 'A', 'D', 'R', 'C', '!', '~'];
+
+/// Supported version control systems other than git
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum VcsKind {
+    Sapling,
+    Svn,
+}
+
+impl VcsKind {
+    /// Marker directory at the checkout root
+    fn marker(self) -> &'static str {
+        match self {
+            Self::Sapling => ".sl",
+            Self::Svn => ".svn",
+        }
+    }
+
+    /// Files whose change invalidates cached state
+    fn state_files(self) -> &'static [&'static str] {
+        match self {
+            Self::Sapling => &["dirstate", "bookmarks.current"],
+            Self::Svn => &["wc.db"],
+        }
+    }
+
+    fn command(self) -> String {
+        match self {
+            Self::Sapling => format!("sl log -r . -T '{SAPLING_TEMPLATE}'"),
+            Self::Svn => "svn info --xml && svn status -q".to_string(),
+        }
+    }
+}
+
+/// Checkout state for the status line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct VcsInfo {
+    pub(crate) kind: VcsKind,
+    /// Bookmark or branch name, if any
+    pub(crate) branch: Option<String>,
+    /// Short id of the current commit
+    pub(crate) commit: String,
+    /// Locally modified files, if the VCS reports them
+    pub(crate) changed_files: Option<u32>,
+    /// Pull request linked to the current commit
+    pub(crate) pr: Option<PrInfo>,
+}
+
+impl VcsInfo {
+    /// Name to show in the branch segment: the bookmark, else the commit
+    pub(crate) fn label(&self) -> &str {
+        self.branch.as_deref().unwrap_or(&self.commit)
+    }
+}
+
+/// Find the checkout root and kind for `dir`
+fn find_root(dir: &Path) -> Option<(VcsKind, PathBuf)> {
+    dir.ancestors().find_map(|d| {
+        [VcsKind::Sapling, VcsKind::Svn]
+            .into_iter()
+            .find(|kind| d.join(kind.marker()).is_dir())
+            .map(|kind| (kind, d.to_path_buf()))
+    })
+}
+
+/// Modification times of the state files, as a cache validity stamp
+fn state_stamp(kind: VcsKind, root: &Path) -> String {
+    let marker = root.join(kind.marker());
+    kind.state_files()
+        .iter()
+        .map(|f| {
+            fs::metadata(marker.join(f))
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_nanos())
+                .to_string()
+        })
+        .chain((kind == VcsKind::Svn).then(|| (sys::now_secs() / SVN_REFRESH_SECS).to_string()))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Parse `sl log` output for the current commit
+fn parse_sapling(output: &str) -> Option<VcsInfo> {
+    let mut lines = output.lines().map(str::trim);
+    let commit = lines.next().filter(|c| !c.is_empty())?.to_string();
+    let nonempty = |s: Option<&str>| s.filter(|s| !s.is_empty()).map(str::to_string);
+    let branch = nonempty(lines.next());
+    let number = lines.next().and_then(|n| n.parse().ok());
+    let url = nonempty(lines.next()).unwrap_or_default();
+    Some(VcsInfo {
+        kind: VcsKind::Sapling,
+        branch,
+        commit,
+        changed_files: None,
+        pr: number.map(|number| PrInfo {
+            number,
+            url,
+            ..PrInfo::default()
+        }),
+    })
+}
+
+/// Text of the first `<tag>` element in `xml`
+fn xml_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{tag}>"))? + tag.len() + 2;
+    let end = xml[start..].find('<')? + start;
+    Some(&xml[start..end])
+}
+
+/// Branch name from a repo-relative URL: `^/trunk`, `^/branches/<name>`, or `^/tags/<name>`
+fn svn_branch(relative_url: &str) -> Option<String> {
+    let mut parts = relative_url
+        .trim_start_matches('^')
+        .trim_matches('/')
+        .split('/');
+    let mut prev = None;
+    // Layouts may nest projects, e.g. ^/project/branches/name
+    for part in parts.by_ref() {
+        match (prev, part) {
+            (_, "trunk") => return Some("trunk".to_string()),
+            (Some("branches" | "tags"), name) => return Some(name.to_string()),
+            _ => prev = Some(part),
+        }
+    }
+    None
+}
+
+/// Parse `svn info --xml` followed by `svn status -q` output
+fn parse_svn(output: &str) -> Option<VcsInfo> {
+    let (info, status) = output.split_once("</info>")?;
+    let revision = info
+        .split_once("<commit")
+        .and_then(|(_, c)| c.split_once("revision=\""))
+        .and_then(|(_, r)| r.split_once('"'))
+        .map(|(r, _)| r)?;
+    let changed = status
+        .lines()
+        .filter(|l| l.starts_with(SVN_CHANGED))
+        .count();
+    Some(VcsInfo {
+        kind: VcsKind::Svn,
+        branch: xml_text(info, "relative-url").and_then(svn_branch),
+        commit: format!("r{revision}"),
+        changed_files: Some(u32::try_from(changed).unwrap_or(u32::MAX)),
+        pr: None,
+    })
+}
+
+fn parse_output(kind: VcsKind, output: &str) -> Option<VcsInfo> {
+    match kind {
+        VcsKind::Sapling => parse_sapling(output),
+        VcsKind::Svn => parse_svn(output),
+    }
+}
+
+/// Detect a non-git checkout containing `dir` and read its state
+/// The CLI only runs when the checkout changed since the last call
+pub(crate) fn detect(dir: &str) -> Option<VcsInfo> {
+    let (kind, root) = find_root(Path::new(dir))?;
+    let root_str = root.to_string_lossy();
+    let stamp = state_stamp(kind, &root);
+    let cache_path = get_cache_dir().join(format!("vcs-{:016x}.cache", hash_path(&root_str)));
+
+    // Cache file format: 1st line state stamp, rest is CLI output
+    if let Some(output) = fs::read_to_string(&cache_path)
+        .ok()
+        .and_then(|c| c.strip_prefix(&format!("{stamp}\n")).map(str::to_string))
+    {
+        return parse_output(kind, &output);
+    }
+
+    if sys::sandboxed() {
+        return None;
+    }
+    let output = run_command_with_timeout(&kind.command(), &root_str, VCS_TIMEOUT)?;
+    let temp_path = get_cache_dir().join(format!("vcs-tmp-{}.cache", unique_hex()));
+    if fs::write(&temp_path, format!("{stamp}\n{output}")).is_ok() {
+        let _ = atomic_rename(&temp_path, &cache_path);
+    }
+    parse_output(kind, &output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn sapling_log_output() {
+        let info =
+            parse_sapling("a1b2c3d4e5f6\nfeature\n42\nhttps://github.com/o/r/pull/42\n").unwrap();
+        assert_eq!(info.label(), "feature");
+        assert_eq!(info.commit, "a1b2c3d4e5f6");
+        let pr = info.pr.unwrap();
+        assert_eq!(pr.number, 42);
+        assert_eq!(pr.url, "https://github.com/o/r/pull/42");
+
+        // No bookmark or PR: the commit is shown instead
+        let info = parse_sapling("a1b2c3d4e5f6\n\n\n\n").unwrap();
+        assert_eq!(info.label(), "a1b2c3d4e5f6");
+        assert_eq!(info.pr, None);
+        assert_eq!(parse_sapling(""), None);
+    }
+
+    #[test]
+    fn svn_info_and_status_output() {
+        let output = r#"<?xml version="1.0" encoding="UTF-8"?>
+<info>
+<entry kind="dir" path="." revision="1207">
+<url>https://svn.example.com/repo/branches/release-2</url>
+<relative-url>^/branches/release-2</relative-url>
+<commit revision="1198">
+<author>dev</author>
+</commit>
+</entry>
+</info>
+M       src/main.c
+A       src/new.c
+?       notes.txt
+"#;
+        let info = parse_svn(output).unwrap();
+        assert_eq!(info.label(), "release-2");
+        assert_eq!(info.commit, "r1198");
+        assert_eq!(info.changed_files, Some(2));
+        assert_eq!(parse_svn("svn: E155007: not a working copy"), None);
+    }
+
+    #[test]
+    fn svn_branch_names() {
+        assert_eq!(svn_branch("^/trunk/src").as_deref(), Some("trunk"));
+        assert_eq!(
+            svn_branch("^/proj/branches/fix-1").as_deref(),
+            Some("fix-1")
+        );
+        assert_eq!(svn_branch("^/tags/v1.0").as_deref(), Some("v1.0"));
+        assert_eq!(svn_branch("^/random"), None);
+    }
+
+    #[test]
+    fn finds_sapling_root() {
+        let root = env::temp_dir().join(format!("cc-statusline-vcs-{}", unique_hex()));
+        let sub = root.join("a/b");
+        fs::create_dir_all(&sub).unwrap();
+        fs::create_dir_all(root.join(".sl")).unwrap();
+        assert_eq!(find_root(&sub), Some((VcsKind::Sapling, root.clone())));
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,97 @@
+//! Watch a repository's git metadata and refresh the stats cache (watch feature)
+//!
+//! `cc-statusline --watch [DIR]` keeps running next to Claude Code. Whenever
+//! `HEAD`, the index, or a ref changes it recomputes the working-tree stats and
+//! rewrites the mmap cache, so the per-prompt render is a pure cache hit.
+
+use crate::git::{GitMode, GitRepo, diff_summary, get_git_repo};
+use notify::{Event, RecursiveMode, Watcher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Quiet period after a change before refreshing; git writes several files per operation
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Whether a changed path can affect HEAD, the index, or refs
+pub(crate) fn is_relevant(path: &Path) -> bool {
+    if path.extension().is_some_and(|e| e == "lock") {
+        return false;
+    }
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    matches!(name, "HEAD" | "index" | "packed-refs")
+        || path.components().any(|c| c.as_os_str() == "refs")
+}
+
+/// Recompute stats for the repo at `dir`; the branch may have changed since the last refresh
+fn refresh(dir: &str, mode: GitMode) {
+    if let Some(git) = get_git_repo(dir) {
+        diff_summary(&git, mode, None);
+    }
+}
+
+/// Have `watcher` report changes to the repo's HEAD, index, and refs;
+/// returns the watched paths
+pub(crate) fn watch_repo(watcher: &mut impl Watcher, git: &GitRepo) -> io::Result<Vec<PathBuf>> {
+    let git_dir = git.repo.git_dir().to_path_buf();
+    let refs_dir = git.repo.common_dir().join("refs");
+    // Watch the git dir itself (not recursively) so index/HEAD replacements via rename are seen
+    watcher
+        .watch(&git_dir, RecursiveMode::NonRecursive)
+        .map_err(io::Error::other)?;
+    watcher
+        .watch(&refs_dir, RecursiveMode::Recursive)
+        .map_err(io::Error::other)?;
+    Ok(vec![git_dir, refs_dir])
+}
+
+/// Watch the repository containing `dir` until the watcher fails
+pub fn run(dir: &str, mode: GitMode) -> io::Result<()> {
+    if mode == GitMode::Minimal {
+        return Err(io::Error::other(
+            "git_mode is minimal; there is no index cache to keep warm",
+        ));
+    }
+    let git = get_git_repo(dir)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not a git repository"))?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        if let Ok(event) = res
+            && event.paths.iter().any(|p| is_relevant(p))
+        {
+            let _ = tx.send(());
+        }
+    })
+    .map_err(io::Error::other)?;
+    watch_repo(&mut watcher, &git)?;
+    drop(git);
+
+    refresh(dir, mode);
+    while rx.recv().is_ok() {
+        while rx.recv_timeout(DEBOUNCE).is_ok() {}
+        refresh(dir, mode);
+    }
+    Err(io::Error::other("file watcher stopped"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relevant_paths() {
+        assert!(is_relevant(Path::new("/r/.git/HEAD")));
+        assert!(is_relevant(Path::new("/r/.git/index")));
+        assert!(is_relevant(Path::new("/r/.git/packed-refs")));
+        assert!(is_relevant(Path::new("/r/.git/refs/heads/main")));
+        assert!(is_relevant(Path::new("/r/.git/refs/remotes/origin/main")));
+        assert!(!is_relevant(Path::new("/r/.git/index.lock")));
+        assert!(!is_relevant(Path::new("/r/.git/refs/heads/main.lock")));
+        assert!(!is_relevant(Path::new("/r/.git/FETCH_HEAD")));
+        assert!(!is_relevant(Path::new("/r/.git/objects/ab/cdef")));
+    }
+}
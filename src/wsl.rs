@@ -0,0 +1,108 @@
+//! Windows Subsystem for Linux
+//!
+//! Windows drives are mounted under `/mnt/<drive>` over DrvFS (9P on WSL 2),
+//! where every stat crosses into Windows, so repos there get the slow
+//! filesystem treatment. Claude Code running on the Windows side reports
+//! paths like `C:\Users\me\app`, which are mapped onto those mounts.
+
+use crate::sys;
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+static IS_WSL: OnceLock<bool> = OnceLock::new();
+
+/// Running under WSL 1 or 2
+pub(crate) fn is_wsl() -> bool {
+    if sys::sandboxed() {
+        return sys::var("WSL_DISTRO_NAME").is_some();
+    }
+    *IS_WSL.get_or_init(|| {
+        sys::var("WSL_DISTRO_NAME").is_some()
+            || fs::read_to_string("/proc/sys/kernel/osrelease")
+                .is_ok_and(|release| release.to_ascii_lowercase().contains("microsoft"))
+    })
+}
+
+/// Whether `path` is on a Windows drive mounted into WSL, like `/mnt/c/src`
+pub(crate) fn is_drvfs(path: &Path) -> bool {
+    is_wsl() && path.to_str().and_then(mount_drive).is_some()
+}
+
+/// Drive letter of a `/mnt/<drive>` path
+fn mount_drive(path: &str) -> Option<char> {
+    let rest = path.strip_prefix("/mnt/")?;
+    let mut chars = rest.chars();
+    let drive = chars.next().filter(char::is_ascii_alphabetic)?;
+    matches!(chars.next(), None | Some('/')).then_some(drive)
+}
+
+/// A Windows path as seen from WSL: `C:\src\app` is `/mnt/c/src/app`, and
+/// `\\wsl$\Ubuntu\home\me` (or `\\wsl.localhost\...`) is `/home/me`
+/// Other paths, and every path outside WSL, are returned unchanged
+pub(crate) fn translate_path(path: &str) -> Cow<'_, str> {
+    if !is_wsl() {
+        return Cow::Borrowed(path);
+    }
+    map_windows_path(path).map_or(Cow::Borrowed(path), Cow::Owned)
+}
+
+fn map_windows_path(path: &str) -> Option<String> {
+    for share in [r"\\wsl$\", r"\\wsl.localhost\"] {
+        if let Some(rest) = path.strip_prefix(share) {
+            // Skip the distro name
+            let rest = rest.split_once('\\').map_or("", |(_, rest)| rest);
+            return Some(format!("/{}", rest.replace('\\', "/")));
+        }
+    }
+    let mut chars = path.chars();
+    let drive = chars.next().filter(char::is_ascii_alphabetic)?;
+    if chars.next() != Some(':') {
+        return None;
+    }
+    let rest = chars.as_str();
+    if !(rest.is_empty() || rest.starts_with(['\\', '/'])) {
+        return None; // `C:foo` is relative to the drive's cwd
+    }
+    let rest = rest.trim_start_matches(['\\', '/']).replace('\\', "/");
+    let drive = drive.to_ascii_lowercase();
+    Some(if rest.is_empty() {
+        format!("/mnt/{drive}")
+    } else {
+        format!("/mnt/{drive}/{rest}")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_paths_map_to_mounts() {
+        assert_eq!(
+            map_windows_path(r"C:\Users\me\app").as_deref(),
+            Some("/mnt/c/Users/me/app")
+        );
+        assert_eq!(map_windows_path("D:/src").as_deref(), Some("/mnt/d/src"));
+        assert_eq!(map_windows_path(r"E:\").as_deref(), Some("/mnt/e"));
+        assert_eq!(
+            map_windows_path(r"\\wsl$\Ubuntu\home\me\app").as_deref(),
+            Some("/home/me/app")
+        );
+        assert_eq!(
+            map_windows_path(r"\\wsl.localhost\Ubuntu\home").as_deref(),
+            Some("/home")
+        );
+        assert_eq!(map_windows_path("/home/me"), None);
+        assert_eq!(map_windows_path("C:foo"), None);
+    }
+
+    #[test]
+    fn mount_paths_name_a_drive() {
+        assert_eq!(mount_drive("/mnt/c/src/app"), Some('c'));
+        assert_eq!(mount_drive("/mnt/d"), Some('d'));
+        assert_eq!(mount_drive("/mnt/data/app"), None);
+        assert_eq!(mount_drive("/home/me"), None);
+    }
+}
@@ -11,6 +11,9 @@ use std::process::{Command, Stdio};
 
 use tempfile::TempDir;
 
+mod support;
+use support::mock_github::MockGitHub;
+
 /// Get the path to the built binary
 fn get_binary_path() -> PathBuf {
     // The binary is in target/debug/cc-statusline (or release)
@@ -191,6 +194,405 @@ fn detect_changed_files() {
     );
 }
 
+#[test]
+fn changed_files_match_git_status() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    make_commit(&repo_path, "second commit");
+
+    // Staged: the index differs from HEAD while the file matches the index
+    fs::write(repo_path.join("file-initial-commit.txt"), "staged change").unwrap();
+    Command::new("git")
+        .args(["add", "file-initial-commit.txt"])
+        .current_dir(&repo_path)
+        .output()
+        .expect("failed to stage");
+
+    // Touched but identical: a newer mtime alone isn't a change
+    let touched = fs::File::options()
+        .write(true)
+        .open(repo_path.join("file-second-commit.txt"))
+        .unwrap();
+    touched
+        .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))
+        .unwrap();
+
+    // The test's own config and cache dirs aren't part of the count
+    fs::write(repo_path.join(".git/info/exclude"), ".claude/\n.cache/\n").unwrap();
+
+    let config = r#"{"rows": [["files"]]}"#;
+    let stdout = run_with_config(&repo_path, "{}", config);
+    assert!(
+        stdout.contains("1 files"),
+        "Expected one changed file: {stdout}"
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn mode_and_same_second_edits_count_like_git_status() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "a");
+    make_commit(&repo_path, "b");
+    fs::write(repo_path.join(".git/info/exclude"), ".claude/\n.cache/\n").unwrap();
+
+    // Same size and mtime as the committed file; only the content differs
+    let edited = repo_path.join("file-a.txt");
+    let mtime = fs::metadata(&edited).unwrap().modified().unwrap();
+    fs::write(&edited, "A").unwrap();
+    fs::File::options()
+        .write(true)
+        .open(&edited)
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+    fs::set_permissions(
+        repo_path.join("file-b.txt"),
+        fs::Permissions::from_mode(0o755),
+    )
+    .unwrap();
+
+    let config = r#"{"rows": [["files"]]}"#;
+    let stdout = run_with_config(&repo_path, "{}", config);
+    assert!(stdout.contains("2 files"), "{stdout}");
+}
+
+#[test]
+fn merge_conflicts_are_counted() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .current_dir(&repo_path)
+            .output()
+            .expect("failed to run git");
+    };
+    let commit_all = |content: &str| {
+        for name in ["a.txt", "b.txt"] {
+            fs::write(repo_path.join(name), content).unwrap();
+        }
+        git(&["add", "a.txt", "b.txt"]);
+        git(&["commit", "-q", "-m", content]);
+    };
+    commit_all("base");
+    git(&["checkout", "-q", "-b", "other"]);
+    commit_all("theirs");
+    git(&["checkout", "-q", "-"]);
+    commit_all("ours");
+    git(&["merge", "-q", "other"]);
+    fs::write(repo_path.join(".git/info/exclude"), ".claude/\n.cache/\n").unwrap();
+
+    let config = r#"{"rows": [["files", "conflicts"]]}"#;
+    let stdout = run_with_config(&repo_path, "{}", config);
+    assert!(
+        stdout.contains("2 files") && stdout.contains("✖ 2 conflicts"),
+        "Expected two conflicted files: {stdout}"
+    );
+}
+
+#[test]
+fn unpushed_commits_show_their_age() {
+    let (_remote_dir, remote_path) = create_git_repo();
+    make_commit(&remote_path, "initial commit");
+    let clone_dir = TempDir::new().expect("failed to create temp dir");
+    let repo_path = clone_dir.path().join("clone");
+    Command::new("git")
+        .args(["clone", "-q"])
+        .arg(&remote_path)
+        .arg(&repo_path)
+        .output()
+        .expect("failed to clone");
+    fs::write(repo_path.join("local.txt"), "local").unwrap();
+    fs::write(repo_path.join(".git/info/exclude"), ".claude/\n.cache/\n").unwrap();
+    let three_hours_ago = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        - 3 * 60 * 60;
+    let date = format!("@{three_hours_ago} +0000");
+    for args in [&["add", "local.txt"][..], &["commit", "-q", "-m", "local"]] {
+        Command::new("git")
+            .args(args)
+            .current_dir(&repo_path)
+            .env("GIT_AUTHOR_NAME", "Test User")
+            .env("GIT_AUTHOR_EMAIL", "test@example.com")
+            .env("GIT_COMMITTER_NAME", "Test User")
+            .env("GIT_COMMITTER_EMAIL", "test@example.com")
+            .env("GIT_COMMITTER_DATE", &date)
+            .output()
+            .expect("failed to commit");
+    }
+
+    let config = |after| format!(r#"{{"rows": [["ahead_behind"]], "unpushed_after": {after}}}"#);
+    let stdout = run_with_config(&repo_path, "{}", &config(120));
+    assert!(stdout.contains("↑1 (3h unpushed)"), "{stdout}");
+    let stdout = run_with_config(&repo_path, "{}", &config(240));
+    assert!(
+        stdout.contains("↑1") && !stdout.contains("unpushed"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn signed_head_and_signing_config() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    fs::write(repo_path.join(".git/info/exclude"), ".claude/\n.cache/\n").unwrap();
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(&repo_path)
+            .output()
+            .expect("failed to run git");
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+    let config = r#"{"rows": [["signed"]]}"#;
+
+    let stdout = run_with_config(&repo_path, "{}", config);
+    assert!(stdout.contains("⚠ unsigned"), "{stdout}");
+
+    // A commit carrying a signature header; it isn't verified
+    let commit = format!(
+        "tree {}\nparent {}\nauthor A <a@example.com> 1700000000 +0000\n\
+         committer A <a@example.com> 1700000000 +0000\n\
+         gpgsig -----BEGIN PGP SIGNATURE-----\n \n -----END PGP SIGNATURE-----\n\nsigned\n",
+        git(&["rev-parse", "HEAD^{tree}"]),
+        git(&["rev-parse", "HEAD"]),
+    );
+    let commit_path = repo_path.join(".git/signed-commit");
+    fs::write(&commit_path, commit).unwrap();
+    let id = git(&[
+        "hash-object",
+        "-t",
+        "commit",
+        "-w",
+        commit_path.to_str().unwrap(),
+    ]);
+    git(&["update-ref", "HEAD", &id]);
+    let stdout = run_with_config(&repo_path, "{}", config);
+    assert!(stdout.contains("🔏"), "{stdout}");
+}
+
+#[test]
+fn lfs_pointers_and_missing_git_lfs() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    fs::write(repo_path.join(".gitattributes"), "*.bin filter=lfs -text\n").unwrap();
+    fs::write(
+        repo_path.join("model.bin"),
+        "version https://git-lfs.github.com/spec/v1\noid sha256:abc\nsize 1048576\n",
+    )
+    .unwrap();
+    make_commit(&repo_path, "initial commit");
+    fs::write(repo_path.join(".git/info/exclude"), ".claude/\n.cache/\n").unwrap();
+    fs::create_dir_all(repo_path.join(".claude")).unwrap();
+    fs::write(
+        repo_path.join(".claude/cc-statusline.json"),
+        r#"{"rows": [["lfs"]]}"#,
+    )
+    .unwrap();
+    let bin_dir = TempDir::new().expect("failed to create temp dir");
+    let home = repo_path.to_str().unwrap();
+
+    let empty_path = bin_dir.path().to_str().unwrap();
+    let stdout = run_with_json_env(&repo_path, "{}", &[("HOME", home), ("PATH", empty_path)]);
+    assert!(stdout.contains("⚠ git-lfs missing"), "{stdout}");
+
+    let git_lfs = if cfg!(windows) {
+        "git-lfs.exe"
+    } else {
+        "git-lfs"
+    };
+    fs::write(bin_dir.path().join(git_lfs), "").unwrap();
+    let stdout = run_with_json_env(&repo_path, "{}", &[("HOME", home), ("PATH", empty_path)]);
+    assert!(stdout.contains("lfs: 1 not pulled"), "{stdout}");
+}
+
+#[test]
+fn index_stats_count_entries() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    make_commit(&repo_path, "second commit");
+
+    let stdout = run_with_config(&repo_path, "{}", r#"{"rows": [["index_stats"]]}"#);
+    assert!(
+        stdout.contains("2 files · ") && stdout.contains("ms"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn dirty_mode_stops_at_the_first_change() {
+    let config = r#"{"rows": [["branch", "files"]], "git_mode": "dirty"}"#;
+    let repo = |extra: &[(&str, &str)]| {
+        let (temp_dir, repo_path) = create_git_repo();
+        make_commit(&repo_path, "a");
+        make_commit(&repo_path, "b");
+        fs::write(repo_path.join(".git/info/exclude"), ".claude/\n.cache/\n").unwrap();
+        for (name, contents) in extra {
+            fs::write(repo_path.join(name), contents).unwrap();
+        }
+        (temp_dir, repo_path)
+    };
+
+    let (_clean_dir, clean) = repo(&[]);
+    let stdout = run_with_config(&clean, "{}", config);
+    assert!(
+        stdout.contains("master") && !stdout.contains('●'),
+        "{stdout}"
+    );
+
+    // Untracked files alone make the tree dirty
+    let (_untracked_dir, untracked) = repo(&[("new.txt", "untracked")]);
+    let stdout = run_with_config(&untracked, "{}", config);
+    assert!(stdout.contains('●'), "{stdout}");
+
+    let changes = [
+        ("file-a.txt", "changed"),
+        ("file-b.txt", "changed"),
+        ("new.txt", "untracked"),
+    ];
+    let (_dirty_dir, dirty) = repo(&changes);
+    let stdout = run_with_config(&dirty, "{}", config);
+    assert!(
+        stdout.contains('●') && !stdout.contains("files"),
+        "{stdout}"
+    );
+
+    // Expanding to counts recomputes rather than reusing the capped scan
+    let stdout = run_with_config(&dirty, "{}", r#"{"rows": [["files"]]}"#);
+    assert!(stdout.contains("3 files"), "{stdout}");
+}
+
+#[test]
+fn max_files_caps_the_count() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    for name in ["a", "b", "c", "d"] {
+        make_commit(&repo_path, name);
+    }
+    fs::write(repo_path.join(".git/info/exclude"), ".claude/\n.cache/\n").unwrap();
+    for name in ["a", "b", "c"] {
+        fs::write(repo_path.join(format!("file-{name}.txt")), "changed").unwrap();
+    }
+
+    let stdout = run_with_config(&repo_path, "{}", r#"{"rows": [["files"]]}"#);
+    assert!(stdout.contains("3 files"), "{stdout}");
+    let stdout = run_with_config(&repo_path, "{}", r#"{"rows": [["files"]], "max_files": 2}"#);
+    assert!(stdout.contains("2+ files"), "{stdout}");
+}
+
+#[test]
+#[cfg(unix)]
+fn non_utf8_tracked_paths_are_scanned() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let (_temp_dir, repo_path) = create_git_repo();
+    let bad = repo_path.join(std::ffi::OsStr::from_bytes(b"bad\xff.txt"));
+    fs::write(&bad, "original").unwrap();
+    make_commit(&repo_path, "a");
+    fs::write(repo_path.join(".git/info/exclude"), ".claude/\n.cache/\n").unwrap();
+    fs::write(&bad, "changed").unwrap();
+    fs::write(repo_path.join("file-a.txt"), "changed").unwrap();
+    fs::write(repo_path.join("new.txt"), "untracked").unwrap();
+
+    let stdout = run_with_config(&repo_path, "{}", r#"{"rows": [["files"]]}"#);
+    assert!(stdout.contains("3 files"), "{stdout}");
+}
+
+#[test]
+fn new_untracked_files_show_up_without_an_index_change() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    fs::write(repo_path.join(".git/info/exclude"), ".claude/\n.cache/\n").unwrap();
+    let config = r#"{"rows": [["model", "files"]], "deadline_ms": 5000}"#;
+    let input = r#"{"model": {"display_name": "Opus"}}"#;
+
+    let stdout = run_with_config(&repo_path, input, config);
+    assert!(
+        stdout.contains("Opus") && !stdout.contains("files"),
+        "{stdout}"
+    );
+
+    // New files change their directory's mtime, which retakes the cached count
+    fs::write(repo_path.join("u1.txt"), "new").unwrap();
+    fs::write(repo_path.join("u2.txt"), "new").unwrap();
+    let stdout = run_with_config(&repo_path, input, config);
+    assert!(stdout.contains("2 files"), "{stdout}");
+
+    // Also below the root, in a directory that holds tracked files
+    fs::remove_file(repo_path.join("u1.txt")).unwrap();
+    fs::remove_file(repo_path.join("u2.txt")).unwrap();
+    fs::create_dir_all(repo_path.join("src/deep")).unwrap();
+    fs::write(repo_path.join("src/deep/lib.rs"), "tracked").unwrap();
+    make_commit(&repo_path, "tracked");
+    let stdout = run_with_config(&repo_path, input, config);
+    assert!(!stdout.contains("files"), "{stdout}");
+    fs::write(repo_path.join("src/deep/new.rs"), "new").unwrap();
+    let stdout = run_with_config(&repo_path, input, config);
+    assert!(stdout.contains("1 files"), "{stdout}");
+}
+
+#[test]
+fn untracked_files_follow_git_config() {
+    let (temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    let home = temp_dir.path().join("home");
+    let config_dir = home.join(".claude");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        r#"{"rows": [["files"]], "deadline_ms": 5000}"#,
+    )
+    .unwrap();
+
+    fs::write(repo_path.join("notes.txt"), "untracked").unwrap();
+    fs::create_dir_all(repo_path.join("scratch")).unwrap();
+    fs::write(repo_path.join("scratch/a.txt"), "a").unwrap();
+    fs::write(repo_path.join("scratch/b.txt"), "b").unwrap();
+    fs::write(repo_path.join("debug.log"), "ignored").unwrap();
+    fs::write(repo_path.join(".env"), "ignored globally").unwrap();
+    fs::write(repo_path.join(".gitignore"), "*.log\nhome/\n").unwrap();
+    let excludes = home.join("excludes");
+    fs::write(&excludes, ".env\n").unwrap();
+
+    let git_config = |key: &str, value: &str| {
+        Command::new("git")
+            .args(["config", key, value])
+            .current_dir(&repo_path)
+            .output()
+            .expect("failed to set git config");
+    };
+    git_config("core.excludesFile", excludes.to_str().unwrap());
+    let files = || {
+        let cache_dir = TempDir::new().unwrap();
+        run_with_json_env(
+            &repo_path,
+            "{}",
+            &[
+                ("HOME", home.to_str().unwrap()),
+                ("XDG_CACHE_HOME", cache_dir.path().to_str().unwrap()),
+            ],
+        )
+    };
+
+    // Like `git status`: notes.txt, .gitignore, and scratch/ as one entry
+    let stdout = files();
+    assert!(
+        stdout.contains("3 files"),
+        "Expected collapsed dir: {stdout}"
+    );
+
+    git_config("status.showUntrackedFiles", "all");
+    let stdout = files();
+    assert!(stdout.contains("4 files"), "Expected every file: {stdout}");
+
+    git_config("status.showUntrackedFiles", "no");
+    let stdout = files();
+    assert!(!stdout.contains("files"), "Expected no untracked: {stdout}");
+}
+
 // =============================================================================
 // JSON Input Tests
 // =============================================================================
@@ -333,6 +735,55 @@ fn detect_worktree() {
     );
 }
 
+#[test]
+fn git_dir_env_and_bare_repo() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let bare = temp_dir.path().join("dotfiles.git");
+    let work = temp_dir.path().join("home");
+    fs::create_dir_all(&work).expect("failed to create work tree");
+    Command::new("git")
+        .args(["init", "--bare", "-q"])
+        .arg(&bare)
+        .output()
+        .expect("failed to init bare repo");
+    Command::new("git")
+        .args(["symbolic-ref", "HEAD", "refs/heads/dots"])
+        .current_dir(&bare)
+        .output()
+        .expect("failed to set HEAD");
+
+    let config = r#"{"rows": [["no_git", "branch", "files"]]}"#;
+    let config_dir = work.join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(config_dir.join("cc-statusline.json"), config).expect("failed to write config");
+
+    // Dotfiles layout: the repo comes from GIT_DIR/GIT_WORK_TREE, not discovery
+    let json = format!(r#"{{"cwd": "{}"}}"#, work.display());
+    let stdout = run_with_json_env(
+        &work,
+        &json,
+        &[
+            ("HOME", work.to_str().unwrap()),
+            ("GIT_DIR", bare.to_str().unwrap()),
+            ("GIT_WORK_TREE", work.to_str().unwrap()),
+        ],
+    );
+    assert!(
+        stdout.contains("dots"),
+        "Expected branch from GIT_DIR: {stdout}"
+    );
+
+    // Inside a bare repo the branch shows without work tree stats
+    let json = format!(r#"{{"cwd": "{}"}}"#, bare.display());
+    let stdout = run_with_json_env(&bare, &json, &[("HOME", work.to_str().unwrap())]);
+    assert!(
+        stdout.contains("dots"),
+        "Expected bare repo branch: {stdout}"
+    );
+    assert!(!stdout.contains("no git"), "{stdout}");
+    assert!(!stdout.contains("files"), "{stdout}");
+}
+
 // =============================================================================
 // Empty Input Tests
 // =============================================================================
@@ -522,6 +973,196 @@ fn run_with_config(work_dir: &PathBuf, json_input: &str, config_content: &str) -
     )
 }
 
+#[test]
+#[cfg(unix)]
+fn render_cache_reprints_unchanged_input() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let path = temp_dir.path().to_path_buf();
+    fs::create_dir_all(path.join(".claude")).unwrap();
+    fs::write(
+        path.join(".claude").join("cc-statusline.json"),
+        r#"{
+            "rows": [["model", "runs"]],
+            "custom": {"runs": {"command": "echo x >> runs.txt; wc -l < runs.txt", "ttl": 0}},
+            "render_cache": 60
+        }"#,
+    )
+    .unwrap();
+    let env = [
+        ("HOME", path.to_str().unwrap()),
+        ("XDG_CACHE_HOME", path.to_str().unwrap()),
+    ];
+    let input = |model: &str| {
+        format!(
+            r#"{{"cwd": "{}", "model": {{"display_name": "{model}"}}}}"#,
+            path.display()
+        )
+    };
+
+    let first = run_with_json_env(&path, &input("Opus"), &env);
+    let second = run_with_json_env(&path, &input("Opus"), &env);
+    assert_eq!(first, second);
+    let runs = fs::read_to_string(path.join("runs.txt")).unwrap();
+    assert_eq!(runs.lines().count(), 1, "second render should be cached");
+
+    let other = run_with_json_env(&path, &input("Sonnet"), &env);
+    assert!(other.contains("Sonnet"), "{other}");
+    let runs = fs::read_to_string(path.join("runs.txt")).unwrap();
+    assert_eq!(runs.lines().count(), 2);
+}
+
+#[test]
+fn render_cache_follows_the_mapped_repo() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    fs::write(
+        repo_path.join(".git/info/exclude"),
+        ".claude/\n.cache/\nruns.txt\n",
+    )
+    .unwrap();
+    fs::create_dir_all(repo_path.join(".claude")).unwrap();
+    let write_config = |rows: &str| {
+        fs::write(
+            repo_path.join(".claude").join("cc-statusline.json"),
+            format!(
+                r#"{{
+                    "rows": [{rows}],
+                    "custom": {{"runs": {{"command": "echo x >> runs.txt; wc -l < runs.txt", "ttl": 0}}}},
+                    "path_map": {{"/workspaces/app": "{}"}},
+                    "render_cache": 60,
+                    "deadline_ms": 5000
+                }}"#,
+                repo_path.display()
+            ),
+        )
+        .unwrap();
+    };
+    let cache_dir = repo_path.join(".cache");
+    let env = [
+        ("HOME", repo_path.to_str().unwrap()),
+        ("XDG_CACHE_HOME", cache_dir.to_str().unwrap()),
+    ];
+    // The input names the container path; only the mapped one is a repo
+    let input = r#"{"cwd": "/workspaces/app", "model": {"display_name": "Opus"}}"#;
+    let runs = || {
+        fs::read_to_string(repo_path.join("runs.txt"))
+            .unwrap()
+            .lines()
+            .count()
+    };
+
+    write_config(r#"["branch", "runs"]"#);
+    run_with_json_env(&repo_path, input, &env);
+    run_with_json_env(&repo_path, input, &env);
+    assert_eq!(runs(), 1, "second render should be cached");
+
+    // A branch switch changes the mapped repo's HEAD
+    Command::new("git")
+        .args(["checkout", "-q", "-b", "feature"])
+        .current_dir(&repo_path)
+        .output()
+        .expect("failed to run git");
+    // The repo lookup cache keys on whole seconds; move HEAD past them
+    fs::File::options()
+        .write(true)
+        .open(repo_path.join(".git/HEAD"))
+        .unwrap()
+        .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(5))
+        .unwrap();
+    let stdout = run_with_json_env(&repo_path, input, &env);
+    assert!(stdout.contains("feature"), "{stdout}");
+    assert_eq!(runs(), 2);
+
+    // Working-tree counts can't be keyed without a filesystem monitor
+    write_config(r#"["files", "runs"]"#);
+    run_with_json_env(&repo_path, input, &env);
+    run_with_json_env(&repo_path, input, &env);
+    assert_eq!(runs(), 4, "renders with file counts should not be cached");
+}
+
+#[test]
+fn env_overrides_config_file() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let path = temp_dir.path().to_path_buf();
+    fs::create_dir_all(path.join(".claude")).unwrap();
+    fs::write(
+        path.join(".claude").join("cc-statusline.json"),
+        r#"{"rows": [["model", "cost"]]}"#,
+    )
+    .unwrap();
+
+    let stdout = run_with_json_env(
+        &path,
+        r#"{"model": {"display_name": "Opus"}, "cost": {"total_cost_usd": 1.5}}"#,
+        &[
+            ("HOME", path.to_str().unwrap()),
+            ("CC_STATUSLINE_COMPONENTS_MODEL", "false"),
+        ],
+    );
+    assert!(
+        stdout.contains("$1.50") && !stdout.contains("Opus"),
+        "{stdout}"
+    );
+
+    let stdout = run_with_json_env(
+        &path,
+        r#"{"model": {"display_name": "Opus"}, "cost": {"total_cost_usd": 1.5}}"#,
+        &[
+            ("HOME", path.to_str().unwrap()),
+            ("CC_STATUSLINE_ROWS", r#"[["model"]]"#),
+        ],
+    );
+    assert!(
+        stdout.contains("Opus") && !stdout.contains("$1.50"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn env_overrides_beat_profile_and_file() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let path = temp_dir.path().to_path_buf();
+    fs::create_dir_all(path.join(".claude")).unwrap();
+    fs::write(
+        path.join(".claude").join("cc-statusline.json"),
+        r#"{"rows": [["model"]], "profiles": {"work": {"rows": [["model", "cost"]]}}}"#,
+    )
+    .unwrap();
+    let json = r#"{"model": {"display_name": "Opus"}, "cost": {"total_cost_usd": 1.5}}"#;
+    let home = ("HOME", path.to_str().unwrap());
+    let profile = ("CC_STATUSLINE_PROFILE", "work");
+
+    let stdout = run_with_json_env(&path, json, &[home]);
+    assert!(
+        stdout.contains("Opus") && !stdout.contains("$1.50"),
+        "file: {stdout}"
+    );
+
+    let stdout = run_with_json_env(&path, json, &[home, profile]);
+    assert!(
+        stdout.contains("Opus") && stdout.contains("$1.50"),
+        "profile over file: {stdout}"
+    );
+
+    for toggle in [
+        "CC_STATUSLINE_MODULES_MODEL",
+        "CC_STATUSLINE_COMPONENTS_MODEL",
+    ] {
+        let stdout = run_with_json_env(&path, json, &[home, profile, (toggle, "false")]);
+        assert!(
+            stdout.contains("$1.50") && !stdout.contains("Opus"),
+            "{toggle} over profile: {stdout}"
+        );
+    }
+
+    let rows = ("CC_STATUSLINE_ROWS", r#"[["cost"]]"#);
+    let stdout = run_with_json_env(&path, json, &[home, profile, rows]);
+    assert!(
+        stdout.contains("$1.50") && !stdout.contains("Opus"),
+        "env over profile: {stdout}"
+    );
+}
+
 #[test]
 fn config_file_missing_uses_defaults() {
     let temp_dir = TempDir::new().expect("failed to create temp dir");
@@ -542,6 +1183,54 @@ fn config_file_missing_uses_defaults() {
     );
 }
 
+#[cfg(windows)]
+#[test]
+fn windows_config_in_appdata() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let path = temp_dir.path().to_path_buf();
+    let app_data = path.join("AppData").join("Roaming");
+    fs::create_dir_all(app_data.join("cc-statusline")).unwrap();
+    fs::write(
+        app_data.join("cc-statusline").join("cc-statusline.json"),
+        r#"{"rows": [["cost"]]}"#,
+    )
+    .unwrap();
+
+    // No config under HOME\.claude, so %APPDATA% is used
+    let stdout = run_with_json_env(
+        &path,
+        r#"{"model": {"display_name": "Opus"}, "cost": {"total_cost_usd": 1.5}}"#,
+        &[
+            ("HOME", path.to_str().unwrap()),
+            ("APPDATA", app_data.to_str().unwrap()),
+        ],
+    );
+    assert!(
+        stdout.contains("$1.50") && !stdout.contains("Opus"),
+        "{stdout}"
+    );
+}
+
+#[cfg(windows)]
+#[test]
+fn windows_cache_in_local_appdata() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    let local = TempDir::new().expect("failed to create temp dir");
+
+    run_with_json_env_full(
+        &repo_path,
+        r#"{"model": {"display_name": "Opus"}}"#,
+        &[("LOCALAPPDATA", local.path().to_str().unwrap())],
+        &["XDG_CACHE_HOME"],
+    );
+    let cache_dir = local.path().join("cc-statusline");
+    let names: Vec<String> = fs::read_dir(&cache_dir)
+        .expect("cache dir under %LOCALAPPDATA%")
+        .filter_map(|e| e.ok()?.file_name().into_string().ok())
+        .collect();
+    assert!(names.iter().any(|n| n.starts_with("gitpath-")), "{names:?}");
+}
+
 #[test]
 fn config_file_empty_rows_hides_components() {
     let temp_dir = TempDir::new().expect("failed to create temp dir");
@@ -676,59 +1365,1140 @@ fn config_init_creates_file() {
 }
 
 #[test]
-fn config_unknown_component_ignored() {
-    let temp_dir = TempDir::new().expect("failed to create temp dir");
-    let path = temp_dir.path().to_path_buf();
+fn completions_and_man_page() {
+    let binary = get_binary_path();
+    let output = Command::new(&binary)
+        .args(["completions", "bash"])
+        .output()
+        .expect("failed to run completions");
+    assert!(output.status.success(), "completions should succeed");
+    let script = String::from_utf8_lossy(&output.stdout);
+    assert!(script.contains("--config-init"), "{script}");
 
-    // Config with unknown component
-    let config = r#"{
-        "rows": [
-            ["project", "unknown_future_component", "path"],
-            ["no_git", "branch"],
-            ["model"]
-        ]
-    }"#;
+    let output = Command::new(&binary)
+        .arg("--man")
+        .output()
+        .expect("failed to run --man");
+    assert!(output.status.success(), "--man should succeed");
+    assert!(String::from_utf8_lossy(&output.stdout).contains(".TH cc-statusline"));
+}
 
-    let stdout = run_with_config(
-        &path,
-        r#"{"model": {"display_name": "Claude Test"}}"#,
-        config,
+#[test]
+fn serve_renders_each_input_line() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    let mut child = Command::new(get_binary_path())
+        .arg("--serve")
+        .current_dir(&repo_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn binary");
+    let input = format!(
+        "{}\n\n{}\n",
+        r#"{"model": {"display_name": "Opus"}}"#, r#"{"model": {"display_name": "Sonnet"}}"#
     );
+    child
+        .stdin
+        .as_mut()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .expect("failed to write stdin");
+    let output = child.wait_with_output().expect("failed to wait");
+    assert!(output.status.success());
 
-    // Should still work, just skip the unknown component
-    assert!(
-        stdout.contains("Claude Test"),
-        "Should handle unknown components gracefully: {}",
-        stdout
-    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let blocks: Vec<&str> = stdout.trim_end().split("\n\n").collect();
+    assert_eq!(blocks.len(), 2, "{stdout}");
+    assert!(blocks[0].contains("Opus") && !blocks[0].contains("Sonnet"));
+    assert!(blocks[1].contains("Sonnet"));
 }
 
-// =============================================================================
-// Official JSON Fixture Test (Issue #20)
-// =============================================================================
-
 #[test]
-fn official_docs_json_fixture() {
-    let temp_dir = TempDir::new().expect("failed to create temp dir");
-    let path = temp_dir.path().to_path_buf();
+fn serve_does_not_wait_for_late_lookups() {
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::time::{Duration, Instant};
 
-    // Load the full official JSON from https://code.claude.com/docs/en/statusline
-    // Contains fields not used by ClaudeInput (hook_event_name, session_id,
-    // version, model.id, context_window.current_usage). Serde ignores unknown
-    // fields by default, so this confirms the binary doesn't crash.
-    let json_input =
-        fs::read_to_string("tests/fixtures/official_input.json").expect("failed to read fixture");
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    Command::new("git")
+        .args(["remote", "add", "origin", "git@github.com:octo/app.git"])
+        .current_dir(&repo_path)
+        .output()
+        .expect("failed to run git");
+
+    // An API that stalls, so the PR lookup misses every frame's deadline
+    let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind");
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            std::thread::sleep(Duration::from_secs(3));
+            drop(stream);
+        }
+    });
+    let cache_dir = TempDir::new().expect("failed to create temp dir");
+    fs::create_dir_all(repo_path.join(".claude")).unwrap();
+    fs::write(
+        repo_path.join(".claude").join("cc-statusline.json"),
+        r#"{"rows": [["model", "pr_number"]], "deadline_ms": 100}"#,
+    )
+    .unwrap();
 
-    let stdout = run_with_json(&path, &json_input);
+    let mut child = Command::new(get_binary_path())
+        .arg("--serve")
+        .current_dir(&repo_path)
+        .env("HOME", &repo_path)
+        .env("XDG_CACHE_HOME", cache_dir.path())
+        .env("GITHUB_TOKEN", "test-token")
+        .env("CC_STATUSLINE_GITHUB_API_URL", &url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to spawn binary");
+    let start = Instant::now();
+    let mut stdin = child.stdin.take().unwrap();
+    for model in ["Opus", "Sonnet"] {
+        writeln!(stdin, r#"{{"model": {{"display_name": "{model}"}}}}"#).unwrap();
+    }
 
-    // Binary should not crash — output must be non-empty
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    for model in ["Opus", "Sonnet"] {
+        let frame = lines
+            .by_ref()
+            .map(Result::unwrap)
+            .find(|line| !line.is_empty())
+            .expect("expected a frame");
+        assert!(frame.contains(model), "{frame}");
+    }
+    let elapsed = start.elapsed();
     assert!(
-        !stdout.is_empty(),
-        "Expected non-empty output from official JSON fixture"
+        elapsed < Duration::from_millis(2000),
+        "Expected frames without waiting for the stalled lookup: {elapsed:?}"
     );
 
-    // Model display name
-    assert!(
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn serve_reports_invalid_conditions_once() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let home = temp_dir.path().to_path_buf();
+    fs::create_dir_all(home.join(".claude")).unwrap();
+    fs::write(
+        home.join(".claude").join("cc-statusline.json"),
+        r#"{"rows": [["model", "cost"]], "conditions": {"cost": "cost >"}}"#,
+    )
+    .unwrap();
+    let mut child = Command::new(get_binary_path())
+        .arg("--serve")
+        .current_dir(&home)
+        .env("HOME", &home)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn binary");
+    let input = r#"{"model": {"display_name": "Opus"}, "cost": {"total_cost_usd": 1.5}}"#;
+    child
+        .stdin
+        .as_mut()
+        .unwrap()
+        .write_all(format!("{input}\n{input}\n{input}\n").as_bytes())
+        .expect("failed to write stdin");
+    let output = child.wait_with_output().expect("failed to wait");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches("$1.50").count(), 3, "{stdout}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(
+        stderr.matches("invalid condition for 'cost'").count(),
+        1,
+        "{stderr}"
+    );
+}
+
+#[test]
+fn serve_diff_sends_changed_rows() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let home = temp_dir.path().to_path_buf();
+    fs::create_dir_all(home.join(".claude")).unwrap();
+    fs::write(
+        home.join(".claude").join("cc-statusline.json"),
+        r#"{"rows": [["model"], ["cost"]]}"#,
+    )
+    .unwrap();
+    let mut child = Command::new(get_binary_path())
+        .args(["--serve", "--diff"])
+        .current_dir(&home)
+        .env("HOME", &home)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn binary");
+    let input = [
+        r#"{"model": {"display_name": "Opus"}, "cost": {"total_cost_usd": 1.5}}"#,
+        r#"{"model": {"display_name": "Opus"}, "cost": {"total_cost_usd": 2.5}}"#,
+        r#"{"model": {"display_name": "Opus"}, "cost": {"total_cost_usd": 2.5}}"#,
+    ]
+    .join("\n");
+    child
+        .stdin
+        .as_mut()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().expect("failed to wait");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let frames: Vec<&str> = stdout.trim_end().split("\n\n").collect();
+    assert_eq!(frames.len(), 3, "{stdout}");
+    let rows = |frame: &str| -> Vec<String> {
+        frame
+            .lines()
+            .skip(1)
+            .map(|l| l.split_once('\t').unwrap().0.to_string())
+            .collect()
+    };
+    assert!(frames[0].starts_with("@2\n"), "{}", frames[0]);
+    assert_eq!(rows(frames[0]), ["0", "1"]);
+    assert_eq!(rows(frames[1]), ["1"]);
+    assert!(frames[1].contains("$2.50"), "{}", frames[1]);
+    assert_eq!(frames[2], "@2");
+}
+
+#[test]
+fn serve_refreshes_memoized_stats_when_the_index_changes() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "a");
+    make_commit(&repo_path, "b");
+    fs::write(repo_path.join(".git/info/exclude"), ".claude/\n.cache/\n").unwrap();
+    let home = repo_path.to_str().unwrap();
+    let config_path = repo_path.join(".claude").join("cc-statusline.json");
+    fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fs::write(
+        &config_path,
+        r#"{"rows": [["files"]], "deadline_ms": 10000}"#,
+    )
+    .unwrap();
+
+    let mut child = Command::new(get_binary_path())
+        .arg("--serve")
+        .current_dir(&repo_path)
+        .env("HOME", home)
+        .env("XDG_CACHE_HOME", repo_path.join(".cache"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn binary");
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = std::io::BufReader::new(child.stdout.take().unwrap());
+    let mut render = || {
+        writeln!(stdin, "{{}}").unwrap();
+        let mut block = String::new();
+        loop {
+            let mut line = String::new();
+            std::io::BufRead::read_line(&mut stdout, &mut line).unwrap();
+            if line.trim().is_empty() {
+                return block;
+            }
+            block.push_str(&line);
+        }
+    };
+
+    fs::write(repo_path.join("file-a.txt"), "changed").unwrap();
+    let first = render();
+    assert!(first.contains("1 files"), "{first}");
+    assert!(render().contains("1 files"));
+
+    fs::write(repo_path.join("file-b.txt"), "changed").unwrap();
+    Command::new("git")
+        .args(["add", "file-b.txt"])
+        .current_dir(&repo_path)
+        .output()
+        .unwrap();
+    // The on-disk cache keys on whole seconds; move the index past them
+    fs::File::options()
+        .write(true)
+        .open(repo_path.join(".git/index"))
+        .unwrap()
+        .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(5))
+        .unwrap();
+    // A file watcher reports the change asynchronously, so allow it a moment
+    let updated = (0..20).any(|_| {
+        let after = render();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        after.contains("2 files")
+    });
+    assert!(updated);
+
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn stats_summarizes_recorded_sessions() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let home = temp_dir.path().to_path_buf();
+    fs::create_dir_all(home.join(".claude")).unwrap();
+    fs::write(
+        home.join(".claude").join("cc-statusline.json"),
+        r#"{"rows": [["model"]], "session_stats": true}"#,
+    )
+    .unwrap();
+    let env = [
+        ("HOME", home.to_str().unwrap()),
+        ("XDG_CACHE_HOME", home.to_str().unwrap()),
+    ];
+    for (session, project, cost) in [("s1", "app", 1.25), ("s2", "lib", 0.5)] {
+        run_with_json_env(
+            &home,
+            &format!(
+                r#"{{"session_id": "{session}", "workspace": {{"project_dir": "/src/{project}"}},
+                    "model": {{"display_name": "Opus"}}, "cost": {{"total_cost_usd": {cost}}}}}"#
+            ),
+            &env,
+        );
+    }
+    let records = fs::read_to_string(home.join(".local/share/cc-statusline/sessions.jsonl"))
+        .expect("sessions should be recorded");
+    assert_eq!(records.lines().count(), 2, "{records}");
+
+    let stats = |args: &[&str]| {
+        let output = Command::new(get_binary_path())
+            .arg("stats")
+            .args(args)
+            .envs(env)
+            .output()
+            .expect("failed to run stats");
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    let all = stats(&["--today"]);
+    assert!(all.contains("Sessions  2"), "{all}");
+    assert!(all.contains("Cost      $1.75"), "{all}");
+    assert!(all.contains("app   1         $1.25"), "{all}");
+    let app = stats(&["--repo", "lib"]);
+    assert!(
+        app.contains("Sessions  1") && app.contains("$0.50"),
+        "{app}"
+    );
+}
+
+#[test]
+fn recent_lists_visited_projects() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let home = temp_dir.path().to_path_buf();
+    fs::create_dir_all(home.join(".claude")).unwrap();
+    fs::write(
+        home.join(".claude").join("cc-statusline.json"),
+        r#"{"rows": [["model"]], "project_history": true}"#,
+    )
+    .unwrap();
+    let env = [
+        ("HOME", home.to_str().unwrap()),
+        ("XDG_CACHE_HOME", home.to_str().unwrap()),
+    ];
+    for (session, project) in [("s1", "lib"), ("s2", "app"), ("s3", "app")] {
+        run_with_json_env(
+            &home,
+            &format!(r#"{{"session_id": "{session}", "cwd": "/src/{project}"}}"#),
+            &env,
+        );
+    }
+    let output = Command::new(get_binary_path())
+        .arg("recent")
+        .envs(env)
+        .output()
+        .expect("failed to run recent");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "/src/app\n/src/lib\n"
+    );
+}
+
+#[test]
+fn budget_adds_recorded_and_live_spend() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let home = temp_dir.path().to_path_buf();
+    fs::create_dir_all(home.join(".claude")).unwrap();
+    fs::write(
+        home.join(".claude").join("cc-statusline.json"),
+        r#"{"rows": [["budget"]], "budget": {"cost": 5}}"#,
+    )
+    .unwrap();
+    let data_dir = home.join("data").join("cc-statusline");
+    fs::create_dir_all(&data_dir).unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    fs::write(
+        data_dir.join("sessions.jsonl"),
+        format!(r#"{{"session": "earlier", "time": {now}, "cost_usd": 3.0}}"#) + "\n",
+    )
+    .unwrap();
+
+    let data_home = home.join("data");
+    let env = [
+        ("HOME", home.to_str().unwrap()),
+        ("XDG_CACHE_HOME", home.to_str().unwrap()),
+        ("XDG_DATA_HOME", data_home.to_str().unwrap()),
+    ];
+    let render = |session: &str, cost: f64| {
+        run_with_json_env(
+            &home,
+            &format!(r#"{{"session_id": "{session}", "cost": {{"total_cost_usd": {cost}}}}}"#),
+            &env,
+        )
+    };
+    assert!(render("live", 1.5).contains("$4.50/$5"));
+    // The live total replaces the line recorded for the same session
+    let over = render("live", 2.5);
+    assert!(over.contains("$5.50/$5"), "{over}");
+    assert!(
+        over.contains("\x1b[38;2;247;118;142m"),
+        "over budget should be red: {over:?}"
+    );
+}
+
+#[test]
+fn serve_reloads_edited_config() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let home = temp_dir.path().to_path_buf();
+    let config_path = home.join(".claude").join("cc-statusline.json");
+    fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fs::write(&config_path, r#"{"rows": [["model"]]}"#).unwrap();
+
+    let mut child = Command::new(get_binary_path())
+        .arg("--serve")
+        .current_dir(&home)
+        .env("HOME", &home)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn binary");
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = std::io::BufReader::new(child.stdout.take().unwrap());
+    let mut render = |json: &str| {
+        writeln!(stdin, "{json}").unwrap();
+        let mut block = String::new();
+        loop {
+            let mut line = String::new();
+            std::io::BufRead::read_line(&mut stdout, &mut line).unwrap();
+            if line.trim().is_empty() {
+                return block;
+            }
+            block.push_str(&line);
+        }
+    };
+    let input = r#"{"model": {"display_name": "Opus"}, "cost": {"total_cost_usd": 1.5}}"#;
+    let before = render(input);
+    assert!(
+        before.contains("Opus") && !before.contains("$1.50"),
+        "{before}"
+    );
+
+    fs::write(&config_path, r#"{"rows": [["model", "cost"]]}"#).unwrap();
+    // Bump the mtime in case the edit lands within the filesystem's timestamp resolution
+    fs::File::options()
+        .write(true)
+        .open(&config_path)
+        .unwrap()
+        .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(5))
+        .unwrap();
+    let after = render(input);
+    assert!(after.contains("$1.50"), "{after}");
+
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn config_check_reports_unknown_keys() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let home = temp_dir.path().to_path_buf();
+    fs::create_dir_all(home.join(".claude")).unwrap();
+    let check = |config: &str| {
+        fs::write(home.join(".claude").join("cc-statusline.json"), config).unwrap();
+        Command::new(get_binary_path())
+            .args(["config", "check"])
+            .env("HOME", &home)
+            .output()
+            .expect("failed to run config check")
+    };
+
+    let output = check(r#"{"rows": [["model"]], "pr_verbosity": "compact"}"#);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("Config OK"), "{stdout}");
+    assert!(stdout.contains(r#""pr_verbosity": "compact""#), "{stdout}");
+
+    let output = check(r#"{"rows": [["model"]], "ofline": true}"#);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(
+        stdout.contains("unknown key `ofline` (did you mean `offline`?)"),
+        "{stdout}"
+    );
+
+    let output = check(r#"{"rows": "model"}"#);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn validate_input_reports_schema_drift() {
+    let validate = |json: &str| {
+        let mut child = Command::new(get_binary_path())
+            .arg("--validate-input")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("failed to spawn binary");
+        child
+            .stdin
+            .as_mut()
+            .unwrap()
+            .write_all(json.as_bytes())
+            .expect("failed to write stdin");
+        child.wait_with_output().expect("failed to wait")
+    };
+
+    let output = validate(
+        r#"{"cwd": "/repo", "model": {"display_name": "Opus"},
+            "workspace": {"current_dir": "/repo", "project_dir": "/repo"}}"#,
+    );
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "Input OK");
+
+    let output = validate(
+        r#"{"cwd": "/repo", "model": {"name": "Opus"}, "brand_new": true,
+            "workspace": {"current_dir": "/repo", "project_dir": "/repo"}}"#,
+    );
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("unknown field `brand_new`"), "{stdout}");
+    assert!(
+        stdout.contains("field `model.name` was renamed to `model.display_name`"),
+        "{stdout}"
+    );
+
+    let output = validate("not json");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid input JSON"));
+}
+
+#[test]
+fn config_unknown_component_ignored() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let path = temp_dir.path().to_path_buf();
+
+    // Config with unknown component
+    let config = r#"{
+        "rows": [
+            ["project", "unknown_future_component", "path"],
+            ["no_git", "branch"],
+            ["model"]
+        ]
+    }"#;
+
+    let stdout = run_with_config(
+        &path,
+        r#"{"model": {"display_name": "Claude Test"}}"#,
+        config,
+    );
+
+    // Should still work, just skip the unknown component
+    assert!(
+        stdout.contains("Claude Test"),
+        "Should handle unknown components gracefully: {}",
+        stdout
+    );
+}
+
+#[test]
+fn config_ignore_glob_skips_git_stats() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+
+    // `model` only renders when git stats were not collected
+    let base = r#""rows": [["branch", "model"]], "conditions": {"model": "files == null"}, "deadline_ms": 5000"#;
+    let json = r#"{"model": {"display_name": "Claude Test"}}"#;
+
+    let stdout = run_with_config(&repo_path, json, &format!("{{{base}}}"));
+    assert!(
+        !stdout.contains("Claude Test"),
+        "Stats should be collected: {stdout}"
+    );
+
+    let config = format!(
+        r#"{{{base}, "ignore": ["{}/**"]}}"#,
+        repo_path.to_str().unwrap()
+    );
+    let stdout = run_with_config(&repo_path, json, &config);
+    assert!(
+        stdout.contains("Claude Test"),
+        "Ignored repo should skip stats: {stdout}"
+    );
+    assert!(
+        stdout.contains("main") || stdout.contains("master"),
+        "Ignored repo should still show branch: {stdout}"
+    );
+}
+
+#[test]
+fn git_mode_minimal_skips_index_walk() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+
+    // `model` only renders when the index was not walked but ahead/behind was
+    let rows = r#""rows": [["branch", "model"]], "conditions": {"model": "files == null && ahead == 0"}, "deadline_ms": 5000"#;
+    let json = r#"{"model": {"display_name": "Claude Test"}}"#;
+
+    let stdout = run_with_config(&repo_path, json, &format!("{{{rows}}}"));
+    assert!(
+        !stdout.contains("Claude Test"),
+        "Fast mode should count files: {stdout}"
+    );
+
+    let stdout = run_with_config(
+        &repo_path,
+        json,
+        &format!(r#"{{{rows}, "git_mode": "minimal"}}"#),
+    );
+    assert!(
+        stdout.contains("Claude Test"),
+        "Minimal mode should skip files: {stdout}"
+    );
+
+    // The environment overrides the config file
+    let stdout = run_with_json_env(
+        &repo_path,
+        json,
+        &[
+            ("HOME", repo_path.to_str().unwrap()),
+            ("CC_STATUSLINE_GIT_MODE", "fast"),
+        ],
+    );
+    assert!(
+        !stdout.contains("Claude Test"),
+        "CC_STATUSLINE_GIT_MODE should override config: {stdout}"
+    );
+
+    // A profile overrides the file, and the environment overrides the profile
+    let config = format!(
+        r#"{{{rows}, "git_mode": "minimal", "profiles": {{"work": {{"git_mode": "fast"}}}}}}"#
+    );
+    let stdout = run_with_config(&repo_path, json, &config);
+    assert!(stdout.contains("Claude Test"), "file: {stdout}");
+    let home = ("HOME", repo_path.to_str().unwrap());
+    let profile = ("CC_STATUSLINE_PROFILE", "work");
+    let stdout = run_with_json_env(&repo_path, json, &[home, profile]);
+    assert!(
+        !stdout.contains("Claude Test"),
+        "profile over file: {stdout}"
+    );
+    let stdout = run_with_json_env(
+        &repo_path,
+        json,
+        &[home, profile, ("CC_STATUSLINE_GIT_MODE", "MINIMAL")],
+    );
+    assert!(stdout.contains("Claude Test"), "env over profile: {stdout}");
+}
+
+#[test]
+fn slow_fs_drops_line_counts() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    fs::write(repo_path.join(".git/info/exclude"), ".claude/\n.cache/\n").unwrap();
+    fs::write(repo_path.join("file-initial-commit.txt"), "one\ntwo\n").unwrap();
+
+    let config =
+        r#"{"rows": [["branch", "files", "lines"]], "git_mode": "full", "deadline_ms": 5000}"#;
+    let stdout = run_with_config(&repo_path, "{}", config);
+    assert!(stdout.contains("+2"), "Expected line counts: {stdout}");
+    assert!(
+        !stdout.contains("slow fs"),
+        "Local disk isn't slow: {stdout}"
+    );
+
+    let stdout = run_with_json_env(
+        &repo_path,
+        "{}",
+        &[
+            ("HOME", repo_path.to_str().unwrap()),
+            ("CC_STATUSLINE_SLOW_FS", "1"),
+        ],
+    );
+    assert!(stdout.contains("(slow fs)"), "Expected indicator: {stdout}");
+    assert!(stdout.contains("1 files"), "Files still count: {stdout}");
+    assert!(!stdout.contains("+2"), "Lines are skipped: {stdout}");
+}
+
+#[test]
+fn path_mode_repo_shows_repo_relative_path() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    let api = repo_path.join("src/api");
+    fs::create_dir_all(&api).expect("failed to create dir");
+    let repo_name = repo_path
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .into_owned();
+
+    let config = r#"{"rows": [["path"]], "path_mode": "repo"}"#;
+    let json = format!(r#"{{"cwd": "{}"}}"#, api.display());
+    let stdout = run_with_config(&repo_path, &json, config);
+    assert!(
+        stdout.contains(&format!("{repo_name}//src/api")),
+        "{stdout}"
+    );
+
+    // At the root only the repo name is shown
+    let json = format!(r#"{{"cwd": "{}"}}"#, repo_path.display());
+    let stdout = run_with_config(&repo_path, &json, config);
+    assert!(stdout.contains(&repo_name), "{stdout}");
+    assert!(!stdout.contains("//"), "{stdout}");
+}
+
+#[test]
+fn package_segment_shows_monorepo_subpackage() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    let web = repo_path.join("apps/web-frontend");
+    fs::create_dir_all(web.join("src")).expect("failed to create package dir");
+    fs::write(web.join("package.json"), "{}").expect("failed to write manifest");
+
+    let config = r#"{"rows": [["package", "model"]]}"#;
+    let json = format!(
+        r#"{{"cwd": "{}", "model": {{"display_name": "Claude Test"}}}}"#,
+        web.join("src").display()
+    );
+    let stdout = run_with_config(&repo_path, &json, config);
+    assert!(stdout.contains("pkg: web-frontend"), "{stdout}");
+
+    // No package between the repo root and cwd
+    let json = format!(r#"{{"cwd": "{}"}}"#, repo_path.join("apps").display());
+    let stdout = run_with_config(&repo_path, &json, config);
+    assert!(!stdout.contains("pkg:"), "{stdout}");
+}
+
+#[test]
+fn config_conditions_control_components() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let path = temp_dir.path().to_path_buf();
+    fs::create_dir_all(path.join("k8s")).expect("failed to create k8s dir");
+
+    let config = r#"{
+        "rows": [["model", "tokens", "cost", "style"]],
+        "conditions": {
+            "tokens": "tokens >= 1000",
+            "cost": "cost > 0.5",
+            "style": "exists(\"k8s\")"
+        }
+    }"#;
+
+    let json_input = format!(
+        r#"{{"cwd": "{}", "model": {{"display_name": "Claude Test"}}, "output_style": {{"name": "verbose"}}, "context_window": {{"total_input_tokens": 400, "total_output_tokens": 100}}, "cost": {{"total_cost_usd": 1.25}}}}"#,
+        path.display()
+    );
+    let stdout = run_with_config(&path, &json_input, config);
+
+    assert!(
+        !stdout.contains("400/100"),
+        "Tokens below 1K should be hidden: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("$1.25"),
+        "Cost above $0.50 should be shown: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("verbose"),
+        "Style should show when k8s/ exists: {}",
+        stdout
+    );
+
+    fs::remove_dir(path.join("k8s")).expect("failed to remove k8s dir");
+    let stdout = run_with_config(&path, &json_input, config);
+    assert!(
+        !stdout.contains("verbose"),
+        "Style should hide when k8s/ is missing: {}",
+        stdout
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn config_custom_command_segment_is_cached() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let path = temp_dir.path().to_path_buf();
+
+    // Each run appends to a counter file so we can tell whether the command ran
+    let config = r#"{
+        "rows": [["model", "kube"]],
+        "custom": {
+            "kube": {"command": "echo x >> runs.txt; echo ctx-prod", "ttl": 3600, "timeout_ms": 2000, "color": "cyan"}
+        }
+    }"#;
+    let json_input = format!(
+        r#"{{"cwd": "{}", "model": {{"display_name": "Claude Test"}}}}"#,
+        path.display()
+    );
+
+    let stdout = run_with_config(&path, &json_input, config);
+    assert!(
+        stdout.contains("ctx-prod"),
+        "Expected custom command output: {}",
+        stdout
+    );
+
+    let stdout = run_with_config(&path, &json_input, config);
+    assert!(
+        stdout.contains("ctx-prod"),
+        "Expected cached custom output: {}",
+        stdout
+    );
+
+    let runs = fs::read_to_string(path.join("runs.txt")).expect("command should have run");
+    assert_eq!(
+        runs.lines().count(),
+        1,
+        "Command should run once within ttl"
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn config_custom_command_failure_is_cached() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let path = temp_dir.path().to_path_buf();
+
+    let config = r#"{
+        "rows": [["model", "broken"]],
+        "custom": {"broken": {"command": "echo x >> runs.txt; exit 1", "ttl": 3600}}
+    }"#;
+    let json_input = format!(
+        r#"{{"cwd": "{}", "model": {{"display_name": "Claude Test"}}}}"#,
+        path.display()
+    );
+
+    for _ in 0..2 {
+        let stdout = run_with_config(&path, &json_input, config);
+        assert!(stdout.contains("Claude Test"), "{stdout}");
+    }
+
+    let runs = fs::read_to_string(path.join("runs.txt")).expect("command should have run");
+    assert_eq!(
+        runs.lines().count(),
+        1,
+        "Failing command should run once within ttl"
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn sapling_checkout_shows_bookmark_and_pr() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let path = temp_dir.path().to_path_buf();
+    fs::create_dir_all(path.join(".sl")).expect("failed to create .sl");
+
+    // Stand-in `sl` answering the log template
+    let bin = path.join("bin");
+    fs::create_dir_all(&bin).expect("failed to create bin dir");
+    let sl = bin.join("sl");
+    fs::write(
+        &sl,
+        "#!/bin/sh\nprintf 'a1b2c3d4e5f6\\nfeature\\n42\\nhttps://github.com/o/r/pull/42\\n'\n",
+    )
+    .expect("failed to write sl");
+    fs::set_permissions(&sl, fs::Permissions::from_mode(0o755)).expect("failed to chmod sl");
+
+    let config_dir = path.join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        r#"{"rows": [["no_git", "branch"], ["pr_number", "pr_state"]]}"#,
+    )
+    .expect("failed to write config");
+
+    let env_path = format!(
+        "{}:{}",
+        bin.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let stdout = run_with_json_env(
+        &path,
+        &format!(r#"{{"cwd": "{}"}}"#, path.display()),
+        &[("HOME", path.to_str().unwrap()), ("PATH", &env_path)],
+    );
+    assert!(stdout.contains("feature"), "Expected bookmark: {stdout}");
+    assert!(!stdout.contains("no git"), "{stdout}");
+    assert!(stdout.contains("#42"), "Expected Sapling PR: {stdout}");
+}
+
+#[test]
+#[cfg(unix)]
+fn config_custom_command_timeout_hides_segment() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let path = temp_dir.path().to_path_buf();
+
+    let config = r#"{
+        "rows": [["model", "slow"]],
+        "custom": {"slow": {"command": "sleep 5; echo too-late", "timeout_ms": 50}}
+    }"#;
+
+    let stdout = run_with_config(
+        &path,
+        r#"{"model": {"display_name": "Claude Test"}}"#,
+        config,
+    );
+    assert!(
+        stdout.contains("Claude Test") && !stdout.contains("too-late"),
+        "Timed out command should be skipped: {}",
+        stdout
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn config_custom_command_slower_than_timeout_lands_later() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let path = temp_dir.path().to_path_buf();
+
+    let config = r#"{
+        "rows": [["model", "slow"]],
+        "custom": {"slow": {"command": "echo x >> runs.txt; sleep 0.5; echo eventually", "ttl": 3600, "timeout_ms": 50}}
+    }"#;
+    let json_input = format!(
+        r#"{{"cwd": "{}", "model": {{"display_name": "Claude Test"}}}}"#,
+        path.display()
+    );
+
+    let stdout = run_with_config(&path, &json_input, config);
+    assert!(!stdout.contains("eventually"), "{stdout}");
+
+    // The command isn't killed at the timeout; it fills the cache when it finishes
+    std::thread::sleep(std::time::Duration::from_millis(1500));
+    let stdout = run_with_config(&path, &json_input, config);
+    assert!(
+        stdout.contains("eventually"),
+        "Expected the slow command's output: {stdout}"
+    );
+    let runs = fs::read_to_string(path.join("runs.txt")).expect("command should have run");
+    assert_eq!(
+        runs.lines().count(),
+        1,
+        "Command should run once within ttl"
+    );
+}
+
+#[test]
+#[cfg(feature = "plugins")]
+fn lua_plugin_segment() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let path = temp_dir.path().to_path_buf();
+
+    let plugins_dir = path.join(".config").join("cc-statusline").join("plugins");
+    fs::create_dir_all(&plugins_dir).expect("failed to create plugins dir");
+    fs::write(
+        plugins_dir.join("hello.lua"),
+        r#"return function(ctx) return { text = "hi " .. ctx.input.model.display_name, color = "green" } end"#,
+    )
+    .expect("failed to write plugin");
+    // Plugins in the directory earlier versions used are still found
+    let legacy_dir = path.join(".claude").join("cc-statusline").join("plugins");
+    fs::create_dir_all(&legacy_dir).expect("failed to create plugins dir");
+    fs::write(
+        legacy_dir.join("bye.lua"),
+        r#"return function() return "bye" end"#,
+    )
+    .expect("failed to write plugin");
+    fs::write(
+        path.join(".claude").join("cc-statusline.json"),
+        r#"{"rows": [["hello", "bye"]]}"#,
+    )
+    .expect("failed to write config");
+
+    let stdout = run_with_json_env_full(
+        &path,
+        r#"{"model": {"display_name": "Claude Test"}}"#,
+        &[("HOME", path.to_str().unwrap())],
+        &["XDG_CONFIG_HOME"],
+    );
+    assert!(
+        stdout.contains("hi Claude Test"),
+        "Expected plugin output: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("bye"),
+        "Expected legacy plugin output: {stdout}"
+    );
+}
+
+#[test]
+#[cfg(feature = "trace")]
+fn trace_file_lists_pipeline_spans() {
+    let (temp_dir, repo_path) = create_git_repo();
+    let trace_path = temp_dir.path().join("trace.json");
+    let json = format!(
+        r#"{{"workspace": {{"current_dir": "{}"}}}}"#,
+        repo_path.display()
+    );
+    run_with_json_env(
+        &repo_path,
+        &json,
+        &[("CC_STATUSLINE_TRACE", trace_path.to_str().unwrap())],
+    );
+
+    let trace: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&trace_path).unwrap()).unwrap();
+    let names: Vec<&str> = trace
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|e| e["name"].as_str())
+        .collect();
+    for span in ["stdin", "parse", "discover", "git", "render"] {
+        assert!(names.contains(&span), "{span} missing from {names:?}");
+    }
+}
+
+// =============================================================================
+// Profile Tests
+// =============================================================================
+
+#[test]
+fn profile_flag_selects_builtin_preset() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let path = temp_dir.path().to_path_buf();
+
+    let binary = get_binary_path();
+    let mut child = Command::new(&binary)
+        .args(["--profile", "minimal"])
+        .env("HOME", path.join("nonexistent").to_str().unwrap())
+        .current_dir(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn binary");
+    child
+        .stdin
+        .as_mut()
+        .unwrap()
+        .write_all(
+            br#"{"model": {"display_name": "Claude Test"}, "cost": {"total_duration_ms": 60000}}"#,
+        )
+        .expect("failed to write stdin");
+    let output = child.wait_with_output().expect("failed to wait");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(
+        stdout.contains("Claude Test"),
+        "Minimal profile should show model: {}",
+        stdout
+    );
+    assert!(
+        !stdout.contains("1m"),
+        "Minimal profile should hide duration: {}",
+        stdout
+    );
+
+    let stdout = run_with_json_env(
+        &path,
+        r#"{"model": {"display_name": "Claude Test"}, "cost": {"total_duration_ms": 60000}}"#,
+        &[
+            ("HOME", path.join("nonexistent").to_str().unwrap()),
+            ("CC_STATUSLINE_PROFILE", "full"),
+        ],
+    );
+    assert!(
+        stdout.contains("Claude Test") && stdout.contains("1m"),
+        "Full profile should show model and duration: {stdout}"
+    );
+}
+
+#[test]
+fn profile_env_selects_user_profile() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let path = temp_dir.path().to_path_buf();
+
+    let config_dir = path.join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        r#"{
+            "rows": [["model"]],
+            "profiles": {"tokens-only": {"rows": [["tokens"]]}}
+        }"#,
+    )
+    .expect("failed to write config");
+
+    let json_input = r#"{"model": {"display_name": "Claude Test"}, "context_window": {"total_input_tokens": 5000, "total_output_tokens": 3000}}"#;
+
+    let stdout = run_with_json_env(
+        &path,
+        json_input,
+        &[
+            ("HOME", path.to_str().unwrap()),
+            ("CC_STATUSLINE_PROFILE", "tokens-only"),
+        ],
+    );
+    assert!(
+        stdout.contains("5K/3K") && !stdout.contains("Claude Test"),
+        "Expected only tokens from profile: {}",
+        stdout
+    );
+
+    // Without the env var, the base rows apply
+    let stdout = run_with_json_env(&path, json_input, &[("HOME", path.to_str().unwrap())]);
+    assert!(
+        stdout.contains("Claude Test") && !stdout.contains("5K/3K"),
+        "Expected base config without profile: {}",
+        stdout
+    );
+}
+
+// =============================================================================
+// Official JSON Fixture Test (Issue #20)
+// =============================================================================
+
+#[test]
+fn official_docs_json_fixture() {
+    let temp_dir = TempDir::new().expect("failed to create temp dir");
+    let path = temp_dir.path().to_path_buf();
+
+    // Load the full official JSON from https://code.claude.com/docs/en/statusline
+    // Contains fields not used by ClaudeInput (hook_event_name, session_id,
+    // version, model.id, context_window.current_usage). Serde ignores unknown
+    // fields by default, so this confirms the binary doesn't crash.
+    let json_input =
+        fs::read_to_string("tests/fixtures/official_input.json").expect("failed to read fixture");
+
+    let stdout = run_with_json(&path, &json_input);
+
+    // Binary should not crash — output must be non-empty
+    assert!(
+        !stdout.is_empty(),
+        "Expected non-empty output from official JSON fixture"
+    );
+
+    // Model display name
+    assert!(
         stdout.contains("Opus"),
         "Expected model display_name 'Opus' in output: {}",
         stdout
@@ -755,3 +2525,890 @@ fn official_docs_json_fixture() {
         stdout
     );
 }
+
+// =============================================================================
+// PR Fetch Tests (mock GitHub API)
+// =============================================================================
+
+#[test]
+fn pr_fetched_from_api_url_override() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    for args in [
+        &["remote", "add", "origin", "git@github.com:octo/app.git"][..],
+        &["checkout", "-q", "-b", "feature"][..],
+    ] {
+        Command::new("git")
+            .args(args)
+            .current_dir(&repo_path)
+            .output()
+            .expect("failed to run git");
+    }
+
+    let mock = MockGitHub::with_pr("octo", "app", "feature", 7);
+    let cache_dir = TempDir::new().expect("failed to create temp dir");
+    let config_dir = repo_path.join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        r#"{"rows": [["pr_number", "pr_comments", "pr_files", "pr_checks", "pr_reviews"]], "deadline_ms": 5000}"#,
+    )
+    .expect("failed to write config");
+
+    let stdout = run_with_json_env(
+        &repo_path,
+        "{}",
+        &[
+            ("HOME", repo_path.to_str().unwrap()),
+            ("XDG_CACHE_HOME", cache_dir.path().to_str().unwrap()),
+            ("GITHUB_TOKEN", "test-token"),
+            ("CC_STATUSLINE_GITHUB_API_URL", mock.url()),
+        ],
+    );
+
+    assert!(stdout.contains("#7"), "Expected PR number: {stdout}");
+    assert!(
+        stdout.contains("3 comments"),
+        "Expected comment count: {stdout}"
+    );
+    assert!(
+        stdout.contains("5 files"),
+        "Expected changed files: {stdout}"
+    );
+    assert!(
+        stdout.contains("checks passed"),
+        "Expected passing checks: {stdout}"
+    );
+    assert!(
+        stdout.contains("approved"),
+        "Expected review decision: {stdout}"
+    );
+    assert_eq!(
+        mock.requests().len(),
+        4,
+        "Expected pulls, detail, check-runs, and reviews requests: {:?}",
+        mock.requests()
+    );
+}
+
+#[test]
+fn deployment_fetched_with_pr() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    for args in [
+        &["remote", "add", "origin", "git@github.com:octo/app.git"][..],
+        &["checkout", "-q", "-b", "feature"][..],
+    ] {
+        Command::new("git")
+            .args(args)
+            .current_dir(&repo_path)
+            .output()
+            .expect("failed to run git");
+    }
+
+    let mock = MockGitHub::with_pr("octo", "app", "feature", 7);
+    let cache_dir = TempDir::new().expect("failed to create temp dir");
+    let config_dir = repo_path.join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        r#"{"rows": [["pr_number", "deployment"]], "deadline_ms": 5000}"#,
+    )
+    .expect("failed to write config");
+
+    let stdout = run_with_json_env(
+        &repo_path,
+        "{}",
+        &[
+            ("HOME", repo_path.to_str().unwrap()),
+            ("XDG_CACHE_HOME", cache_dir.path().to_str().unwrap()),
+            ("GITHUB_TOKEN", "test-token"),
+            ("CC_STATUSLINE_GITHUB_API_URL", mock.url()),
+        ],
+    );
+    assert!(
+        stdout.contains("🚀 staging") && stdout.contains('✓'),
+        "Expected successful staging deployment: {stdout}"
+    );
+    assert!(
+        mock.requests()
+            .iter()
+            .any(|r| r == "/repos/octo/app/deployments/11/statuses?per_page=1"),
+        "Expected deployment status lookup: {:?}",
+        mock.requests()
+    );
+}
+
+#[test]
+fn azure_devops_pr_and_build_status() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    for args in [
+        &[
+            "remote",
+            "add",
+            "origin",
+            "https://acme@dev.azure.com/acme/Web/_git/app",
+        ][..],
+        &["checkout", "-q", "-b", "feature"][..],
+    ] {
+        Command::new("git")
+            .args(args)
+            .current_dir(&repo_path)
+            .output()
+            .expect("failed to run git");
+    }
+
+    let base = "/acme/Web/_apis";
+    let mut routes = std::collections::HashMap::new();
+    routes.insert(
+        format!(
+            "{base}/git/repositories/app/pullrequests?searchCriteria.sourceRefName=\
+             refs%2Fheads%2Ffeature&searchCriteria.status=all&$top=1&api-version=7.1"
+        ),
+        (
+            200,
+            r#"{"value": [{"pullRequestId": 12, "status": "active",
+                "repository": {"webUrl": "https://dev.azure.com/acme/Web/_git/app"},
+                "reviewers": [{"vote": 10}], "mergeStatus": "succeeded"}]}"#
+                .to_string(),
+        ),
+    );
+    routes.insert(
+        format!("{base}/git/repositories/app/pullRequests/12/threads?api-version=7.1"),
+        (
+            200,
+            r#"{"value": [{"comments": [{"commentType": "text"}, {"commentType": "system"}]}]}"#
+                .to_string(),
+        ),
+    );
+    routes.insert(
+        format!(
+            "{base}/build/builds?branchName=refs%2Fpull%2F12%2Fmerge\
+             &queryOrder=queueTimeDescending&$top=1&api-version=7.1"
+        ),
+        (
+            200,
+            r#"{"value": [{"status": "completed", "result": "failed"}]}"#.to_string(),
+        ),
+    );
+    let mock = MockGitHub::start(routes);
+    let cache_dir = TempDir::new().expect("failed to create temp dir");
+    let config_dir = repo_path.join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        r#"{"rows": [["pr_number", "pr_comments", "pr_checks"]], "deadline_ms": 5000}"#,
+    )
+    .expect("failed to write config");
+
+    let stdout = run_with_json_env(
+        &repo_path,
+        "{}",
+        &[
+            ("HOME", repo_path.to_str().unwrap()),
+            ("XDG_CACHE_HOME", cache_dir.path().to_str().unwrap()),
+            ("AZURE_DEVOPS_EXT_PAT", "test-pat"),
+            ("CC_STATUSLINE_AZURE_API_URL", mock.url()),
+        ],
+    );
+    assert!(
+        stdout.contains("#12")
+            && stdout.contains("1 comment")
+            && stdout.contains("checks failed")
+            && stdout.contains("dev.azure.com/acme/Web/_git/app/pullrequest/12"),
+        "Expected Azure DevOps PR with build status: {stdout}"
+    );
+}
+
+#[test]
+fn gitea_pr_with_commit_status() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+
+    let mut routes = std::collections::HashMap::new();
+    routes.insert(
+        "/api/v1/repos/team/app/pulls?state=all&sort=recentupdate&limit=50".to_string(),
+        (
+            200,
+            r#"[{"number": 3, "state": "open", "head": {"ref": "other", "sha": "bbb"}},
+                {"number": 5, "state": "open", "html_url": "https://git.example.com/team/app/pulls/5",
+                 "comments": 2, "head": {"ref": "feature", "sha": "abc123"}}]"#
+                .to_string(),
+        ),
+    );
+    routes.insert(
+        "/api/v1/repos/team/app/commits/abc123/status".to_string(),
+        (
+            200,
+            r#"{"state": "success", "statuses": [{"status": "success"}]}"#.to_string(),
+        ),
+    );
+    let mock = MockGitHub::start(routes);
+    for args in [
+        &[
+            "remote",
+            "add",
+            "origin",
+            &format!("{}/team/app.git", mock.url()),
+        ][..],
+        &["checkout", "-q", "-b", "feature"][..],
+    ] {
+        Command::new("git")
+            .args(args)
+            .current_dir(&repo_path)
+            .output()
+            .expect("failed to run git");
+    }
+    let cache_dir = TempDir::new().expect("failed to create temp dir");
+    let config_dir = repo_path.join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        format!(
+            r#"{{"rows": [["pr_number", "pr_comments", "pr_checks"]], "deadline_ms": 5000,
+                "gitea": {{"hosts": ["{}"]}}}}"#,
+            mock.url()
+        ),
+    )
+    .expect("failed to write config");
+
+    let stdout = run_with_json_env(
+        &repo_path,
+        "{}",
+        &[
+            ("HOME", repo_path.to_str().unwrap()),
+            ("XDG_CACHE_HOME", cache_dir.path().to_str().unwrap()),
+            ("GITEA_TOKEN", "test-token"),
+        ],
+    );
+    assert!(
+        stdout.contains("#5") && stdout.contains("2 comments") && stdout.contains("checks passed"),
+        "Expected Gitea PR for the branch: {stdout}"
+    );
+}
+
+#[test]
+fn gerrit_change_scores_for_head_change_id() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    Command::new("git")
+        .args([
+            "commit",
+            "-q",
+            "--allow-empty",
+            "-m",
+            "Fix parser\n\nChange-Id: I8473b95934b5732ac55d26311a706c9c2bde9940",
+        ])
+        .current_dir(&repo_path)
+        .output()
+        .expect("failed to run git");
+
+    let mut routes = std::collections::HashMap::new();
+    routes.insert(
+        "/changes/?q=change%3AI8473b95934b5732ac55d26311a706c9c2bde9940&o=DETAILED_LABELS&n=1"
+            .to_string(),
+        (
+            200,
+            r#")]}'
+[{"_number": 4711, "labels": {"Code-Review": {"all": [{"value": 2}]},
+  "Verified": {"all": [{"value": 1}]}}}]"#
+                .to_string(),
+        ),
+    );
+    let mock = MockGitHub::start(routes);
+    let cache_dir = TempDir::new().expect("failed to create temp dir");
+    let config_dir = repo_path.join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        format!(
+            r#"{{"rows": [["change"]], "gerrit": {{"url": "{}"}}}}"#,
+            mock.url()
+        ),
+    )
+    .expect("failed to write config");
+    let env = [
+        ("HOME", repo_path.to_str().unwrap()),
+        ("XDG_CACHE_HOME", cache_dir.path().to_str().unwrap()),
+    ];
+
+    run_with_json_env(&repo_path, "{}", &env);
+    let stdout = run_with_json_env(&repo_path, "{}", &env);
+    assert!(
+        stdout.contains("4711") && stdout.contains("CR+2 V+1"),
+        "Expected Gerrit change with scores: {stdout}"
+    );
+}
+
+#[test]
+fn pr_access_shown_when_repo_is_not_visible() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    for args in [
+        &["remote", "add", "origin", "git@github.com:octo/secret.git"][..],
+        &["checkout", "-q", "-b", "feature"][..],
+    ] {
+        Command::new("git")
+            .args(args)
+            .current_dir(&repo_path)
+            .output()
+            .expect("failed to run git");
+    }
+
+    // No routes: every lookup is a 404, as for a private repo the token can't see
+    let mock = MockGitHub::start(std::collections::HashMap::new());
+    let cache_dir = TempDir::new().expect("failed to create temp dir");
+    let config_dir = repo_path.join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        r#"{"rows": [["pr_number", "pr_access"]], "deadline_ms": 5000}"#,
+    )
+    .expect("failed to write config");
+
+    let stdout = run_with_json_env(
+        &repo_path,
+        "{}",
+        &[
+            ("HOME", repo_path.to_str().unwrap()),
+            ("XDG_CACHE_HOME", cache_dir.path().to_str().unwrap()),
+            ("GITHUB_TOKEN", "test-token"),
+            ("CC_STATUSLINE_GITHUB_API_URL", mock.url()),
+        ],
+    );
+    assert!(
+        stdout.contains("pr: no access"),
+        "Expected access marker: {stdout}"
+    );
+}
+
+#[test]
+fn release_shows_commits_since_latest_tag() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    for args in [
+        &["remote", "add", "origin", "git@github.com:octo/app.git"][..],
+        &["tag", "-a", "v1.0.0", "-m", "release"][..],
+    ] {
+        Command::new("git")
+            .args(args)
+            .current_dir(&repo_path)
+            .output()
+            .expect("failed to run git");
+    }
+    make_commit(&repo_path, "second");
+    make_commit(&repo_path, "third");
+
+    let mut routes = std::collections::HashMap::new();
+    routes.insert(
+        "/repos/octo/app/releases/latest".to_string(),
+        (200, r#"{"tag_name": "v1.0.0"}"#.to_string()),
+    );
+    let mock = MockGitHub::start(routes);
+    let cache_dir = TempDir::new().expect("failed to create temp dir");
+    let config_dir = repo_path.join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        r#"{"rows": [["release"]]}"#,
+    )
+    .expect("failed to write config");
+    let env = [
+        ("HOME", repo_path.to_str().unwrap()),
+        ("XDG_CACHE_HOME", cache_dir.path().to_str().unwrap()),
+        ("GITHUB_TOKEN", "test-token"),
+        ("CC_STATUSLINE_GITHUB_API_URL", mock.url()),
+    ];
+
+    // The first render fetches in the background; the next one reads the cache
+    run_with_json_env(&repo_path, "{}", &env);
+    let stdout = run_with_json_env(&repo_path, "{}", &env);
+    assert!(
+        stdout.contains("v1.0.0") && stdout.contains("+2 commits"),
+        "Expected release with commits since: {stdout}"
+    );
+    assert_eq!(
+        mock.requests().len(),
+        1,
+        "Release is cached between renders"
+    );
+}
+
+#[test]
+fn vulns_shows_open_dependabot_alerts() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    Command::new("git")
+        .args(["remote", "add", "origin", "git@github.com:octo/app.git"])
+        .current_dir(&repo_path)
+        .output()
+        .expect("failed to run git");
+
+    let mut routes = std::collections::HashMap::new();
+    routes.insert(
+        "/repos/octo/app/dependabot/alerts?state=open&per_page=100".to_string(),
+        (
+            200,
+            r#"[{"number": 1}, {"number": 2}, {"number": 3}]"#.to_string(),
+        ),
+    );
+    let mock = MockGitHub::start(routes);
+    let cache_dir = TempDir::new().expect("failed to create temp dir");
+    let config_dir = repo_path.join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        r#"{"rows": [["vulns"]]}"#,
+    )
+    .expect("failed to write config");
+    let env = [
+        ("HOME", repo_path.to_str().unwrap()),
+        ("XDG_CACHE_HOME", cache_dir.path().to_str().unwrap()),
+        ("GITHUB_TOKEN", "test-token"),
+        ("CC_STATUSLINE_GITHUB_API_URL", mock.url()),
+    ];
+
+    run_with_json_env(&repo_path, "{}", &env);
+    let stdout = run_with_json_env(&repo_path, "{}", &env);
+    assert!(
+        stdout.contains("⚠ 3 vulns"),
+        "Expected open alert count: {stdout}"
+    );
+    assert_eq!(
+        mock.requests().len(),
+        1,
+        "Alerts are cached between renders"
+    );
+}
+
+#[test]
+fn notifications_counted_for_current_repo() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    Command::new("git")
+        .args(["remote", "add", "origin", "git@github.com:octo/app.git"])
+        .current_dir(&repo_path)
+        .output()
+        .expect("failed to run git");
+
+    let mut routes = std::collections::HashMap::new();
+    routes.insert(
+        "/repos/octo/app/notifications?per_page=50".to_string(),
+        (200, r#"[{"id": "1"}, {"id": "2"}]"#.to_string()),
+    );
+    let mock = MockGitHub::start(routes);
+    let cache_dir = TempDir::new().expect("failed to create temp dir");
+    let config_dir = repo_path.join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        r#"{"rows": [["notifications"]], "notifications_repo_only": true}"#,
+    )
+    .expect("failed to write config");
+    let env = [
+        ("HOME", repo_path.to_str().unwrap()),
+        ("XDG_CACHE_HOME", cache_dir.path().to_str().unwrap()),
+        ("GITHUB_TOKEN", "test-token"),
+        ("CC_STATUSLINE_GITHUB_API_URL", mock.url()),
+    ];
+
+    run_with_json_env(&repo_path, "{}", &env);
+    let stdout = run_with_json_env(&repo_path, "{}", &env);
+    assert!(stdout.contains("🔔 2"), "Expected unread count: {stdout}");
+    assert_eq!(
+        mock.requests().len(),
+        1,
+        "Notifications are cached between renders"
+    );
+}
+
+#[test]
+fn pr_checks_from_configured_ci_provider() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    Command::new("git")
+        .args(["checkout", "-b", "feature/ci"])
+        .current_dir(&repo_path)
+        .output()
+        .expect("failed to run git");
+
+    let mut routes = std::collections::HashMap::new();
+    routes.insert(
+        "/organizations/acme/pipelines/app/builds?branch=feature%2Fci&per_page=1".to_string(),
+        (
+            200,
+            r#"[{"state": "failed", "web_url": "https://buildkite.com/acme/app/builds/7"}]"#
+                .to_string(),
+        ),
+    );
+    let mock = MockGitHub::start(routes);
+    let cache_dir = TempDir::new().expect("failed to create temp dir");
+    let config_dir = repo_path.join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        format!(
+            r#"{{"rows": [["pr_checks"]], "ci": {{"provider": "buildkite", "api": "{}", "pipeline": "acme/app"}}}}"#,
+            mock.url()
+        ),
+    )
+    .expect("failed to write config");
+    let env = [
+        ("HOME", repo_path.to_str().unwrap()),
+        ("XDG_CACHE_HOME", cache_dir.path().to_str().unwrap()),
+        ("BUILDKITE_API_TOKEN", "test-token"),
+    ];
+
+    // No PR is needed; the build status comes from the CI service
+    run_with_json_env(&repo_path, "{}", &env);
+    let stdout = run_with_json_env(&repo_path, "{}", &env);
+    assert!(
+        stdout.contains("checks failed") && stdout.contains("buildkite.com/acme/app/builds/7"),
+        "Expected Buildkite status with build link: {stdout}"
+    );
+    assert_eq!(
+        mock.requests().len(),
+        1,
+        "CI status is cached between renders"
+    );
+}
+
+#[test]
+fn pr_fetched_from_upstream_for_fork_branch() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    for args in [
+        &["remote", "add", "origin", "git@github.com:me/app.git"][..],
+        &[
+            "remote",
+            "add",
+            "upstream",
+            "https://github.com/octo/app.git",
+        ][..],
+        &["checkout", "-q", "-b", "feature"][..],
+        &["config", "branch.feature.remote", "origin"][..],
+    ] {
+        Command::new("git")
+            .args(args)
+            .current_dir(&repo_path)
+            .output()
+            .expect("failed to run git");
+    }
+
+    let mock = MockGitHub::with_fork_pr("octo", "app", "me", "feature", 11);
+    let cache_dir = TempDir::new().expect("failed to create temp dir");
+    let config_dir = repo_path.join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        r#"{"rows": [["pr_number"]], "pr_remote": "upstream", "deadline_ms": 5000}"#,
+    )
+    .expect("failed to write config");
+
+    let stdout = run_with_json_env(
+        &repo_path,
+        "{}",
+        &[
+            ("HOME", repo_path.to_str().unwrap()),
+            ("XDG_CACHE_HOME", cache_dir.path().to_str().unwrap()),
+            ("GITHUB_TOKEN", "test-token"),
+            ("CC_STATUSLINE_GITHUB_API_URL", mock.url()),
+        ],
+    );
+
+    assert!(stdout.contains("#11"), "Expected fork PR number: {stdout}");
+    assert!(
+        mock.requests()
+            .iter()
+            .any(|r| r.contains("/repos/octo/app/pulls?head=me:feature")),
+        "Expected lookup on upstream with fork head: {:?}",
+        mock.requests()
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinked_repo_shares_cache_with_real_path() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    let home = TempDir::new().expect("failed to create temp dir");
+    let link = home.path().join("link");
+    std::os::unix::fs::symlink(&repo_path, &link).expect("failed to create symlink");
+    let config_dir = home.path().join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        r#"{"deadline_ms": 5000}"#,
+    )
+    .expect("failed to write config");
+
+    let cache_home = home.path().join("cache");
+    for dir in [&repo_path, &link] {
+        let json = format!(r#"{{"workspace": {{"current_dir": "{}"}}}}"#, dir.display());
+        run_with_json_env(
+            &repo_path,
+            &json,
+            &[
+                ("HOME", home.path().to_str().unwrap()),
+                ("XDG_CACHE_HOME", cache_home.to_str().unwrap()),
+            ],
+        );
+    }
+
+    let stats_caches = fs::read_dir(cache_home.join("cc-statusline"))
+        .expect("cache dir should exist")
+        .filter_map(Result::ok)
+        .filter(|e| e.file_name().to_string_lossy().starts_with("status-"))
+        .count();
+    assert_eq!(stats_caches, 1, "Expected one stats cache for both paths");
+}
+
+#[test]
+fn offline_shows_expired_pr_cache_as_stale() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    for args in [
+        &["remote", "add", "origin", "git@github.com:octo/app.git"][..],
+        &["checkout", "-q", "-b", "feature"][..],
+    ] {
+        Command::new("git")
+            .args(args)
+            .current_dir(&repo_path)
+            .output()
+            .expect("failed to run git");
+    }
+
+    let mock = MockGitHub::with_pr("octo", "app", "feature", 7);
+    let cache_dir = TempDir::new().expect("failed to create temp dir");
+    let config_dir = repo_path.join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        r#"{"rows": [["pr_number"]], "deadline_ms": 5000}"#,
+    )
+    .expect("failed to write config");
+    let env = [
+        ("HOME", repo_path.to_str().unwrap()),
+        ("XDG_CACHE_HOME", cache_dir.path().to_str().unwrap()),
+        ("GITHUB_TOKEN", "test-token"),
+        ("CC_STATUSLINE_GITHUB_API_URL", mock.url()),
+    ];
+    let stdout = run_with_json_env(&repo_path, "{}", &env);
+    assert!(stdout.contains("#7"), "Expected PR number: {stdout}");
+    let fetched = mock.requests().len();
+
+    // Expire the cached PR by replacing it with an old entry in the text
+    // format of earlier versions, which is migrated on read
+    for entry in fs::read_dir(cache_dir.path().join("cc-statusline")).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        if name.starts_with("pr-") && name.ends_with(".cache") {
+            let payload =
+                r#"{"number": 7, "state": "OPEN", "url": "https://github.com/octo/app/pull/7"}"#;
+            fs::write(&path, format!("0\nfeature\n{payload}")).unwrap();
+        }
+    }
+
+    let mut offline = env.to_vec();
+    offline.push(("NO_NETWORK", "1"));
+    let stdout = run_with_json_env(&repo_path, "{}", &offline);
+    assert!(
+        stdout.contains("#7") && stdout.contains("(stale)"),
+        "Expected stale PR number: {stdout}"
+    );
+    assert_eq!(
+        mock.requests().len(),
+        fetched,
+        "Offline mode must not call the API"
+    );
+}
+
+#[test]
+fn pr_fetch_honors_proxy_and_no_proxy() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    for args in [
+        &["remote", "add", "origin", "git@github.com:octo/app.git"][..],
+        &["checkout", "-q", "-b", "feature"][..],
+    ] {
+        Command::new("git")
+            .args(args)
+            .current_dir(&repo_path)
+            .output()
+            .expect("failed to run git");
+    }
+
+    let mock = MockGitHub::with_pr("octo", "app", "feature", 7);
+    let config_dir = repo_path.join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        r#"{"rows": [["pr_number"]], "deadline_ms": 5000}"#,
+    )
+    .expect("failed to write config");
+
+    // Nothing listens on the proxy port, so going through it fails
+    for (no_proxy, expect_pr) in [("", false), ("localhost,127.0.0.1", true)] {
+        let cache_dir = TempDir::new().expect("failed to create temp dir");
+        let stdout = run_with_json_env(
+            &repo_path,
+            "{}",
+            &[
+                ("HOME", repo_path.to_str().unwrap()),
+                ("XDG_CACHE_HOME", cache_dir.path().to_str().unwrap()),
+                ("GITHUB_TOKEN", "test-token"),
+                ("CC_STATUSLINE_GITHUB_API_URL", mock.url()),
+                ("HTTP_PROXY", "http://127.0.0.1:1"),
+                ("NO_PROXY", no_proxy),
+            ],
+        );
+        assert_eq!(
+            stdout.contains("#7"),
+            expect_pr,
+            "NO_PROXY={no_proxy:?}: {stdout}"
+        );
+    }
+}
+
+#[test]
+fn pr_fetch_retries_transient_failures() {
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    for args in [
+        &["remote", "add", "origin", "git@github.com:octo/app.git"][..],
+        &["checkout", "-q", "-b", "feature"][..],
+    ] {
+        Command::new("git")
+            .args(args)
+            .current_dir(&repo_path)
+            .output()
+            .expect("failed to run git");
+    }
+
+    let mock = MockGitHub::with_pr("octo", "app", "feature", 7);
+    mock.fail_next(1);
+    let cache_dir = TempDir::new().expect("failed to create temp dir");
+    let config_dir = repo_path.join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        r#"{"rows": [["pr_number"]], "deadline_ms": 5000}"#,
+    )
+    .expect("failed to write config");
+
+    let render = || {
+        run_with_json_env(
+            &repo_path,
+            "{}",
+            &[
+                ("HOME", repo_path.to_str().unwrap()),
+                ("XDG_CACHE_HOME", cache_dir.path().to_str().unwrap()),
+                ("GITHUB_TOKEN", "test-token"),
+                ("CC_STATUSLINE_GITHUB_API_URL", mock.url()),
+            ],
+        )
+    };
+
+    // The render makes one attempt and records when to try again
+    let stdout = render();
+    assert!(!stdout.contains("#7"), "Expected no PR yet: {stdout}");
+    assert_eq!(mock.requests().len(), 1, "{:?}", mock.requests());
+    render();
+    assert_eq!(
+        mock.requests().len(),
+        1,
+        "Expected no retry before the retry time: {:?}",
+        mock.requests()
+    );
+
+    std::thread::sleep(std::time::Duration::from_millis(2100));
+    let stdout = render();
+    assert!(
+        stdout.contains("#7"),
+        "Expected PR after the retry: {stdout}"
+    );
+    assert_eq!(
+        mock.requests().len(),
+        5,
+        "Expected a failed pulls request, then pulls, detail, check-runs, and reviews: {:?}",
+        mock.requests()
+    );
+}
+
+#[test]
+fn late_collectors_do_not_hold_the_output_open() {
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::time::{Duration, Instant};
+
+    let (_temp_dir, repo_path) = create_git_repo();
+    make_commit(&repo_path, "initial commit");
+    Command::new("git")
+        .args(["remote", "add", "origin", "git@github.com:octo/app.git"])
+        .current_dir(&repo_path)
+        .output()
+        .expect("failed to run git");
+
+    // An API that stalls, so the PR lookup misses the deadline
+    let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind");
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            std::thread::sleep(Duration::from_secs(2));
+            drop(stream);
+        }
+    });
+    let cache_dir = TempDir::new().expect("failed to create temp dir");
+    let config_dir = repo_path.join(".claude");
+    fs::create_dir_all(&config_dir).expect("failed to create config dir");
+    fs::write(
+        config_dir.join("cc-statusline.json"),
+        r#"{"rows": [["branch", "pr_number"]], "deadline_ms": 100}"#,
+    )
+    .expect("failed to write config");
+
+    let mut child = Command::new(get_binary_path())
+        .current_dir(&repo_path)
+        .env("HOME", &repo_path)
+        .env("XDG_CACHE_HOME", cache_dir.path())
+        .env("GITHUB_TOKEN", "test-token")
+        .env("CC_STATUSLINE_GITHUB_API_URL", &url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn binary");
+    child.stdin.take().unwrap().write_all(b"{}").unwrap();
+
+    let start = Instant::now();
+    let mut stdout = String::new();
+    child
+        .stdout
+        .take()
+        .unwrap()
+        .read_to_string(&mut stdout)
+        .unwrap();
+    let mut stderr = String::new();
+    child
+        .stderr
+        .take()
+        .unwrap()
+        .read_to_string(&mut stderr)
+        .unwrap();
+    let elapsed = start.elapsed();
+
+    assert!(
+        stdout.contains("master") || stdout.contains("main"),
+        "{stdout}"
+    );
+    assert!(
+        elapsed < Duration::from_millis(1500),
+        "Expected EOF at the deadline, not after the stalled lookup: {elapsed:?}"
+    );
+    assert!(
+        child.try_wait().unwrap().is_none(),
+        "Expected the process to still be finishing the lookup"
+    );
+    assert!(child.wait().unwrap().success());
+}
@@ -0,0 +1,146 @@
+//! End-to-end latency budget for a warm render (latency-budget feature)
+//!
+//! Renders a medium-size repo whose stats and PR caches are already warm and
+//! checks the p95 wall time, so a regression like synchronous HTTP on the hot
+//! path fails CI. Meant for release builds:
+//!
+//! ```bash
+//! cargo test --release --features latency-budget --test latency
+//! ```
+//!
+//! `CC_STATUSLINE_LATENCY_BUDGET_MS` overrides the default budget. Debug
+//! builds only check that warm renders make no requests, unless it is set.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+#[allow(dead_code)] // only part of the mock is used here
+mod support;
+use support::mock_github::MockGitHub;
+
+const DEFAULT_BUDGET_MS: u64 = 10;
+/// Timed renders; p95 ignores the slowest one
+const RUNS: usize = 40;
+/// Files in the repo, of which some are modified and some untracked
+const FILES: usize = 1_000;
+const COMMITS: usize = 50;
+const DIRTY: usize = 10;
+
+const INPUT: &str = r#"{"model": {"display_name": "Opus"},
+    "context_window": {"remaining_percentage": 60, "total_input_tokens": 5000},
+    "cost": {"total_duration_ms": 60000}}"#;
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .expect("failed to run git");
+    assert!(status.success(), "git {args:?} failed");
+}
+
+/// Repo with FILES files over nested dirs, COMMITS commits, a GitHub origin, and a dirty tree
+fn medium_repo() -> TempDir {
+    let dir = TempDir::new().expect("failed to create temp dir");
+    let path = dir.path();
+    git(path, &["init", "-q", "-b", "main"]);
+    git(path, &["config", "user.email", "test@example.com"]);
+    git(path, &["config", "user.name", "Test User"]);
+    git(
+        path,
+        &["remote", "add", "origin", "git@github.com:octo/app.git"],
+    );
+    let file = |i: usize| path.join(format!("src/mod{}/file{i}.rs", i % 20));
+    for i in 0..FILES {
+        let file = file(i);
+        fs::create_dir_all(file.parent().unwrap()).expect("failed to create dir");
+        fs::write(file, format!("// file {i}\nfn f() {{}}\n")).expect("failed to write file");
+    }
+    git(path, &["add", "-A"]);
+    git(path, &["commit", "-q", "-m", "initial"]);
+    for n in 1..COMMITS {
+        fs::write(file(n), format!("// file {n} v2\n")).expect("failed to write file");
+        git(path, &["commit", "-q", "-a", "-m", &format!("change {n}")]);
+    }
+    for i in 0..DIRTY {
+        fs::write(file(FILES - 1 - i), "// dirty\n").expect("failed to write file");
+        fs::write(path.join(format!("new{i}.txt")), "new\n").expect("failed to write file");
+    }
+    dir
+}
+
+/// Render once with the default config; returns stdout and the wall time
+fn render(repo: &Path, home: &Path, api: &str) -> (String, Duration) {
+    let start = Instant::now();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cc-statusline"))
+        .current_dir(repo)
+        .env("HOME", home)
+        .env("XDG_CACHE_HOME", home.join(".cache"))
+        .env("GITHUB_TOKEN", "test-token")
+        .env("CC_STATUSLINE_GITHUB_API_URL", api)
+        .env_remove("CC_STATUSLINE_PROFILE")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to spawn binary");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(INPUT.as_bytes())
+        .expect("failed to write stdin");
+    let output = child.wait_with_output().expect("failed to wait");
+    let elapsed = start.elapsed();
+    (
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        elapsed,
+    )
+}
+
+#[test]
+fn warm_render_within_latency_budget() {
+    let budget = std::env::var("CC_STATUSLINE_LATENCY_BUDGET_MS")
+        .ok()
+        .and_then(|ms| ms.parse().ok())
+        .or((!cfg!(debug_assertions)).then_some(DEFAULT_BUDGET_MS));
+    let repo = medium_repo();
+    let home = TempDir::new().expect("failed to create temp dir");
+    let mock = MockGitHub::with_pr("octo", "app", "main", 7);
+
+    // The first renders fill the stats and PR caches
+    let mut warm = String::new();
+    for _ in 0..3 {
+        warm = render(repo.path(), home.path(), mock.url()).0;
+    }
+    assert!(warm.contains("#7"), "PR cache should be warm: {warm}");
+    let requests = mock.requests().len();
+
+    let mut times: Vec<Duration> = (0..RUNS)
+        .map(|_| render(repo.path(), home.path(), mock.url()).1)
+        .collect();
+    times.sort();
+    let p95 = times[RUNS * 95 / 100 - 1];
+
+    assert_eq!(
+        mock.requests().len(),
+        requests,
+        "warm renders made HTTP requests: {:?}",
+        &mock.requests()[requests..]
+    );
+    let Some(budget) = budget else {
+        return; // Unoptimized timings say nothing about the budget
+    };
+    assert!(
+        p95 <= Duration::from_millis(budget),
+        "p95 {p95:?} over the {budget}ms budget (median {:?})",
+        times[RUNS / 2]
+    );
+}
@@ -0,0 +1,103 @@
+//! Property tests for the parsing and encoding helpers
+//!
+//! Malformed remotes, odd unicode paths, and arbitrary strings must never
+//! panic or produce output that breaks the helper's contract.
+
+use cc_statusline::{
+    PathStyle, abbreviate_path, abbreviate_path_with, parse_github_url, percent_encode,
+    shell_escape,
+};
+use proptest::prelude::*;
+
+/// Undo `shell_escape` the way a POSIX shell reads it
+fn shell_unescape(escaped: &str) -> Option<String> {
+    let inner = escaped.strip_prefix('\'')?.strip_suffix('\'')?;
+    Some(inner.replace("'\\''", "'"))
+}
+
+fn percent_decode(encoded: &str) -> Option<Vec<u8>> {
+    let bytes = encoded.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = encoded.get(i + 1..i + 3)?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Some(out)
+}
+
+/// Remote-like strings: mostly GitHub prefixes followed by arbitrary text
+fn remote() -> impl Strategy<Value = String> {
+    let prefix = prop_oneof![
+        Just("git@github.com:"),
+        Just("https://github.com/"),
+        Just("HTTP://GitHub.com/"),
+        Just("ssh://git@github.com:22/"),
+        Just("https://notgithub.com/"),
+        Just(""),
+    ];
+    (prefix, any::<String>()).prop_map(|(p, rest)| format!("{p}{rest}"))
+}
+
+/// Path-like strings built from unicode segments
+fn path() -> impl Strategy<Value = String> {
+    let segment = prop_oneof![
+        Just(String::new()),
+        Just("~".to_string()),
+        "[^/]{1,12}",
+        "\\.[a-z]{1,8}",
+    ];
+    prop::collection::vec(segment, 1..12).prop_map(|s| s.join("/"))
+}
+
+proptest! {
+    #[test]
+    fn shell_escape_round_trips(s in any::<String>()) {
+        let escaped = shell_escape(&s);
+        prop_assert_eq!(shell_unescape(&escaped), Some(s));
+    }
+
+    #[test]
+    fn percent_encode_is_url_safe_and_round_trips(s in any::<String>()) {
+        let encoded = percent_encode(&s);
+        prop_assert!(encoded
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-._~%".contains(&b)));
+        prop_assert_eq!(percent_decode(&encoded), Some(s.into_bytes()));
+    }
+
+    #[test]
+    fn parse_github_url_yields_single_segments(url in remote()) {
+        if let Some((owner, repo)) = parse_github_url(&url) {
+            prop_assert!(!owner.is_empty() && !repo.is_empty());
+            prop_assert!(!owner.contains('/') && !repo.contains('/'), "{owner}/{repo}");
+        }
+    }
+
+    #[test]
+    fn abbreviate_path_keeps_leaf_and_never_grows(path in path(), width in 0usize..80) {
+        let result = abbreviate_path(&path, width);
+        prop_assert!(result.len() <= path.len());
+        let leaf = path.rsplit('/').next().unwrap_or_default();
+        prop_assert!(result.ends_with(leaf));
+        prop_assert_eq!(result.split('/').count(), path.split('/').count());
+    }
+
+    #[test]
+    fn abbreviate_path_with_style_never_panics(
+        path in path(),
+        width in 0usize..80,
+        keep in prop::option::of(0usize..6),
+        unique in any::<bool>(),
+    ) {
+        let style = PathStyle { keep, full: vec!["src".into(), "*.rs".into()], unique };
+        let result = abbreviate_path_with(&path, width, &style, Some(std::path::Path::new(&path)));
+        prop_assert_eq!(result.split('/').count(), path.split('/').count());
+    }
+}
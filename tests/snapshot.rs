@@ -0,0 +1,140 @@
+//! Full-output snapshot tests run inside a `testing::Sandbox`
+//!
+//! Run with `cargo test --features testing`.
+
+use cc_statusline::testing::Sandbox;
+use cc_statusline::{ClaudeInput, Config, StatusLine};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+fn render(json: &str, config: &str) -> String {
+    let config: Config = serde_json::from_str(config).expect("invalid config");
+    let mut status = StatusLine::builder(ClaudeInput::from_json(json))
+        .config(config)
+        .build();
+    let mut out = Vec::new();
+    status.render(&mut out).expect("render failed");
+    status.wait_for_background();
+    String::from_utf8(out).expect("output is not UTF-8")
+}
+
+#[test]
+fn snapshot_json_input() {
+    let sandbox = Sandbox::new();
+    sandbox.set_var("HOME", "/home/dev");
+
+    let out = render(
+        r#"{"cwd": "/home/dev/app/src",
+            "workspace": {"project_dir": "/home/dev/app"},
+            "model": {"display_name": "Opus"},
+            "context_window": {"remaining_percentage": 47, "total_input_tokens": 125000, "total_output_tokens": 42000},
+            "git": {"branch": "main", "changed_files": 3, "ahead": 2},
+            "pr": {"number": 42, "state": "OPEN", "url": "https://github.com/o/r/pull/42", "check_status": "passed"}}"#,
+        r#"{"rows": [["project", "path"], ["branch", "files", "ahead_behind"], ["pr_number", "pr_checks"], ["model", "context", "tokens"]]}"#,
+    );
+    assert_snapshot(&out, "snapshot_json_input");
+}
+
+#[test]
+fn update_check_uses_fake_http_and_clock() {
+    let sandbox = Sandbox::new();
+    sandbox.respond_with(|url| {
+        assert!(
+            url.ends_with("/releases/latest"),
+            "unexpected request {url}"
+        );
+        (200, r#"{"tag_name": "v99.0.0"}"#.to_string())
+    });
+    let config = r#"{"rows": [["update"]]}"#;
+
+    // First render only starts the daily check; the marker shows once it is cached
+    assert_eq!(render("{}", config), "");
+    assert_snapshot(&render("{}", config), "update_available");
+
+    // A day later the check runs again and finds we're current
+    sandbox.respond_with(|_| (200, r#"{"tag_name": "v0.0.1"}"#.to_string()));
+    sandbox.advance(Duration::from_secs(24 * 60 * 60));
+    render("{}", config);
+    assert_eq!(render("{}", config), "");
+}
+
+#[test]
+fn idle_counts_from_last_usage_change() {
+    let sandbox = Sandbox::new();
+    let config = r#"{"rows": [["idle"]], "idle_after": 10}"#;
+    let input = |cost| format!(r#"{{"session_id": "s1", "cost": {{"total_cost_usd": {cost}}}}}"#);
+
+    assert_eq!(render(&input(0.5), config), "");
+    sandbox.advance(Duration::from_secs(9 * 60));
+    assert_eq!(render(&input(0.5), config), "");
+    sandbox.advance(Duration::from_secs(3 * 60));
+    assert!(render(&input(0.5), config).contains("idle 12m"));
+
+    // New usage resets the clock
+    assert_eq!(render(&input(0.75), config), "");
+}
+
+#[test]
+fn last_visit_shows_earlier_sessions() {
+    let sandbox = Sandbox::new();
+    let config = r#"{"rows": [["last_visit"]]}"#;
+    let input = |session| format!(r#"{{"session_id": "{session}", "cwd": "/nonexistent/app"}}"#);
+
+    assert_eq!(render(&input("s1"), config), "");
+    sandbox.advance(Duration::from_secs(3 * 24 * 60 * 60));
+    assert_eq!(render(&input("s1"), config), "");
+    sandbox.advance(Duration::from_secs(2 * 24 * 60 * 60));
+    assert!(render(&input("s2"), config).contains("last here 2d ago"));
+}
+
+#[test]
+fn project_label_replaces_directory_name() {
+    let sandbox = Sandbox::new();
+    sandbox.set_var("HOME", "/home/dev");
+    let config = r##"{"rows": [["project"]],
+        "projects": {"~/work/api": {"label": "megacorp", "emoji": "🏦", "color": "#ff0000"}}}"##;
+    let input = |dir| format!(r#"{{"cwd": "{dir}/src", "workspace": {{"project_dir": "{dir}"}}}}"#);
+
+    let out = render(&input("/home/dev/work/api"), config);
+    assert!(out.contains("\x1b[38;2;255;0;0m🏦 megacorp"), "{out:?}");
+    let out = render(&input("/home/dev/personal/api"), config);
+    assert!(out.contains("api") && !out.contains("megacorp"), "{out:?}");
+}
+
+#[test]
+fn wsl_maps_windows_paths() {
+    let sandbox = Sandbox::new();
+    sandbox.set_var("HOME", "/home/dev");
+    let json =
+        r#"{"cwd": "C:\\Users\\dev\\app", "workspace": {"project_dir": "C:\\Users\\dev\\app"}}"#;
+    let config = r#"{"rows": [["project", "path"]]}"#;
+
+    assert!(render(json, config).contains(r"C:\Users\dev\app"));
+    sandbox.set_var("WSL_DISTRO_NAME", "Ubuntu");
+    let input = ClaudeInput::from_json(json);
+    assert_eq!(input.cwd.as_deref(), Some("/mnt/c/Users/dev/app"));
+    assert!(render(json, config).contains("/mnt/c/Users/dev/app"));
+}
+
+/// Compare `out` with the checked-in snapshot under tests/snapshots/
+/// Set `UPDATE_SNAPSHOTS=1` to rewrite snapshots from the current output.
+fn assert_snapshot(out: &str, name: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{name}.txt"));
+    let escaped = out.replace('\x1b', "\\e").replace('\x07', "\\a");
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &escaped).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "missing snapshot {}; run with UPDATE_SNAPSHOTS=1",
+            path.display()
+        )
+    });
+    assert_eq!(escaped, expected, "snapshot {name} changed");
+}
@@ -0,0 +1,158 @@
+//! Local HTTP server emulating the GitHub REST endpoints used for PR lookups
+//!
+//! Point the binary at it with `CC_STATUSLINE_GITHUB_API_URL` (see [`MockGitHub::url`]).
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Canned responses keyed by request path (including the query string)
+pub struct MockGitHub {
+    url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+    failures: Arc<AtomicUsize>,
+}
+
+impl MockGitHub {
+    /// Serve `routes` (path -> (status, JSON body)); unknown paths get 404
+    pub fn start(routes: HashMap<String, (u16, String)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind mock server");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let failures = Arc::new(AtomicUsize::new(0));
+
+        let log = Arc::clone(&requests);
+        let pending_failures = Arc::clone(&failures);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                handle(stream, &routes, &log, &pending_failures);
+            }
+        });
+        Self {
+            url,
+            requests,
+            failures,
+        }
+    }
+
+    /// A server for one open PR #`number` on `owner/repo` branch `branch`
+    pub fn with_pr(owner: &str, repo: &str, branch: &str, number: u32) -> Self {
+        Self::with_fork_pr(owner, repo, owner, branch, number)
+    }
+
+    /// Like [`MockGitHub::with_pr`], for a PR opened from `head_owner`'s fork
+    pub fn with_fork_pr(
+        owner: &str,
+        repo: &str,
+        head_owner: &str,
+        branch: &str,
+        number: u32,
+    ) -> Self {
+        let sha = "0123456789abcdef0123456789abcdef01234567";
+        let mut routes = HashMap::new();
+        routes.insert(
+            format!("/repos/{owner}/{repo}/pulls?head={head_owner}:{branch}&state=all"),
+            (
+                200,
+                format!(
+                    r#"[{{"number": {number}, "state": "open",
+                        "html_url": "https://github.com/{owner}/{repo}/pull/{number}",
+                        "head": {{"sha": "{sha}"}}}}]"#
+                ),
+            ),
+        );
+        routes.insert(
+            format!("/repos/{owner}/{repo}/pulls/{number}"),
+            (
+                200,
+                r#"{"comments": 2, "review_comments": 1, "changed_files": 5}"#.to_string(),
+            ),
+        );
+        routes.insert(
+            format!("/repos/{owner}/{repo}/pulls/{number}/reviews?per_page=100"),
+            (
+                200,
+                r#"[{"user": {"login": "reviewer"}, "state": "APPROVED"}]"#.to_string(),
+            ),
+        );
+        routes.insert(
+            format!("/repos/{owner}/{repo}/deployments?ref={branch}&per_page=1"),
+            (200, r#"[{"id": 11, "environment": "staging"}]"#.to_string()),
+        );
+        routes.insert(
+            format!("/repos/{owner}/{repo}/deployments/11/statuses?per_page=1"),
+            (200, r#"[{"state": "success"}]"#.to_string()),
+        );
+        routes.insert(
+            format!("/repos/{owner}/{repo}/commits/{sha}/check-runs"),
+            (
+                200,
+                r#"{"check_runs": [{"conclusion": "success"}, {"conclusion": "success"}]}"#
+                    .to_string(),
+            ),
+        );
+        Self::start(routes)
+    }
+
+    /// Base URL to use as the API override
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Answer the next `count` requests with 503, as during an outage
+    pub fn fail_next(&self, count: usize) {
+        self.failures.store(count, Ordering::SeqCst);
+    }
+
+    /// Paths requested so far
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn handle(
+    mut stream: TcpStream,
+    routes: &HashMap<String, (u16, String)>,
+    log: &Mutex<Vec<String>>,
+    failures: &AtomicUsize,
+) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    // Drain headers; GETs have no body
+    let mut line = String::new();
+    while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+        line.clear();
+    }
+
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .to_string();
+    log.lock().unwrap().push(path.clone());
+
+    let failing = failures
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+        .is_ok();
+    let (status, body) = if failing {
+        (503, r#"{"message": "Service Unavailable"}"#.to_string())
+    } else {
+        routes
+            .get(&path)
+            .cloned()
+            .unwrap_or((404, r#"{"message": "Not Found"}"#.to_string()))
+    };
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {status} Mock\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+}